@@ -0,0 +1,85 @@
+//! Benchmarks for demultiplexing PAF files, to give a baseline before optimising the hot loop.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use readfish_tools::{_demultiplex_paf, paf::PafRecord, DemultiplexOptions};
+use std::{fs, path::PathBuf};
+
+/// Get the path to a file in the bundled resources directory.
+fn get_test_file(file: &str) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("resources/");
+    path.push(file);
+    path
+}
+
+/// Write a copy of `test_hum_4000.paf` with a `ch` tag on every line, so it can be demultiplexed
+/// without a sequencing summary. Reads are spread across all 512 channels of a MinION flowcell.
+fn write_tagged_paf() -> PathBuf {
+    let paf = fs::read_to_string(get_test_file("test_hum_4000.paf")).unwrap();
+    let tagged: String = paf
+        .lines()
+        .enumerate()
+        .map(|(index, line)| format!("{}\tch:i:{}\n", line, index % 512 + 1))
+        .collect();
+    let tagged_path = std::env::temp_dir().join("readfish_tools_bench_tagged.paf");
+    fs::write(&tagged_path, tagged).unwrap();
+    tagged_path
+}
+
+/// Benchmark demultiplexing a mid-size PAF file, with and without a sequencing summary.
+fn bench_demultiplex(c: &mut Criterion) {
+    let mut group = c.benchmark_group("demultiplex_paf");
+
+    let toml_path = get_test_file("human_barcode.toml");
+    let paf_path = get_test_file("test_paf_barcode05_NA12878.chr.paf");
+    let seq_sum_path = get_test_file("seq_sum_PAK09329.txt");
+    group.bench_function("with_seq_sum", |b| {
+        b.iter(|| {
+            _demultiplex_paf(
+                &toml_path,
+                &[&paf_path],
+                Some(&seq_sum_path),
+                &DemultiplexOptions {
+                    strict: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        })
+    });
+
+    let toml_path = get_test_file("RAPID_CNS2.toml");
+    let tagged_path = write_tagged_paf();
+    group.bench_function("without_seq_sum", |b| {
+        b.iter(|| {
+            _demultiplex_paf(
+                &toml_path,
+                &[&tagged_path],
+                None::<PathBuf>,
+                &DemultiplexOptions {
+                    strict: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        })
+    });
+
+    group.finish();
+    fs::remove_file(tagged_path).unwrap();
+}
+
+/// Benchmark parsing the lines of a PAF file into records, the first step of demultiplexing every line.
+fn bench_parse_paf_lines(c: &mut Criterion) {
+    let paf = fs::read_to_string(get_test_file("test_hum_4000.paf")).unwrap();
+    c.bench_function("parse_paf_lines", |b| {
+        b.iter(|| {
+            for line in paf.lines() {
+                black_box(PafRecord::from_line(line).unwrap());
+            }
+        })
+    });
+}
+
+criterion_group!(benches, bench_demultiplex, bench_parse_paf_lines);
+criterion_main!(benches);
@@ -0,0 +1,99 @@
+//! Benchmarks for target overlap queries, comparing the indexed lookup used for on-target classification
+//! against a naive scan over every target interval of the contig.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use readfish_tools::readfish::Conf;
+use std::{collections::HashMap, fs, path::PathBuf};
+
+/// The region of `RAPID_CNS2.toml` whose targets are the panel BED file.
+const REGION: &str = "Rapid_CNS";
+
+/// Get the path to a file in the bundled resources directory.
+fn get_test_file(file: &str) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("resources/");
+    path.push(file);
+    path
+}
+
+/// Write a BED file of exome-like targets, 200 bp every 10 Kb across 22 contigs, to benchmark a large panel.
+fn write_exome_bed() -> PathBuf {
+    let bed: String = (1..=22)
+        .flat_map(|contig| {
+            (0..200_000_000_usize)
+                .step_by(10_000)
+                .map(move |start| format!("chr{}\t{}\t{}\n", contig, start, start + 200))
+        })
+        .collect();
+    let bed_path = std::env::temp_dir().join("readfish_tools_bench_exome.bed");
+    fs::write(&bed_path, bed).unwrap();
+    bed_path
+}
+
+/// Generate `count` 1 Kb query ranges spread over the targeted contigs, with a fixed seed so runs are comparable.
+fn queries<'a>(contigs: &[&'a str], count: usize) -> Vec<(&'a str, usize, usize)> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    (0..count)
+        .map(|index| {
+            // xorshift, so the queries hit and miss the targets without pulling in a random number crate
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let start = (state % 200_000_000) as usize;
+            (contigs[index % contigs.len()], start, start + 1000)
+        })
+        .collect()
+}
+
+/// Benchmark checking whether 100,000 reads overlap the targets of `conf`, indexed and by naive scan.
+fn bench_conf(c: &mut Criterion, group_name: &str, conf: &Conf) {
+    let contigs = conf.target_contigs(REGION);
+    let queries = queries(&contigs, 100_000);
+    let naive_targets: HashMap<&str, Vec<(usize, usize)>> = contigs
+        .iter()
+        .map(|&contig| (contig, conf.get_contig_targets(REGION, contig, usize::MAX)))
+        .collect();
+    let naive_overlaps = |contig: &str, start: usize, end: usize| {
+        naive_targets[contig]
+            .iter()
+            .any(|&(target_start, target_stop)| target_start < end && start < target_stop)
+    };
+    assert!(queries.iter().all(|&(contig, start, end)| {
+        conf.overlaps(REGION, contig, start, end) == naive_overlaps(contig, start, end)
+    }));
+
+    let mut group = c.benchmark_group(group_name);
+    // A naive scan of an exome sized panel takes over a second, so keep to the minimum number of samples
+    group.sample_size(10);
+    group.bench_function("indexed", |b| {
+        b.iter(|| {
+            black_box(&queries)
+                .iter()
+                .filter(|&&(contig, start, end)| conf.overlaps(REGION, contig, start, end))
+                .count()
+        })
+    });
+    group.bench_function("naive_scan", |b| {
+        b.iter(|| {
+            black_box(&queries)
+                .iter()
+                .filter(|&&(contig, start, end)| naive_overlaps(contig, start, end))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+/// Benchmark target overlaps for the bundled CNS panel, and for an exome sized panel.
+fn bench_target_overlaps(c: &mut Criterion) {
+    let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+    bench_conf(c, "target_overlaps_panel", &conf);
+
+    let bed_path = write_exome_bed();
+    conf.load_bed_targets(&bed_path).unwrap();
+    bench_conf(c, "target_overlaps_exome", &conf);
+    fs::remove_file(bed_path).unwrap();
+}
+
+criterion_group!(benches, bench_target_overlaps);
+criterion_main!(benches);
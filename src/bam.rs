@@ -0,0 +1,1540 @@
+//! BAM and CRAM file functions
+//! In this module we wrap alignments from a BAM or CRAM file in a [`BamRecord`], which implements the same
+//! [`AlignmentRecord`] trait as the [`PafRecord`] used for PAF files, so that they can be demultiplexed and summarised
+//! in exactly the same way. CRAM files are decoded against their reference FASTA, see [`demultiplex`].
+//!
+
+use crate::{
+    paf::{
+        self, classify_record, skip_filtered_read, skip_missing_read, AlignmentRecord, PafRecord,
+        ReadFilter, MAPQ_MISSING,
+    },
+    readfish::Conf,
+    readfish_io::{read_fai, DynResult},
+    sequencing_summary::SeqSum,
+    Summary,
+};
+use md5::{Digest, Md5};
+use noodles_bam as bam;
+use noodles_bgzf::{self as bgzf, io::writer::CompressionLevel};
+use noodles_cram as cram;
+use noodles_fasta as fasta;
+use noodles_sam::{
+    self as sam,
+    alignment::{
+        io::Write,
+        record::{
+            cigar::op::Kind,
+            data::field::{Tag, Value},
+        },
+    },
+    header::record::value::{
+        map::{
+            self,
+            header::{sort_order::COORDINATE, tag::SORT_ORDER},
+            reference_sequence::tag::MD5_CHECKSUM,
+        },
+        Map,
+    },
+};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fs::{create_dir_all, remove_file, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// The magic bytes at the start of every CRAM file.
+const CRAM_MAGIC: &[u8; 4] = b"CRAM";
+
+/// A thin wrapper over an alignment from a BAM, CRAM (or SAM) file, implementing [`AlignmentRecord`] so that it can be
+/// classified and summarised without first being converted into a [`PafRecord`].
+///
+/// The alignment coordinates need a pass over the CIGAR, so are calculated once when the wrapper is created, see
+/// [`BamRecord::new`]. The optional tags are read from the wrapped record when asked for.
+pub struct BamRecord<'a, R: sam::alignment::Record + ?Sized> {
+    /// The wrapped alignment record.
+    record: &'a R,
+    /// The name of the read, or `*` if it has none.
+    query_name: &'a str,
+    /// The length of the read, including clipped bases.
+    query_length: usize,
+    /// The start of the alignment on the read, relative to the strand it was sequenced on.
+    query_start: usize,
+    /// The end of the alignment on the read, relative to the strand it was sequenced on.
+    query_end: usize,
+    /// The strand of the alignment, `+`, `-`, or `*` if the read is unmapped.
+    strand: char,
+    /// The name of the reference sequence, or `*` if the read is unmapped.
+    target_name: &'a str,
+    /// The length of the reference sequence.
+    target_length: usize,
+    /// The 0-based start of the alignment on the reference sequence.
+    target_start: usize,
+    /// The end of the alignment on the reference sequence, exclusive.
+    target_end: usize,
+    /// The number of residue matches in the alignment.
+    nmatch: usize,
+    /// The alignment block length, including gaps.
+    aln_len: usize,
+    /// The mapping quality of the alignment, [`MAPQ_MISSING`] if it is missing.
+    mapq: u8,
+    /// Whether the alignment is secondary.
+    secondary: bool,
+}
+
+/// Get a read or reference name as a string, or `*` if it is missing or not valid UTF-8.
+fn name_str(name: Option<&[u8]>) -> &str {
+    name.and_then(|name| std::str::from_utf8(name).ok())
+        .unwrap_or("*")
+}
+
+impl<'a, R: sam::alignment::Record + ?Sized> BamRecord<'a, R> {
+    /// Wraps a mapped alignment from a BAM (or SAM) file.
+    ///
+    /// The query coordinates are calculated from the CIGAR, and are reported relative to the strand of the read as
+    /// it was sequenced, as in a PAF file. Clipped bases, including hard clips, count towards the query length.
+    /// The number of residue matches is calculated from the `NM` tag if present, or from the `=` CIGAR operations
+    /// for alignments with extended CIGARs.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The alignment record.
+    /// * `header` - The header of the alignment file, used to look up the name and length of the reference sequence.
+    ///
+    /// # Returns
+    ///
+    /// The wrapped alignment, or `None` if the read is unmapped, see [`BamRecord::unmapped`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the fields of the alignment record cannot be decoded.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let mut reader = noodles_bam::io::reader::Builder::default().build_from_path("resources/test_human.bam")?;
+    /// let header = reader.read_header()?;
+    /// for record in reader.records() {
+    ///     let record = record?;
+    ///     if let Some(alignment) = BamRecord::new(&record, &header)? {
+    ///         println!("{}", alignment.target_name());
+    ///     }
+    /// }
+    /// ```
+    pub fn new(record: &'a R, header: &'a sam::Header) -> DynResult<Option<Self>> {
+        let flags = record.flags()?;
+        if flags.is_unmapped() {
+            return Ok(None);
+        }
+        let (target_name, reference_sequence) = match record.reference_sequence(header) {
+            Some(reference_sequence) => reference_sequence?,
+            None => return Ok(None),
+        };
+        let target_start = match record.alignment_start() {
+            Some(alignment_start) => usize::from(alignment_start?) - 1,
+            None => return Ok(None),
+        };
+
+        let mut leading_clip = 0;
+        let mut trailing_clip = 0;
+        let mut query_length = 0;
+        let mut target_span = 0;
+        let mut aln_len = 0;
+        let mut aligned_len = 0;
+        let mut sequence_matches = 0;
+        let mut indels = 0;
+        let mut seen_alignment = false;
+        for op in record.cigar().iter() {
+            let op = op?;
+            let len = op.len();
+            match op.kind() {
+                Kind::SoftClip | Kind::HardClip => {
+                    if seen_alignment {
+                        trailing_clip += len;
+                    } else {
+                        leading_clip += len;
+                    }
+                    query_length += len;
+                    continue;
+                }
+                Kind::Match | Kind::SequenceMismatch => aligned_len += len,
+                Kind::SequenceMatch => {
+                    aligned_len += len;
+                    sequence_matches += len;
+                }
+                Kind::Insertion | Kind::Deletion => indels += len,
+                Kind::Skip | Kind::Pad => {}
+            }
+            seen_alignment = true;
+            if op.kind().consumes_read() {
+                query_length += len;
+            }
+            if op.kind().consumes_reference() {
+                target_span += len;
+            }
+            if !matches!(op.kind(), Kind::Skip | Kind::Pad) {
+                aln_len += len;
+            }
+        }
+
+        // NM counts mismatches, inserted and deleted bases, so remove the indels to get the mismatches
+        let edit_distance = record
+            .data()
+            .get(&Tag::EDIT_DISTANCE)
+            .transpose()?
+            .and_then(|value| value.as_int());
+        let nmatch = match edit_distance {
+            Some(edit_distance) => {
+                aligned_len.saturating_sub((edit_distance as usize).saturating_sub(indels))
+            }
+            None if sequence_matches > 0 => sequence_matches,
+            None => aligned_len,
+        };
+
+        let reverse = flags.is_reverse_complemented();
+        let (query_start, query_end) = if reverse {
+            (trailing_clip, query_length - leading_clip)
+        } else {
+            (leading_clip, query_length - trailing_clip)
+        };
+        let mapq = match record.mapping_quality() {
+            Some(mapping_quality) => u8::from(mapping_quality?),
+            None => MAPQ_MISSING,
+        };
+
+        Ok(Some(BamRecord {
+            record,
+            query_name: name_str(record.name().map(|name| &**name)),
+            query_length,
+            query_start,
+            query_end,
+            strand: if reverse { '-' } else { '+' },
+            target_name: name_str(Some(&**target_name)),
+            target_length: usize::from(reference_sequence.length()),
+            target_start,
+            target_end: target_start + target_span,
+            nmatch,
+            aln_len,
+            mapq,
+            secondary: flags.is_secondary(),
+        }))
+    }
+
+    /// Wraps an unmapped read from an alignment file, following minimap2's `--paf-no-hit` convention.
+    ///
+    /// The target name and strand are `*`, and every alignment field is 0, so [`AlignmentRecord::is_unmapped`] is
+    /// true.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The unmapped alignment record.
+    pub fn unmapped(record: &'a R) -> Self {
+        BamRecord {
+            record,
+            query_name: name_str(record.name().map(|name| &**name)),
+            query_length: record.sequence().len(),
+            query_start: 0,
+            query_end: 0,
+            strand: '*',
+            target_name: "*",
+            target_length: 0,
+            target_start: 0,
+            target_end: 0,
+            nmatch: 0,
+            aln_len: 0,
+            mapq: 0,
+            secondary: false,
+        }
+    }
+
+    /// Convert the alignment into a [`PafRecord`]. Mapped alignments are given a `tp:A:S` tag if secondary, and
+    /// `tp:A:P` otherwise, and keep the minimap2 `de` tag if present, so the identity is the same. Unmapped reads have
+    /// no tags.
+    pub fn to_paf_record(&self) -> PafRecord {
+        let mut tags = Vec::new();
+        if !self.is_unmapped() {
+            tags.push(format!("tp:A:{}", self.alignment_type()));
+            if let Some(divergence) = self.tag("de") {
+                tags.push(format!("de:f:{divergence}"));
+            }
+        }
+        PafRecord {
+            query_name: self.query_name.to_string(),
+            query_length: self.query_length,
+            query_start: self.query_start,
+            query_end: self.query_end,
+            strand: self.strand,
+            target_name: self.target_name.to_string(),
+            target_length: self.target_length,
+            target_start: self.target_start,
+            target_end: self.target_end,
+            nmatch: self.nmatch,
+            aln_len: self.aln_len,
+            mapq: self.mapq,
+            tags,
+        }
+    }
+
+    /// The minimap2 `tp` alignment type of the alignment, `S` if it is secondary and `P` otherwise.
+    fn alignment_type(&self) -> char {
+        if self.secondary {
+            'S'
+        } else {
+            'P'
+        }
+    }
+}
+
+impl<R: sam::alignment::Record + ?Sized> AlignmentRecord for BamRecord<'_, R> {
+    fn query_name(&self) -> &str {
+        self.query_name
+    }
+
+    fn query_length(&self) -> usize {
+        self.query_length
+    }
+
+    fn target_name(&self) -> &str {
+        self.target_name
+    }
+
+    fn target_length(&self) -> usize {
+        self.target_length
+    }
+
+    fn target_start(&self) -> usize {
+        self.target_start
+    }
+
+    fn target_end(&self) -> usize {
+        self.target_end
+    }
+
+    fn strand(&self) -> char {
+        self.strand
+    }
+
+    fn mapping_quality(&self) -> Option<u8> {
+        (self.mapq != MAPQ_MISSING).then_some(self.mapq)
+    }
+
+    fn alignment_block_length(&self) -> usize {
+        self.aln_len
+    }
+
+    /// The identity from the minimap2 `de` tag if present, as for a [`PafRecord`], otherwise the residue matches
+    /// divided by the alignment block length.
+    fn identity(&self) -> Option<f64> {
+        if let Some(paf::Tag::Float(divergence)) = self.tag("de") {
+            return Some(1.0 - divergence);
+        }
+        (self.aln_len > 0).then(|| self.nmatch as f64 / self.aln_len as f64)
+    }
+
+    /// The typed value of a tag of the wrapped record. Integer tags of any width are [`paf::Tag::Int`], and hex
+    /// strings are [`paf::Tag::Str`]. Array tags have no PAF equivalent, so are `None`. A mapped alignment without a
+    /// `tp` tag is given one from its flags, as in [`BamRecord::to_paf_record`].
+    fn tag(&self, key: &str) -> Option<paf::Tag> {
+        let key: [u8; 2] = key.as_bytes().try_into().ok()?;
+        let data = self.record.data();
+        let tag = match data.get(&Tag::from(key)).and_then(Result::ok) {
+            Some(Value::Character(value)) => paf::Tag::Char(char::from(value)),
+            Some(Value::Float(value)) => paf::Tag::Float(f64::from(value)),
+            Some(Value::String(value) | Value::Hex(value)) => paf::Tag::Str(value.to_string()),
+            Some(Value::Array(_)) => return None,
+            Some(value) => paf::Tag::Int(value.as_int()?),
+            None if &key == b"tp" && !self.is_unmapped() => paf::Tag::Char(self.alignment_type()),
+            None => return None,
+        };
+        Some(tag)
+    }
+
+    fn is_unmapped(&self) -> bool {
+        self.target_name == "*" || self.aln_len == 0
+    }
+}
+
+/// Converts an alignment from a BAM (or SAM) file into a [`PafRecord`], see [`BamRecord::new`] and
+/// [`BamRecord::to_paf_record`]. Secondary alignments are given the `tp:A:S` tag, and all others `tp:A:P`.
+///
+/// # Arguments
+///
+/// * `record` - The alignment record.
+/// * `header` - The header of the alignment file, used to look up the name and length of the reference sequence.
+///
+/// # Returns
+///
+/// The [`PafRecord`] for the alignment, or `None` if the read is unmapped.
+///
+/// # Errors
+///
+/// Returns an error if any of the fields of the alignment record cannot be decoded.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// let mut reader = noodles_bam::io::reader::Builder::default().build_from_path("resources/test_human.bam")?;
+/// let header = reader.read_header()?;
+/// for record in reader.records() {
+///     if let Some(paf_record) = paf_record_from_alignment(&record?, &header)? {
+///         println!("{}", paf_record.target_name);
+///     }
+/// }
+/// ```
+pub fn paf_record_from_alignment<R: sam::alignment::Record + ?Sized>(
+    record: &R,
+    header: &sam::Header,
+) -> DynResult<Option<PafRecord>> {
+    Ok(BamRecord::new(record, header)?.map(|alignment| alignment.to_paf_record()))
+}
+
+/// Convert an unmapped read in an alignment file into a [`PafRecord`], following minimap2's `--paf-no-hit` convention.
+///
+/// The target name and strand are `*`, and every alignment column is 0, so [`PafRecord::is_unmapped`] is true.
+///
+/// # Arguments
+///
+/// * `record` - The unmapped alignment record.
+pub fn unmapped_paf_record<R: sam::alignment::Record + ?Sized>(record: &R) -> PafRecord {
+    BamRecord::unmapped(record).to_paf_record()
+}
+
+/// The number of records [`ConditionBamWriters`] holds in memory, across every condition, before sorting them and
+/// spilling them to temporary files to be merged.
+const SORT_CHUNK_RECORDS: usize = 500_000;
+
+/// The `(reference sequence ID, alignment start)` sort key of a record, with unmapped reads without a position
+/// sorted after every placed read.
+type SortKey = (usize, usize);
+
+/// The records of a condition held by [`ConditionBamWriters`] until they can be sorted, with their [`SortKey`].
+type UnsortedRecords<R> = Vec<(SortKey, R)>;
+
+/// Get the [`SortKey`] of a record.
+///
+/// # Errors
+///
+/// Returns an error if the position of the record cannot be decoded.
+fn sort_key<R: sam::alignment::Record + ?Sized>(
+    header: &sam::Header,
+    record: &R,
+) -> DynResult<SortKey> {
+    let reference_sequence_id = record.reference_sequence_id(header).transpose()?;
+    let alignment_start = record.alignment_start().transpose()?;
+    Ok((
+        reference_sequence_id.unwrap_or(usize::MAX),
+        alignment_start.map_or(usize::MAX, usize::from),
+    ))
+}
+
+/// Writes the records of each condition to a coordinate sorted BAM file of their own, `<condition>.bam` in a
+/// directory, see [`demultiplex`].
+///
+/// Every file has the header of the input BAM, with the sort order set to `coordinate`. The reference sequence
+/// dictionary is copied unchanged, so the reference sequence IDs of the records stay valid. If the input is already
+/// sorted by coordinate, records are written as they are read. Otherwise they are held in memory, and once
+/// [`SORT_CHUNK_RECORDS`] are held, each condition's records are sorted and spilled to a temporary BAM file beside its
+/// output, so memory use is bounded. The spilled chunks are merged into the output by
+/// [`ConditionBamWriters::finish`].
+///
+/// The records held in memory are those of the input, either BAM records, or decoded CRAM records.
+struct ConditionBamWriters<R> {
+    /// The directory the BAM files are written to.
+    output_dir: PathBuf,
+    /// The header written to every BAM file.
+    header: sam::Header,
+    /// Whether the input is sorted by coordinate, so records are written as they are read.
+    input_sorted: bool,
+    /// The number of records held in memory before they are spilled, see [`SORT_CHUNK_RECORDS`].
+    chunk_records: usize,
+    /// The open writer of each condition.
+    writers: HashMap<String, bam::io::Writer<bgzf::io::Writer<File>>>,
+    /// The records of each condition held to be sorted, if the input isn't sorted by coordinate.
+    unsorted: HashMap<String, UnsortedRecords<R>>,
+    /// The number of records held in `unsorted`, across every condition.
+    held_records: usize,
+    /// The sorted temporary files spilled for each condition, in the order they were written.
+    chunks: HashMap<String, Vec<PathBuf>>,
+}
+
+impl<R: sam::alignment::Record + Clone> ConditionBamWriters<R> {
+    /// Create the writers, without opening any files until a condition has a record.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_dir` - The directory to write the BAM files to.
+    /// * `header` - The header of the input BAM file.
+    fn new(output_dir: &Path, header: &sam::Header) -> Self {
+        let input_sorted = header
+            .header()
+            .and_then(|header| header.other_fields().get(&SORT_ORDER))
+            .is_some_and(|sort_order| sort_order.as_slice() == COORDINATE);
+        let mut header = header.clone();
+        header
+            .header_mut()
+            .get_or_insert_with(|| Map::<map::Header>::new(Default::default()))
+            .other_fields_mut()
+            .insert(SORT_ORDER, COORDINATE.into());
+        ConditionBamWriters {
+            output_dir: output_dir.to_path_buf(),
+            header,
+            input_sorted,
+            chunk_records: SORT_CHUNK_RECORDS,
+            writers: HashMap::new(),
+            unsorted: HashMap::new(),
+            held_records: 0,
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Create a BAM file and write the header to it, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created, or the header cannot be written.
+    fn create(
+        path: &Path,
+        header: &sam::Header,
+        compression_level: CompressionLevel,
+    ) -> DynResult<bam::io::Writer<bgzf::io::Writer<File>>> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        let mut writer = bam::io::Writer::from(
+            bgzf::io::writer::Builder::default()
+                .set_compression_level(compression_level)
+                .build_from_writer(File::create(path)?),
+        );
+        writer.write_header(header)?;
+        Ok(writer)
+    }
+
+    /// Get the writer of a condition, creating its BAM file and writing the header if this is its first record.
+    ///
+    /// Composite region/barcode keys are written into a directory per region, as for PAF output. Takes the fields it
+    /// needs rather than `self`, so the header can still be borrowed to write records.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created, or the header cannot be written.
+    fn writer<'a>(
+        writers: &'a mut HashMap<String, bam::io::Writer<bgzf::io::Writer<File>>>,
+        output_dir: &Path,
+        header: &sam::Header,
+        condition_name: &str,
+    ) -> DynResult<&'a mut bam::io::Writer<bgzf::io::Writer<File>>> {
+        if !writers.contains_key(condition_name) {
+            let out_path = output_dir.join(format!("{}.bam", condition_name));
+            let writer = Self::create(&out_path, header, CompressionLevel::default())?;
+            writers.insert(condition_name.to_string(), writer);
+        }
+        Ok(writers.get_mut(condition_name).unwrap())
+    }
+
+    /// Write a record to the BAM file of its condition, or hold it to be sorted if the input isn't sorted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the position of the record cannot be decoded, or the record or a spilled chunk cannot be
+    /// written.
+    fn write(&mut self, condition_name: &str, record: &R) -> DynResult<()> {
+        if self.input_sorted {
+            Self::writer(
+                &mut self.writers,
+                &self.output_dir,
+                &self.header,
+                condition_name,
+            )?
+            .write_alignment_record(&self.header, record)?;
+            return Ok(());
+        }
+        let key = sort_key(&self.header, record)?;
+        self.unsorted
+            .entry(condition_name.to_string())
+            .or_default()
+            .push((key, record.clone()));
+        self.held_records += 1;
+        if self.held_records >= self.chunk_records {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Sort the records held for each condition, and write them to a new temporary BAM file beside its output.
+    ///
+    /// The chunks are written with the fastest compression, as they are only read back once to be merged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a chunk cannot be written.
+    fn spill(&mut self) -> DynResult<()> {
+        for (condition_name, records) in self.unsorted.iter_mut() {
+            if records.is_empty() {
+                continue;
+            }
+            records.sort_by_key(|(key, _)| *key);
+            let chunks = self.chunks.entry(condition_name.clone()).or_default();
+            let chunk_path =
+                self.output_dir
+                    .join(format!("{}.bam.sort.{}.tmp", condition_name, chunks.len()));
+            let mut writer = Self::create(&chunk_path, &self.header, CompressionLevel::FAST)?;
+            chunks.push(chunk_path);
+            for (_, record) in records.drain(..) {
+                writer.write_alignment_record(&self.header, &record)?;
+            }
+            writer.try_finish()?;
+        }
+        self.held_records = 0;
+        Ok(())
+    }
+
+    /// Merge the sorted chunks spilled for a condition into its BAM file, then remove them.
+    ///
+    /// The next record of each chunk is held in a min-heap by its [`SortKey`], and the chunk index, so that records
+    /// with the same position are written in the order they were read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a chunk cannot be read or removed, or a record cannot be written.
+    fn merge_chunks(
+        writer: &mut bam::io::Writer<bgzf::io::Writer<File>>,
+        header: &sam::Header,
+        chunk_paths: &[PathBuf],
+    ) -> DynResult<()> {
+        let mut readers = Vec::with_capacity(chunk_paths.len());
+        let mut records = Vec::with_capacity(chunk_paths.len());
+        let mut heap = BinaryHeap::with_capacity(chunk_paths.len());
+        for (index, chunk_path) in chunk_paths.iter().enumerate() {
+            let mut reader = bam::io::reader::Builder.build_from_path(chunk_path)?;
+            reader.read_header()?;
+            let mut record = bam::Record::default();
+            if reader.read_record(&mut record)? != 0 {
+                heap.push(Reverse((sort_key(header, &record)?, index)));
+            }
+            readers.push(reader);
+            records.push(record);
+        }
+        while let Some(Reverse((_, index))) = heap.pop() {
+            writer.write_record(header, &records[index])?;
+            if readers[index].read_record(&mut records[index])? != 0 {
+                heap.push(Reverse((sort_key(header, &records[index])?, index)));
+            }
+        }
+        for chunk_path in chunk_paths {
+            remove_file(chunk_path)?;
+        }
+        Ok(())
+    }
+
+    /// Sort and write any records held in memory, merging in any spilled chunks, then flush and close every BAM file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a record cannot be written, a chunk cannot be merged, or a file cannot be closed.
+    fn finish(mut self) -> DynResult<()> {
+        if !self.chunks.is_empty() {
+            self.spill()?;
+        }
+        for (condition_name, chunk_paths) in std::mem::take(&mut self.chunks) {
+            let writer = Self::writer(
+                &mut self.writers,
+                &self.output_dir,
+                &self.header,
+                &condition_name,
+            )?;
+            Self::merge_chunks(writer, &self.header, &chunk_paths)?;
+        }
+        for (condition_name, mut records) in std::mem::take(&mut self.unsorted) {
+            if records.is_empty() {
+                continue;
+            }
+            records.sort_by_key(|(key, _)| *key);
+            let writer = Self::writer(
+                &mut self.writers,
+                &self.output_dir,
+                &self.header,
+                &condition_name,
+            )?;
+            for (_, record) in &records {
+                writer.write_alignment_record(&self.header, record)?;
+            }
+        }
+        for writer in self.writers.values_mut() {
+            writer.try_finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Check whether a file is a CRAM file, from its magic bytes, so it is decoded as CRAM rather than failing to
+/// decompress as a BAM file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or read.
+fn is_cram(path: impl AsRef<Path>) -> std::io::Result<bool> {
+    let mut magic = Vec::with_capacity(CRAM_MAGIC.len());
+    File::open(path)?
+        .take(CRAM_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    Ok(magic == CRAM_MAGIC)
+}
+
+/// Calculate the MD5 of a reference sequence, as a lowercase hex string, in the same way as the `M5` tag of a SAM
+/// header. Any bytes outside `!` to `~` are skipped, and lowercase bases are uppercased, see § 1.3.2 of the SAM
+/// specification.
+///
+/// # Arguments
+///
+/// * `sequence` - The bases of the reference sequence.
+fn reference_md5(sequence: &[u8]) -> String {
+    let mut hasher = Md5::new();
+    let normalized: Vec<u8> = sequence
+        .iter()
+        .filter(|base| base.is_ascii_graphic())
+        .map(u8::to_ascii_uppercase)
+        .collect();
+    hasher.update(&normalized);
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// The path of the samtools faidx `.fai` index of a reference FASTA, which is the FASTA path with `.fai` appended.
+///
+/// # Arguments
+///
+/// * `reference_path` - The path to the reference FASTA.
+pub(crate) fn reference_fai_path(reference_path: &Path) -> PathBuf {
+    let mut fai_path = reference_path.as_os_str().to_owned();
+    fai_path.push(".fai");
+    PathBuf::from(fai_path)
+}
+
+/// Check that a reference FASTA has every reference sequence of a CRAM header, with the same length, and the same
+/// MD5 as its `M5` tag if it has one.
+///
+/// Every sequence is checked before any record is decoded, so a missing or mismatched reference fails with a clear
+/// error, rather than partway through the file. The names and lengths are checked against the `.fai` index, and the
+/// repository is cleared after each MD5 is calculated, so only one sequence is held in memory at a time.
+///
+/// # Arguments
+///
+/// * `cram_path` - The path to the CRAM file, for the error messages.
+/// * `header` - The header of the CRAM file.
+/// * `reference_path` - The path to the reference FASTA, for the error messages.
+/// * `reference_lengths` - The length of each sequence of the reference, from its `.fai` index.
+/// * `repository` - The reference sequences of the FASTA.
+///
+/// # Errors
+///
+/// Returns an error if a reference sequence is missing from the FASTA, cannot be read, or has a different length or
+/// MD5.
+fn check_reference(
+    cram_path: &Path,
+    header: &sam::Header,
+    reference_path: &Path,
+    reference_lengths: &HashMap<String, usize>,
+    repository: &fasta::Repository,
+) -> DynResult<()> {
+    for (name, reference_sequence) in header.reference_sequences() {
+        let missing = || {
+            format!(
+                "Reference sequence {} of {} is missing from the reference {}",
+                name,
+                cram_path.display(),
+                reference_path.display()
+            )
+        };
+        let reference_length = *reference_lengths
+            .get(&name.to_string())
+            .ok_or_else(missing)?;
+        let length = usize::from(reference_sequence.length());
+        if reference_length != length {
+            return Err(format!(
+                "Reference sequence {} of {} is {} bases long, but {} bases in the reference {}",
+                name,
+                cram_path.display(),
+                length,
+                reference_length,
+                reference_path.display()
+            )
+            .into());
+        }
+        let Some(expected_md5) = reference_sequence.other_fields().get(&MD5_CHECKSUM) else {
+            continue;
+        };
+        let sequence = repository.get(name).transpose()?.ok_or_else(missing)?;
+        let md5 = reference_md5((*sequence).as_ref());
+        repository.clear();
+        if !md5.eq_ignore_ascii_case(&expected_md5.to_string()) {
+            return Err(format!(
+                "Reference sequence {} of {} has an MD5 of {}, which doesn't match the MD5 {} of the reference {}. \
+                 Is it the reference the CRAM file was compressed against?",
+                name,
+                cram_path.display(),
+                expected_md5,
+                md5,
+                reference_path.display()
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Open a CRAM file to be decoded against a reference FASTA, and read its header, checking the reference matches it,
+/// see [`check_reference`].
+///
+/// # Arguments
+///
+/// * `cram_path` - The path to the CRAM file.
+/// * `reference_path` - The path to the reference FASTA the CRAM file was compressed against, which must be indexed
+///   with `samtools faidx`.
+///
+/// # Errors
+///
+/// Returns an error if the reference or its `.fai` index cannot be read, if the CRAM header cannot be read, or if
+/// the reference doesn't match the CRAM header.
+fn open_cram(
+    cram_path: &Path,
+    reference_path: &Path,
+) -> DynResult<(cram::io::Reader<File>, sam::Header)> {
+    let reference_lengths = read_fai(reference_fai_path(reference_path)).map_err(|err| {
+        format!(
+            "The reference {} must be indexed with samtools faidx: {err}",
+            reference_path.display()
+        )
+    })?;
+    let fasta_reader = fasta::io::indexed_reader::Builder::default()
+        .build_from_path(reference_path)
+        .map_err(|err| {
+            format!(
+                "Could not open the reference {}: {err}",
+                reference_path.display()
+            )
+        })?;
+    let repository = fasta::Repository::new(fasta::repository::adapters::IndexedReader::new(
+        fasta_reader,
+    ));
+    let mut reader = cram::io::reader::Builder::default()
+        .set_reference_sequence_repository(repository.clone())
+        .build_from_path(cram_path)?;
+    let header = reader.read_header()?;
+    check_reference(
+        cram_path,
+        &header,
+        reference_path,
+        &reference_lengths,
+        &repository,
+    )?;
+    Ok((reader, header))
+}
+
+/// Demultiplexes the alignments in a BAM or CRAM file by condition, updating the [`Summary`] with each alignment.
+///
+/// Each mapped alignment is wrapped in a [`BamRecord`] with [`BamRecord::new`], and then classified and summarised
+/// through the same [`AlignmentRecord`] trait as a line from a PAF file, see [`crate::paf::Paf::demultiplex`].
+/// Unmapped reads are wrapped with [`BamRecord::unmapped`], and counted as unmapped in their condition. CRAM files
+/// are recognised by their magic bytes, and decoded against `reference_path`, whose sequences are checked against the
+/// CRAM header first.
+///
+/// # Arguments
+///
+/// * `bam_path` - The path to the BAM or CRAM file.
+/// * `_toml` - A reference to the `Conf` struct, which contains configuration settings.
+/// * `sequencing_summary` - The sequencing summary, used to look up the channel and barcode of each read.
+/// * `summary` - An optional mutable reference to a [`Summary`], which is updated with each alignment.
+/// * `skip_secondary` - Whether to skip secondary and supplementary alignments. If they are kept, each read is still
+///   only counted once, by its primary alignment, and the others only update the summaries of their contigs, see
+///   [`Summary::add_secondary_alignment`].
+/// * `strict_seqsum` - Whether reads missing from the sequencing summary are an error, rather than being skipped and
+///   counted in [`Summary::reads_missing_from_seqsum`].
+/// * `read_filter` - The filters alignments must pass, failing alignments are skipped and counted in
+///   [`Summary::filtered_out`], see [`ReadFilter`].
+/// * `output_dir` - An optional directory to write the records of each condition to, as a coordinate sorted BAM file
+///   named `<condition>.bam` with the header of the input BAM. Unless the input is already sorted by coordinate,
+///   records are sorted in chunks that are spilled to temporary files beside the output and merged at the end, so
+///   memory use is bounded.
+/// * `reference_path` - The path to the reference FASTA, indexed with `samtools faidx`, which a CRAM file was
+///   compressed against. Required for CRAM files, and unused for BAM files.
+///
+/// # Errors
+///
+/// Returns an error if the BAM or CRAM file cannot be read, if an output BAM file cannot be written, if it is a CRAM
+/// file and no reference is provided, or the reference doesn't match it, if no sequencing summary is provided, or if
+/// a read cannot be classified, see [`crate::paf::_parse_paf_line`].
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// let conf = Conf::from_file("resources/RAPID_CNS2.toml");
+/// let mut seq_sum = SeqSum::from_file("resources/sequencing_summary_0.txt")?;
+/// let mut summary = Summary::new();
+/// demultiplex("resources/test_human.bam", &conf, Some(&mut seq_sum), Some(&mut summary), true, false, ReadFilter::default(), None, None)?;
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn demultiplex(
+    bam_path: impl AsRef<Path>,
+    _toml: &Conf,
+    sequencing_summary: Option<&mut SeqSum>,
+    mut summary: Option<&mut Summary>,
+    skip_secondary: bool,
+    strict_seqsum: bool,
+    read_filter: ReadFilter,
+    output_dir: Option<&Path>,
+    reference_path: Option<&Path>,
+) -> DynResult<()> {
+    let bam_path = bam_path.as_ref();
+    let seq_sum =
+        sequencing_summary.ok_or("A sequencing summary is required to demultiplex a BAM file")?;
+    if is_cram(bam_path)? {
+        let reference_path = reference_path.ok_or_else(|| {
+            format!(
+                "{} is a CRAM file, which can only be decoded with the reference FASTA it was compressed against. \
+                 Pass the path of the reference, indexed with samtools faidx",
+                bam_path.display()
+            )
+        })?;
+        let (mut reader, header) = open_cram(bam_path, reference_path)?;
+        log::debug!("Demultiplexing CRAM file {}", bam_path.display());
+        demultiplex_records(
+            reader.records(&header),
+            &header,
+            _toml,
+            seq_sum,
+            summary.as_deref_mut(),
+            skip_secondary,
+            strict_seqsum,
+            read_filter,
+            output_dir,
+        )?;
+    } else {
+        let mut reader = bam::io::reader::Builder.build_from_path(bam_path)?;
+        let header = reader.read_header()?;
+        log::debug!("Demultiplexing BAM file {}", bam_path.display());
+        demultiplex_records(
+            reader.records(),
+            &header,
+            _toml,
+            seq_sum,
+            summary.as_deref_mut(),
+            skip_secondary,
+            strict_seqsum,
+            read_filter,
+            output_dir,
+        )?;
+    }
+    if let Some(summary) = summary {
+        summary.finalize();
+    }
+    Ok(())
+}
+
+/// Demultiplex the records of a BAM or CRAM file, as read by [`demultiplex`], without finalizing the [`Summary`].
+///
+/// Generic over the record type, so that BAM records and decoded CRAM records are classified, summarised and written
+/// out in exactly the same way.
+///
+/// # Errors
+///
+/// Returns an error if a record cannot be read or classified, or an output BAM file cannot be written.
+#[allow(clippy::too_many_arguments)]
+fn demultiplex_records<R: sam::alignment::Record + Clone>(
+    records: impl Iterator<Item = std::io::Result<R>>,
+    header: &sam::Header,
+    _toml: &Conf,
+    seq_sum: &mut SeqSum,
+    mut summary: Option<&mut Summary>,
+    skip_secondary: bool,
+    strict_seqsum: bool,
+    read_filter: ReadFilter,
+    output_dir: Option<&Path>,
+) -> DynResult<()> {
+    let mut bam_writers = output_dir.map(|output_dir| ConditionBamWriters::new(output_dir, header));
+    for record in records {
+        let record = record?;
+        let flags = record.flags()?;
+        if skip_secondary && (flags.is_secondary() || flags.is_supplementary()) {
+            continue;
+        }
+        let alignment = if flags.is_unmapped() {
+            BamRecord::unmapped(&record)
+        } else {
+            let Some(alignment) = BamRecord::new(&record, header)? else {
+                continue;
+            };
+            alignment
+        };
+        if !read_filter.passes(&alignment) {
+            skip_filtered_read(&alignment, summary.as_deref_mut());
+            continue;
+        }
+        let read = match classify_record(alignment, _toml, None, Some(seq_sum)) {
+            Ok(read) => read,
+            Err(err) => {
+                skip_missing_read(strict_seqsum, err, summary.as_deref_mut())?;
+                continue;
+            }
+        };
+        log::trace!(
+            "read {} is in condition {}, on target: {}",
+            read.record.query_name(),
+            read.condition_name,
+            read.read_on
+        );
+        if let Some(bam_writers) = bam_writers.as_mut() {
+            bam_writers.write(&read.condition_name, &record)?;
+        }
+        if let Some(summary) = summary.as_deref_mut() {
+            // The read is counted once, by its primary alignment, as PAF files are with `deduplicate_reads`
+            if flags.is_secondary() || flags.is_supplementary() {
+                read.add_secondary_to(summary)?;
+            } else {
+                read.add_to(summary)?;
+            }
+        }
+    }
+    if let Some(bam_writers) = bam_writers {
+        bam_writers.finish()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn get_resource_dir() -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("resources/");
+        path
+    }
+
+    fn get_test_file(file: &str) -> PathBuf {
+        let mut path = get_resource_dir();
+        path.push(file);
+        path
+    }
+
+    /// Parse the records from a SAM formatted string, alongside the header.
+    fn read_sam(sam_text: &str) -> (sam::Header, Vec<sam::Record>) {
+        let mut reader = sam::io::Reader::new(sam_text.as_bytes());
+        let header = reader.read_header().unwrap();
+        let records = reader.records().map(|record| record.unwrap()).collect();
+        (header, records)
+    }
+
+    fn write_bam(bam_path: &Path, header: &sam::Header, records: &[sam::Record]) {
+        let mut writer = bam::io::Writer::new(File::create(bam_path).unwrap());
+        writer.write_header(header).unwrap();
+        for record in records {
+            writer.write_alignment_record(header, record).unwrap();
+        }
+        writer.try_finish().unwrap();
+    }
+
+    #[test]
+    fn test_paf_record_from_alignment() {
+        let (header, records) = read_sam(
+            "@SQ\tSN:chr2\tLN:100000\n\
+             read1\t0\tchr2\t3001\t60\t10S80M5I3D2H\t*\t0\t0\t*\t*\tNM:i:12\n\
+             read2\t16\tchr2\t3001\t60\t10S80M5I3D2H\t*\t0\t0\t*\t*\n\
+             read3\t256\tchr2\t101\t255\t50=2X\t*\t0\t0\t*\t*\n\
+             read4\t4\t*\t0\t0\t*\t*\t0\t0\t*\t*\n",
+        );
+        let paf_record = paf_record_from_alignment(&records[0], &header)
+            .unwrap()
+            .unwrap();
+        assert_eq!(paf_record.query_name, "read1");
+        assert_eq!(paf_record.query_length, 97);
+        assert_eq!(paf_record.query_start, 10);
+        assert_eq!(paf_record.query_end, 95);
+        assert_eq!(paf_record.strand, '+');
+        assert_eq!(paf_record.target_name, "chr2");
+        assert_eq!(paf_record.target_length, 100_000);
+        assert_eq!(paf_record.target_start, 3000);
+        assert_eq!(paf_record.target_end, 3083);
+        // 12 edits, of which 8 are indels
+        assert_eq!(paf_record.nmatch, 76);
+        assert_eq!(paf_record.aln_len, 88);
+        assert_eq!(paf_record.mapq, 60);
+        assert_eq!(paf_record.tag_value("tp"), Some("P"));
+
+        // Reverse strand alignments report query coordinates on the original read
+        let paf_record = paf_record_from_alignment(&records[1], &header)
+            .unwrap()
+            .unwrap();
+        assert_eq!(paf_record.strand, '-');
+        assert_eq!(paf_record.query_start, 2);
+        assert_eq!(paf_record.query_end, 87);
+
+        let paf_record = paf_record_from_alignment(&records[2], &header)
+            .unwrap()
+            .unwrap();
+        assert_eq!(paf_record.nmatch, 50);
+        assert_eq!(paf_record.mapq, MAPQ_MISSING);
+        assert_eq!(paf_record.tag_value("tp"), Some("S"));
+
+        assert!(paf_record_from_alignment(&records[3], &header)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_bam_record_alignment_record() {
+        let (header, records) = read_sam(
+            "@SQ\tSN:chr2\tLN:100000\n\
+             read1\t16\tchr2\t3001\t60\t10S80M5I3D2H\t*\t0\t0\t*\t*\tNM:i:12\tch:i:7\tba:Z:barcode01\tde:f:0.25\n\
+             read2\t256\tchr2\t101\t255\t50=2X\t*\t0\t0\t*\t*\n\
+             read3\t4\t*\t0\t0\t*\t*\t0\t0\tACGTACGT\t*\n",
+        );
+        let alignment = BamRecord::new(&records[0], &header).unwrap().unwrap();
+        let paf_record = alignment.to_paf_record();
+        assert_eq!(alignment.query_name(), "read1");
+        assert_eq!(alignment.query_length(), paf_record.query_length);
+        assert_eq!(alignment.target_name(), "chr2");
+        assert_eq!(alignment.target_length(), 100_000);
+        assert_eq!(alignment.target_start(), paf_record.target_start);
+        assert_eq!(alignment.target_end(), paf_record.target_end);
+        assert_eq!(alignment.strand(), '-');
+        assert_eq!(alignment.mapping_quality(), Some(60));
+        assert_eq!(alignment.alignment_block_length(), 88);
+        assert_eq!(alignment.tag("ch"), Some(paf::Tag::Int(7)));
+        assert_eq!(
+            alignment.tag("ba"),
+            Some(paf::Tag::Str("barcode01".to_string()))
+        );
+        assert_eq!(alignment.tag("tp"), Some(paf::Tag::Char('P')));
+        assert_eq!(alignment.tag("cg"), None);
+        assert_eq!(alignment.identity(), Some(0.75));
+        assert!(!alignment.is_unmapped());
+
+        let alignment = BamRecord::new(&records[1], &header).unwrap().unwrap();
+        assert_eq!(alignment.mapping_quality(), None);
+        assert_eq!(alignment.tag("tp"), Some(paf::Tag::Char('S')));
+        assert_eq!(alignment.identity(), Some(50.0 / 52.0));
+
+        assert!(BamRecord::new(&records[2], &header).unwrap().is_none());
+        let alignment = BamRecord::unmapped(&records[2]);
+        assert!(alignment.is_unmapped());
+        assert_eq!(alignment.tag("tp"), None);
+
+        // A BAM record and the PAF record converted from it update a summary in the same way
+        let alignment = BamRecord::new(&records[0], &header).unwrap().unwrap();
+        let mut from_bam = Summary::new();
+        from_bam
+            .add_record("barcode01", 7, &alignment, true, None)
+            .unwrap();
+        let mut from_paf = Summary::new();
+        from_paf
+            .add_record("barcode01", 7, &alignment.to_paf_record(), true, None)
+            .unwrap();
+        from_bam.finalize();
+        from_paf.finalize();
+        assert!(from_bam.conditions["barcode01"].approx_eq(&from_paf.conditions["barcode01"], 1e-9));
+    }
+
+    #[test]
+    fn test_unmapped_paf_record() {
+        let (_header, records) = read_sam("read4\t4\t*\t0\t0\t*\t*\t0\t0\tACGTACGT\t*\n");
+        let paf_record = unmapped_paf_record(&records[0]);
+        assert_eq!(paf_record.query_name, "read4");
+        assert_eq!(paf_record.query_length, 8);
+        assert_eq!(paf_record.target_name, "*");
+        assert!(paf_record.is_unmapped());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_unmapped_bam() {
+        // Every read in the test BAM is unmapped, and none are in the sequencing summary, so nothing is summarised
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+        let mut summary = Summary::new();
+        demultiplex(
+            get_test_file("test_human.bam"),
+            &conf,
+            Some(&mut seq_sum),
+            Some(&mut summary),
+            true,
+            false,
+            ReadFilter::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(summary.conditions.is_empty());
+        assert_eq!(summary.reads_missing_from_seqsum, 4000);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_output_dir() {
+        // The secondary alignment comes first in the unsorted input, but is on the first reference sequence
+        let (header, records) = read_sam(
+            "@HD\tVN:1.6\tSO:unsorted\n\
+             @SQ\tSN:chr1\tLN:100000\n\
+             @SQ\tSN:chr2\tLN:100000\n\
+             ec44322e-01f0-486e-9708-75619d26daaf\t0\tchr2\t3901\t60\t100M\t*\t0\t0\t*\t*\n\
+             ec44322e-01f0-486e-9708-75619d26daaf\t256\tchr1\t501\t0\t100M\t*\t0\t0\t*\t*\n",
+        );
+        let bam_path = std::env::temp_dir().join("readfish_tools_test_output_dir.bam");
+        write_bam(&bam_path, &header, &records);
+
+        let output_dir = std::env::temp_dir().join("readfish_tools_test_bam_output_dir");
+        let _ = std::fs::remove_dir_all(&output_dir);
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+        let mut summary = Summary::new();
+        demultiplex(
+            &bam_path,
+            &conf,
+            Some(&mut seq_sum),
+            Some(&mut summary),
+            false,
+            false,
+            ReadFilter::default(),
+            Some(&output_dir),
+            None,
+        )
+        .unwrap();
+        let condition_name = summary.conditions.keys().next().unwrap();
+        let mut reader = bam::io::reader::Builder
+            .build_from_path(output_dir.join(format!("{}.bam", condition_name)))
+            .unwrap();
+        let out_header = reader.read_header().unwrap();
+        assert_eq!(
+            out_header.reference_sequences(),
+            header.reference_sequences()
+        );
+        let sort_order = out_header.header().unwrap().other_fields().get(&SORT_ORDER);
+        assert_eq!(sort_order.map(|value| value.as_slice()), Some(COORDINATE));
+        let reference_sequence_ids: Vec<usize> = reader
+            .records()
+            .map(|record| record.unwrap().reference_sequence_id().unwrap().unwrap())
+            .collect();
+        assert_eq!(reference_sequence_ids, [0, 1]);
+        std::fs::remove_dir_all(&output_dir).unwrap();
+        std::fs::remove_file(bam_path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_secondary_alignments() {
+        // One read with a primary and a secondary alignment, which is only counted once, as in a deduplicated PAF
+        let (header, records) = read_sam(
+            "@HD\tVN:1.6\tSO:unsorted\n\
+             @SQ\tSN:chr1\tLN:100000\n\
+             @SQ\tSN:chr2\tLN:100000\n\
+             ec44322e-01f0-486e-9708-75619d26daaf\t0\tchr2\t3901\t60\t100M\t*\t0\t0\t*\t*\n\
+             ec44322e-01f0-486e-9708-75619d26daaf\t256\tchr1\t501\t0\t100M\t*\t0\t0\t*\t*\n",
+        );
+        let bam_path = std::env::temp_dir().join("readfish_tools_test_secondary.bam");
+        write_bam(&bam_path, &header, &records);
+        let paf_path = std::env::temp_dir().join("readfish_tools_test_secondary.paf");
+        let paf_lines: Vec<String> = records
+            .iter()
+            .map(|record| {
+                BamRecord::new(record, &header)
+                    .unwrap()
+                    .unwrap()
+                    .to_paf_record()
+                    .to_string()
+            })
+            .collect();
+        std::fs::write(&paf_path, paf_lines.join("\n")).unwrap();
+
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+        let mut from_bam = Summary::new();
+        demultiplex(
+            &bam_path,
+            &conf,
+            Some(&mut seq_sum),
+            Some(&mut from_bam),
+            false,
+            false,
+            ReadFilter::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        let condition = from_bam.conditions.values().next().unwrap();
+        assert_eq!(condition.total_reads, 1);
+        assert_eq!(condition.total_yield(), 100);
+        // The secondary alignment still counts towards its contig
+        assert_eq!(condition.contigs["chr1"].total_reads(), 1);
+        assert_eq!(condition.contigs["chr2"].total_reads(), 1);
+
+        let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+        let mut from_paf = Summary::new();
+        let mut paf = crate::paf::Paf::new(&paf_path);
+        paf.set_deduplicate_reads(true);
+        paf.demultiplex(
+            &mut conf,
+            Some(&mut seq_sum),
+            Some(&mut from_paf),
+            None,
+            None,
+        )
+        .unwrap();
+        from_bam.finalize();
+        from_paf.finalize();
+        for (condition_name, condition) in &from_bam.conditions {
+            assert!(condition.approx_eq(&from_paf.conditions[condition_name], 1e-9));
+        }
+        std::fs::remove_file(bam_path).unwrap();
+        std::fs::remove_file(paf_path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_condition_bam_writers_spill() {
+        // Chunks of two records are spilled and merged back in coordinate order, with unmapped reads last
+        let (header, records) = read_sam(
+            "@HD\tVN:1.6\tSO:unsorted\n\
+             @SQ\tSN:chr1\tLN:100000\n\
+             @SQ\tSN:chr2\tLN:100000\n\
+             read1\t0\tchr2\t3901\t60\t100M\t*\t0\t0\t*\t*\n\
+             read2\t4\t*\t0\t0\t*\t*\t0\t0\t*\t*\n\
+             read3\t0\tchr1\t701\t60\t100M\t*\t0\t0\t*\t*\n\
+             read4\t0\tchr2\t101\t60\t100M\t*\t0\t0\t*\t*\n\
+             read5\t0\tchr1\t501\t60\t100M\t*\t0\t0\t*\t*\n\
+             read6\t0\tchr1\t201\t60\t100M\t*\t0\t0\t*\t*\n",
+        );
+        let bam_path = std::env::temp_dir().join("readfish_tools_test_spill.bam");
+        write_bam(&bam_path, &header, &records);
+
+        let output_dir = std::env::temp_dir().join("readfish_tools_test_bam_spill");
+        let _ = std::fs::remove_dir_all(&output_dir);
+        let mut bam_writers = ConditionBamWriters::new(&output_dir, &header);
+        bam_writers.chunk_records = 2;
+        let mut reader = bam::io::reader::Builder.build_from_path(&bam_path).unwrap();
+        reader.read_header().unwrap();
+        for (i, record) in reader.records().enumerate() {
+            let condition_name = if i % 3 == 2 {
+                "other"
+            } else {
+                "region/barcode01"
+            };
+            bam_writers.write(condition_name, &record.unwrap()).unwrap();
+        }
+        bam_writers.finish().unwrap();
+
+        let read_names = |condition_name: &str| -> Vec<String> {
+            let mut reader = bam::io::reader::Builder
+                .build_from_path(output_dir.join(format!("{}.bam", condition_name)))
+                .unwrap();
+            reader.read_header().unwrap();
+            reader
+                .records()
+                .map(|record| record.unwrap().name().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(
+            read_names("region/barcode01"),
+            ["read5", "read4", "read1", "read2"]
+        );
+        assert_eq!(read_names("other"), ["read6", "read3"]);
+        // Only the output files are left behind
+        let mut region_files: Vec<_> = std::fs::read_dir(output_dir.join("region"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        region_files.sort();
+        assert_eq!(region_files, ["barcode01.bam"]);
+        let mut files: Vec<_> = std::fs::read_dir(&output_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["other.bam", "region"]);
+        std::fs::remove_dir_all(&output_dir).unwrap();
+        std::fs::remove_file(bam_path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_cram() {
+        // The CRAM test file was written from test_alignments.sam, against test_reference.fa, so is summarised and
+        // written out the same as a BAM of the same alignments
+        let (header, records) =
+            read_sam(&std::fs::read_to_string(get_test_file("test_alignments.sam")).unwrap());
+        let bam_path = std::env::temp_dir().join("readfish_tools_test_cram_alignments.bam");
+        write_bam(&bam_path, &header, &records);
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let output_dir = std::env::temp_dir().join("readfish_tools_test_cram_output_dir");
+        let _ = std::fs::remove_dir_all(&output_dir);
+        let mut summaries = Vec::new();
+        for (path, output_dir) in [
+            (
+                get_test_file("test_alignments.cram"),
+                Some(output_dir.as_path()),
+            ),
+            (bam_path.clone(), None),
+        ] {
+            let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+            let mut summary = Summary::new();
+            demultiplex(
+                path,
+                &conf,
+                Some(&mut seq_sum),
+                Some(&mut summary),
+                true,
+                false,
+                ReadFilter::default(),
+                output_dir,
+                Some(&get_test_file("test_reference.fa")),
+            )
+            .unwrap();
+            summaries.push(summary);
+        }
+        let (from_cram, from_bam) = (&summaries[0], &summaries[1]);
+        assert_eq!(
+            from_cram
+                .conditions
+                .values()
+                .map(|condition| condition.total_reads)
+                .sum::<usize>(),
+            4
+        );
+        assert_eq!(from_cram.conditions.len(), from_bam.conditions.len());
+        for (condition_name, condition) in &from_cram.conditions {
+            assert!(condition.approx_eq(&from_bam.conditions[condition_name], 1e-9));
+        }
+        // The decoded CRAM records are written out as BAM
+        let written: usize = from_cram
+            .conditions
+            .keys()
+            .map(|condition_name| {
+                let mut reader = bam::io::reader::Builder
+                    .build_from_path(output_dir.join(format!("{}.bam", condition_name)))
+                    .unwrap();
+                let out_header = reader.read_header().unwrap();
+                assert_eq!(
+                    out_header.reference_sequences(),
+                    header.reference_sequences()
+                );
+                reader.records().count()
+            })
+            .sum();
+        assert_eq!(written, 4);
+        std::fs::remove_dir_all(&output_dir).unwrap();
+        std::fs::remove_file(bam_path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_cram_reference_errors() {
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let demultiplex_cram = |reference_path: Option<&Path>| {
+            let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+            demultiplex(
+                get_test_file("test_alignments.cram"),
+                &conf,
+                Some(&mut seq_sum),
+                None,
+                true,
+                false,
+                ReadFilter::default(),
+                None,
+                reference_path,
+            )
+            .unwrap_err()
+            .to_string()
+        };
+        assert!(demultiplex_cram(None).contains("is a CRAM file"));
+        assert!(is_cram(get_test_file("test_alignments.cram")).unwrap());
+        assert!(!is_cram(get_test_file("test_human.bam")).unwrap());
+
+        // Write a reference of one line per sequence, with its .fai
+        let write_reference = |file_name: &str, sequences: &[(&str, &str)]| -> PathBuf {
+            let reference_path = std::env::temp_dir().join(file_name);
+            let mut fasta = String::new();
+            let mut fai = String::new();
+            for (name, sequence) in sequences {
+                fasta.push_str(&format!(">{}\n", name));
+                fai.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{}\n",
+                    name,
+                    sequence.len(),
+                    fasta.len(),
+                    sequence.len(),
+                    sequence.len() + 1
+                ));
+                fasta.push_str(&format!("{}\n", sequence));
+            }
+            std::fs::write(&reference_path, fasta).unwrap();
+            std::fs::write(format!("{}.fai", reference_path.display()), fai).unwrap();
+            reference_path
+        };
+        let reference = std::fs::read_to_string(get_test_file("test_reference.fa")).unwrap();
+        let sequences: Vec<(&str, String)> = reference
+            .split('>')
+            .skip(1)
+            .map(|record| {
+                let (name, sequence) = record.split_once('\n').unwrap();
+                (name, sequence.replace('\n', ""))
+            })
+            .collect();
+        let (chr1, chr2) = (sequences[0].1.as_str(), sequences[1].1.as_str());
+        // Lowercase bases are uppercased for the MD5, so a soft-masked reference still matches
+        assert_eq!(
+            reference_md5(chr1.to_ascii_lowercase().as_bytes()),
+            reference_md5(chr1.as_bytes())
+        );
+
+        let missing_contig = write_reference("readfish_tools_test_missing.fa", &[("chr1", chr1)]);
+        let err = demultiplex_cram(Some(&missing_contig));
+        assert!(err.contains("Reference sequence chr2 of"));
+        assert!(err.contains("is missing from the reference"));
+
+        let mut mutated = chr2.to_string();
+        mutated.replace_range(0..1, if chr2.starts_with('A') { "C" } else { "A" });
+        let mismatched = write_reference(
+            "readfish_tools_test_mismatched.fa",
+            &[("chr1", chr1), ("chr2", &mutated)],
+        );
+        assert!(demultiplex_cram(Some(&mismatched)).contains("doesn't match the MD5"));
+
+        let truncated = write_reference(
+            "readfish_tools_test_truncated.fa",
+            &[("chr1", chr1), ("chr2", &chr2[..4000])],
+        );
+        assert!(demultiplex_cram(Some(&truncated)).contains("is 5000 bases long, but 4000 bases"));
+
+        let unindexed = std::env::temp_dir().join("readfish_tools_test_unindexed.fa");
+        std::fs::copy(get_test_file("test_reference.fa"), &unindexed).unwrap();
+        assert!(demultiplex_cram(Some(&unindexed)).contains("samtools faidx"));
+
+        for reference_path in [missing_contig, mismatched, truncated] {
+            std::fs::remove_file(format!("{}.fai", reference_path.display())).unwrap();
+            std::fs::remove_file(reference_path).unwrap();
+        }
+        std::fs::remove_file(unindexed).unwrap();
+    }
+
+    #[test]
+    fn test_demultiplex_requires_sequencing_summary() {
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let err = demultiplex(
+            get_test_file("test_human.bam"),
+            &conf,
+            None,
+            None,
+            true,
+            false,
+            ReadFilter::default(),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("sequencing summary"));
+    }
+}
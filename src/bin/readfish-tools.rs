@@ -0,0 +1,361 @@
+//! Command line interface to readfish-tools, to demultiplex and summarise readfish runs from shell pipelines,
+//! without Python.
+//!
+//! ```bash
+//! readfish-tools demultiplex --toml readfish.toml --paf reads.paf --seq-sum sequencing_summary.txt --format json
+//! readfish-tools demultiplex --toml readfish.toml --seq-sum sequencing_summary.txt
+//! readfish-tools demultiplex --toml readfish.toml --validate
+//! readfish-tools flowcell --size 512 --split 4 --format toml
+//! ```
+
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use itertools::Itertools;
+use readfish_tools::{
+    _demultiplex_paf, _summarise_seqsum, init_logging,
+    nanopore::{generate_flowcell, regions_toml, render_flowcell},
+    paf::AssignmentsFormat,
+    readfish::Conf,
+    DemultiplexOptions, RenderOptions, Summary,
+};
+use std::{
+    error::Error,
+    fs::File,
+    io::{stdout, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+/// Build the command line parser, with `demultiplex` and `flowcell` subcommands.
+fn cli() -> Command {
+    Command::new("readfish-tools")
+        .about("Tools for analysing the output of readfish runs")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("demultiplex")
+                .about("Demultiplex PAF records by condition, and summarise them")
+                .arg(
+                    Arg::new("toml")
+                        .long("toml")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf))
+                        .help("The readfish TOML configuration of the run"),
+                )
+                .arg(
+                    Arg::new("paf")
+                        .long("paf")
+                        .required_unless_present_any(["validate", "seq-sum"])
+                        .action(ArgAction::Append)
+                        .value_parser(value_parser!(PathBuf))
+                        .help("The PAF file to demultiplex, or - for stdin. Can be given more than once. If not given, the run is summarised from --seq-sum alone"),
+                )
+                .arg(
+                    Arg::new("seq-sum")
+                        .long("seq-sum")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("The sequencing summary of the run. If not given, the ch and ba PAF tags are used"),
+                )
+                .arg(
+                    Arg::new("out-dir")
+                        .long("out-dir")
+                        .requires("paf")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("A directory to write the PAF records of each condition to"),
+                )
+                .arg(
+                    Arg::new("assignments")
+                        .long("assignments")
+                        .requires("paf")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("A TSV file to write the condition, on-target flag and contig of every read to, or NDJSON if it ends in .ndjson"),
+                )
+                .arg(
+                    Arg::new("strict-contig-lengths")
+                        .long("strict-contig-lengths")
+                        .requires("paf")
+                        .action(ArgAction::SetTrue)
+                        .help("Exit with an error if a contig length disagrees between alignments or with the reference, rather than counting the mismatches"),
+                )
+                .arg(
+                    Arg::new("keep-read-lengths")
+                        .long("keep-read-lengths")
+                        .requires("paf")
+                        .action(ArgAction::SetTrue)
+                        .help("Keep the read length of every alignment to show the N50 of each contig, which takes memory per alignment"),
+                )
+                .arg(
+                    Arg::new("sqlite")
+                        .long("sqlite")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("A SQLite database to append the run to, created if it doesn't exist, along with the --assignments if they are a TSV"),
+                )
+                .arg(
+                    Arg::new("sql")
+                        .long("sql")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("A file to write a SQL script to, which loads the summary into a SQLite database with `sqlite3 runs.db < FILE`, rather than writing the database with --sqlite"),
+                )
+                .arg(
+                    Arg::new("run-name")
+                        .long("run-name")
+                        .help("The name the run is stored under by --sqlite and --sql, replacing an earlier run of the same name. Defaults to the name of the first PAF file"),
+                )
+                .arg(
+                    Arg::new("genome-size")
+                        .long("genome-size")
+                        .value_parser(value_parser!(usize))
+                        .help("The approximate size of the genome in base pairs, to show the estimated coverage of each condition"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .default_value("table")
+                        .value_parser(["table", "json", "csv"])
+                        .help("The format to print the summary to stdout in"),
+                )
+                .arg(
+                    Arg::new("max-contigs")
+                        .long("max-contigs")
+                        .value_parser(value_parser!(usize))
+                        .help("Only show the contigs with the highest yield in the contig table of each condition"),
+                )
+                .arg(
+                    Arg::new("targets")
+                        .long("targets")
+                        .requires("paf")
+                        .action(ArgAction::SetTrue)
+                        .help("Show a table of the on-target reads and yield of every target interval"),
+                )
+                .arg(
+                    Arg::new("validate")
+                        .long("validate")
+                        .action(ArgAction::SetTrue)
+                        .help("Only check the TOML for mistakes, printing any errors and warnings, rather than demultiplexing"),
+                )
+                .arg(
+                    Arg::new("verbose")
+                        .long("verbose")
+                        .short('v')
+                        .action(ArgAction::SetTrue)
+                        .help("Log debug messages to stderr"),
+                ),
+        )
+        .subcommand(
+            Command::new("flowcell")
+                .about("Print how a flowcell is split into sections, to design the regions of a readfish TOML")
+                .arg(
+                    Arg::new("size")
+                        .long("size")
+                        .default_value("512")
+                        .value_parser(value_parser!(usize))
+                        .help("The number of channels on the flowcell, 126 (Flongle), 512 (MinION) or 3000 (PromethION)"),
+                )
+                .arg(
+                    Arg::new("split")
+                        .long("split")
+                        .default_value("2")
+                        .value_parser(value_parser!(usize))
+                        .help("The number of sections to split the flowcell into"),
+                )
+                .arg(
+                    Arg::new("axis")
+                        .long("axis")
+                        .default_value("1")
+                        .value_parser(value_parser!(u8).range(0..=1))
+                        .help("The axis to split the flowcell along, 0 for rows or 1 for columns"),
+                )
+                .arg(
+                    Arg::new("odd-even")
+                        .long("odd-even")
+                        .action(ArgAction::SetTrue)
+                        .help("Split the flowcell into odd and even channels, ignoring --split and --axis"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .default_value("grid")
+                        .value_parser(["grid", "channels", "toml"])
+                        .help("Print the sections as a grid, as lists of channels, or as readfish TOML [[regions]]"),
+                )
+                .arg(
+                    Arg::new("names")
+                        .long("names")
+                        .value_delimiter(',')
+                        .help("Comma separated names of the regions in the TOML, one per section. Defaults to region_1, region_2, ..."),
+                ),
+        )
+}
+
+/// Demultiplex the PAF files given to the `demultiplex` subcommand, and print the summary in the chosen format.
+/// With `--validate`, only the TOML is checked, see [`validate`]. Without a PAF file, the run is summarised from the
+/// sequencing summary alone, see [`_summarise_seqsum`].
+///
+/// # Errors
+///
+/// Returns an error if demultiplexing fails, or if the summary cannot be written to stdout.
+fn demultiplex(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    init_logging(args.get_flag("verbose"));
+    if args.get_flag("validate") {
+        return validate(args.get_one::<PathBuf>("toml").unwrap());
+    }
+    let paf_paths: Vec<&PathBuf> = args
+        .get_many::<PathBuf>("paf")
+        .unwrap_or_default()
+        .collect();
+    let toml_path = args.get_one::<PathBuf>("toml").unwrap();
+    let seq_sum_path = args.get_one::<PathBuf>("seq-sum");
+    let mut summary = match (paf_paths.is_empty(), seq_sum_path) {
+        (true, Some(seq_sum_path)) => {
+            _summarise_seqsum(toml_path, seq_sum_path, false, None::<PathBuf>)?
+        }
+        _ => _demultiplex_paf(
+            toml_path,
+            &paf_paths,
+            seq_sum_path,
+            &DemultiplexOptions {
+                strict_contig_lengths: args.get_flag("strict-contig-lengths"),
+                keep_read_lengths: args.get_flag("keep-read-lengths"),
+                output_dir: args.get_one::<PathBuf>("out-dir").cloned(),
+                assignments_out: args.get_one::<PathBuf>("assignments").cloned(),
+                ..Default::default()
+            },
+        )?,
+    };
+    if let Some(&genome_size) = args.get_one::<usize>("genome-size") {
+        summary.set_genome_size(genome_size);
+    }
+    let run_name = || match args.get_one::<String>("run-name") {
+        Some(run_name) => run_name.clone(),
+        None => paf_run_name(paf_paths.first().copied().or(seq_sum_path).unwrap()),
+    };
+    if let Some(sqlite_path) = args.get_one::<PathBuf>("sqlite") {
+        // NDJSON assignments aren't loaded, as only the TSV can be read back
+        let assignments = args.get_one::<PathBuf>("assignments").filter(|path| {
+            AssignmentsFormat::from_path(path).is_ok_and(|format| format == AssignmentsFormat::Tsv)
+        });
+        summary.write_sqlite(sqlite_path, &run_name(), assignments)?;
+    }
+    if let Some(sql_path) = args.get_one::<PathBuf>("sql") {
+        summary.write_sql(BufWriter::new(File::create(sql_path)?), &run_name())?;
+    }
+    let format = args.get_one::<String>("format").unwrap();
+    let options = RenderOptions {
+        max_contigs: args.get_one::<usize>("max-contigs").copied(),
+        show_targets: args.get_flag("targets"),
+        ..RenderOptions::default()
+    };
+    write_summary(&summary, format, &options, &mut stdout().lock())
+}
+
+/// The default run name of a PAF file in the `--sqlite` and `--sql` output, its file name without the `.paf` or `.paf.gz` extension.
+/// Runs summarised from the sequencing summary alone are named after its file name.
+fn paf_run_name(paf_path: &Path) -> String {
+    let file_name = paf_path
+        .file_name()
+        .map_or_else(|| paf_path.to_string_lossy(), |name| name.to_string_lossy());
+    let file_name = file_name.strip_suffix(".gz").unwrap_or(&file_name);
+    file_name
+        .strip_suffix(".paf")
+        .unwrap_or(file_name)
+        .to_string()
+}
+
+/// Check the readfish TOML for mistakes with [`Conf::validate`], printing each error and warning.
+///
+/// # Errors
+///
+/// Returns an error if the TOML cannot be parsed, or if it has any errors. Warnings alone are not an error.
+fn validate(toml_path: &PathBuf) -> Result<(), Box<dyn Error>> {
+    let validation = Conf::from_file(toml_path)?.validate();
+    print!("{}", validation);
+    if !validation.is_valid() {
+        return Err(format!(
+            "{} has {} error(s)",
+            toml_path.display(),
+            validation.errors.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Write the summary to `w` as rendered tables, JSON or a CSV row per condition. The render options only apply to
+/// tables.
+///
+/// # Errors
+///
+/// Returns an error if the summary cannot be written.
+fn write_summary(
+    summary: &Summary,
+    format: &str,
+    options: &RenderOptions,
+    w: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        "json" => {
+            summary.to_json_writer(&mut *w)?;
+            writeln!(w)?;
+        }
+        "csv" => summary.write_csv(&mut *w)?,
+        _ => summary.render_with_options(w, options)?,
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Print the flowcell split given to the `flowcell` subcommand, as a grid, as the channels of each section, or as a
+/// readfish TOML `[[regions]]` entry with the `name` and `channels` of each section, see [`regions_toml`].
+///
+/// # Errors
+///
+/// Returns an error if the flowcell size is not recognised or can't be split evenly, see [`generate_flowcell`], or if
+/// the number of region names differs from the number of sections.
+fn flowcell(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let size = *args.get_one::<usize>("size").unwrap();
+    let split = *args.get_one::<usize>("split").unwrap();
+    let axis = usize::from(*args.get_one::<u8>("axis").unwrap());
+    let odd_even = args.get_flag("odd-even");
+    let format = args.get_one::<String>("format").unwrap();
+    let mut w = stdout().lock();
+    if format == "grid" {
+        writeln!(w, "{}", render_flowcell(size, split, axis, odd_even)?)?;
+        return Ok(());
+    }
+    let sections = generate_flowcell(size, split, axis, odd_even)?;
+    if format == "toml" {
+        let names: Vec<String> = match args.get_many::<String>("names") {
+            Some(names) => names.cloned().collect(),
+            None => (1..=sections.len())
+                .map(|section| format!("region_{}", section))
+                .collect(),
+        };
+        write!(w, "{}", regions_toml(size, &sections, &names)?)?;
+        return Ok(());
+    }
+    for (section, channels) in sections.into_iter().enumerate() {
+        // Padding positions in the flowcell array that have no channel are 0
+        let channels = channels
+            .into_iter()
+            .filter(|channel| *channel != 0)
+            .sorted()
+            .join(", ");
+        writeln!(w, "Section {}: {}", section + 1, channels)?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let matches = cli().get_matches();
+    let result = match matches.subcommand() {
+        Some(("demultiplex", args)) => demultiplex(args),
+        Some(("flowcell", args)) => flowcell(args),
+        _ => unreachable!("a subcommand is required"),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -2,10 +2,13 @@
 //! Hashmap literals of channel number => row and column on flowcell array.
 //!
 //! Initialised once at runtime.
+//!
+//! Custom flowcell layouts can be registered at runtime in `CUSTOM_CHANNELS`.
 
 use lazy_static::lazy_static;
 use maplit::hashmap;
 use std::collections::HashMap;
+use std::sync::RwLock;
 
 lazy_static! {
     pub static ref MINION_CHANNELS: HashMap<usize, (usize, usize)> = {
@@ -653,3 +656,12 @@ lazy_static! {
         126 => (11, 0),}
     };
 }
+
+/// Channel number => column and row on flowcell array.
+pub type ChannelMap = HashMap<usize, (usize, usize)>;
+
+lazy_static! {
+    /// Registered custom flowcell layouts, keyed by flowcell size.
+    pub static ref CUSTOM_CHANNELS: RwLock<HashMap<usize, ChannelMap>> =
+        RwLock::new(HashMap::new());
+}
@@ -13,54 +13,89 @@
 //! The crate is split into modules handling separate functionalities.
 //!
 //! ## Modules
+//! bam - BAM related functionality.
 //! nanopore - Flowcell related functionality.
 //! channels - Channel Hashmaps for MinION and Flongle.
 //! paf - PAF related functionality.
 //! readfish - Readfish TOML related functionality.
 //! readfish_io - Custom functions and wrappers related IO functionality.
 //! sequencing_summary - Sequencing summary related functionality.
+pub mod bam;
 mod channels;
 pub mod nanopore;
 pub mod paf;
 pub mod readfish;
 mod readfish_io;
-mod sequencing_summary;
+pub mod sequencing_summary;
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{hash_map::Entry, HashMap},
     fmt,
-    ops::Deref,
+    io::{IsTerminal, Write},
+    ops::{Deref, Range},
     path::{Path, PathBuf},
 };
 
 use itertools::Itertools;
-use nanopore::{format_bases, running_mean};
+use log::LevelFilter;
+use nanopore::{
+    format_bases, get_coords, histogram, intersection_length, median, merge_means_f64,
+    merged_interval_length, n50, quartiles, running_mean_f64, standard_deviation,
+};
 use num_format::{Locale, ToFormattedString};
 #[cfg(feature = "pyo3_support")]
-use paf::{Metadata, _parse_paf_line};
-use paf::{Paf, PafRecord};
-use prettytable::{color, row, Attr, Cell, Row, Table};
+use paf::{_parse_paf_line, Metadata};
+use paf::{AlignmentRecord, Paf, ReadFilter};
+use prettytable::{color, Attr, Cell, Row, Table};
 #[cfg(feature = "pyo3_support")]
-use pyo3::{prelude::*, types::PyIterator};
-use readfish::Conf;
+use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyIterator};
+use readfish::{merge_intervals, overlaps_intervals, Conf};
 use readfish_io::DynResult;
-use sequencing_summary::SeqSum;
+use sequencing_summary::{EndReason, SeqSum, SeqSumRecord};
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
 /// Represents the mean read lengths for on-target, off-target, and total reads.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct MeanReadLengths {
     /// The mean read length of on-target reads.
     pub on_target: isize,
     /// Number of on target reads analysed
     on_target_count: isize,
+    /// Summed length of on target reads analysed
+    on_target_sum: isize,
     /// The mean read length of off-target reads.
     pub off_target: isize,
     /// Number of off target reads analysed
     off_target_count: isize,
+    /// Summed length of off target reads analysed
+    off_target_sum: isize,
     /// The mean read length of all reads (on-target + off-target).
     pub total: isize,
     /// Number of reads analysed
     total_count: isize,
+    /// Summed length of all reads analysed
+    total_sum: isize,
+}
+
+/// Add `count` values summing to `sum` to a mean, recalculating the mean from the running totals.
+///
+/// Means are always derived from the totals, rather than updated incrementally, so that the integer
+/// division doesn't drift, and two means can be merged exactly. The mean is truncated to whole bases.
+fn add_to_mean(
+    mean: &mut isize,
+    running_count: &mut isize,
+    running_sum: &mut isize,
+    count: isize,
+    sum: isize,
+) {
+    *running_count += count;
+    *running_sum += sum;
+    if *running_count > 0 {
+        *mean = *running_sum / *running_count;
+    }
 }
 
 impl MeanReadLengths {
@@ -69,19 +104,22 @@ impl MeanReadLengths {
         MeanReadLengths {
             on_target: 0,
             on_target_count: 0,
+            on_target_sum: 0,
             off_target: 0,
             off_target_count: 0,
+            off_target_sum: 0,
             total: 0,
             total_count: 0,
+            total_sum: 0,
         }
     }
 
     /// Updates the mean read lengths for on-target, off-target, and total reads based on the provided
-    /// PAF record and whether the read is on-target or off-target.
+    /// alignment and whether the read is on-target or off-target.
     ///
     /// # Arguments
     ///
-    /// * `paf` - A reference to the [`PafRecord`] representing the alignment record for a read.
+    /// * `record` - The alignment of a read, such as a [`paf::PafRecord`].
     /// * `on_target` - A boolean indicating whether the read is on-target (true) or off-target (false).
     ///
     /// # Example
@@ -92,24 +130,60 @@ impl MeanReadLengths {
     /// let paf_record = PafRecord::new("read123 200 0 200 + contig123 300 0 300 200 200 50 ch=1".split(" ").collect()).unwrap();
     /// mean_lengths.update_lengths(&paf_record, true);
     /// ```
-    pub fn update_lengths(&mut self, paf: &PafRecord, on_target: bool) {
+    pub fn update_lengths(&mut self, record: &impl AlignmentRecord, on_target: bool) {
+        let query_length = record.query_length() as isize;
         if on_target {
-            running_mean(
+            add_to_mean(
                 &mut self.on_target,
                 &mut self.on_target_count,
-                &mut (paf.query_length as isize),
+                &mut self.on_target_sum,
+                1,
+                query_length,
             );
         } else {
-            running_mean(
+            add_to_mean(
                 &mut self.off_target,
                 &mut self.off_target_count,
-                &mut (paf.query_length as isize),
+                &mut self.off_target_sum,
+                1,
+                query_length,
             );
         }
-        running_mean(
+        add_to_mean(
+            &mut self.total,
+            &mut self.total_count,
+            &mut self.total_sum,
+            1,
+            query_length,
+        );
+    }
+
+    /// Merge the read lengths of another `MeanReadLengths` into this one, recalculating the means from the combined totals.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The mean read lengths to merge in.
+    pub fn merge(&mut self, other: &MeanReadLengths) {
+        add_to_mean(
+            &mut self.on_target,
+            &mut self.on_target_count,
+            &mut self.on_target_sum,
+            other.on_target_count,
+            other.on_target_sum,
+        );
+        add_to_mean(
+            &mut self.off_target,
+            &mut self.off_target_count,
+            &mut self.off_target_sum,
+            other.off_target_count,
+            other.off_target_sum,
+        );
+        add_to_mean(
             &mut self.total,
             &mut self.total_count,
-            &mut (paf.query_length as isize),
+            &mut self.total_sum,
+            other.total_count,
+            other.total_sum,
         );
     }
 }
@@ -120,9 +194,21 @@ impl Default for MeanReadLengths {
     }
 }
 
+impl Default for ContigSummary {
+    /// An empty contig summary with no name and a length of 0, as [`ContigSummary::new`].
+    fn default() -> Self {
+        Self::new(String::new(), 0)
+    }
+}
+
 /// Represents a summary of a contig or sequence from a sequencing experiment.
 /// It includes various metrics related to the contig's characteristics and read mapping.
-#[derive(Debug)]
+///
+/// Equality compares every field exactly, including the floating point coverage and quality, so summaries of the same
+/// alignments added in a different order can differ by rounding. Use [`ContigSummary::approx_eq`] to compare them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct ContigSummary {
     /// The name or identifier of the contig.
     pub name: String,
@@ -134,8 +220,9 @@ pub struct ContigSummary {
     pub mean_read_quality: f64,
     /// Yield of mapped reads
     pub total_bases: usize,
-    /// The N50 metric for the contig, representing the length at which the cumulative
-    /// sum of contig lengths reaches half of the total assembly length.
+    /// The read N50 of the alignments to the contig, the length at which the cumulative sum of the read lengths,
+    /// longest first, reaches half of the contig's yield. Only populated once the owning [`ConditionSummary`] has been
+    /// finalized, and left at 0 unless it keeps the read lengths, see [`ConditionSummary::set_keep_read_lengths`].
     pub n50: usize,
     /// The count of reads that are mapped on the target region (on-target reads).
     pub on_target_read_count: usize,
@@ -145,6 +232,43 @@ pub struct ContigSummary {
     pub yield_on_target: usize,
     /// The total yield (base pairs) of off-target reads for this contig.
     pub yield_off_target: usize,
+    /// The mean depth of coverage, the total aligned bases divided by the contig length.
+    pub mean_coverage: f64,
+    /// The fraction of contig positions covered by at least one alignment.
+    /// Only populated once the owning [`ConditionSummary`] has been finalized.
+    pub breadth_of_coverage: f64,
+    /// The fraction of the targets on the contig covered by at least one on-target alignment, or of the whole contig
+    /// if its targets haven't been set with [`ContigSummary::set_targets`]. Only populated once the owning
+    /// [`ConditionSummary`] has been finalized.
+    pub on_target_breadth: f64,
+    /// The total number of contig bases spanned by alignments.
+    aligned_bases: usize,
+    /// The `(target_start, target_end)` spans of the alignments to the contig, merged whenever they have doubled in
+    /// number, see [`compact_intervals`], so they grow with the regions covered rather than with every alignment.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    aligned_intervals: Vec<(usize, usize)>,
+    /// The number of `aligned_intervals` when they were last merged.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    compacted_aligned_intervals: usize,
+    /// The `(target_start, target_end)` spans of the on-target alignments to the contig, merged as they accumulate,
+    /// as the `aligned_intervals` are, and when finalized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    on_target_intervals: Vec<(usize, usize)>,
+    /// The number of `on_target_intervals` when they were last merged.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    compacted_on_target_intervals: usize,
+    /// The `(read_count, yield)` of each merged target of the condition on the contig, in the order of
+    /// [`Conf::get_contig_targets`], counted as on-target alignments are added, see [`ContigSummary::add_target_hits`].
+    /// Targets after the last one with reads are left out.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    target_counts: Vec<(usize, usize)>,
+    /// The merged `(start, stop)` targets of the condition on the contig, see [`ContigSummary::set_targets`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    targets: Vec<(usize, usize)>,
+    /// Lengths of the reads of every alignment to the contig, retained to calculate the N50. Empty unless the owning
+    /// [`ConditionSummary`] keeps read lengths, as this grows with every alignment.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    read_lengths: Vec<usize>,
 }
 impl ContigSummary {
     /// Create a new `ContigSummary` instance with default values for all fields except `name` and `length`.
@@ -165,8 +289,47 @@ impl ContigSummary {
             off_target_read_count: 0,
             yield_on_target: 0,
             yield_off_target: 0,
+            mean_coverage: 0.0,
+            breadth_of_coverage: 0.0,
+            on_target_breadth: 0.0,
+            aligned_bases: 0,
+            aligned_intervals: Vec::new(),
+            compacted_aligned_intervals: 0,
+            on_target_intervals: Vec::new(),
+            compacted_on_target_intervals: 0,
+            target_counts: Vec::new(),
+            targets: Vec::new(),
+            read_lengths: Vec::new(),
         }
     }
+
+    /// Compare two contig summaries, allowing the floating point fields to differ by up to `epsilon`.
+    ///
+    /// Every other field must be equal, apart from the alignment spans and read lengths, which are only kept to
+    /// calculate the breadth of coverage and N50, and are compared through them.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The contig summary to compare against.
+    /// * `epsilon` - The largest absolute difference allowed between floating point fields.
+    pub fn approx_eq(&self, other: &ContigSummary, epsilon: f64) -> bool {
+        let close = |a: f64, b: f64| (a - b).abs() <= epsilon;
+        self.name == other.name
+            && self.length == other.length
+            && self.mean_read_lengths == other.mean_read_lengths
+            && close(self.mean_read_quality, other.mean_read_quality)
+            && self.total_bases == other.total_bases
+            && self.n50 == other.n50
+            && self.on_target_read_count == other.on_target_read_count
+            && self.off_target_read_count == other.off_target_read_count
+            && self.yield_on_target == other.yield_on_target
+            && self.yield_off_target == other.yield_off_target
+            && close(self.mean_coverage, other.mean_coverage)
+            && close(self.breadth_of_coverage, other.breadth_of_coverage)
+            && close(self.on_target_breadth, other.on_target_breadth)
+            && self.aligned_bases == other.aligned_bases
+    }
+
     /// Get the total number of reads on the contig.
     pub fn total_reads(&self) -> usize {
         self.on_target_read_count + self.off_target_read_count
@@ -184,9 +347,272 @@ impl ContigSummary {
     pub fn off_target_mean_read_length(&self) -> usize {
         self.mean_read_lengths.off_target as usize
     }
+
+    /// Add the span of an alignment on the contig, updating the mean coverage.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The alignment to the contig, such as a [`paf::PafRecord`].
+    pub fn add_alignment_span(&mut self, record: &impl AlignmentRecord) {
+        self.aligned_bases += record.target_end().saturating_sub(record.target_start());
+        self.aligned_intervals
+            .push((record.target_start(), record.target_end()));
+        compact_intervals(
+            &mut self.aligned_intervals,
+            &mut self.compacted_aligned_intervals,
+        );
+        if self.length > 0 {
+            self.mean_coverage = self.aligned_bases as f64 / self.length as f64;
+        }
+    }
+
+    /// Merge the counts, yields and alignments of another summary of the same contig into this one.
+    ///
+    /// The mean read lengths and mean coverage are recalculated from the combined totals. The breadth of
+    /// coverage, on-target breadth and N50 need every alignment, so are recalculated by [`ContigSummary::finalize`].
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The summary of the same contig to merge in.
+    pub fn merge(&mut self, other: &ContigSummary) {
+        self.total_bases += other.total_bases;
+        self.on_target_read_count += other.on_target_read_count;
+        self.off_target_read_count += other.off_target_read_count;
+        self.yield_on_target += other.yield_on_target;
+        self.yield_off_target += other.yield_off_target;
+        self.mean_read_lengths.merge(&other.mean_read_lengths);
+        self.aligned_bases += other.aligned_bases;
+        self.aligned_intervals
+            .extend_from_slice(&other.aligned_intervals);
+        compact_intervals(
+            &mut self.aligned_intervals,
+            &mut self.compacted_aligned_intervals,
+        );
+        self.on_target_intervals
+            .extend_from_slice(&other.on_target_intervals);
+        compact_intervals(
+            &mut self.on_target_intervals,
+            &mut self.compacted_on_target_intervals,
+        );
+        if self.target_counts.len() < other.target_counts.len() {
+            self.target_counts.resize(other.target_counts.len(), (0, 0));
+        }
+        for (counts, other_counts) in self.target_counts.iter_mut().zip(&other.target_counts) {
+            counts.0 += other_counts.0;
+            counts.1 += other_counts.1;
+        }
+        if self.targets.is_empty() {
+            self.targets = other.targets.clone();
+        }
+        self.read_lengths.extend_from_slice(&other.read_lengths);
+        if self.length > 0 {
+            self.mean_coverage = self.aligned_bases as f64 / self.length as f64;
+        }
+    }
+
+    /// Set the length of the contig, recalculating the mean coverage.
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - The length of the contig, for example from the `.fai` index of the reference.
+    pub fn set_length(&mut self, length: usize) {
+        self.length = length;
+        self.mean_coverage = if length > 0 {
+            self.aligned_bases as f64 / length as f64
+        } else {
+            0.0
+        };
+    }
+
+    /// Check whether any on-target alignment overlaps the half-open range `start..stop`.
+    ///
+    /// The on-target alignment spans are merged when the contig is finalized, so this should only be called
+    /// after [`ContigSummary::finalize`].
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The start of the range, such as a target interval.
+    /// * `stop` - The end of the range, exclusive.
+    pub fn is_covered_on_target(&self, start: usize, stop: usize) -> bool {
+        overlaps_intervals(&self.on_target_intervals, start, stop)
+    }
+
+    /// Count an on-target alignment in each of the targets it overlaps, adding the read length to their yield.
+    ///
+    /// # Arguments
+    ///
+    /// * `targets` - The indices of the overlapped targets in the merged targets of the condition on the contig, see
+    ///   [`Conf::overlapping_targets`].
+    /// * `query_length` - The length of the read.
+    pub fn add_target_hits(&mut self, targets: Range<usize>, query_length: usize) {
+        if self.target_counts.len() < targets.end {
+            self.target_counts.resize(targets.end, (0, 0));
+        }
+        for counts in &mut self.target_counts[targets] {
+            counts.0 += 1;
+            counts.1 += query_length;
+        }
+    }
+
+    /// The on-target reads overlapping each target interval, and their yield, see [`ContigSummary::add_target_hits`].
+    ///
+    /// An alignment that spans several targets is counted in each of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_count` - The number of merged targets of the condition on the contig, see
+    ///   [`Conf::get_contig_targets`].
+    ///
+    /// # Returns
+    ///
+    /// The `(read_count, yield)` of each target, in the same order as [`Conf::get_contig_targets`].
+    pub fn target_read_counts(&self, target_count: usize) -> Vec<(usize, usize)> {
+        let mut counts = self.target_counts.clone();
+        counts.resize(target_count, (0, 0));
+        counts
+    }
+
+    /// Set the targets of the condition on the contig, so the on-target breadth is the fraction of the targets
+    /// covered, rather than of the whole contig, recalculating it if the contig has been finalized.
+    ///
+    /// # Arguments
+    ///
+    /// * `targets` - The merged `(start, stop)` target intervals on the contig, see [`Conf::get_contig_targets`].
+    pub fn set_targets(&mut self, targets: Vec<(usize, usize)>) {
+        self.targets = targets;
+        self.set_on_target_breadth();
+    }
+
+    /// Calculate the on-target breadth from the merged on-target alignment spans, relative to the targets if set,
+    /// otherwise to the contig length. Left at 0 if that length is 0.
+    fn set_on_target_breadth(&mut self) {
+        let (covered, length) = if self.targets.is_empty() {
+            (
+                merged_interval_length(&mut self.on_target_intervals),
+                self.length,
+            )
+        } else {
+            (
+                intersection_length(&self.on_target_intervals, &self.targets),
+                self.targets.iter().map(|(start, stop)| stop - start).sum(),
+            )
+        };
+        if length > 0 {
+            self.on_target_breadth = covered as f64 / length as f64;
+        }
+    }
+
+    /// Calculate the breadth of coverage by merging the spans of all alignments to the contig, the on-target
+    /// breadth by merging the spans of the on-target alignments, and the read N50 of the alignments.
+    /// Leaves the breadth of coverage at 0 if the contig length is 0.
+    pub fn finalize(&mut self) {
+        self.n50 = n50(&mut self.read_lengths);
+        merge_intervals(&mut self.on_target_intervals);
+        self.compacted_on_target_intervals = self.on_target_intervals.len();
+        self.set_on_target_breadth();
+        merge_intervals(&mut self.aligned_intervals);
+        self.compacted_aligned_intervals = self.aligned_intervals.len();
+        if self.length > 0 {
+            self.breadth_of_coverage =
+                merged_interval_length(&mut self.aligned_intervals) as f64 / self.length as f64;
+        }
+    }
+}
+
+/// The fewest alignment spans that are merged by [`compact_intervals`], so a contig with only a few alignments isn't
+/// merged over and over.
+const MIN_INTERVALS_TO_COMPACT: usize = 1024;
+
+/// Merge the alignment spans of a contig in place, once they have doubled in number since they were last merged, see
+/// [`merge_intervals`]. Merging whenever the spans double keeps the cost of merging proportional to the number of
+/// alignments, whilst bounding the spans held to twice the number of distinct regions covered.
+///
+/// # Arguments
+///
+/// * `intervals` - The `(start, end)` alignment spans, merged and truncated in place.
+/// * `compacted` - The number of spans when they were last merged, updated if they are merged.
+fn compact_intervals(intervals: &mut Vec<(usize, usize)>, compacted: &mut usize) {
+    if intervals.len() >= (*compacted * 2).max(MIN_INTERVALS_TO_COMPACT) {
+        merge_intervals(intervals);
+        *compacted = intervals.len();
+    }
+}
+/// The reads and yield sequenced on a single flowcell channel, used to plot the activity of the flowcell.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ChannelStats {
+    /// The number of reads sequenced on the channel.
+    pub read_count: usize,
+    /// The total yield (base pairs) of the reads sequenced on the channel.
+    pub total_yield: usize,
+    /// The count of reads on the channel that are mapped to the target regions.
+    pub on_target_read_count: usize,
+    /// The count of reads on the channel that are mapped off the target regions.
+    pub off_target_read_count: usize,
+}
+
+/// A channel number, the (column, row) of the channel on the flowcell, and its stats, see [`Summary::channel_coords`].
+pub type ChannelCoords<S> = (usize, (usize, usize), S);
+
+impl ChannelStats {
+    /// Count a read sequenced on the channel. Unmapped reads are counted in the reads and yield, but are
+    /// neither on nor off-target.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The alignment of the read, such as a [`paf::PafRecord`].
+    /// * `on_target` - Whether the read is on target.
+    pub fn update(&mut self, record: &impl AlignmentRecord, on_target: bool) {
+        self.read_count += 1;
+        self.total_yield += record.query_length();
+        if record.is_unmapped() {
+            return;
+        }
+        if on_target {
+            self.on_target_read_count += 1;
+        } else {
+            self.off_target_read_count += 1;
+        }
+    }
+
+    /// Merge the counts of another summary of the same channel into this one.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The stats of the same channel to merge in.
+    pub fn merge(&mut self, other: &ChannelStats) {
+        self.read_count += other.read_count;
+        self.total_yield += other.total_yield;
+        self.on_target_read_count += other.on_target_read_count;
+        self.off_target_read_count += other.off_target_read_count;
+    }
 }
-#[derive(Debug)]
+
+/// The on-target reads overlapping a single target interval of a condition, see [`Summary::target_summaries`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TargetSummary {
+    /// The contig the target is on.
+    pub contig: String,
+    /// The 0-based start of the target.
+    pub start: usize,
+    /// The end of the target, exclusive.
+    pub stop: usize,
+    /// The number of on-target reads overlapping the target.
+    pub read_count: usize,
+    /// The total yield (base pairs) of the on-target reads overlapping the target.
+    pub total_bases: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 /// Represents a summary of sequencing data, including various metrics related to the output of the experiment.
+///
+/// Equality compares every field exactly, including the floating point means, so summaries of the same reads merged
+/// in a different order can differ by rounding. Use [`ConditionSummary::approx_eq`] to compare them.
 pub struct ConditionSummary {
     /// The name or identifier of the sequencing data.
     pub name: String,
@@ -198,16 +624,32 @@ pub struct ConditionSummary {
     pub off_target_read_count: usize,
     /// The count of reads that are mapped to the target regions (on-target reads).
     pub on_target_read_count: usize,
+    /// The count of on-target reads aligned to the forward (`+`) strand.
+    pub forward_on_target_read_count: usize,
+    /// The count of on-target reads aligned to the reverse (`-`) strand.
+    pub reverse_on_target_read_count: usize,
     /// The percentage of off-target reads in the sequencing data.
     pub off_target_percent: f64,
     /// The total yield (base pairs) of off-target reads in the sequencing data.
     pub off_target_yield: usize,
     /// The total yield (base pairs) of on-target reads in the sequencing data.
     pub on_target_yield: usize,
-    /// The mean read quality of off-target reads.
+    /// The mean read quality of off-target reads. Remains 0 if no sequencing summary qscores are available.
     pub off_target_mean_read_quality: f64,
-    /// The mean read quality of on-target reads.
+    /// Number of off-target reads with a mean read quality.
+    off_target_quality_count: usize,
+    /// The mean read quality of on-target reads. Remains 0 if no sequencing summary qscores are available.
     pub on_target_mean_read_quality: f64,
+    /// Number of on-target reads with a mean read quality.
+    on_target_quality_count: usize,
+    /// The mean gap-compressed identity of on-target alignments.
+    pub on_target_mean_identity: f64,
+    /// Number of on-target alignments with an identity.
+    on_target_identity_count: usize,
+    /// The mean gap-compressed identity of off-target alignments.
+    pub off_target_mean_identity: f64,
+    /// Number of off-target alignments with an identity.
+    off_target_identity_count: usize,
     /// The N50 metric for the entire dataset, representing the length at which the cumulative
     /// sum of contig lengths reaches half of the total assembly length.
     pub n50: usize,
@@ -217,9 +659,62 @@ pub struct ConditionSummary {
     /// The N50 metric for off-target reads, representing the length at which the cumulative
     /// sum of contig lengths reaches half of the total assembly length for off-target reads.
     pub off_target_n50: usize,
+    /// Lengths of every on-target read, retained to calculate the N50.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    on_target_read_lengths: Vec<usize>,
+    /// Lengths of every off-target read, retained to calculate the N50.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    off_target_read_lengths: Vec<usize>,
+    /// The number of reads that were unblocked (rejected) by readfish, according to their end reason.
+    pub unblocked_read_count: usize,
+    /// The total yield (base pairs) of reads that were unblocked (rejected) by readfish.
+    pub unblocked_yield: usize,
+    /// The number of reads with each end reason in the sequencing summary, keyed on the end reason as written in the
+    /// summary, see [`EndReason`]. Reads without a sequencing summary end reason are not counted.
+    pub end_reasons: HashMap<String, usize>,
+    /// The number of reads with no alignment, counted in `total_reads` but not as on or off-target.
+    pub unmapped_read_count: usize,
+    /// The total yield (base pairs) of reads with no alignment.
+    pub unmapped_yield: usize,
+    /// The mean read quality of reads with no alignment. Remains 0 if no sequencing summary qscores are available.
+    pub unmapped_mean_read_quality: f64,
+    /// Number of unmapped reads with a mean read quality.
+    unmapped_quality_count: usize,
+    /// The N50 of reads with no alignment, which are not included in the overall `n50`.
+    pub unmapped_n50: usize,
+    /// Lengths of every unmapped read, retained to calculate the N50 and median.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    unmapped_read_lengths: Vec<usize>,
+    /// The `(start_time, read_length)` of every read with a sequencing summary start time.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    read_start_times: Vec<(f64, usize)>,
+    /// Number of reads that had no start time, so are excluded from the yield over time.
+    reads_without_start_time: usize,
+    /// The fraction of the reference genome that is targeted, used to display the enrichment.
+    pub target_fraction: Option<f64>,
+    /// The approximate size of the genome in base pairs, used to display the estimated coverage when there is no
+    /// `.fai` index of the reference, see [`ConditionSummary::estimated_coverage`].
+    pub genome_size: Option<usize>,
+    /// The `(contig, start, stop)` target intervals that no on-target alignment overlaps, set by
+    /// [`Summary::set_uncovered_targets`]. [`None`] if the targets have not been checked.
+    pub uncovered_targets: Option<Vec<(String, usize, usize)>>,
+    /// The read count and yield of every target interval, sorted by contig and start, set by
+    /// [`Summary::set_target_summaries`]. [`None`] if the targets have not been counted.
+    pub targets: Option<Vec<TargetSummary>>,
     /// A vector of `ContigSummary` representing summaries of individual contigs or sequences
     /// in the sequencing data.
     pub contigs: HashMap<String, ContigSummary>,
+    /// Whether the read length of every alignment is kept on its contig to calculate the contig N50, see
+    /// [`ConditionSummary::set_keep_read_lengths`]. Defaults to `false`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    keep_read_lengths: bool,
+}
+
+impl Default for ConditionSummary {
+    /// An empty condition summary with no name, as [`ConditionSummary::new`].
+    fn default() -> Self {
+        Self::new(String::new())
+    }
 }
 
 impl fmt::Display for ConditionSummary {
@@ -228,9 +723,33 @@ impl fmt::Display for ConditionSummary {
         writeln!(f, "Total Reads: {}", self.total_reads)?;
         writeln!(f, "Off-Target Read Count: {}", self.off_target_read_count)?;
         writeln!(f, "On-Target Read Count: {}", self.on_target_read_count)?;
+        writeln!(
+            f,
+            "Forward On-Target Read Count: {}",
+            self.forward_on_target_read_count
+        )?;
+        writeln!(
+            f,
+            "Reverse On-Target Read Count: {}",
+            self.reverse_on_target_read_count
+        )?;
+        writeln!(f, "Unmapped Read Count: {}", self.unmapped_read_count)?;
         writeln!(f, "Off-Target Percent: {:.2}%", self.off_target_percent)?;
+        writeln!(f, "On-Target Percent: {:.2}%", self.on_target_percent())?;
+        writeln!(
+            f,
+            "On/Off-Target Ratio: {}",
+            self.on_to_off_ratio()
+                .map_or_else(|| "-".to_string(), |ratio| format!("{:.2}", ratio))
+        )?;
         writeln!(f, "Off-Target Yield: {}", self.off_target_yield)?;
         writeln!(f, "On-Target Yield: {}", self.on_target_yield)?;
+        writeln!(
+            f,
+            "On-Target Yield Percent: {:.2}%",
+            self.on_target_yield_percent()
+        )?;
+        writeln!(f, "Unmapped Yield: {}", self.unmapped_yield)?;
         writeln!(
             f,
             "Off-Target Mean Read Length: {}",
@@ -241,19 +760,65 @@ impl fmt::Display for ConditionSummary {
             "On-Target Mean Read Length: {}",
             self.on_target_mean_read_length()
         )?;
-        // writeln!(
-        //     f,
-        //     "Off-Target Mean Read Quality: {:.2}",
-        //     self.off_target_mean_read_quality
-        // )?;
-        // writeln!(
-        //     f,
-        //     "On-Target Mean Read Quality: {:.2}",
-        //     self.on_target_mean_read_quality
-        // )?;
-        // writeln!(f, "N50: {}", self.n50)?;
-        // writeln!(f, "On-Target N50: {}", self.on_target_n50)?;
-        // writeln!(f, "Off-Target N50: {}", self.off_target_n50)?;
+        writeln!(f, "Read Length SD: {:.2}", self.read_length_std_dev())?;
+        writeln!(
+            f,
+            "Off-Target Read Length SD: {:.2}",
+            self.off_target_read_length_std_dev()
+        )?;
+        writeln!(
+            f,
+            "On-Target Read Length SD: {:.2}",
+            self.on_target_read_length_std_dev()
+        )?;
+        writeln!(
+            f,
+            "Off-Target Mean Identity: {:.2}%",
+            self.off_target_mean_identity * 100.0
+        )?;
+        writeln!(
+            f,
+            "On-Target Mean Identity: {:.2}%",
+            self.on_target_mean_identity * 100.0
+        )?;
+        writeln!(
+            f,
+            "Off-Target Mean Read Quality: {:.2}",
+            self.off_target_mean_read_quality
+        )?;
+        writeln!(
+            f,
+            "On-Target Mean Read Quality: {:.2}",
+            self.on_target_mean_read_quality
+        )?;
+        writeln!(
+            f,
+            "Unmapped Mean Read Quality: {:.2}",
+            self.unmapped_mean_read_quality
+        )?;
+        writeln!(f, "N50: {}", self.n50)?;
+        writeln!(f, "On-Target N50: {}", self.on_target_n50)?;
+        writeln!(f, "Off-Target N50: {}", self.off_target_n50)?;
+        writeln!(f, "Unmapped N50: {}", self.unmapped_n50)?;
+        if let Some(genome_size) = self.genome_size {
+            writeln!(
+                f,
+                "Estimated Coverage: {:.2}x",
+                self.estimated_coverage(genome_size)
+            )?;
+            if let Some(target_fraction) = self.target_fraction {
+                writeln!(
+                    f,
+                    "On-Target Estimated Coverage: {:.2}x",
+                    self.on_target_estimated_coverage(genome_size, target_fraction)
+                )?;
+                writeln!(
+                    f,
+                    "Off-Target Estimated Coverage: {:.2}x",
+                    self.off_target_estimated_coverage(genome_size, target_fraction)
+                )?;
+            }
+        }
 
         writeln!(f, "Contigs:")?;
         for (contig_name, contig_summary) in &self.contigs {
@@ -268,16 +833,20 @@ impl fmt::Display for ConditionSummary {
 }
 
 impl ConditionSummary {
-    /// Update the `ConditionSummary` with information from the provided `PafRecord`.
+    /// Update the `ConditionSummary` with information from the provided alignment.
     ///
     /// This method updates the fields of the `ConditionSummary` based on the information
-    /// from the given `PafRecord`. It increments the appropriate read counts (on-target
-    /// or off-target), calculates the mean read lengths and read qualities, updates the
-    /// total reads count, and calculates the off-target percentage.
+    /// from the given [`AlignmentRecord`], such as a [`paf::PafRecord`] or a [`bam::BamRecord`].
+    /// It increments the appropriate read counts (on-target or off-target), calculates the mean
+    /// read lengths and read qualities, updates the total reads count, and calculates the
+    /// off-target percentage.
+    ///
+    /// Unmapped reads, see [`AlignmentRecord::is_unmapped`], are counted in the total reads and the
+    /// unmapped read count and yield, but are neither on nor off-target and have no contig.
     ///
     /// # Arguments
     ///
-    /// * `paf` - The [`PafRecord`] containing the information about the alignment.
+    /// * `record` - The alignment of the read.
     /// * `on_target` - A boolean flag indicating whether the alignment is on-target or off-target.
     ///
     /// # Returns
@@ -285,39 +854,263 @@ impl ConditionSummary {
     /// This function returns a [`DynResult`] (a dynamic result that can contain any error).
     /// If the operation is successful, the `DynResult` will hold an `Ok(())`. Otherwise, it
     /// will hold an `Err` containing a helpful error message.
-    pub fn update(&mut self, paf: PafRecord, on_target: bool) -> DynResult<()> {
+    pub fn update(&mut self, record: &impl AlignmentRecord, on_target: bool) -> DynResult<()> {
         // update the condition struct
+        if record.is_unmapped() {
+            self.update_unmapped(record.query_length());
+            return Ok(());
+        }
         self.total_reads += 1;
-        self.mean_read_lengths.update_lengths(&paf, on_target);
+        self.mean_read_lengths.update_lengths(record, on_target);
         if on_target {
             self.on_target_read_count += 1;
-            self.on_target_yield += paf.query_length;
-            // self.on_target_mean_read_quality += paf.tlen as f64;
+            match record.strand() {
+                '+' => self.forward_on_target_read_count += 1,
+                '-' => self.reverse_on_target_read_count += 1,
+                _ => {}
+            }
+            self.on_target_yield += record.query_length();
+            self.on_target_read_lengths.push(record.query_length());
         } else {
             self.off_target_read_count += 1;
-            self.off_target_yield += paf.query_length;
-            // self.off_target_mean_read_quality += paf.tlen as f64;
+            self.off_target_yield += record.query_length();
+            self.off_target_read_lengths.push(record.query_length());
+        }
+        if let Some(identity) = record.identity() {
+            if on_target {
+                running_mean_f64(
+                    &mut self.on_target_mean_identity,
+                    &mut self.on_target_identity_count,
+                    identity,
+                );
+            } else {
+                running_mean_f64(
+                    &mut self.off_target_mean_identity,
+                    &mut self.off_target_identity_count,
+                    identity,
+                );
+            }
         }
         self.off_target_percent =
             self.off_target_read_count as f64 / self.total_reads as f64 * 100.0;
-        let contig = self.get_or_add_contig(&paf.target_name, paf.target_length);
-        contig.total_bases += paf.query_length;
-        contig.mean_read_lengths.update_lengths(&paf, on_target);
+        self.update_contig(record, on_target);
+        // contig.mean_read_quality = paf.tlen;
+        // contig.on_target_read_count = paf.tlen;
+        // contig.off_target_read_count = paf.tlen;
+
+        Ok(())
+    }
+
+    /// Count a read with no alignment in the total reads and the unmapped read count, yield and lengths.
+    ///
+    /// Called by [`ConditionSummary::update`] for unmapped alignments, and by [`Summary::add_unaligned_read`] for
+    /// reads from a sequencing summary alone, which have not been aligned at all.
+    ///
+    /// # Arguments
+    ///
+    /// * `read_length` - The length of the read in bases.
+    pub fn update_unmapped(&mut self, read_length: usize) {
+        self.total_reads += 1;
+        self.unmapped_read_count += 1;
+        self.unmapped_yield += read_length;
+        self.unmapped_read_lengths.push(read_length);
+        self.off_target_percent =
+            self.off_target_read_count as f64 / self.total_reads as f64 * 100.0;
+    }
+
+    /// Update the summary of the contig an alignment is on, without counting the read in the condition totals.
+    ///
+    /// Called by [`ConditionSummary::update`] for every read, and on its own for the secondary and supplementary
+    /// alignments of a read that has already been counted, so the per-contig statistics still cover every alignment.
+    /// Unmapped reads have no contig, so are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The alignment of the read.
+    /// * `on_target` - A boolean flag indicating whether the alignment is on-target or off-target.
+    pub fn update_contig(&mut self, record: &impl AlignmentRecord, on_target: bool) {
+        if record.is_unmapped() {
+            return;
+        }
+        let keep_read_lengths = self.keep_read_lengths;
+        let contig = self.get_or_add_contig(record.target_name(), record.target_length());
+        contig.total_bases += record.query_length();
+        if keep_read_lengths {
+            contig.read_lengths.push(record.query_length());
+        }
+        contig.mean_read_lengths.update_lengths(record, on_target);
+        contig.add_alignment_span(record);
         if on_target {
             contig.on_target_read_count += 1;
-            // self.on_target_mean_read_quality += paf.tlen as f64;
+            contig.yield_on_target += record.query_length();
+            contig
+                .on_target_intervals
+                .push((record.target_start(), record.target_end()));
+            compact_intervals(
+                &mut contig.on_target_intervals,
+                &mut contig.compacted_on_target_intervals,
+            );
         } else {
             contig.off_target_read_count += 1;
-            contig.yield_off_target += paf.target_length;
-            // self.off_target_mean_read_quality += paf.tlen as f64;
+            contig.yield_off_target += record.query_length();
         }
-        // contig.mean_read_quality = paf.tlen;
-        // contig.n50 = paf.tlen;
-        // contig.on_target_read_count = paf.tlen;
-        // contig.off_target_read_count = paf.tlen;
+    }
 
-        Ok(())
+    /// Update the on or off-target mean read quality with the mean qscore of a read.
+    ///
+    /// The mean qscore is taken from the `mean_qscore_template` column of the sequencing summary,
+    /// and is averaged per read. If no qscore is available for a read, this method should not be
+    /// called, leaving the mean read quality at 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `mean_qscore` - The mean qscore of the read.
+    /// * `on_target` - A boolean flag indicating whether the read is on-target or off-target.
+    pub fn update_mean_read_quality(&mut self, mean_qscore: f64, on_target: bool) {
+        if on_target {
+            running_mean_f64(
+                &mut self.on_target_mean_read_quality,
+                &mut self.on_target_quality_count,
+                mean_qscore,
+            );
+        } else {
+            running_mean_f64(
+                &mut self.off_target_mean_read_quality,
+                &mut self.off_target_quality_count,
+                mean_qscore,
+            );
+        }
+    }
+
+    /// Update the mean read quality of unmapped reads with the mean qscore of a read, as
+    /// [`ConditionSummary::update_mean_read_quality`] does for mapped reads.
+    ///
+    /// # Arguments
+    ///
+    /// * `mean_qscore` - The mean qscore of the unmapped read.
+    pub fn update_unmapped_read_quality(&mut self, mean_qscore: f64) {
+        running_mean_f64(
+            &mut self.unmapped_mean_read_quality,
+            &mut self.unmapped_quality_count,
+            mean_qscore,
+        );
+    }
+
+    /// Record the start time of a read, so the yield can be binned over the run time.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_time` - The start time of the read in seconds from the sequencing summary. Reads
+    ///   without a start time are counted, but excluded from [`ConditionSummary::yield_over_time`].
+    /// * `read_length` - The length of the read in bases.
+    pub fn update_start_time(&mut self, start_time: Option<f64>, read_length: usize) {
+        match start_time {
+            Some(start_time) => self.read_start_times.push((start_time, read_length)),
+            None => self.reads_without_start_time += 1,
+        }
+    }
+
+    /// Record a read that was unblocked (rejected) by readfish.
+    ///
+    /// # Arguments
+    ///
+    /// * `read_length` - The length of the unblocked read in bases.
+    pub fn update_unblocked(&mut self, read_length: usize) {
+        self.unblocked_read_count += 1;
+        self.unblocked_yield += read_length;
+    }
+
+    /// Count the end reason of a read from the sequencing summary, see [`ConditionSummary::end_reasons`].
+    ///
+    /// # Arguments
+    ///
+    /// * `end_reason` - The reason the read ended.
+    pub fn update_end_reason(&mut self, end_reason: &EndReason) {
+        *self
+            .end_reasons
+            .entry(end_reason.as_str().to_string())
+            .or_insert(0) += 1;
+    }
+
+    /// Get the number of reads that had no start time recorded.
+    pub fn reads_without_start_time(&self) -> usize {
+        self.reads_without_start_time
+    }
+
+    /// Bin the yield of reads by their start time into fixed windows, to show throughput over the run.
+    ///
+    /// Reads without a start time are skipped, and a warning with their count is logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `bin_secs` - The width of each time window in seconds. A `bin_secs` of 0 is clamped to 1.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `(window_start_secs, cumulative_bases)` pairs, covering 0 up to the latest read start time.
+    pub fn yield_over_time(&self, bin_secs: u64) -> Vec<(u64, usize)> {
+        if self.reads_without_start_time > 0 {
+            log::warn!(
+                "{} reads in condition {} have no start time and are excluded from the yield over time",
+                self.reads_without_start_time, self.name
+            );
+        }
+        let bin_secs = bin_secs.max(1);
+        let binned_lengths: Vec<(u64, usize)> = self
+            .read_start_times
+            .iter()
+            .map(|(start_time, read_length)| {
+                ((start_time.max(0.0) as u64) / bin_secs, *read_length)
+            })
+            .collect();
+        let max_bin = match binned_lengths.iter().map(|(bin, _)| *bin).max() {
+            Some(max_bin) => max_bin,
+            None => return Vec::new(),
+        };
+        let mut bin_yields = vec![0; max_bin as usize + 1];
+        for (bin, read_length) in binned_lengths {
+            bin_yields[bin as usize] += read_length;
+        }
+        bin_yields
+            .into_iter()
+            .enumerate()
+            .scan(0, |cumulative_bases, (bin, bin_yield)| {
+                *cumulative_bases += bin_yield;
+                Some((bin as u64 * bin_secs, *cumulative_bases))
+            })
+            .collect()
+    }
+
+    /// Estimate the additional run time until the reads of this condition reach a mean depth of coverage, by
+    /// extrapolating the yield over time linearly, see [`nanopore::time_to_coverage`].
+    ///
+    /// Every read of the condition with a start time counts towards the coverage, so `genome_size` should be the size
+    /// the reads are spread over, such as the reference for a region that samples everything.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_coverage` - The desired mean depth of coverage.
+    /// * `genome_size` - The size in bases of the genome the reads are spread over.
+    /// * `bin_secs` - The width of the time windows the yield is binned into before fitting, see
+    ///   [`ConditionSummary::yield_over_time`].
+    ///
+    /// # Returns
+    ///
+    /// The estimated run time after the latest read start until the coverage is reached, or `None` if the yield is flat
+    /// or declining, or there are too few reads with a start time to tell.
+    pub fn time_to_coverage(
+        &self,
+        target_coverage: f64,
+        genome_size: usize,
+        bin_secs: u64,
+    ) -> Option<std::time::Duration> {
+        nanopore::time_to_coverage(
+            &self.yield_over_time(bin_secs),
+            target_coverage,
+            genome_size,
+        )
     }
+
     /// Create a new `Summary` instance with default values for all fields except `name`.
     ///
     /// # Arguments
@@ -329,25 +1122,180 @@ impl ConditionSummary {
             total_reads: 0,
             off_target_read_count: 0,
             on_target_read_count: 0,
+            forward_on_target_read_count: 0,
+            reverse_on_target_read_count: 0,
             off_target_percent: 0.0,
             off_target_yield: 0,
             on_target_yield: 0,
             mean_read_lengths: MeanReadLengths::new(),
             off_target_mean_read_quality: 0.0,
+            off_target_quality_count: 0,
             on_target_mean_read_quality: 0.0,
+            on_target_quality_count: 0,
+            on_target_mean_identity: 0.0,
+            on_target_identity_count: 0,
+            off_target_mean_identity: 0.0,
+            off_target_identity_count: 0,
             n50: 0,
             on_target_n50: 0,
             off_target_n50: 0,
+            on_target_read_lengths: Vec::new(),
+            off_target_read_lengths: Vec::new(),
+            unblocked_read_count: 0,
+            unblocked_yield: 0,
+            end_reasons: HashMap::new(),
+            unmapped_read_count: 0,
+            unmapped_yield: 0,
+            unmapped_mean_read_quality: 0.0,
+            unmapped_quality_count: 0,
+            unmapped_n50: 0,
+            unmapped_read_lengths: Vec::new(),
+            read_start_times: Vec::new(),
+            reads_without_start_time: 0,
+            target_fraction: None,
+            genome_size: None,
+            uncovered_targets: None,
+            targets: None,
             contigs: HashMap::new(),
+            keep_read_lengths: false,
         }
     }
 
-    /// Get the name or identifier of the sequencing data.
-    pub fn name(&self) -> &str {
-        &self.name
-    }
-
-    /// Set the name or identifier of the sequencing data.
+    /// Set whether the read length of every alignment is kept on its contig, to calculate the N50 of each contig.
+    ///
+    /// This defaults to `false`, as the read lengths grow with every alignment, which adds up on huge references. The
+    /// N50 of each contig is then left at 0, whilst the N50 of the condition is always calculated.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep_read_lengths` - `true` to keep the read lengths and calculate the N50 of each contig.
+    pub fn set_keep_read_lengths(&mut self, keep_read_lengths: bool) {
+        self.keep_read_lengths = keep_read_lengths;
+    }
+
+    /// Finalize the metrics that can only be calculated once every read has been seen.
+    ///
+    /// Calculates the overall, on-target, off-target and unmapped N50 from the read lengths
+    /// collected during [`ConditionSummary::update`], and the breadth of coverage of each contig.
+    /// The overall N50 only covers mapped reads.
+    pub fn finalize(&mut self) {
+        for contig in self.contigs.values_mut() {
+            contig.finalize();
+        }
+        self.on_target_n50 = n50(&mut self.on_target_read_lengths);
+        self.off_target_n50 = n50(&mut self.off_target_read_lengths);
+        self.unmapped_n50 = n50(&mut self.unmapped_read_lengths);
+        let mut read_lengths = [
+            self.on_target_read_lengths.as_slice(),
+            self.off_target_read_lengths.as_slice(),
+        ]
+        .concat();
+        self.n50 = n50(&mut read_lengths);
+    }
+
+    /// Merge the reads of another summary of the same condition into this one.
+    ///
+    /// Counts, yields and read lengths are summed, and the contigs merged by name. The means are recalculated from
+    /// the combined totals rather than averaged: the mean read lengths, mean read qualities and mean identities are
+    /// weighted by their counts, and the off-target percentage recalculated. The N50s and breadth of coverage need
+    /// every read, so are left to [`ConditionSummary::finalize`], which should be called once every summary has been
+    /// merged, rather than after each merge, to give the same results as if every read had been seen in a single pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The summary of the same condition to merge in.
+    pub fn merge(&mut self, other: &ConditionSummary) {
+        self.merge_reads(other);
+        for (contig_name, contig_summary) in &other.contigs {
+            self.get_or_add_contig(contig_name, contig_summary.length)
+                .merge(contig_summary);
+        }
+    }
+
+    /// Sum the counts, yields and read lengths of another summary into this one, and recalculate the means and
+    /// off-target percentage, without merging the contigs, see [`ConditionSummary::merge`]. The N50s are only
+    /// recalculated by [`ConditionSummary::finalize`].
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The summary to merge in.
+    fn merge_reads(&mut self, other: &ConditionSummary) {
+        self.total_reads += other.total_reads;
+        self.on_target_read_count += other.on_target_read_count;
+        self.off_target_read_count += other.off_target_read_count;
+        self.forward_on_target_read_count += other.forward_on_target_read_count;
+        self.reverse_on_target_read_count += other.reverse_on_target_read_count;
+        self.on_target_yield += other.on_target_yield;
+        self.off_target_yield += other.off_target_yield;
+        self.mean_read_lengths.merge(&other.mean_read_lengths);
+        merge_means_f64(
+            &mut self.on_target_mean_read_quality,
+            &mut self.on_target_quality_count,
+            other.on_target_mean_read_quality,
+            other.on_target_quality_count,
+        );
+        merge_means_f64(
+            &mut self.off_target_mean_read_quality,
+            &mut self.off_target_quality_count,
+            other.off_target_mean_read_quality,
+            other.off_target_quality_count,
+        );
+        merge_means_f64(
+            &mut self.on_target_mean_identity,
+            &mut self.on_target_identity_count,
+            other.on_target_mean_identity,
+            other.on_target_identity_count,
+        );
+        merge_means_f64(
+            &mut self.off_target_mean_identity,
+            &mut self.off_target_identity_count,
+            other.off_target_mean_identity,
+            other.off_target_identity_count,
+        );
+        self.on_target_read_lengths
+            .extend_from_slice(&other.on_target_read_lengths);
+        self.off_target_read_lengths
+            .extend_from_slice(&other.off_target_read_lengths);
+        self.unblocked_read_count += other.unblocked_read_count;
+        self.unblocked_yield += other.unblocked_yield;
+        for (end_reason, count) in &other.end_reasons {
+            *self.end_reasons.entry(end_reason.clone()).or_insert(0) += count;
+        }
+        self.unmapped_read_count += other.unmapped_read_count;
+        self.unmapped_yield += other.unmapped_yield;
+        merge_means_f64(
+            &mut self.unmapped_mean_read_quality,
+            &mut self.unmapped_quality_count,
+            other.unmapped_mean_read_quality,
+            other.unmapped_quality_count,
+        );
+        self.unmapped_read_lengths
+            .extend_from_slice(&other.unmapped_read_lengths);
+        self.read_start_times
+            .extend_from_slice(&other.read_start_times);
+        self.reads_without_start_time += other.reads_without_start_time;
+        if self.target_fraction.is_none() {
+            self.target_fraction = other.target_fraction;
+        }
+        if self.genome_size.is_none() {
+            self.genome_size = other.genome_size;
+        }
+        // Merged alignments may cover targets that were uncovered, so they must be checked again
+        self.uncovered_targets = None;
+        self.targets = None;
+        self.off_target_percent = if self.total_reads > 0 {
+            self.off_target_read_count as f64 / self.total_reads as f64 * 100.0
+        } else {
+            0.0
+        };
+    }
+
+    /// Get the name or identifier of the sequencing data.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Set the name or identifier of the sequencing data.
     pub fn set_name(&mut self, name: String) {
         self.name = name;
     }
@@ -426,6 +1374,116 @@ impl ConditionSummary {
         self.mean_read_lengths.on_target as usize
     }
 
+    /// Get the median read length of all reads, 0 if there are no reads.
+    pub fn median_read_length(&self) -> usize {
+        median(
+            &[
+                self.on_target_read_lengths.as_slice(),
+                self.off_target_read_lengths.as_slice(),
+            ]
+            .concat(),
+        )
+    }
+
+    /// Get the median read length of on-target reads, 0 if there are no on-target reads.
+    pub fn on_target_median_read_length(&self) -> usize {
+        median(&self.on_target_read_lengths)
+    }
+
+    /// Get the median read length of off-target reads, 0 if there are no off-target reads.
+    pub fn off_target_median_read_length(&self) -> usize {
+        median(&self.off_target_read_lengths)
+    }
+
+    /// Get the mean read length of unmapped reads, 0 if there are no unmapped reads.
+    pub fn unmapped_mean_read_length(&self) -> usize {
+        if self.unmapped_read_count == 0 {
+            return 0;
+        }
+        self.unmapped_yield / self.unmapped_read_count
+    }
+
+    /// Get the median read length of unmapped reads, 0 if there are no unmapped reads.
+    pub fn unmapped_median_read_length(&self) -> usize {
+        median(&self.unmapped_read_lengths)
+    }
+
+    /// Get the standard deviation of the read lengths of all reads, 0 if there are fewer than two reads.
+    /// See [`standard_deviation`].
+    pub fn read_length_std_dev(&self) -> f64 {
+        standard_deviation(
+            &[
+                self.on_target_read_lengths.as_slice(),
+                self.off_target_read_lengths.as_slice(),
+            ]
+            .concat(),
+        )
+    }
+
+    /// Get the standard deviation of the read lengths of on-target reads, 0 if there are fewer than two.
+    pub fn on_target_read_length_std_dev(&self) -> f64 {
+        standard_deviation(&self.on_target_read_lengths)
+    }
+
+    /// Get the standard deviation of the read lengths of off-target reads, 0 if there are fewer than two.
+    pub fn off_target_read_length_std_dev(&self) -> f64 {
+        standard_deviation(&self.off_target_read_lengths)
+    }
+
+    /// Get the first and third quartiles of the read lengths of all reads, `(0, 0)` if there are no reads.
+    /// See [`quartiles`].
+    pub fn read_length_quartiles(&self) -> (usize, usize) {
+        quartiles(
+            &[
+                self.on_target_read_lengths.as_slice(),
+                self.off_target_read_lengths.as_slice(),
+            ]
+            .concat(),
+        )
+    }
+
+    /// Get the first and third quartiles of the read lengths of on-target reads, `(0, 0)` if there are none.
+    pub fn on_target_read_length_quartiles(&self) -> (usize, usize) {
+        quartiles(&self.on_target_read_lengths)
+    }
+
+    /// Get the first and third quartiles of the read lengths of off-target reads, `(0, 0)` if there are none.
+    pub fn off_target_read_length_quartiles(&self) -> (usize, usize) {
+        quartiles(&self.off_target_read_lengths)
+    }
+
+    /// Get a histogram of the read lengths of all reads.
+    ///
+    /// # Arguments
+    ///
+    /// * `bin_size` - The width of each bin in bases. A `bin_size` of 0 is clamped to 1.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `(bin_start, count)` pairs covering 0 up to the longest read length.
+    pub fn read_length_histogram(&self, bin_size: usize) -> Vec<(usize, usize)> {
+        histogram(
+            &[
+                self.on_target_read_lengths.as_slice(),
+                self.off_target_read_lengths.as_slice(),
+            ]
+            .concat(),
+            bin_size,
+        )
+    }
+
+    /// Get a histogram of the read lengths of on-target reads.
+    /// See [`ConditionSummary::read_length_histogram`].
+    pub fn on_target_read_length_histogram(&self, bin_size: usize) -> Vec<(usize, usize)> {
+        histogram(&self.on_target_read_lengths, bin_size)
+    }
+
+    /// Get a histogram of the read lengths of off-target reads.
+    /// See [`ConditionSummary::read_length_histogram`].
+    pub fn off_target_read_length_histogram(&self, bin_size: usize) -> Vec<(usize, usize)> {
+        histogram(&self.off_target_read_lengths, bin_size)
+    }
+
     /// Get the mean read quality of off-target reads.
     pub fn off_target_mean_read_quality(&self) -> f64 {
         self.off_target_mean_read_quality
@@ -446,6 +1504,16 @@ impl ConditionSummary {
         self.on_target_mean_read_quality = on_target_mean_read_quality;
     }
 
+    /// Get the mean gap-compressed identity of on-target alignments.
+    pub fn on_target_mean_identity(&self) -> f64 {
+        self.on_target_mean_identity
+    }
+
+    /// Get the mean gap-compressed identity of off-target alignments.
+    pub fn off_target_mean_identity(&self) -> f64 {
+        self.off_target_mean_identity
+    }
+
     /// Get the N50 metric for the entire dataset.
     pub fn n50(&self) -> usize {
         self.n50
@@ -486,6 +1554,108 @@ impl ConditionSummary {
         &mut self.contigs
     }
 
+    /// Compare two condition summaries, allowing the floating point fields to differ by up to `epsilon`, such as a
+    /// summary merged from chunks against a summary of every read at once.
+    ///
+    /// Every other field must be equal, and every contig must be approximately equal, see
+    /// [`ContigSummary::approx_eq`]. The read lengths and start times, which are only kept to calculate the N50s and
+    /// yield over time, are compared through the N50s.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The condition summary to compare against.
+    /// * `epsilon` - The largest absolute difference allowed between floating point fields.
+    pub fn approx_eq(&self, other: &ConditionSummary, epsilon: f64) -> bool {
+        let close = |a: f64, b: f64| (a - b).abs() <= epsilon;
+        self.name == other.name
+            && self.total_reads == other.total_reads
+            && self.mean_read_lengths == other.mean_read_lengths
+            && self.off_target_read_count == other.off_target_read_count
+            && self.on_target_read_count == other.on_target_read_count
+            && self.forward_on_target_read_count == other.forward_on_target_read_count
+            && self.reverse_on_target_read_count == other.reverse_on_target_read_count
+            && close(self.off_target_percent, other.off_target_percent)
+            && self.off_target_yield == other.off_target_yield
+            && self.on_target_yield == other.on_target_yield
+            && close(
+                self.off_target_mean_read_quality,
+                other.off_target_mean_read_quality,
+            )
+            && close(
+                self.on_target_mean_read_quality,
+                other.on_target_mean_read_quality,
+            )
+            && close(self.on_target_mean_identity, other.on_target_mean_identity)
+            && close(
+                self.off_target_mean_identity,
+                other.off_target_mean_identity,
+            )
+            && self.n50 == other.n50
+            && self.on_target_n50 == other.on_target_n50
+            && self.off_target_n50 == other.off_target_n50
+            && self.unblocked_read_count == other.unblocked_read_count
+            && self.unblocked_yield == other.unblocked_yield
+            && self.end_reasons == other.end_reasons
+            && self.unmapped_read_count == other.unmapped_read_count
+            && self.unmapped_yield == other.unmapped_yield
+            && close(
+                self.unmapped_mean_read_quality,
+                other.unmapped_mean_read_quality,
+            )
+            && self.unmapped_n50 == other.unmapped_n50
+            && match (self.target_fraction, other.target_fraction) {
+                (Some(a), Some(b)) => close(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+            && self.genome_size == other.genome_size
+            && self.uncovered_targets == other.uncovered_targets
+            && self.targets == other.targets
+            && self.contigs.len() == other.contigs.len()
+            && self.contigs.iter().all(|(contig_name, contig)| {
+                other
+                    .contigs
+                    .get(contig_name)
+                    .is_some_and(|other_contig| contig.approx_eq(other_contig, epsilon))
+            })
+    }
+
+    /// Get the `n` contigs with the highest yield, in descending order of yield. Contigs with the same yield are in
+    /// natural order of their names.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The maximum number of contigs to return.
+    pub fn top_contigs_by_yield(&self, n: usize) -> Vec<&ContigSummary> {
+        self.top_contigs_by(n, |contig| contig.total_bases)
+    }
+
+    /// Get the `n` contigs with the most reads, in descending order of read count. Contigs with the same read count
+    /// are in natural order of their names.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The maximum number of contigs to return.
+    pub fn top_contigs_by_reads(&self, n: usize) -> Vec<&ContigSummary> {
+        self.top_contigs_by(n, ContigSummary::total_reads)
+    }
+
+    /// Get the `n` contigs with the highest `key`, in descending order, see [`ConditionSummary::top_contigs_by_yield`].
+    fn top_contigs_by(
+        &self,
+        n: usize,
+        key: impl Fn(&ContigSummary) -> usize,
+    ) -> Vec<&ContigSummary> {
+        self.contigs
+            .values()
+            .sorted_by(|a, b| {
+                key(b)
+                    .cmp(&key(a))
+                    .then_with(|| natord::compare(&a.name, &b.name))
+            })
+            .take(n)
+            .collect()
+    }
+
     /// Get the ContigSummary associated with the given contig name or
     ///  add a new ContigSummary with the specified name and length if it doesn't exist.
     ///
@@ -521,12 +1691,405 @@ impl ConditionSummary {
             .or_insert(ContigSummary::new(contig.to_string(), length))
     }
 
-    /// get the total yield
+    /// get the total yield, including unmapped reads
     pub fn total_yield(&self) -> usize {
-        self.on_target_yield + self.off_target_yield
+        self.on_target_yield + self.off_target_yield + self.unmapped_yield
+    }
+
+    /// Get the percentage of reads that are on target.
+    ///
+    /// Unmapped reads are neither on nor off-target, so this is not always `100 - off_target_percent`.
+    pub fn on_target_percent(&self) -> f64 {
+        if self.total_reads == 0 {
+            return 0.0;
+        }
+        self.on_target_read_count as f64 / self.total_reads as f64 * 100.0
+    }
+
+    /// Get the ratio of on-target to off-target reads.
+    ///
+    /// # Returns
+    ///
+    /// The number of on-target reads per off-target read, or [`None`] if there are no off-target reads.
+    pub fn on_to_off_ratio(&self) -> Option<f64> {
+        if self.off_target_read_count == 0 {
+            return None;
+        }
+        Some(self.on_target_read_count as f64 / self.off_target_read_count as f64)
+    }
+
+    /// Get the percentage of the total yield that is on-target, 0 if there is no yield.
+    /// See [`ConditionSummary::on_target_yield_fraction`].
+    pub fn on_target_yield_percent(&self) -> f64 {
+        self.on_target_yield_fraction() * 100.0
+    }
+
+    /// Calculate the fold enrichment of on-target yield.
+    ///
+    /// The enrichment is the fraction of the total yield that is on-target, divided by the
+    /// fraction of the reference genome that is targeted:
+    ///
+    /// `enrichment = (on_target_yield / total_yield) / target_fraction`
+    ///
+    /// An enrichment of 1.0 means on-target bases were sequenced at the rate expected without
+    /// adaptive sampling.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_fraction` - The fraction (0.0 - 1.0) of the reference genome that is targeted.
+    ///
+    /// # Returns
+    ///
+    /// The fold enrichment, or 0.0 if `target_fraction` is not positive or there is no yield.
+    pub fn enrichment(&self, target_fraction: f64) -> f64 {
+        if target_fraction <= 0.0 {
+            return 0.0;
+        }
+        self.on_target_yield_fraction() / target_fraction
+    }
+
+    /// Get the fraction of the total yield that is on-target, 0 if there is no yield.
+    pub fn on_target_yield_fraction(&self) -> f64 {
+        if self.total_yield() == 0 {
+            return 0.0;
+        }
+        self.on_target_yield as f64 / self.total_yield() as f64
+    }
+
+    /// Set the fraction of the reference genome that is targeted, so that the enrichment is displayed.
+    pub fn set_target_fraction(&mut self, target_fraction: f64) {
+        self.target_fraction = Some(target_fraction);
+    }
+
+    /// Estimate the mean depth of coverage of the genome from its approximate size, for when there is no `.fai` index
+    /// of the reference to calculate the coverage of each contig from.
+    ///
+    /// This is only an estimate, as it assumes every base of every read, including unmapped reads, covers the genome
+    /// evenly:
+    ///
+    /// `estimated_coverage = total_yield / genome_size`
+    ///
+    /// # Arguments
+    ///
+    /// * `genome_size` - The approximate size of the genome in base pairs.
+    ///
+    /// # Returns
+    ///
+    /// The estimated fold coverage, or 0.0 if `genome_size` is 0.
+    pub fn estimated_coverage(&self, genome_size: usize) -> f64 {
+        if genome_size == 0 {
+            return 0.0;
+        }
+        self.total_yield() as f64 / genome_size as f64
+    }
+
+    /// Estimate the mean depth of coverage of the targeted part of the genome, from the on-target yield.
+    ///
+    /// `on_target_estimated_coverage = on_target_yield / (genome_size * target_fraction)`
+    ///
+    /// # Arguments
+    ///
+    /// * `genome_size` - The approximate size of the genome in base pairs.
+    /// * `target_fraction` - The fraction (0.0 - 1.0) of the genome that is targeted.
+    ///
+    /// # Returns
+    ///
+    /// The estimated fold coverage of the targets, or 0.0 if no part of the genome is targeted.
+    pub fn on_target_estimated_coverage(&self, genome_size: usize, target_fraction: f64) -> f64 {
+        let targeted_size = genome_size as f64 * target_fraction;
+        if targeted_size <= 0.0 {
+            return 0.0;
+        }
+        self.on_target_yield as f64 / targeted_size
+    }
+
+    /// Estimate the mean depth of coverage of the part of the genome that isn't targeted, from the off-target yield.
+    ///
+    /// `off_target_estimated_coverage = off_target_yield / (genome_size * (1 - target_fraction))`
+    ///
+    /// # Arguments
+    ///
+    /// * `genome_size` - The approximate size of the genome in base pairs.
+    /// * `target_fraction` - The fraction (0.0 - 1.0) of the genome that is targeted.
+    ///
+    /// # Returns
+    ///
+    /// The estimated fold coverage outside the targets, or 0.0 if the whole genome is targeted.
+    pub fn off_target_estimated_coverage(&self, genome_size: usize, target_fraction: f64) -> f64 {
+        let untargeted_size = genome_size as f64 * (1.0 - target_fraction);
+        if untargeted_size <= 0.0 {
+            return 0.0;
+        }
+        self.off_target_yield as f64 / untargeted_size
+    }
+
+    /// Set the approximate size of the genome, so that the estimated coverage is displayed.
+    pub fn set_genome_size(&mut self, genome_size: usize) {
+        self.genome_size = Some(genome_size);
+    }
+
+    /// Add every contig of the reference, so that contigs without any alignments are reported with zero coverage.
+    ///
+    /// A contig whose length, taken from the `target_length` of its PAF records, disagrees with the reference
+    /// is given the reference length.
+    ///
+    /// # Arguments
+    ///
+    /// * `reference_lengths` - The length of every contig in the reference, keyed on the contig name.
+    ///
+    /// # Returns
+    ///
+    /// The `(contig, paf_length, reference_length)` of every contig whose length disagreed with the reference.
+    pub fn add_reference_contigs(
+        &mut self,
+        reference_lengths: &HashMap<String, usize>,
+    ) -> Vec<(String, usize, usize)> {
+        let mut mismatches = Vec::new();
+        for (contig_name, &reference_length) in reference_lengths {
+            let contig = self.get_or_add_contig(contig_name, reference_length);
+            if contig.length != reference_length {
+                mismatches.push((contig_name.clone(), contig.length, reference_length));
+                contig.set_length(reference_length);
+            }
+        }
+        mismatches
+    }
+}
+
+/// The sequencing summary end reasons recorded by MinKNOW for reads unblocked by readfish.
+pub const DEFAULT_UNBLOCK_END_REASONS: [&str; 1] = ["data_service_unblock_mux_change"];
+
+/// The name of the condition that reads matching no region or barcode in the TOML are summarised under.
+pub const UNCLASSIFIED_CONDITION: &str = "unclassified";
+
+/// The name of the [`ConditionSummary`] of every condition together, see [`Summary::total`].
+pub const TOTAL_CONDITION: &str = "Total";
+
+/// The number of reads whose sequencing summary and PAF `ch` tag channels disagree that are logged, see
+/// [`Summary::add_channel_mismatch`].
+pub const LOGGED_CHANNEL_MISMATCHES: usize = 5;
+
+/// The columns of the per-condition CSV written by [`Summary::write_csv`], in order. Other tabular exports of the
+/// conditions should use the same column names, so they stay consistent with the CSV.
+pub const CONDITION_CSV_COLUMNS: [&str; 13] = [
+    "condition",
+    "total_reads",
+    "on_target_read_count",
+    "off_target_read_count",
+    "total_yield",
+    "on_target_yield",
+    "off_target_yield",
+    "off_target_percent",
+    "n50",
+    "on_target_n50",
+    "off_target_n50",
+    "unmapped_read_count",
+    "unmapped_yield",
+];
+
+/// The SQLite schema of the tables written by [`Summary::write_sqlite`] and [`Summary::write_sql`], created if they
+/// don't exist. The columns after `run` are those of [`CONDITION_CSV_COLUMNS`], [`CONTIG_CSV_COLUMNS`] and
+/// [`paf::ASSIGNMENTS_HEADER`]. A percentage or coverage that isn't a finite number, such as the off-target percentage
+/// of a condition without reads, is `NULL`.
+pub const SQL_SCHEMA: &str = "\
+CREATE TABLE IF NOT EXISTS runs (
+    run TEXT PRIMARY KEY
+);
+CREATE TABLE IF NOT EXISTS conditions (
+    run TEXT NOT NULL REFERENCES runs (run),
+    condition TEXT NOT NULL,
+    total_reads INTEGER NOT NULL,
+    on_target_read_count INTEGER NOT NULL,
+    off_target_read_count INTEGER NOT NULL,
+    total_yield INTEGER NOT NULL,
+    on_target_yield INTEGER NOT NULL,
+    off_target_yield INTEGER NOT NULL,
+    off_target_percent REAL,
+    n50 INTEGER NOT NULL,
+    on_target_n50 INTEGER NOT NULL,
+    off_target_n50 INTEGER NOT NULL,
+    unmapped_read_count INTEGER NOT NULL,
+    unmapped_yield INTEGER NOT NULL,
+    PRIMARY KEY (run, condition)
+);
+CREATE TABLE IF NOT EXISTS contigs (
+    run TEXT NOT NULL,
+    condition TEXT NOT NULL,
+    contig TEXT NOT NULL,
+    contig_length INTEGER NOT NULL,
+    total_reads INTEGER NOT NULL,
+    on_target_read_count INTEGER NOT NULL,
+    off_target_read_count INTEGER NOT NULL,
+    total_yield INTEGER NOT NULL,
+    on_target_yield INTEGER NOT NULL,
+    off_target_yield INTEGER NOT NULL,
+    mean_read_length INTEGER NOT NULL,
+    mean_coverage REAL,
+    PRIMARY KEY (run, condition, contig),
+    FOREIGN KEY (run, condition) REFERENCES conditions (run, condition)
+);
+CREATE TABLE IF NOT EXISTS assignments (
+    run TEXT NOT NULL REFERENCES runs (run),
+    read_id TEXT NOT NULL,
+    condition TEXT NOT NULL,
+    on_target INTEGER NOT NULL,
+    contig TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS assignments_run_read_id ON assignments (run, read_id);
+";
+
+/// Quote a string as a SQL string literal, doubling any single quotes in it.
+fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Collect typed columns into a polars [`DataFrame`](polars::frame::DataFrame), with `String`, `UInt64` and
+/// `Float64` columns.
+///
+/// # Errors
+///
+/// Returns an error if the columns have different lengths.
+#[cfg(feature = "polars")]
+fn typed_dataframe(
+    columns: Vec<(&'static str, TypedColumn)>,
+) -> DynResult<polars::frame::DataFrame> {
+    use polars::prelude::{Column, DataFrame};
+
+    let columns = columns
+        .into_iter()
+        .map(|(name, column)| match column {
+            TypedColumn::Text(values) => Column::new(name.into(), values),
+            TypedColumn::Integer(values) => Column::new(name.into(), values),
+            TypedColumn::Real(values) => Column::new(name.into(), values),
+        })
+        .collect();
+    Ok(DataFrame::new(columns)?)
+}
+
+/// Write typed columns to a Parquet file, as a single record batch.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be created or written.
+#[cfg(feature = "parquet")]
+fn write_parquet_table(
+    path: impl AsRef<Path>,
+    columns: Vec<(&'static str, TypedColumn)>,
+) -> DynResult<()> {
+    use arrow::{
+        array::{ArrayRef, Float64Array, StringArray, UInt64Array},
+        datatypes::{DataType, Field, Schema},
+        record_batch::RecordBatch,
+    };
+    use std::sync::Arc;
+
+    let (fields, arrays): (Vec<Field>, Vec<ArrayRef>) = columns
+        .into_iter()
+        .map(|(name, column)| match column {
+            TypedColumn::Text(values) => (
+                Field::new(name, DataType::Utf8, false),
+                Arc::new(StringArray::from(values)) as ArrayRef,
+            ),
+            TypedColumn::Integer(values) => (
+                Field::new(name, DataType::UInt64, false),
+                Arc::new(UInt64Array::from(values)) as ArrayRef,
+            ),
+            TypedColumn::Real(values) => (
+                Field::new(name, DataType::Float64, true),
+                Arc::new(Float64Array::from(values)) as ArrayRef,
+            ),
+        })
+        .unzip();
+    let schema = Arc::new(Schema::new(fields));
+    let batch = RecordBatch::try_new(schema.clone(), arrays)?;
+    let mut writer =
+        parquet::arrow::ArrowWriter::try_new(std::fs::File::create(path)?, schema, None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Convert a formatted number into a SQLite value, storing any that isn't finite, such as `NaN`, as `NULL`.
+#[cfg(feature = "sqlite")]
+fn sqlite_number(value: &str) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match (value.parse::<i64>(), value.parse::<f64>()) {
+        (Ok(number), _) => Value::Integer(number),
+        (_, Ok(number)) if number.is_finite() => Value::Real(number),
+        _ => Value::Null,
     }
 }
 
+/// Join formatted numbers into a list of SQL values, writing any that aren't finite, such as `NaN`, as `NULL`.
+fn sql_numbers(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| match value.parse::<f64>() {
+            Ok(number) if number.is_finite() => value.as_str(),
+            _ => "NULL",
+        })
+        .join(", ")
+}
+
+/// The columns of the per-contig CSV written by [`Summary::write_contig_csv`], in order. Other tabular exports of
+/// the contigs should use the same column names, so they stay consistent with the CSV.
+pub const CONTIG_CSV_COLUMNS: [&str; 11] = [
+    "condition",
+    "contig",
+    "contig_length",
+    "total_reads",
+    "on_target_read_count",
+    "off_target_read_count",
+    "total_yield",
+    "on_target_yield",
+    "off_target_yield",
+    "mean_read_length",
+    "mean_coverage",
+];
+
+/// The values of a column of the conditions or contigs, typed for the columnar exports, see [`typed_columns`].
+#[cfg(any(feature = "parquet", feature = "polars"))]
+enum TypedColumn {
+    /// The condition or contig names.
+    Text(Vec<String>),
+    /// Counts, yields and lengths.
+    Integer(Vec<u64>),
+    /// Percentages and coverages, which are null if they aren't a finite number.
+    Real(Vec<Option<f64>>),
+}
+
+/// Split the records of [`Summary::condition_records`] or [`Summary::contig_records`] into a typed column per name
+/// of `columns`, so the columnar exports share the column names and values of the CSVs. The `condition` and `contig`
+/// columns are text, the `off_target_percent` and `mean_coverage` columns are real numbers, and every other column is
+/// an integer.
+///
+/// # Errors
+///
+/// Returns an error if an integer column has a value that isn't an integer.
+#[cfg(any(feature = "parquet", feature = "polars"))]
+fn typed_columns<const N: usize>(
+    columns: [&'static str; N],
+    records: &[[String; N]],
+) -> DynResult<Vec<(&'static str, TypedColumn)>> {
+    columns
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let values = records.iter().map(|record| record[index].as_str());
+            let column = match name {
+                "condition" | "contig" => TypedColumn::Text(values.map(str::to_string).collect()),
+                "off_target_percent" | "mean_coverage" => TypedColumn::Real(
+                    values
+                        .map(|value| value.parse::<f64>().ok().filter(|value| value.is_finite()))
+                        .collect(),
+                ),
+                _ => TypedColumn::Integer(values.map(str::parse).collect::<Result<_, _>>()?),
+            };
+            Ok((name, column))
+        })
+        .collect()
+}
+
 /// A struct representing a summary of conditions.
 ///
 /// The `Summary` struct contains a hashmap where each key represents the name of a condition, and the corresponding value is a `ConditionSummary` struct
@@ -546,6 +2109,10 @@ impl ConditionSummary {
 /// // Create a new Summary
 /// let mut summary = Summary {
 ///     conditions: HashMap::new(),
+///     unblock_end_reasons: vec!["data_service_unblock_mux_change".to_string()],
+///     unclassified_read_count: 0,
+///     skipped_lines: 0,
+///     reads_missing_from_seqsum: 0,
 /// };
 ///
 /// // Add some condition summaries
@@ -568,739 +2135,5751 @@ impl ConditionSummary {
 ///     println!("Summary for ConditionA: {:?}", condition_summary);
 /// }
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Summary {
     /// Conditions summary for a given region or barcode.
     pub conditions: HashMap<String, ConditionSummary>,
+    /// Sequencing summary end reasons that mark a read as unblocked (rejected) by readfish.
+    /// Defaults to [`DEFAULT_UNBLOCK_END_REASONS`].
+    pub unblock_end_reasons: Vec<String>,
+    /// The number of reads that matched no region or barcode, summarised under [`UNCLASSIFIED_CONDITION`].
+    pub unclassified_read_count: usize,
+    /// The number of malformed PAF lines that were skipped whilst demultiplexing, see [`paf::Paf::set_strict`].
+    pub skipped_lines: usize,
+    /// The number of records skipped because their read was not in the sequencing summary, see
+    /// [`paf::Paf::set_strict_seqsum`]. A large count suggests the sequencing summary is from a different run.
+    pub reads_missing_from_seqsum: usize,
+    /// The number of PAF records whose `ch` tag disagrees with the channel in the sequencing summary, see
+    /// [`SeqSum::set_channel_source`]. Any mismatch suggests the PAF and sequencing summary are from different runs.
+    pub channel_mismatches: usize,
+    /// The length of each contig, from the first alignment to it, that later alignments are checked against, see
+    /// [`Summary::check_contig_length`].
+    pub contig_lengths: HashMap<String, usize>,
+    /// The number of alignments whose `target_length` disagrees with the `.fai`, or with the first alignment to the
+    /// same contig. Any mismatch suggests the reads were aligned to different versions of the reference.
+    pub contig_length_mismatches: usize,
+    /// The `(contig, expected_length, length)` of every distinct contig length disagreement, in the order they were
+    /// found. The expected length is from the `.fai` if one was loaded, or else from the first alignment to the contig.
+    pub contig_length_discrepancies: Vec<(String, usize, usize)>,
+    /// Whether a contig length mismatch is an error, rather than being counted, see
+    /// [`Summary::set_strict_contig_lengths`].
+    pub strict_contig_lengths: bool,
+    /// Whether the reads were classified against stranded targets, so the on-target reads of each condition are
+    /// rendered split by strand, see [`Summary::set_strand_aware`].
+    pub strand_aware: bool,
+    /// Whether the contig N50s are calculated and rendered, see [`Summary::set_keep_read_lengths`].
+    pub keep_read_lengths: bool,
+    /// The number of alignments excluded by the [`paf::ReadFilter`] whilst demultiplexing.
+    pub filtered_out: usize,
+    /// The reads and yield of each flowcell channel, keyed on the channel number, see [`Summary::channel_coords`].
+    pub channels: HashMap<usize, ChannelStats>,
+    /// The length of every contig in the reference, keyed on the contig name, see [`Summary::load_fai`].
+    /// Empty if no `.fai` was loaded, in which case only contigs with alignments are reported.
+    pub reference_lengths: HashMap<String, usize>,
 }
 
-impl fmt::Display for Summary {
+impl Default for Summary {
+    /// An empty summary, with the [`DEFAULT_UNBLOCK_END_REASONS`].
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How the reads given to a [`Summary`] were accounted for, see [`Summary::read_reconciliation`].
+///
+/// Every read is either assigned to a condition, or dropped for one of the reasons below, so the counts add up to
+/// `reads_in`. Reads are counted as they were summarised, so with `deduplicate_reads` only the first alignment of
+/// each read counts, whilst filtered out alignments and skipped lines are counted per PAF line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ReadReconciliation {
+    /// The number of reads given to the summary, assigned or not.
+    pub reads_in: usize,
+    /// The number of mapped reads summarised under a region or barcode.
+    pub assigned: usize,
+    /// The number of reads that matched no region or barcode, see [`Summary::unclassified_read_count`].
+    pub unclassified: usize,
+    /// The number of unmapped reads of a region or barcode, which are neither on nor off-target.
+    pub unmapped: usize,
+    /// The number of alignments excluded by the [`paf::ReadFilter`], see [`Summary::filtered_out`].
+    pub filtered_out: usize,
+    /// The number of reads missing from the sequencing summary, see [`Summary::reads_missing_from_seqsum`].
+    pub missing_from_seqsum: usize,
+    /// The number of malformed PAF lines, see [`Summary::skipped_lines`].
+    pub skipped_lines: usize,
+}
+
+impl ReadReconciliation {
+    /// Get the number of reads that were not assigned to a region or barcode.
+    pub fn dropped(&self) -> usize {
+        self.reads_in - self.assigned
+    }
+
+    /// Get the percentage of reads that were assigned to a region or barcode.
+    ///
+    /// # Returns
+    ///
+    /// The assigned reads as a percentage of the reads in, or 0 if there are no reads.
+    pub fn assigned_percent(&self) -> f64 {
+        if self.reads_in == 0 {
+            0.0
+        } else {
+            self.assigned as f64 / self.reads_in as f64 * 100.0
+        }
+    }
+}
+
+impl fmt::Display for ReadReconciliation {
+    /// Write a one line reconciliation of the reads in against the reads assigned, such as
+    /// `1,234,567 reads in, 1,230,000 assigned (99.63%)`, followed by the reasons any reads were dropped.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Todo rewrite to use Macro!
-        let mut condition_table = Table::new();
-        condition_table.add_row(Row::new(vec![
-            Cell::new("Condition")
-                .with_style(Attr::Bold)
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-            Cell::new("Total reads")
-                .with_style(Attr::Bold)
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-            Cell::new("# Off-target \nreads")
-                .with_style(Attr::Bold)
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-            Cell::new("# On-target \nreads")
-                .with_style(Attr::Bold)
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-            Cell::new("Total Yield")
-                .with_style(Attr::Bold)
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-            Cell::new("Off Target\n Yield")
-                .with_style(Attr::Bold)
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-            Cell::new("On Target\n yield")
-                .with_style(Attr::Bold)
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-            Cell::new("Mean read\n length")
-                .with_style(Attr::Bold)
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-            Cell::new("On target\nMean read\n length")
-                .with_style(Attr::Bold)
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-            Cell::new("Off target\nMean read\n length")
-                .with_style(Attr::Bold)
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-        ]));
-        for (condition_name, condition_summary) in &self.conditions {
-            condition_table.add_row(Row::new(vec![
-                Cell::new(condition_name).with_style(Attr::ForegroundColor(color::BRIGHT_YELLOW)),
-                // total reads
-                Cell::new(
-                    &condition_summary
-                        .total_reads
-                        .to_formatted_string(&Locale::en),
-                )
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-                // off target reads
-                Cell::new(&format!(
-                    "{} ({:.2}%)",
-                    condition_summary
-                        .off_target_read_count
-                        .to_formatted_string(&Locale::en),
-                    condition_summary.off_target_percent
-                ))
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-                // on target reads
-                Cell::new(&format!(
-                    "{} ({:.2}%)",
-                    condition_summary
-                        .on_target_read_count
-                        .to_formatted_string(&Locale::en),
-                    100_f64 - condition_summary.off_target_percent
-                ))
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-                // total yield
-                Cell::new(&format_bases(condition_summary.total_yield()))
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
-                // on target yield
-                Cell::new(&format_bases(condition_summary.off_target_yield))
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
-                // on target yield
-                Cell::new(&format_bases(condition_summary.on_target_yield))
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
-                // mean read length
-                Cell::new(&format_bases(condition_summary.mean_read_length()))
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
-                // on target mean read length
-                Cell::new(&format_bases(
-                    condition_summary.on_target_mean_read_length(),
-                ))
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-                // off target mean read length
-                Cell::new(&format_bases(
-                    condition_summary.off_target_mean_read_length(),
-                ))
-                .with_style(Attr::ForegroundColor(color::GREEN)),
-            ]));
+        write!(
+            f,
+            "{} reads in, {} assigned ({:.2}%)",
+            self.reads_in.to_formatted_string(&Locale::en),
+            self.assigned.to_formatted_string(&Locale::en),
+            self.assigned_percent()
+        )?;
+        let dropped = [
+            (self.unclassified, "unclassified"),
+            (self.unmapped, "unmapped"),
+            (self.filtered_out, "filtered out"),
+            (
+                self.missing_from_seqsum,
+                "missing from the sequencing summary",
+            ),
+            (self.skipped_lines, "malformed"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, reason)| format!("{} {}", count.to_formatted_string(&Locale::en), reason))
+        .join(", ");
+        if !dropped.is_empty() {
+            write!(f, "; dropped {}", dropped)?;
+        }
+        Ok(())
+    }
+}
 
-            // writeln!(
-            //     f,
-            //     "  Off-Target Mean Read Quality: {:.2}",
-            //     condition_summary.off_target_mean_read_quality
-            // )?;
-            // writeln!(
-            //     f,
-            //     "  On-Target Mean Read Quality: {:.2}",
-            //     condition_summary.on_target_mean_read_quality
-            // )?;
-            // writeln!(f, "  N50: {}", condition_summary.n50)?;
-            // writeln!(f, "  On-Target N50: {}", condition_summary.on_target_n50)?;
-            // writeln!(f, "  Off-Target N50: {}", condition_summary.off_target_n50)?;
-        }
-        condition_table.printstd();
-        writeln!(f, "Contigs:")?;
+/// Options controlling how the [`Summary`] tables are rendered.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    /// Whether to style the tables with colours and bold text, using ANSI escape codes.
+    pub color: bool,
+    /// The maximum number of contigs shown in the contig table of each condition. If a condition has more, only the
+    /// contigs with the highest yield are shown, see [`ConditionSummary::top_contigs_by_yield`], followed by a count
+    /// of the rest. `None` shows every contig.
+    pub max_contigs: Option<usize>,
+    /// Whether to render a table of the read count and yield of every target interval, see
+    /// [`Summary::set_target_summaries`].
+    pub show_targets: bool,
+}
 
-        for condition_summary in self.conditions.values() {
-            let mut contig_table = Table::new();
-            contig_table.add_row(row![bFg->"Condition Name", BriH2->&condition_summary.name]);
+impl Default for RenderOptions {
+    /// Colour is enabled if stdout is a TTY and the `NO_COLOR` environment variable is not set. Every contig is shown,
+    /// and the targets table is not.
+    fn default() -> Self {
+        RenderOptions {
+            color: std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal(),
+            max_contigs: None,
+            show_targets: false,
+        }
+    }
+}
+
+/// Apply styles to a table [`Cell`] only when colour is enabled in the [`RenderOptions`].
+trait StyledCell {
+    /// Apply the `attr` style to the cell if `options` has colour enabled, otherwise return the cell unchanged.
+    fn styled(self, options: &RenderOptions, attr: Attr) -> Self;
+}
+
+impl StyledCell for Cell {
+    fn styled(self, options: &RenderOptions, attr: Attr) -> Self {
+        if options.color {
+            self.with_style(attr)
+        } else {
+            self
+        }
+    }
+}
+
+/// Print a table to the writer, with its styling if colour is enabled and a terminfo
+/// terminal is available, otherwise as plain text.
+///
+/// # Arguments
+///
+/// * `table` - The table to print.
+/// * `w` - The writer to print the table to.
+/// * `options` - The options controlling the styling of the table.
+fn print_table(table: &Table, w: &mut dyn Write, options: &RenderOptions) -> std::io::Result<()> {
+    if options.color {
+        if let Some(mut terminal) = term::TerminfoTerminal::new(&mut *w) {
+            table.print_term(&mut terminal)?;
+            return Ok(());
+        }
+    }
+    table.print(w)?;
+    Ok(())
+}
+
+/// Build the row of the condition table for a condition, see [`Summary::render_with_options`].
+///
+/// # Arguments
+///
+/// * `condition_name` - The name of the condition, shown in the first column.
+/// * `condition_summary` - The summary of the condition.
+/// * `options` - The options controlling the styling of the table.
+/// * `total` - Whether this is the total row of every condition, see [`Summary::total`], which is highlighted in bold.
+fn condition_row(
+    condition_name: &str,
+    condition_summary: &ConditionSummary,
+    options: &RenderOptions,
+    total: bool,
+) -> Row {
+    let value = |text: &str| {
+        let cell = Cell::new(text).styled(options, Attr::ForegroundColor(color::GREEN));
+        if total {
+            cell.styled(options, Attr::Bold)
+        } else {
+            cell
+        }
+    };
+    let name_cell = Cell::new(condition_name);
+    let name_cell = if total {
+        name_cell
+            .styled(options, Attr::ForegroundColor(color::BRIGHT_CYAN))
+            .styled(options, Attr::Bold)
+    } else {
+        name_cell.styled(options, Attr::ForegroundColor(color::BRIGHT_YELLOW))
+    };
+    Row::new(vec![
+        name_cell,
+        // total reads
+        value(
+            &condition_summary
+                .total_reads
+                .to_formatted_string(&Locale::en),
+        ),
+        // off target reads
+        value(&format!(
+            "{} ({:.2}%)",
+            condition_summary
+                .off_target_read_count
+                .to_formatted_string(&Locale::en),
+            condition_summary.off_target_percent
+        )),
+        // on target reads
+        value(&format!(
+            "{} ({:.2}%)",
+            condition_summary
+                .on_target_read_count
+                .to_formatted_string(&Locale::en),
+            condition_summary.on_target_percent()
+        )),
+        // unmapped reads
+        value(
+            &condition_summary
+                .unmapped_read_count
+                .to_formatted_string(&Locale::en),
+        ),
+        // total yield
+        value(&format_bases(condition_summary.total_yield())),
+        // on target yield
+        value(&format_bases(condition_summary.off_target_yield)),
+        // on target yield
+        value(&format_bases(condition_summary.on_target_yield)),
+        // mean read length
+        value(&format_bases(condition_summary.mean_read_length())),
+        // on target mean read length
+        value(&format_bases(
+            condition_summary.on_target_mean_read_length(),
+        )),
+        // off target mean read length
+        value(&format_bases(
+            condition_summary.off_target_mean_read_length(),
+        )),
+        // median read length
+        value(&format_bases(condition_summary.median_read_length())),
+        // on target mean identity
+        value(&format!(
+            "{:.2}%",
+            condition_summary.on_target_mean_identity * 100.0
+        )),
+        // off target mean identity
+        value(&format!(
+            "{:.2}%",
+            condition_summary.off_target_mean_identity * 100.0
+        )),
+        // on target mean read quality
+        value(&format!(
+            "{:.2}",
+            condition_summary.on_target_mean_read_quality
+        )),
+        // off target mean read quality
+        value(&format!(
+            "{:.2}",
+            condition_summary.off_target_mean_read_quality
+        )),
+        // unblocked reads
+        value(
+            &condition_summary
+                .unblocked_read_count
+                .to_formatted_string(&Locale::en),
+        ),
+        // enrichment
+        value(&match condition_summary.target_fraction {
+            Some(target_fraction) => {
+                format!("{:.2}x", condition_summary.enrichment(target_fraction))
+            }
+            None => "-".to_string(),
+        }),
+        // n50
+        value(&format_bases(condition_summary.n50)),
+        // on target n50
+        value(&format_bases(condition_summary.on_target_n50)),
+        // off target n50
+        value(&format_bases(condition_summary.off_target_n50)),
+        // estimated coverage
+        value(&match condition_summary.genome_size {
+            Some(genome_size) => {
+                format!("{:.2}x", condition_summary.estimated_coverage(genome_size))
+            }
+            None => "-".to_string(),
+        }),
+        // on target estimated coverage
+        value(&match condition_summary
+            .genome_size
+            .zip(condition_summary.target_fraction)
+        {
+            Some((genome_size, target_fraction)) => format!(
+                "{:.2}x",
+                condition_summary.on_target_estimated_coverage(genome_size, target_fraction)
+            ),
+            None => "-".to_string(),
+        }),
+    ])
+}
+
+/// The metrics of a condition in one of the summaries compared by [`Summary::diff`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DiffMetrics {
+    /// The total yield (base pairs) of the condition, including unmapped reads.
+    pub total_yield: usize,
+    /// The percentage of reads of the condition that are on-target, see [`ConditionSummary::on_target_percent`].
+    pub on_target_percent: f64,
+    /// The fold enrichment of the condition, or `None` if its target fraction is unknown, see
+    /// [`ConditionSummary::enrichment`].
+    pub enrichment: Option<f64>,
+}
+
+impl From<&ConditionSummary> for DiffMetrics {
+    fn from(condition_summary: &ConditionSummary) -> Self {
+        DiffMetrics {
+            total_yield: condition_summary.total_yield(),
+            on_target_percent: condition_summary.on_target_percent(),
+            enrichment: condition_summary
+                .target_fraction
+                .map(|target_fraction| condition_summary.enrichment(target_fraction)),
+        }
+    }
+}
+
+/// The change in a condition between a run and a baseline run, see [`Summary::diff`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ConditionDiff {
+    /// The name of the region or barcode.
+    pub name: String,
+    /// The metrics of the condition in the run, or `None` if the condition is only in the baseline.
+    pub run: Option<DiffMetrics>,
+    /// The metrics of the condition in the baseline, or `None` if the condition is only in the run.
+    pub baseline: Option<DiffMetrics>,
+}
+
+/// Calculate the percentage change from `baseline` to `run`, or `None` if the baseline is 0.
+fn percent_change(run: f64, baseline: f64) -> Option<f64> {
+    (baseline != 0.0).then(|| (run - baseline) / baseline * 100.0)
+}
+
+impl ConditionDiff {
+    /// Get the signed change in total yield from the baseline to the run, or `None` if the condition is missing from
+    /// either.
+    pub fn yield_delta(&self) -> Option<i64> {
+        let (run, baseline) = self.run.zip(self.baseline)?;
+        Some(run.total_yield as i64 - baseline.total_yield as i64)
+    }
+
+    /// Get the percentage change in total yield from the baseline to the run, or `None` if the condition is missing
+    /// from either, or has no baseline yield.
+    pub fn yield_change_percent(&self) -> Option<f64> {
+        let (run, baseline) = self.run.zip(self.baseline)?;
+        percent_change(run.total_yield as f64, baseline.total_yield as f64)
+    }
+
+    /// Get the change in the on-target percentage from the baseline to the run, in percentage points, or `None` if the
+    /// condition is missing from either.
+    pub fn on_target_percent_delta(&self) -> Option<f64> {
+        let (run, baseline) = self.run.zip(self.baseline)?;
+        Some(run.on_target_percent - baseline.on_target_percent)
+    }
+
+    /// Get the signed change in fold enrichment from the baseline to the run, or `None` if the condition is missing
+    /// from either, or either enrichment is unknown.
+    pub fn enrichment_delta(&self) -> Option<f64> {
+        let (run, baseline) = self.run.zip(self.baseline)?;
+        Some(run.enrichment? - baseline.enrichment?)
+    }
+
+    /// Get the percentage change in fold enrichment from the baseline to the run, or `None` if the condition is missing
+    /// from either, either enrichment is unknown, or the baseline enrichment is 0.
+    pub fn enrichment_change_percent(&self) -> Option<f64> {
+        let (run, baseline) = self.run.zip(self.baseline)?;
+        percent_change(run.enrichment?, baseline.enrichment?)
+    }
+}
+
+/// The per condition changes between a run and a baseline run, such as an adaptive sampling run against a control
+/// run on the same reference, see [`Summary::diff`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SummaryDiff {
+    /// The change in every condition in either summary, in natural order of the condition names.
+    pub conditions: Vec<ConditionDiff>,
+}
+
+impl fmt::Display for SummaryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = Vec::new();
+        self.render(&mut buffer).map_err(|_| fmt::Error)?;
+        write!(f, "{}", String::from_utf8_lossy(&buffer))
+    }
+}
+
+/// Format a signed change in bases, such as `+1.20 Mb` or `-300 b`.
+fn format_signed_bases(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{}{}", sign, format_bases(delta.unsigned_abs() as usize))
+}
+
+impl SummaryDiff {
+    /// Render the diff as a table to the provided writer, with a row per condition of the run and baseline yield,
+    /// on-target percentage and enrichment, and the signed change in each.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to render the table to, such as stdout, a file or an in memory buffer.
+    pub fn render(&self, w: &mut dyn Write) -> std::io::Result<()> {
+        self.render_with_options(w, &RenderOptions::default())
+    }
+
+    /// Render the diff as a table to the provided writer, styled according to the given [`RenderOptions`].
+    ///
+    /// A condition in only one of the summaries has `-` for the metrics of the other, and its changes are
+    /// `only in run` or `only in baseline`.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to render the table to.
+    /// * `options` - The options controlling the styling of the table.
+    pub fn render_with_options(
+        &self,
+        w: &mut dyn Write,
+        options: &RenderOptions,
+    ) -> std::io::Result<()> {
+        let mut table = Table::new();
+        table.add_row(Row::new(
+            [
+                "Condition",
+                "Yield",
+                "Baseline\n yield",
+                "Yield\n change",
+                "On target\n reads",
+                "Baseline\n on target\n reads",
+                "On target\n change",
+                "Enrichment",
+                "Baseline\n enrichment",
+                "Enrichment\n change",
+            ]
+            .into_iter()
+            .map(|header| {
+                Cell::new(header)
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN))
+            })
+            .collect(),
+        ));
+        let enrichment = |metrics: &DiffMetrics| match metrics.enrichment {
+            Some(enrichment) => format!("{:.2}x", enrichment),
+            None => "-".to_string(),
+        };
+        for condition_diff in &self.conditions {
+            let missing = match (&condition_diff.run, &condition_diff.baseline) {
+                (None, _) => Some("only in baseline"),
+                (_, None) => Some("only in run"),
+                _ => None,
+            };
+            let change = |change: Option<String>| match missing {
+                Some(missing) => missing.to_string(),
+                None => change.unwrap_or_else(|| "-".to_string()),
+            };
+            let with_percent = |delta: String, percent: Option<f64>| match percent {
+                Some(percent) => format!("{} ({:+.2}%)", delta, percent),
+                None => delta,
+            };
+            let metric = |metrics: &Option<DiffMetrics>,
+                          format: &dyn Fn(&DiffMetrics) -> String| {
+                metrics.as_ref().map_or_else(|| "-".to_string(), format)
+            };
+            let cells = [
+                metric(&condition_diff.run, &|metrics| {
+                    format_bases(metrics.total_yield)
+                }),
+                metric(&condition_diff.baseline, &|metrics| {
+                    format_bases(metrics.total_yield)
+                }),
+                change(condition_diff.yield_delta().map(|delta| {
+                    with_percent(
+                        format_signed_bases(delta),
+                        condition_diff.yield_change_percent(),
+                    )
+                })),
+                metric(&condition_diff.run, &|metrics| {
+                    format!("{:.2}%", metrics.on_target_percent)
+                }),
+                metric(&condition_diff.baseline, &|metrics| {
+                    format!("{:.2}%", metrics.on_target_percent)
+                }),
+                change(
+                    condition_diff
+                        .on_target_percent_delta()
+                        .map(|delta| format!("{:+.2} pp", delta)),
+                ),
+                metric(&condition_diff.run, &enrichment),
+                metric(&condition_diff.baseline, &enrichment),
+                change(condition_diff.enrichment_delta().map(|delta| {
+                    with_percent(
+                        format!("{:+.2}x", delta),
+                        condition_diff.enrichment_change_percent(),
+                    )
+                })),
+            ];
+            let mut row = vec![Cell::new(&condition_diff.name)
+                .styled(options, Attr::ForegroundColor(color::BRIGHT_YELLOW))];
+            row.extend(
+                cells.iter().map(|text| {
+                    Cell::new(text).styled(options, Attr::ForegroundColor(color::GREEN))
+                }),
+            );
+            table.add_row(Row::new(row));
+        }
+        print_table(&table, w, options)
+    }
+}
+
+/// A condition of a run normalised against the matching condition of a control run, see
+/// [`Summary::normalize_against`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct NormalizedCondition {
+    /// The name of the region or barcode.
+    pub name: String,
+    /// The fraction of the yield of the condition in the run that is on-target, see
+    /// [`ConditionSummary::on_target_yield_fraction`].
+    pub on_target_fraction: f64,
+    /// The on-target fraction of the matching condition in the control, or `None` if it isn't in the control.
+    pub control_on_target_fraction: Option<f64>,
+    /// The on-target fraction of the run divided by that of the control, or `None` if the condition isn't in the
+    /// control, or has no on-target yield in the control.
+    pub enrichment: Option<f64>,
+    /// Whether the condition is in the control. Conditions that aren't are passed through without an enrichment.
+    pub in_control: bool,
+}
+
+/// The conditions of a run normalised against a control run, to give a control-corrected enrichment of each
+/// condition, see [`Summary::normalize_against`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "pyo3_support", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct NormalizedSummary {
+    /// Every condition of the run, in natural order of the condition names.
+    pub conditions: Vec<NormalizedCondition>,
+}
+
+impl fmt::Display for NormalizedSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = Vec::new();
+        self.render(&mut buffer).map_err(|_| fmt::Error)?;
+        write!(f, "{}", String::from_utf8_lossy(&buffer))
+    }
+}
+
+impl NormalizedSummary {
+    /// Render the normalised conditions as a table to the provided writer, with a row per condition of the on-target
+    /// fraction in the run and control, and the control-corrected enrichment.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to render the table to, such as stdout, a file or an in memory buffer.
+    pub fn render(&self, w: &mut dyn Write) -> std::io::Result<()> {
+        self.render_with_options(w, &RenderOptions::default())
+    }
+
+    /// Render the normalised conditions as a table to the provided writer, styled according to the given
+    /// [`RenderOptions`].
+    ///
+    /// A condition that isn't in the control has `-` for the control on-target fraction, and an enrichment of
+    /// `not in control`.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to render the table to.
+    /// * `options` - The options controlling the styling of the table.
+    pub fn render_with_options(
+        &self,
+        w: &mut dyn Write,
+        options: &RenderOptions,
+    ) -> std::io::Result<()> {
+        let mut table = Table::new();
+        table.add_row(Row::new(
+            [
+                "Condition",
+                "On target\n yield",
+                "Control\n on target\n yield",
+                "Control\n corrected\n enrichment",
+            ]
+            .into_iter()
+            .map(|header| {
+                Cell::new(header)
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN))
+            })
+            .collect(),
+        ));
+        for condition in &self.conditions {
+            let cells = [
+                format!("{:.2}%", condition.on_target_fraction * 100.0),
+                condition.control_on_target_fraction.map_or_else(
+                    || "-".to_string(),
+                    |fraction| format!("{:.2}%", fraction * 100.0),
+                ),
+                match (condition.in_control, condition.enrichment) {
+                    (false, _) => "not in control".to_string(),
+                    (true, Some(enrichment)) => format!("{:.2}x", enrichment),
+                    (true, None) => "-".to_string(),
+                },
+            ];
+            let mut row = vec![Cell::new(&condition.name)
+                .styled(options, Attr::ForegroundColor(color::BRIGHT_YELLOW))];
+            row.extend(
+                cells.iter().map(|text| {
+                    Cell::new(text).styled(options, Attr::ForegroundColor(color::GREEN))
+                }),
+            );
+            table.add_row(Row::new(row));
+        }
+        print_table(&table, w, options)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = Vec::new();
+        self.render(&mut buffer).map_err(|_| fmt::Error)?;
+        write!(f, "{}", String::from_utf8_lossy(&buffer))
+    }
+}
+
+impl Summary {
+    /// Render the full summary report, the condition table followed by a contig table for each condition,
+    /// to the provided writer.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to render the report to, such as stdout, a file or an in memory buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let summary = Summary::new();
+    /// let mut buffer = Vec::new();
+    /// summary.render(&mut buffer)?;
+    /// ```
+    pub fn render(&self, w: &mut dyn Write) -> std::io::Result<()> {
+        self.render_with_options(w, &RenderOptions::default())
+    }
+
+    /// Render the full summary report to the provided writer, styled according to the given [`RenderOptions`].
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to render the report to.
+    /// * `options` - The options controlling the styling of the tables.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let summary = Summary::new();
+    /// summary.render_with_options(&mut std::io::stdout(), &RenderOptions { color: false, max_contigs: None, show_targets: false })?;
+    /// ```
+    pub fn render_with_options(
+        &self,
+        w: &mut dyn Write,
+        options: &RenderOptions,
+    ) -> std::io::Result<()> {
+        // Todo rewrite to use Macro!
+        let mut condition_table = Table::new();
+        condition_table.add_row(Row::new(vec![
+            Cell::new("Condition")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Total reads")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("# Off-target \nreads")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("# On-target \nreads")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("# Unmapped \nreads")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Total Yield")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Off Target\n Yield")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("On Target\n yield")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Mean read\n length")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("On target\nMean read\n length")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Off target\nMean read\n length")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Median read\n length")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("On target\nMean\n identity")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Off target\nMean\n identity")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("On target\nMean read\n quality")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Off target\nMean read\n quality")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Unblocked\n reads")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Enrichment")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("N50")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("On target\nN50")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Off target\nN50")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("Estimated\n coverage")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+            Cell::new("On target\nestimated\n coverage")
+                .styled(options, Attr::Bold)
+                .styled(options, Attr::ForegroundColor(color::GREEN)),
+        ]));
+        for (condition_name, condition_summary) in &self.conditions {
+            condition_table.add_row(condition_row(
+                condition_name,
+                condition_summary,
+                options,
+                false,
+            ));
+        }
+        if self.conditions.len() > 1 {
+            condition_table.add_row(condition_row(TOTAL_CONDITION, &self.total(), options, true));
+        }
+        print_table(&condition_table, w, options)?;
+        writeln!(w, "{}", self.read_reconciliation())?;
+        writeln!(
+            w,
+            "Unclassified reads: {} ({:.2}%)",
+            self.unclassified_read_count
+                .to_formatted_string(&Locale::en),
+            self.unclassified_percent()
+        )?;
+        if self.skipped_lines > 0 {
+            writeln!(
+                w,
+                "Skipped malformed lines: {}",
+                self.skipped_lines.to_formatted_string(&Locale::en)
+            )?;
+        }
+        if self.reads_missing_from_seqsum > 0 {
+            writeln!(
+                w,
+                "Reads missing from the sequencing summary: {}",
+                self.reads_missing_from_seqsum
+                    .to_formatted_string(&Locale::en)
+            )?;
+        }
+        if self.channel_mismatches > 0 {
+            writeln!(
+                w,
+                "Reads whose ch tag disagrees with the sequencing summary channel: {}",
+                self.channel_mismatches.to_formatted_string(&Locale::en)
+            )?;
+        }
+        if self.contig_length_mismatches > 0 {
+            writeln!(
+                w,
+                "Alignments whose contig length disagrees with the reference: {} (contigs: {})",
+                self.contig_length_mismatches
+                    .to_formatted_string(&Locale::en),
+                self.contig_length_discrepancies
+                    .iter()
+                    .map(|(contig, _, _)| contig)
+                    .unique()
+                    .join(", ")
+            )?;
+        }
+        if self.filtered_out > 0 {
+            writeln!(
+                w,
+                "Alignments filtered out: {}",
+                self.filtered_out.to_formatted_string(&Locale::en)
+            )?;
+        }
+        for condition_summary in self
+            .conditions
+            .values()
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+        {
+            if let Some(uncovered_targets) = &condition_summary.uncovered_targets {
+                writeln!(
+                    w,
+                    "{}: {} targets with zero coverage",
+                    condition_summary.name,
+                    uncovered_targets.len().to_formatted_string(&Locale::en)
+                )?;
+            }
+            if self.strand_aware && condition_summary.on_target_read_count > 0 {
+                writeln!(
+                    w,
+                    "{}: on-target reads by strand: {} forward (+), {} reverse (-)",
+                    condition_summary.name,
+                    condition_summary
+                        .forward_on_target_read_count
+                        .to_formatted_string(&Locale::en),
+                    condition_summary
+                        .reverse_on_target_read_count
+                        .to_formatted_string(&Locale::en)
+                )?;
+            }
+        }
+        self.render_end_reasons(w, options)?;
+        writeln!(w, "Contigs:")?;
+
+        for condition_summary in self.conditions.values() {
+            let mut contig_table = Table::new();
+            contig_table.add_row(Row::new(vec![
+                Cell::new("Condition Name")
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
+                Cell::new(&condition_summary.name)
+                    .styled(options, Attr::BackgroundColor(color::RED))
+                    .styled(options, Attr::Italic(true))
+                    .with_hspan(2),
+            ]));
             // Create a custom format with left-leading spaces
             contig_table.get_format();
-            contig_table.add_row(Row::new(vec![
+            let mut header = Row::new(vec![
                 Cell::new("Contig")
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                 Cell::new("Contig Length")
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                 Cell::new("Read count")
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                 Cell::new("Yield")
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                 Cell::new("Mean \nRead Length")
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                 Cell::new("On Target\n Reads")
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                 Cell::new("Off \nTarget Reads")
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                 Cell::new("Mean read\n length")
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                 Cell::new("On target\nMean read\n length")
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                 Cell::new("Off target\nMean read\n length")
-                    .with_style(Attr::Bold)
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
-            ]));
-            for (contig_name, contig_summary) in condition_summary
-                .contigs
-                .iter()
-                .sorted_by(|(key1, _), (key2, _)| natord::compare(key1, key2))
-            {
-                contig_table.add_row(Row::new(vec![
-                    Cell::new(contig_name)
-                        .with_style(Attr::Bold)
-                        .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
+                Cell::new("Mean\n coverage")
+                    .styled(options, Attr::Bold)
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
+            ]);
+            // The contig N50 is only calculated when the read lengths are kept
+            if self.keep_read_lengths {
+                header.insert_cell(
+                    header.len() - 1,
+                    Cell::new("N50")
+                        .styled(options, Attr::Bold)
+                        .styled(options, Attr::ForegroundColor(color::GREEN)),
+                );
+            }
+            contig_table.add_row(header);
+            let contigs: Vec<&ContigSummary> = match options.max_contigs {
+                Some(max_contigs) if condition_summary.contigs.len() > max_contigs => {
+                    condition_summary.top_contigs_by_yield(max_contigs)
+                }
+                _ => condition_summary
+                    .contigs
+                    .values()
+                    .sorted_by(|a, b| natord::compare(&a.name, &b.name))
+                    .collect(),
+            };
+            for contig_summary in &contigs {
+                let mut row = Row::new(vec![
+                    Cell::new(&contig_summary.name)
+                        .styled(options, Attr::Bold)
+                        .styled(options, Attr::ForegroundColor(color::GREEN)),
                     Cell::new(&contig_summary.length.to_formatted_string(&Locale::en))
-                        .with_style(Attr::ForegroundColor(color::GREEN)),
+                        .styled(options, Attr::ForegroundColor(color::GREEN)),
                     Cell::new(
                         &contig_summary
                             .total_reads()
                             .to_formatted_string(&Locale::en),
                     )
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                     Cell::new(&format_bases(contig_summary.total_bases))
-                        .with_style(Attr::ForegroundColor(color::GREEN)),
+                        .styled(options, Attr::ForegroundColor(color::GREEN)),
                     Cell::new(&format_bases(contig_summary.mean_read_length()))
-                        .with_style(Attr::ForegroundColor(color::GREEN)),
+                        .styled(options, Attr::ForegroundColor(color::GREEN)),
                     Cell::new(
                         &contig_summary
                             .on_target_read_count
                             .to_formatted_string(&Locale::en),
                     )
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                     Cell::new(
                         &contig_summary
                             .off_target_read_count
                             .to_formatted_string(&Locale::en),
                     )
-                    .with_style(Attr::ForegroundColor(color::GREEN)),
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
                     //mean read length
                     Cell::new(&format_bases(contig_summary.mean_read_length()))
-                        .with_style(Attr::ForegroundColor(color::GREEN)),
+                        .styled(options, Attr::ForegroundColor(color::GREEN)),
                     // on target mean read length
                     Cell::new(&format_bases(contig_summary.on_target_mean_read_length()))
-                        .with_style(Attr::ForegroundColor(color::GREEN)),
+                        .styled(options, Attr::ForegroundColor(color::GREEN)),
                     // off target mean read length
                     Cell::new(&format_bases(contig_summary.off_target_mean_read_length()))
-                        .with_style(Attr::ForegroundColor(color::GREEN)),
-                ]));
+                        .styled(options, Attr::ForegroundColor(color::GREEN)),
+                    // mean coverage
+                    Cell::new(&format!("{:.2}x", contig_summary.mean_coverage))
+                        .styled(options, Attr::ForegroundColor(color::GREEN)),
+                ]);
+                if self.keep_read_lengths {
+                    row.insert_cell(
+                        row.len() - 1,
+                        Cell::new(&format_bases(contig_summary.n50))
+                            .styled(options, Attr::ForegroundColor(color::GREEN)),
+                    );
+                }
+                contig_table.add_row(row);
                 // Print other fields from ContigSummary here
                 // For example:
-                // writeln!(f, "    Contig Mean Read Length: {}", contig_summary.mean_read_length)?;
+                // writeln!(w, "    Contig Mean Read Length: {}", contig_summary.mean_read_length)?;
             }
-            contig_table.printstd();
+            print_table(&contig_table, w, options)?;
+            if contigs.len() < condition_summary.contigs.len() {
+                writeln!(
+                    w,
+                    "... and {} more contigs",
+                    (condition_summary.contigs.len() - contigs.len())
+                        .to_formatted_string(&Locale::en)
+                )?;
+            }
+        }
+        if options.show_targets {
+            self.render_targets(w, options)?;
         }
         Ok(())
     }
-}
 
-impl Summary {
+    /// Render a table of the on-target read count and yield of every target interval of every condition. Nothing is
+    /// rendered if the targets haven't been counted with [`Summary::set_target_summaries`].
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to render the table to.
+    /// * `options` - The options controlling the styling of the table.
+    fn render_targets(&self, w: &mut dyn Write, options: &RenderOptions) -> std::io::Result<()> {
+        let conditions: Vec<(&String, &Vec<TargetSummary>)> = self
+            .conditions
+            .values()
+            .filter_map(|condition_summary| {
+                condition_summary
+                    .targets
+                    .as_ref()
+                    .map(|targets| (&condition_summary.name, targets))
+            })
+            .sorted_by(|a, b| a.0.cmp(b.0))
+            .collect();
+        if conditions.is_empty() {
+            return Ok(());
+        }
+        let mut table = Table::new();
+        table.add_row(Row::new(
+            ["Condition", "Contig", "Start", "Stop", "Reads", "Yield"]
+                .into_iter()
+                .map(|header| {
+                    Cell::new(header)
+                        .styled(options, Attr::Bold)
+                        .styled(options, Attr::ForegroundColor(color::GREEN))
+                })
+                .collect(),
+        ));
+        for (condition_name, targets) in conditions {
+            for target in targets {
+                let mut row = vec![Cell::new(condition_name)
+                    .styled(options, Attr::ForegroundColor(color::BRIGHT_YELLOW))];
+                row.extend(
+                    [
+                        target.contig.clone(),
+                        target.start.to_formatted_string(&Locale::en),
+                        target.stop.to_formatted_string(&Locale::en),
+                        target.read_count.to_formatted_string(&Locale::en),
+                        format_bases(target.total_bases),
+                    ]
+                    .iter()
+                    .map(|text| {
+                        Cell::new(text).styled(options, Attr::ForegroundColor(color::GREEN))
+                    }),
+                );
+                table.add_row(Row::new(row));
+            }
+        }
+        writeln!(w, "Targets:")?;
+        print_table(&table, w, options)
+    }
+
+    /// Render a table of the number of reads of each condition with each sequencing summary end reason, which
+    /// reflects the decisions readfish made. Nothing is rendered if no read had an end reason.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to render the table to.
+    /// * `options` - The options controlling the styling of the table.
+    fn render_end_reasons(
+        &self,
+        w: &mut dyn Write,
+        options: &RenderOptions,
+    ) -> std::io::Result<()> {
+        let end_reasons: Vec<EndReason> = self
+            .conditions
+            .values()
+            .flat_map(|condition_summary| condition_summary.end_reasons.keys())
+            .map(|end_reason| EndReason::from(end_reason.as_str()))
+            .unique()
+            .sorted()
+            .collect();
+        if end_reasons.is_empty() {
+            return Ok(());
+        }
+        let mut table = Table::new();
+        table.add_row(Row::new(
+            std::iter::once("Condition")
+                .chain(end_reasons.iter().map(EndReason::as_str))
+                .map(|header| {
+                    Cell::new(header)
+                        .styled(options, Attr::Bold)
+                        .styled(options, Attr::ForegroundColor(color::GREEN))
+                })
+                .collect(),
+        ));
+        for condition_summary in self
+            .conditions
+            .values()
+            .sorted_by(|a, b| natord::compare(&a.name, &b.name))
+        {
+            let reads_with_end_reason: usize = condition_summary.end_reasons.values().sum();
+            let mut row = vec![Cell::new(&condition_summary.name)
+                .styled(options, Attr::ForegroundColor(color::BRIGHT_YELLOW))];
+            for end_reason in &end_reasons {
+                let count = condition_summary
+                    .end_reasons
+                    .get(end_reason.as_str())
+                    .copied()
+                    .unwrap_or(0);
+                let percent = if reads_with_end_reason == 0 {
+                    0.0
+                } else {
+                    count as f64 / reads_with_end_reason as f64 * 100.0
+                };
+                row.push(
+                    Cell::new(&format!(
+                        "{} ({:.2}%)",
+                        count.to_formatted_string(&Locale::en),
+                        percent
+                    ))
+                    .styled(options, Attr::ForegroundColor(color::GREEN)),
+                );
+            }
+            table.add_row(Row::new(row));
+        }
+        writeln!(w, "End reasons:")?;
+        print_table(&table, w, options)
+    }
+
     /// Create a new `Summary` instance with default values for all fields.
     fn new() -> Self {
         Summary {
             conditions: HashMap::new(),
+            unblock_end_reasons: DEFAULT_UNBLOCK_END_REASONS
+                .iter()
+                .map(|end_reason| end_reason.to_string())
+                .collect(),
+            unclassified_read_count: 0,
+            skipped_lines: 0,
+            reads_missing_from_seqsum: 0,
+            channel_mismatches: 0,
+            contig_lengths: HashMap::new(),
+            contig_length_mismatches: 0,
+            contig_length_discrepancies: Vec::new(),
+            strict_contig_lengths: false,
+            strand_aware: false,
+            keep_read_lengths: false,
+            filtered_out: 0,
+            channels: HashMap::new(),
+            reference_lengths: HashMap::new(),
         }
     }
 
-    /// Get the summary for the specified condition. If the condition does not exist in the
-    /// `Summary`, it will be created with default values.
+    /// Add a classified alignment to the summary for its condition.
     ///
-    /// # Arguments
+    /// Updates the read counts, yields and lengths from the alignment, and, if the read has a sequencing summary
+    /// record, the unblocked count, mean read quality and start time. Reads keyed on [`UNCLASSIFIED_CONDITION`]
+    /// are also counted as unclassified. The read is also counted in the [`ChannelStats`] of its channel.
     ///
-    /// * `condition_name`: A `String` representing the name or identifier of the condition.
+    /// # Arguments
     ///
-    /// # Returns
+    /// * `condition_name` - The condition key the read was assigned to.
+    /// * `channel` - The channel the read was sequenced on.
+    /// * `record` - The alignment of the read.
+    /// * `read_on` - Whether the alignment is on target.
+    /// * `seq_sum_record` - The sequencing summary record for the read, if one was found.
     ///
-    /// A reference to the `ConditionSummary` for the specified condition.
+    /// # Errors
     ///
-    /// # Example
+    /// Returns an error if the condition summary cannot be updated with the alignment, or if the length of its contig
+    /// disagrees with earlier alignments in strict mode, see [`Summary::check_contig_length`].
+    pub fn add_record(
+        &mut self,
+        condition_name: &str,
+        channel: usize,
+        record: &impl AlignmentRecord,
+        read_on: bool,
+        seq_sum_record: Option<SeqSumRecord>,
+    ) -> DynResult<()> {
+        self.check_contig_length(record)?;
+        self.channels
+            .entry(channel)
+            .or_default()
+            .update(record, read_on);
+        let unblocked = seq_sum_record
+            .as_ref()
+            .and_then(|seq_sum_record| seq_sum_record.end_reason.as_ref())
+            .is_some_and(|end_reason| self.is_unblocked(end_reason.as_str()));
+        let condition_summary = if condition_name == UNCLASSIFIED_CONDITION {
+            self.unclassified()
+        } else {
+            self.conditions_entry(condition_name)
+        };
+        let query_length = record.query_length();
+        let unmapped = record.is_unmapped();
+        condition_summary.update(record, read_on)?;
+        if unblocked {
+            condition_summary.update_unblocked(query_length);
+        }
+        if let Some(seq_sum_record) = seq_sum_record {
+            // Unmapped reads are neither on nor off-target, so have a read quality of their own
+            match seq_sum_record.mean_qscore {
+                Some(mean_qscore) if unmapped => {
+                    condition_summary.update_unmapped_read_quality(mean_qscore)
+                }
+                Some(mean_qscore) => {
+                    condition_summary.update_mean_read_quality(mean_qscore, read_on)
+                }
+                None => {}
+            }
+            condition_summary.update_start_time(seq_sum_record.start_time, query_length);
+            if let Some(end_reason) = &seq_sum_record.end_reason {
+                condition_summary.update_end_reason(end_reason);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a secondary or supplementary alignment of a read that has already been counted with [`Summary::add_record`].
     ///
-    /// ```rust,ignore
-    /// use std::collections::HashMap;
+    /// Only the summary of the contig the alignment is on is updated, see [`ConditionSummary::update_contig`], so
+    /// the read and its yield are not counted twice in the condition.
     ///
-    /// let mut summary = Summary::new();
+    /// # Arguments
     ///
-    /// // Get or add the condition with the name "Condition A"
-    /// let condition_a = summary.conditions("Condition A".to_string());
+    /// * `condition_name` - The condition key the read was assigned to.
+    /// * `record` - The secondary or supplementary alignment of the read.
+    /// * `read_on` - Whether the alignment is on target.
     ///
-    /// // Modify the fields of the condition summary
-    /// condition_a.set_total_reads(10000);
-    /// condition_a.set_on_target_read_count(8000);
-    /// condition_a.set_off_target_read_count(2000);
-    /// // ...
+    /// # Errors
     ///
-    /// // Get or add another condition
-    /// let condition_b = summary.conditions("Condition B".to_string());
-    /// // ...
-    /// ```
-    pub fn conditions<T: Deref<Target = str>>(
+    /// Returns an error if the length of the contig of the alignment disagrees with earlier alignments in strict mode,
+    /// see [`Summary::check_contig_length`].
+    pub fn add_secondary_alignment(
         &mut self,
-        condition_name: T,
-    ) -> &mut ConditionSummary {
-        self.conditions
-            .entry(condition_name.to_string())
-            .or_insert(ConditionSummary::new(condition_name.to_string()))
-    }
-}
-/// Demultiplex PAF records based on the specified configuration.
-///
-/// This function takes two file paths as inputs, `toml_path` and `paf_path`, representing
-/// the paths to the TOML configuration file and the PAF file, respectively. The TOML configuration
-/// is read using the `readfish::Conf::from_file` function, and the PAF file is opened and checked using the
-/// `paf::open_paf_for_reading` function. The resulting PAF records are then demultiplexed based on the
-/// information provided in the configuration file.
-///
-/// Note: The current implementation initializes a new `paf::Paf` object with a hardcoded PAF file
-/// path "resources/test_paf_With_seq_sum.paf" and calls its `demultiplex` method with the parsed
-/// TOML configuration. However, the line is commented out, so the actual demultiplexing process
-/// is not performed. Please ensure that the proper PAF object is used and uncommented to perform
-/// the demultiplexing.
-///
-/// If there are barcodes present in the Conf TOML file, and the barcode_arrangement column is missing from the
-/// the sequencing summary file, the function will panic.
-///
-/// # Arguments
-///
-/// * `toml_path`: The file path to the TOML configuration file.
-/// * `paf_path`: The file path to the PAF file to be demultiplexed.
-///
-/// # Examples
-///
-/// ```rust,ignore
-/// use std::path::Path;
-/// demultiplex_paf("config.toml", "file.paf");
-/// ```
-///
-pub fn _demultiplex_paf(
-    toml_path: impl AsRef<Path>,
-    paf_path: impl AsRef<Path>,
-    sequencing_summary_path: Option<impl AsRef<Path>>,
-    print_summary: bool,
-    _csv_out: Option<impl AsRef<Path>>,
-) {
-    let toml_path = toml_path.as_ref();
-    let paf_path = paf_path.as_ref();
-    let mut toml = readfish::Conf::from_file(toml_path);
-    let mut paf = paf::Paf::new(paf_path);
-    let seq_sum =
-        sequencing_summary_path.map(|path| sequencing_summary::SeqSum::from_file(path).unwrap());
-    let mut seq_sum = seq_sum;
-    let mut summary = Summary::new();
-    paf.demultiplex(&mut toml, seq_sum.as_mut(), Some(&mut summary))
-        .unwrap();
-    if print_summary {
-        println!("{}", summary);
+        condition_name: &str,
+        record: &impl AlignmentRecord,
+        read_on: bool,
+    ) -> DynResult<()> {
+        self.check_contig_length(record)?;
+        // Not through `unclassified`, as the read has already been counted as unclassified
+        self.conditions_entry(condition_name)
+            .update_contig(record, read_on);
+        Ok(())
     }
-}
 
-// PYTHON PyO3 STuff below ////////////////////////
-#[cfg(feature = "pyo3_support")]
-#[pyclass]
-/// Organise the data and methods for analysing a readfish PAF file.
-pub struct ReadfishSummary {
-    /// Stores the aggregated summary numbers for the readfish run
-    summary: RefCell<Summary>,
-    /// The config TOML file for the readfish tun
-    _conf: Option<Conf>,
-    /// The sequencing summary file
-    _sequencing_summary: Option<SeqSum>,
-    /// The PAF file
-    _paf_file: Option<Paf>,
-}
-#[cfg(not(feature = "pyo3_support"))]
-/// Organise the data and methods for analysing a readfish PAF file.
-pub struct ReadfishSummary {
-    /// Stores the aggregated summary numbers for the readfish run
-    summary: RefCell<Summary>,
-    /// The config TOML file for the readfish tun
-    _conf: Option<Conf>,
-    /// The sequencing summary file
-    _sequencing_summary: Option<SeqSum>,
-    /// The PAF file
-    _paf_file: Option<Paf>,
-}
-
-impl Default for ReadfishSummary {
-    fn default() -> Self {
-        ReadfishSummary::new()
-    }
-}
-
-impl ReadfishSummary {
-    /// Creates a new instance of `ReadfishSummary` with default values.
+    /// Count an on-target alignment in each of the targets of its condition that it overlaps, so that the reads of
+    /// every target can be summarised with [`Summary::target_summaries`], see [`ContigSummary::add_target_hits`].
     ///
-    /// This function initializes a new `ReadfishSummary` struct with default values
-    /// for all fields. The `summary` field will be initialized with an empty `Summary`
-    /// instance. The `_conf`, `_sequencing_summary`, and `_paf_file` fields will be set
-    /// to `None`, indicating that they have not been initialized with specific values yet.
+    /// This is called for each alignment added with [`Summary::add_record`] or [`Summary::add_secondary_alignment`],
+    /// with the targets found when the read was classified.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// A new `ReadfishSummary` instance with default values.
+    /// * `condition_name` - The condition key the read was assigned to.
+    /// * `record` - The alignment of the read.
+    /// * `targets` - The indices of the merged targets of the condition on the contig that the alignment overlaps, see
+    ///   [`Conf::overlapping_targets`]. Empty if the alignment is off target.
+    pub fn add_target_hits(
+        &mut self,
+        condition_name: &str,
+        record: &impl AlignmentRecord,
+        targets: Range<usize>,
+    ) {
+        if targets.is_empty() || record.is_unmapped() {
+            return;
+        }
+        self.conditions_entry(condition_name)
+            .get_or_add_contig(record.target_name(), record.target_length())
+            .add_target_hits(targets, record.query_length());
+    }
+
+    /// Add a read from the sequencing summary that has not been aligned, to summarise a run before alignment, see
+    /// [`_summarise_seqsum`].
     ///
-    /// # Examples
+    /// The read is counted as unmapped in its condition, see [`ConditionSummary::update_unmapped`], with its length
+    /// from the sequencing summary, or 0 if there is no length column. The unblocked count, mean read quality, start
+    /// time and end reason are updated as in [`Summary::add_record`], and the read is counted in the
+    /// [`ChannelStats`] of its channel. Reads keyed on [`UNCLASSIFIED_CONDITION`] are also counted as unclassified.
     ///
-    /// ```
-    /// use readfish_tools::ReadfishSummary;
+    /// # Arguments
     ///
-    /// let summary = ReadfishSummary::new();
-    /// assert_eq!(summary.has_conf(), false); // _conf field is not set yet
-    /// assert_eq!(summary.has_sequencing_summary(), false); // _sequencing_summary field is not set yet
-    /// assert_eq!(summary.has_paf_file(), false); // _paf_file field is not set yet
-    /// ```
-    pub fn new() -> Self {
-        ReadfishSummary {
-            summary: RefCell::new(Summary::new()),
-            _conf: None,
-            _sequencing_summary: None,
-            _paf_file: None,
+    /// * `condition_name` - The condition key the read was assigned to.
+    /// * `seq_sum_record` - The sequencing summary record of the read.
+    pub fn add_unaligned_read(&mut self, condition_name: &str, seq_sum_record: &SeqSumRecord) {
+        let read_length = seq_sum_record.length.unwrap_or(0);
+        let channel_stats = self.channels.entry(seq_sum_record.channel).or_default();
+        channel_stats.read_count += 1;
+        channel_stats.total_yield += read_length;
+        let unblocked = seq_sum_record
+            .end_reason
+            .as_ref()
+            .is_some_and(|end_reason| self.is_unblocked(end_reason.as_str()));
+        let condition_summary = if condition_name == UNCLASSIFIED_CONDITION {
+            self.unclassified()
+        } else {
+            self.conditions_entry(condition_name)
+        };
+        condition_summary.update_unmapped(read_length);
+        if unblocked {
+            condition_summary.update_unblocked(read_length);
+        }
+        if let Some(mean_qscore) = seq_sum_record.mean_qscore {
+            condition_summary.update_unmapped_read_quality(mean_qscore);
+        }
+        condition_summary.update_start_time(seq_sum_record.start_time, read_length);
+        if let Some(end_reason) = &seq_sum_record.end_reason {
+            condition_summary.update_end_reason(end_reason);
         }
-    }
-    /// Update a condition on the summary
-    // pub fn update_condition(
-    //     &mut self,
-    //     condition_name: &str,
-    //     paf_record: PafRecord,
-    //     on_target: bool,
-    // ) {
-    //     let condition_summary = self.summary.borrow_mut().conditions(condition_name);
-    //     condition_summary.update(paf_record, on_target).unwrap();
-    // }
-
-    /// Get the config TOML file for the readfish run.
-    pub fn conf(&self) -> Option<&Conf> {
-        self._conf.as_ref()
-    }
-
-    /// Set the config TOML file for the readfish run.
-    pub fn set_conf(&mut self, conf: Conf) {
-        self._conf = Some(conf);
-    }
-
-    /// Parse a path to a config file and add it to the ReadfishSummary
-    pub fn parse_conf_file(&mut self, conf_path: PathBuf) {
-        let conf = Conf::from_file(conf_path);
-        self._conf = Some(conf);
-    }
-
-    /// Get the sequencing summary file.
-    pub fn sequencing_summary(&self) -> Option<&SeqSum> {
-        self._sequencing_summary.as_ref()
-    }
-
-    /// Set the sequencing summary file.
-    pub fn set_sequencing_summary(&mut self, sequencing_summary: SeqSum) {
-        self._sequencing_summary = Some(sequencing_summary);
     }
 
-    /// Parse a provided sequencing summary file path and set it on the summary
-    pub fn parse_sequencing_summary(&mut self, seq_sum_path: PathBuf) {
-        let sequencing_summary = SeqSum::from_file(seq_sum_path).unwrap();
-        self.set_sequencing_summary(sequencing_summary);
+    /// Set whether an alignment whose contig length disagrees with the `.fai`, or with earlier alignments to the same
+    /// contig, is an error, rather than being logged and counted in [`Summary::contig_length_mismatches`].
+    ///
+    /// # Arguments
+    ///
+    /// * `strict_contig_lengths` - `true` to error on contig length mismatches, `false` to count them.
+    pub fn set_strict_contig_lengths(&mut self, strict_contig_lengths: bool) {
+        self.strict_contig_lengths = strict_contig_lengths;
     }
 
-    /// Get the PAF file.
-    pub fn paf_file(&self) -> Option<&Paf> {
-        self._paf_file.as_ref()
+    /// Set whether the reads were classified against stranded targets, see [`Conf::set_strand_aware`], so that the
+    /// forward and reverse strand on-target read counts of each condition are rendered.
+    ///
+    /// # Arguments
+    ///
+    /// * `strand_aware` - `true` if only targets on the strand of each alignment were considered.
+    pub fn set_strand_aware(&mut self, strand_aware: bool) {
+        self.strand_aware = strand_aware;
     }
 
-    /// Set the PAF file.
-    pub fn set_paf_file(&mut self, paf_file: Paf) {
-        self._paf_file = Some(paf_file);
+    /// Set whether every condition keeps the read lengths of its contigs, to calculate and render the N50 of each
+    /// contig, see [`ConditionSummary::set_keep_read_lengths`]. Off by default, as it keeps a length per alignment.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep_read_lengths` - `true` to calculate the N50 of each contig.
+    pub fn set_keep_read_lengths(&mut self, keep_read_lengths: bool) {
+        self.keep_read_lengths = keep_read_lengths;
+        for condition_summary in self.conditions.values_mut() {
+            condition_summary.set_keep_read_lengths(keep_read_lengths);
+        }
     }
 
-    /// Check if the config TOML file is set.
-    pub fn has_conf(&self) -> bool {
-        self._conf.is_some()
+    /// Check the `target_length` of an alignment against the length of its contig in the `.fai`, if one was loaded,
+    /// or else the length of the first alignment to the contig.
+    ///
+    /// Reads aligned to two versions of a reference, such as hg19 and hg38, have contigs of the same name with
+    /// different lengths. Each mismatching alignment is counted in [`Summary::contig_length_mismatches`], and each
+    /// distinct disagreement is logged as a warning once and kept in [`Summary::contig_length_discrepancies`].
+    /// Unmapped reads have no contig, so aren't checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The alignment to check.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the contig and both lengths if the lengths disagree and
+    /// [`Summary::set_strict_contig_lengths`] is set.
+    pub fn check_contig_length(&mut self, record: &impl AlignmentRecord) -> DynResult<()> {
+        if record.is_unmapped() {
+            return Ok(());
+        }
+        let contig = record.target_name();
+        let length = record.target_length();
+        let expected_length = match self
+            .reference_lengths
+            .get(contig)
+            .or_else(|| self.contig_lengths.get(contig))
+        {
+            Some(&expected_length) => expected_length,
+            None => {
+                self.contig_lengths.insert(contig.to_string(), length);
+                return Ok(());
+            }
+        };
+        if length == expected_length {
+            return Ok(());
+        }
+        if self.strict_contig_lengths {
+            return Err(format!(
+                "Contig {} has a length of {} in the alignment of read {}, but {} elsewhere, were the reads aligned \
+                 to different references?",
+                contig,
+                length,
+                record.query_name(),
+                expected_length
+            )
+            .into());
+        }
+        self.add_contig_length_mismatch(contig, expected_length, length);
+        Ok(())
     }
 
-    /// Check if the sequencing summary file is set.
-    pub fn has_sequencing_summary(&self) -> bool {
-        self._sequencing_summary.is_some()
+    /// Count an alignment whose contig length disagrees with the expected length, logging and recording the
+    /// disagreement if it hasn't been seen before, see [`Summary::check_contig_length`].
+    ///
+    /// # Arguments
+    ///
+    /// * `contig` - The name of the contig.
+    /// * `expected_length` - The length of the contig in the `.fai`, or of the first alignment to it.
+    /// * `length` - The disagreeing length.
+    fn add_contig_length_mismatch(&mut self, contig: &str, expected_length: usize, length: usize) {
+        self.contig_length_mismatches += 1;
+        let discrepancy = (contig.to_string(), expected_length, length);
+        if !self.contig_length_discrepancies.contains(&discrepancy) {
+            log::warn!(
+                "Contig {} has a length of {}, but {} elsewhere, were the reads aligned to different references?",
+                contig,
+                length,
+                expected_length
+            );
+            self.contig_length_discrepancies.push(discrepancy);
+        }
     }
 
-    /// Check if the PAF file is set.
-    pub fn has_paf_file(&self) -> bool {
-        self._paf_file.is_some()
+    /// Merge another `Summary` into this one, for example the partial summaries of parallel workers or separate runs.
+    ///
+    /// Conditions with the same name are merged with [`ConditionSummary::merge`], and conditions only in `other` are
+    /// added. The unclassified read, skipped line, missing read and filtered out counts are summed. The unblock end reasons of this summary are kept.
+    /// Contig length mismatches are summed, and a contig whose first length differs between the summaries is counted
+    /// as one more mismatch, see [`Summary::check_contig_length`].
+    ///
+    /// The conditions aren't finalized, so [`Summary::finalize`] should be called once every summary has been merged.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The summary to merge in.
+    pub fn merge(&mut self, other: Summary) {
+        for (condition_name, condition_summary) in other.conditions {
+            match self.conditions.entry(condition_name) {
+                Entry::Occupied(mut entry) => entry.get_mut().merge(&condition_summary),
+                Entry::Vacant(entry) => {
+                    entry.insert(condition_summary);
+                }
+            }
+        }
+        self.unclassified_read_count += other.unclassified_read_count;
+        self.skipped_lines += other.skipped_lines;
+        self.reads_missing_from_seqsum += other.reads_missing_from_seqsum;
+        self.channel_mismatches += other.channel_mismatches;
+        self.contig_length_mismatches += other.contig_length_mismatches;
+        for discrepancy in other.contig_length_discrepancies {
+            if !self.contig_length_discrepancies.contains(&discrepancy) {
+                self.contig_length_discrepancies.push(discrepancy);
+            }
+        }
+        for (contig, length) in other.contig_lengths {
+            match self.contig_lengths.entry(contig) {
+                Entry::Occupied(entry) if *entry.get() != length => {
+                    let (contig, expected_length) = (entry.key().clone(), *entry.get());
+                    self.add_contig_length_mismatch(&contig, expected_length, length);
+                }
+                Entry::Occupied(_) => {}
+                Entry::Vacant(entry) => {
+                    entry.insert(length);
+                }
+            }
+        }
+        self.filtered_out += other.filtered_out;
+        for (channel, channel_stats) in other.channels {
+            self.channels
+                .entry(channel)
+                .or_default()
+                .merge(&channel_stats);
+        }
+        if self.reference_lengths.is_empty() {
+            self.reference_lengths = other.reference_lengths;
+        }
     }
-}
 
-#[cfg(feature = "pyo3_support")]
-/// Implements methods for interacting with a ReadfishSummary instance from Python.
-#[pymethods]
-impl ReadfishSummary {
-    /// Creates a new instance of ReadfishSummary with default values.
-    /// Returns:
-    ///     A new ReadfishSummary instance.
-    #[new]
-    #[pyo3(signature = ())]
-    fn py_new() -> PyResult<Self> {
-        Ok(ReadfishSummary::default())
-    }
-    /// Adds a PAF record to the ReadfishSummary.
-    /// This method is not implemented and will always return an error.
-    /// Returns:
-    ///     An error indicating that the method is not implemented.
-    fn add_paf_record(&self) -> PyResult<()> {
-        unimplemented!()
+    /// Sum every condition into a single grand total, to compare the overall yield and enrichment of runs.
+    ///
+    /// The reads, yields and on and off-target counts of every condition are summed, and the means, off-target
+    /// percentage and N50s are recalculated from the combined reads, as in [`ConditionSummary::merge`] followed by
+    /// [`ConditionSummary::finalize`]. The same contig can be in several conditions, so the total has no contigs
+    /// rather than counting a contig once per condition. Each condition targets a different fraction of the reference, so the total has no enrichment.
+    ///
+    /// # Returns
+    ///
+    /// A [`ConditionSummary`] named [`TOTAL_CONDITION`], including the unclassified reads.
+    pub fn total(&self) -> ConditionSummary {
+        let mut total = ConditionSummary::new(TOTAL_CONDITION.to_string());
+        for condition_summary in self
+            .conditions
+            .values()
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+        {
+            total.merge_reads(condition_summary);
+        }
+        total.target_fraction = None;
+        total.finalize();
+        total
     }
 
-    /// Parses the provided sequencing summary file and sets it on the ReadfishSummary.
-    /// Args:
-    ///     seq_sum_path: PathBuf - The path to the sequencing summary file to be parsed.
-    /// Returns:
-    ///     Ok(()) if successful, or an error if the file could not be parsed or set on the summary.
-    fn with_sequencing_summary(&mut self, seq_sum_path: PathBuf) -> PyResult<()> {
-        self.parse_sequencing_summary(seq_sum_path);
-        Ok(())
+    /// Account for every read given to the summary, as either assigned to a region or barcode, or dropped as
+    /// unclassified, unmapped, filtered out, missing from the sequencing summary or malformed. The first thing to check
+    /// when a summary looks wrong.
+    ///
+    /// # Returns
+    ///
+    /// A [`ReadReconciliation`], whose counts add up to the reads in.
+    pub fn read_reconciliation(&self) -> ReadReconciliation {
+        let (assigned, unmapped) = self
+            .conditions
+            .values()
+            .filter(|condition_summary| condition_summary.name != UNCLASSIFIED_CONDITION)
+            .fold((0, 0), |(assigned, unmapped), condition_summary| {
+                (
+                    assigned + condition_summary.total_reads
+                        - condition_summary.unmapped_read_count,
+                    unmapped + condition_summary.unmapped_read_count,
+                )
+            });
+        ReadReconciliation {
+            reads_in: assigned
+                + self.unclassified_read_count
+                + unmapped
+                + self.filtered_out
+                + self.reads_missing_from_seqsum
+                + self.skipped_lines,
+            assigned,
+            unclassified: self.unclassified_read_count,
+            unmapped,
+            filtered_out: self.filtered_out,
+            missing_from_seqsum: self.reads_missing_from_seqsum,
+            skipped_lines: self.skipped_lines,
+        }
     }
 
-    /// Parses the provided TOML configuration file and sets it on the ReadfishSummary.
-    /// Args:
-    ///     toml_path: PathBuf - The path to the TOML configuration file to be parsed.
-    /// Returns:
-    ///     Ok(()) if successful, or an error if the file could not be parsed or set on the summary.
-    fn with_toml_conf(&mut self, toml_path: PathBuf) -> PyResult<()> {
-        self.parse_conf_file(toml_path);
-        Ok(())
+    /// Compare this summary against a baseline summary, such as an adaptive sampling run against a control run on
+    /// the same reference, giving the change in yield, on-target percentage and enrichment of each condition.
+    ///
+    /// Conditions are matched by name. A condition in only one of the summaries is kept, with `None` for the metrics
+    /// of the other summary. Load the same `.fai` into both summaries, see [`Summary::load_fai`], to compare their
+    /// enrichment.
+    ///
+    /// # Arguments
+    ///
+    /// * `baseline` - The summary to compare against.
+    ///
+    /// # Returns
+    ///
+    /// A [`SummaryDiff`], with a [`ConditionDiff`] for every condition in either summary.
+    pub fn diff(&self, baseline: &Summary) -> SummaryDiff {
+        let conditions = self
+            .conditions
+            .keys()
+            .chain(baseline.conditions.keys())
+            .unique()
+            .sorted_by(|a, b| natord::compare(a, b))
+            .map(|condition_name| ConditionDiff {
+                name: condition_name.clone(),
+                run: self.get(condition_name).map(DiffMetrics::from),
+                baseline: baseline.get(condition_name).map(DiffMetrics::from),
+            })
+            .collect();
+        SummaryDiff { conditions }
     }
 
-    /// Parses PAF lines from a Python iterator and updates the ReadfishSummary accordingly.
+    /// Normalise the on-target fraction of each condition against the matching condition of a control run, such as a
+    /// run without adaptive sampling, to give a control-corrected enrichment, which controls for the mappability and
+    /// composition biases of the targets.
     ///
-    /// This method takes a Python iterator that provides PAF lines as strings. It iterates over the lines,
-    /// parses each line to extract relevant information, and updates the ReadfishSummary based on the
-    /// extracted data. The extracted metadata is used to make decisions and update the internal state of
-    /// the ReadfishSummary. Finishes by printing a summary of the parsed PAF files to stdout.
+    /// The enrichment of a condition is the fraction of its yield that is on-target in this run, divided by the same
+    /// fraction in the control. Conditions are matched by name, and conditions that aren't in the control are passed
+    /// through with `in_control` unset and no enrichment. Conditions only in the control are left out.
     ///
     /// # Arguments
     ///
-    /// * `iter`: A Python iterator that provides PAF lines as strings.
+    /// * `control` - The control summary to normalise against.
     ///
     /// # Returns
     ///
-    /// A `PyResult` indicating success or an error encountered during parsing.
+    /// A [`NormalizedSummary`], with a [`NormalizedCondition`] for every condition of this summary.
+    pub fn normalize_against(&self, control: &Summary) -> NormalizedSummary {
+        let conditions = self
+            .conditions
+            .iter()
+            .sorted_by(|(a, _), (b, _)| natord::compare(a, b))
+            .map(|(condition_name, condition_summary)| {
+                let on_target_fraction = condition_summary.on_target_yield_fraction();
+                let control_on_target_fraction = control
+                    .get(condition_name)
+                    .map(ConditionSummary::on_target_yield_fraction);
+                NormalizedCondition {
+                    name: condition_name.clone(),
+                    on_target_fraction,
+                    control_on_target_fraction,
+                    enrichment: control_on_target_fraction
+                        .filter(|&control_fraction| control_fraction > 0.0)
+                        .map(|control_fraction| on_target_fraction / control_fraction),
+                    in_control: control_on_target_fraction.is_some(),
+                }
+            })
+            .collect();
+        NormalizedSummary { conditions }
+    }
+
+    /// Find the target intervals of each condition that received no reads, to detect dropouts from an enrichment panel.
     ///
-    /// # Examples
+    /// The merged target intervals of each condition, from [`Conf::get_contig_targets`], are compared against the spans of
+    /// its on-target alignments. Whole-contig targets are clamped to the length of the contig, taken from the `.fai`
+    /// if one was loaded with [`Summary::load_fai`]. Only conditions with reads are checked, and the summary should
+    /// have been finalized.
     ///
-    /// ```rust,ignore
-    /// # use pyo3::types::PyIterator;
-    /// # use readfish_tools::{ReadfishSummary, paf::Metadata, paf::_parse_paf_line};
+    /// # Arguments
     ///
-    /// // Assuming we have valid inputs
-    /// let mut readfish_summary = ReadfishSummary::default();
-    /// let paf_lines: Vec<String> = vec![
-    ///     "read123 200 0 200 + contig123 300 0 300 200 200 50 ch=1".to_string(),
-    ///     "read456 150 0 150 - contig456 200 0 200 150 150 45 ch=2 ba=sampleB".to_string(),
-    ///     // Add more PAF lines as needed
-    /// ];
-    /// let py_iter = PyIterator::new(paf_lines.into_iter());
+    /// * `conf` - The readfish TOML configuration the reads were demultiplexed with.
     ///
-    /// let result = readfish_summary.parse_paf_from_iter(&py_iter);
+    /// # Returns
     ///
-    /// assert!(result.is_ok());
-    /// ```
-    fn parse_paf_from_iter(&mut self, iter: &PyIterator) -> PyResult<()> {
-        let conf = self.conf().unwrap();
-        for paf_line in iter {
-            let paf_line = paf_line?;
-            let (paf_line, meta_tuple): (String, (String, usize, Option<String>)) =
-                paf_line.extract()?;
-            let mut meta_data: Metadata = meta_tuple.into();
-            let (paf_record, on_target, condition_name) =
-                _parse_paf_line(paf_line, conf, Some(&mut meta_data), None).unwrap();
-            {
-                let mut x = self.summary.borrow_mut();
-                let y = x.conditions(condition_name.as_str());
-                y.update(paf_record, on_target).unwrap();
+    /// The `(contig, start, stop)` target intervals with zero coverage, sorted by contig and start, keyed on the
+    /// condition name. Conditions without targets are left out.
+    pub fn uncovered_targets(&self, conf: &Conf) -> HashMap<String, Vec<(String, usize, usize)>> {
+        let mut uncovered_targets = HashMap::new();
+        for (condition_name, condition_summary) in &self.conditions {
+            let target_contigs = conf.target_contigs(condition_name);
+            if target_contigs.is_empty() {
+                continue;
             }
+            let uncovered = target_contigs
+                .into_iter()
+                .flat_map(|contig| {
+                    let contig_summary = condition_summary.contigs.get(contig);
+                    let contig_length = self
+                        .reference_lengths
+                        .get(contig)
+                        .copied()
+                        .or(contig_summary.map(|contig_summary| contig_summary.length))
+                        .unwrap_or(usize::MAX);
+                    conf.get_contig_targets(condition_name, contig, contig_length)
+                        .into_iter()
+                        .filter(move |&(start, stop)| {
+                            !contig_summary.is_some_and(|contig_summary| {
+                                contig_summary.is_covered_on_target(start, stop)
+                            })
+                        })
+                        .map(move |(start, stop)| (contig.to_string(), start, stop))
+                })
+                .collect();
+            uncovered_targets.insert(condition_name.clone(), uncovered);
         }
-        Ok(())
+        uncovered_targets
     }
-    /// Prints the summary of the `ReadfishSummary` to the standard output.
+
+    /// Check the targets of every condition for zero coverage, see [`Summary::uncovered_targets`], so that the
+    /// number of uncovered targets is displayed in the summary.
     ///
-    /// This method borrows the `ReadfishSummary` immutably and prints its summary to the standard output.
-    /// The summary is obtained by calling the `borrow` method on the `RefCell<Summary>` field of the
-    /// `ReadfishSummary`.
+    /// # Arguments
     ///
-    /// # Returns
+    /// * `conf` - The readfish TOML configuration the reads were demultiplexed with.
+    pub fn set_uncovered_targets(&mut self, conf: &Conf) {
+        for (condition_name, uncovered) in self.uncovered_targets(conf) {
+            if let Some(condition_summary) = self.conditions.get_mut(&condition_name) {
+                condition_summary.uncovered_targets = Some(uncovered);
+            }
+        }
+    }
+
+    /// Count the on-target reads and yield of every target interval of every condition, to see which targets were
+    /// sequenced, and how deeply.
     ///
-    /// This function returns a `PyResult<()>` to indicate success or failure. If the summary is
-    /// successfully printed, `Ok(())` is returned. If an error occurs during printing, an appropriate
-    /// `PyErr` will be set, and `Err` will be returned.
+    /// The reads of each of the merged target intervals of each condition, from [`Conf::get_contig_targets`], are
+    /// counted as they are added, see [`Summary::add_target_hits`]. Whole-contig targets are clamped to the length of
+    /// the contig, as in [`Summary::uncovered_targets`]. Only conditions with reads are counted.
     ///
-    /// # Examples
+    /// # Arguments
     ///
-    /// ```rust,ignore
-    /// # use pyo3::prelude::*;
-    /// # use std::cell::RefCell;
-    /// # use std::collections::HashMap;
-    /// # use std::path::PathBuf;
-    /// # use std::error::Error;
+    /// * `conf` - The readfish TOML configuration the reads were demultiplexed with.
     ///
-    /// # #[pyclass]
-    /// # pub struct ReadfishSummary {
-    /// #     // Fields of ReadfishSummary
-    /// #     // ...
-    /// # }
-    /// #
-    /// # #[pymethods]
-    /// # impl ReadfishSummary {
-    /// #     #[getter]
-    /// #     pub fn summary(&self) -> PyResult<Ref<Summary>> {
-    /// #         unimplemented!()
-    /// #     }
-    /// #
-    /// /// Method to print the summary of ReadfishSummary.
-    /// pub fn print_summary(&self) -> PyResult<()> {
-    ///     println!("{}", self.summary.borrow());
-    ///     Ok(())
+    /// # Returns
+    ///
+    /// The [`TargetSummary`] of every target, including those without reads, sorted by contig and start, keyed on
+    /// the condition name. Conditions without targets are left out.
+    pub fn target_summaries(&self, conf: &Conf) -> HashMap<String, Vec<TargetSummary>> {
+        let mut target_summaries = HashMap::new();
+        for (condition_name, condition_summary) in &self.conditions {
+            let target_contigs = conf.target_contigs(condition_name);
+            if target_contigs.is_empty() {
+                continue;
+            }
+            let summaries = target_contigs
+                .into_iter()
+                .flat_map(|contig| {
+                    let contig_summary = condition_summary.contigs.get(contig);
+                    let contig_length = self
+                        .reference_lengths
+                        .get(contig)
+                        .copied()
+                        .or(contig_summary.map(|contig_summary| contig_summary.length))
+                        .unwrap_or(usize::MAX);
+                    let targets = conf.get_contig_targets(condition_name, contig, contig_length);
+                    let counts = contig_summary.map_or_else(
+                        || vec![(0, 0); targets.len()],
+                        |contig_summary| contig_summary.target_read_counts(targets.len()),
+                    );
+                    targets.into_iter().zip(counts).map(
+                        move |((start, stop), (read_count, total_bases))| TargetSummary {
+                            contig: contig.to_string(),
+                            start,
+                            stop,
+                            read_count,
+                            total_bases,
+                        },
+                    )
+                })
+                .collect();
+            target_summaries.insert(condition_name.clone(), summaries);
+        }
+        target_summaries
+    }
+
+    /// Count the reads of every target of every condition, see [`Summary::target_summaries`], so that the targets
+    /// table can be rendered with [`RenderOptions::show_targets`].
+    ///
+    /// # Arguments
+    ///
+    /// * `conf` - The readfish TOML configuration the reads were demultiplexed with.
+    pub fn set_target_summaries(&mut self, conf: &Conf) {
+        for (condition_name, targets) in self.target_summaries(conf) {
+            if let Some(condition_summary) = self.conditions.get_mut(&condition_name) {
+                condition_summary.targets = Some(targets);
+            }
+        }
+    }
+
+    /// Set the targets of every contig of every condition, so the on-target breadth of each contig is the fraction of
+    /// its targets covered by on-target alignments, see [`ContigSummary::set_targets`]. Whole-contig targets are
+    /// clamped to the length of the contig, as in [`Summary::uncovered_targets`].
+    ///
+    /// # Arguments
+    ///
+    /// * `conf` - The readfish TOML configuration the reads were demultiplexed with.
+    pub fn set_target_breadths(&mut self, conf: &Conf) {
+        for (condition_name, condition_summary) in self.conditions.iter_mut() {
+            for (contig, contig_summary) in condition_summary.contigs.iter_mut() {
+                let contig_length = self
+                    .reference_lengths
+                    .get(contig)
+                    .copied()
+                    .unwrap_or(contig_summary.length);
+                let targets = conf.get_contig_targets(condition_name, contig, contig_length);
+                if !targets.is_empty() {
+                    contig_summary.set_targets(targets);
+                }
+            }
+        }
+    }
+
+    /// Load the contig lengths of the reference from a samtools faidx `.fai` index, see [`readfish_io::read_fai`].
+    ///
+    /// Once loaded, every contig of the reference is reported when the summary is finalized, including contigs
+    /// with no alignments, and a warning is logged for any contig whose PAF `target_length` disagrees with the `.fai`.
+    /// The lengths also allow [`Summary::set_target_fractions`] to calculate the enrichment of each condition.
+    ///
+    /// # Arguments
+    ///
+    /// * `fai_path` - The path to the `.fai` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `.fai` file cannot be read or is malformed.
+    pub fn load_fai(&mut self, fai_path: impl AsRef<Path>) -> DynResult<()> {
+        self.reference_lengths = readfish_io::read_fai(fai_path)?;
+        Ok(())
+    }
+
+    /// Set the target fraction of every condition with targets, from the targets in the TOML and the reference
+    /// lengths loaded with [`Summary::load_fai`], so that the enrichment is displayed. See [`Conf::target_fraction`].
+    ///
+    /// Does nothing if no `.fai` has been loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `conf` - The readfish TOML configuration the reads were demultiplexed with.
+    pub fn set_target_fractions(&mut self, conf: &Conf) {
+        for (condition_name, condition_summary) in self.conditions.iter_mut() {
+            if let Some(target_fraction) =
+                conf.target_fraction(condition_name, &self.reference_lengths)
+            {
+                condition_summary.set_target_fraction(target_fraction);
+            }
+        }
+    }
+
+    /// Set the approximate size of the genome of every condition, so that the estimated coverage is displayed when
+    /// there is no `.fai` index of the reference. See [`ConditionSummary::estimated_coverage`].
+    ///
+    /// # Arguments
+    ///
+    /// * `genome_size` - The approximate size of the genome in base pairs.
+    pub fn set_genome_size(&mut self, genome_size: usize) {
+        for condition_summary in self.conditions.values_mut() {
+            condition_summary.set_genome_size(genome_size);
+        }
+    }
+
+    /// Count a read whose PAF `ch` tag disagrees with its channel in the sequencing summary.
+    ///
+    /// The first [`LOGGED_CHANNEL_MISMATCHES`] mismatches are logged as warnings, so a mix-up can be diagnosed
+    /// without flooding the log.
+    ///
+    /// # Arguments
+    ///
+    /// * `query_name` - The read ID.
+    /// * `seq_sum_channel` - The channel of the read in the sequencing summary.
+    /// * `tag_channel` - The channel in the `ch` tag of the PAF record.
+    pub fn add_channel_mismatch(
+        &mut self,
+        query_name: &str,
+        seq_sum_channel: usize,
+        tag_channel: usize,
+    ) {
+        self.channel_mismatches += 1;
+        if self.channel_mismatches <= LOGGED_CHANNEL_MISMATCHES {
+            log::warn!(
+                "Read {} is on channel {} in the sequencing summary, but has a ch tag of {}",
+                query_name,
+                seq_sum_channel,
+                tag_channel
+            );
+        } else if self.channel_mismatches == LOGGED_CHANNEL_MISMATCHES + 1 {
+            log::warn!("Further channel mismatches will not be logged");
+        }
+    }
+
+    /// Get the stats of every channel with reads, alongside the (column, row) of the channel on the flowcell.
+    ///
+    /// This is the data needed to plot the activity of the flowcell as a heatmap.
+    ///
+    /// # Arguments
+    ///
+    /// * `flowcell_size` - The total number of channels on the flowcell, see [`nanopore::get_coords`].
+    ///
+    /// # Returns
+    ///
+    /// A vector of `(channel, (column, row), stats)`, sorted by channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a channel is not on a flowcell of `flowcell_size` channels.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let summary = _demultiplex_paf("config.toml", &["reads.paf"], Some("sequencing_summary.txt"), false, None::<&Path>, false, false, false, None, None::<&Path>, None::<String>, ReadFilter::default(), None::<String>, None::<String>)?;
+    /// for (channel, (column, row), stats) in summary.channel_coords(512)? {
+    ///     println!("{channel} ({column}, {row}): {}", stats.total_yield);
     /// }
-    /// # }
     /// ```
+    pub fn channel_coords(
+        &self,
+        flowcell_size: usize,
+    ) -> DynResult<Vec<ChannelCoords<&ChannelStats>>> {
+        self.channels
+            .iter()
+            .sorted_by_key(|(channel, _)| **channel)
+            .map(|(channel, channel_stats)| {
+                let coords = get_coords(*channel, flowcell_size)
+                    .map_err(|err| format!("Channel {}: {}", channel, err))?;
+                Ok((*channel, coords, channel_stats))
+            })
+            .collect()
+    }
+
+    /// Get the summary for reads that matched no region or barcode, counting the read as unclassified.
     ///
-    /// The method can be called on an instance of `ReadfishSummary` to print its summary.
+    /// This should be called once for each record that fails region/barcode assignment, and the record then
+    /// added to the returned [`ConditionSummary`], so that the totals across all conditions reconcile.
+    ///
+    /// # Returns
+    ///
+    /// A mutable reference to the [`UNCLASSIFIED_CONDITION`] condition summary.
+    pub fn unclassified(&mut self) -> &mut ConditionSummary {
+        self.unclassified_read_count += 1;
+        self.conditions_entry(UNCLASSIFIED_CONDITION)
+    }
+
+    /// Get the percentage of all reads that matched no region or barcode.
+    ///
+    /// # Returns
+    ///
+    /// The unclassified reads as a percentage of the total reads across all conditions, or 0 if there are no reads.
+    pub fn unclassified_percent(&self) -> f64 {
+        let total_reads: usize = self
+            .conditions
+            .values()
+            .map(|condition_summary| condition_summary.total_reads)
+            .sum();
+        if total_reads == 0 {
+            0.0
+        } else {
+            self.unclassified_read_count as f64 / total_reads as f64 * 100.0
+        }
+    }
+
+    /// Set the sequencing summary end reasons that mark a read as unblocked, as naming
+    /// has changed across MinKNOW versions.
+    ///
+    /// # Arguments
+    ///
+    /// * `unblock_end_reasons` - The end reasons that classify a read as unblocked.
+    pub fn set_unblock_end_reasons(&mut self, unblock_end_reasons: Vec<String>) {
+        self.unblock_end_reasons = unblock_end_reasons;
+    }
+
+    /// Check whether an end reason classifies a read as unblocked.
+    ///
+    /// # Arguments
+    ///
+    /// * `end_reason` - The end reason of the read from the sequencing summary.
+    pub fn is_unblocked(&self, end_reason: &str) -> bool {
+        self.unblock_end_reasons
+            .iter()
+            .any(|unblock_end_reason| unblock_end_reason == end_reason)
+    }
+
+    /// Write the `Summary` as JSON, with the contigs of each condition nested under the condition, keyed by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to write the JSON to.
+    ///
+    /// # Examples
     ///
     /// ```rust,ignore
-    /// # use pyo3::prelude::*;
-    /// # use std::cell::RefCell;
-    /// # use std::collections::HashMap;
-    /// # use std::path::PathBuf;
-    /// # use std::error::Error;
-    /// #
-    /// # #[pyclass]
-    /// # pub struct ReadfishSummary {
-    /// #     // Fields of ReadfishSummary
-    /// #     // ...
-    /// # }
-    /// #
-    /// # #[pymethods]
-    /// # impl ReadfishSummary {
-    /// #     #[getter]
-    /// #     pub fn summary(&self) -> PyResult<Ref<Summary>> {
-    /// #         unimplemented!()
-    /// #     }
-    /// #
-    /// #     /// Method to print the summary of ReadfishSummary.
-    /// #     pub fn print_summary(&self) -> PyResult<()> {
-    /// #         println!("{}", self.summary.borrow());
-    /// #         Ok(())
-    /// #     }
-    /// # }
-    /// #
-    /// # fn main() -> PyResult<()> {
-    /// #     Python::with_gil(|py| {
-    /// #         let gil = Python::acquire_gil();
-    /// #         let py = gil.python();
-    /// #
-    /// #         // Create an instance of ReadfishSummary and call the print_summary method
-    /// #         let readfish_summary = ReadfishSummary { /* Initialize fields... */ };
-    /// #         readfish_summary.print_summary()?;
-    /// #         Ok(())
-    /// #     })
-    /// # }
+    /// let summary = Summary::new();
+    /// summary.to_json_writer(std::io::stdout())?;
     /// ```
-    pub fn print_summary(&self) -> PyResult<()> {
-        println!("{}", self.summary.borrow());
+    #[cfg(feature = "serde")]
+    pub fn to_json_writer(&self, w: impl Write) -> DynResult<()> {
+        serde_json::to_writer_pretty(w, self)?;
+        Ok(())
+    }
+
+    /// Write one CSV row per condition, with the read counts, yields, percent off-target and N50s.
+    /// The header is [`CONDITION_CSV_COLUMNS`].
+    ///
+    /// Conditions are written in natural order of their names, and the condition name is quoted.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to write the CSV to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let summary = Summary::new();
+    /// summary.write_csv(std::io::stdout())?;
+    /// ```
+    pub fn write_csv(&self, w: impl Write) -> DynResult<()> {
+        let mut csv_writer = csv::WriterBuilder::new()
+            .quote_style(csv::QuoteStyle::NonNumeric)
+            .from_writer(w);
+        csv_writer.write_record(CONDITION_CSV_COLUMNS)?;
+        for record in self.condition_records() {
+            csv_writer.write_record(record)?;
+        }
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    /// The values of each condition in the columns of [`CONDITION_CSV_COLUMNS`], in natural order of the condition
+    /// names. Every value but the condition name is a number.
+    fn condition_records(&self) -> Vec<[String; CONDITION_CSV_COLUMNS.len()]> {
+        self.conditions
+            .iter()
+            .sorted_by(|(key1, _), (key2, _)| natord::compare(key1, key2))
+            .map(|(condition_name, condition_summary)| {
+                [
+                    condition_name.clone(),
+                    condition_summary.total_reads.to_string(),
+                    condition_summary.on_target_read_count.to_string(),
+                    condition_summary.off_target_read_count.to_string(),
+                    condition_summary.total_yield().to_string(),
+                    condition_summary.on_target_yield.to_string(),
+                    condition_summary.off_target_yield.to_string(),
+                    format!("{:.2}", condition_summary.off_target_percent),
+                    condition_summary.n50.to_string(),
+                    condition_summary.on_target_n50.to_string(),
+                    condition_summary.off_target_n50.to_string(),
+                    condition_summary.unmapped_read_count.to_string(),
+                    condition_summary.unmapped_yield.to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    /// Write one CSV row per (condition, contig) pair, with the contig read counts and yields.
+    /// The header is [`CONTIG_CSV_COLUMNS`].
+    ///
+    /// Rows are written in natural order of condition, then contig name.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to write the CSV to.
+    pub fn write_contig_csv(&self, w: impl Write) -> DynResult<()> {
+        let mut csv_writer = csv::WriterBuilder::new()
+            .quote_style(csv::QuoteStyle::NonNumeric)
+            .from_writer(w);
+        csv_writer.write_record(CONTIG_CSV_COLUMNS)?;
+        for record in self.contig_records() {
+            csv_writer.write_record(record)?;
+        }
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    /// The values of each contig of each condition in the columns of [`CONTIG_CSV_COLUMNS`], in natural order of the
+    /// condition, then contig names. Every value but the condition and contig names is a number.
+    fn contig_records(&self) -> Vec<[String; CONTIG_CSV_COLUMNS.len()]> {
+        self.conditions
+            .iter()
+            .sorted_by(|(key1, _), (key2, _)| natord::compare(key1, key2))
+            .flat_map(|(condition_name, condition_summary)| {
+                condition_summary
+                    .contigs
+                    .iter()
+                    .sorted_by(|(key1, _), (key2, _)| natord::compare(key1, key2))
+                    .map(move |(contig_name, contig_summary)| {
+                        [
+                            condition_name.clone(),
+                            contig_name.clone(),
+                            contig_summary.length.to_string(),
+                            contig_summary.total_reads().to_string(),
+                            contig_summary.on_target_read_count.to_string(),
+                            contig_summary.off_target_read_count.to_string(),
+                            contig_summary.total_bases.to_string(),
+                            contig_summary.yield_on_target.to_string(),
+                            contig_summary.yield_off_target.to_string(),
+                            contig_summary.mean_read_length().to_string(),
+                            format!("{:.2}", contig_summary.mean_coverage),
+                        ]
+                    })
+            })
+            .collect()
+    }
+
+    /// The conditions of the summary as a polars [`DataFrame`](polars::frame::DataFrame), with a row per condition
+    /// and the columns of [`CONDITION_CSV_COLUMNS`], in the same order as the CSV of [`Summary::write_csv`].
+    ///
+    /// The `condition` column is a `String`, the `off_target_percent` is a `Float64` rounded as in the CSV, which
+    /// is null if it isn't a finite number, and every other column is a `UInt64`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the DataFrame cannot be built.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let summary = _demultiplex_paf(...)?;
+    /// let conditions = summary.to_condition_dataframe()?;
+    /// ```
+    #[cfg(feature = "polars")]
+    pub fn to_condition_dataframe(&self) -> DynResult<polars::frame::DataFrame> {
+        typed_dataframe(typed_columns(
+            CONDITION_CSV_COLUMNS,
+            &self.condition_records(),
+        )?)
+    }
+
+    /// The contigs of every condition of the summary as a polars [`DataFrame`](polars::frame::DataFrame), with a row
+    /// per condition and contig and the columns of [`CONTIG_CSV_COLUMNS`], in the same order as the CSV of
+    /// [`Summary::write_contig_csv`].
+    ///
+    /// The `condition` and `contig` columns are `String`s, the `mean_coverage` is a `Float64` rounded as in the CSV,
+    /// and every other column is a `UInt64`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the DataFrame cannot be built.
+    #[cfg(feature = "polars")]
+    pub fn to_contig_dataframe(&self) -> DynResult<polars::frame::DataFrame> {
+        typed_dataframe(typed_columns(CONTIG_CSV_COLUMNS, &self.contig_records())?)
+    }
+
+    /// Write the conditions and contigs of the summary as two Parquet files in a directory, `conditions.parquet` and
+    /// `contigs.parquet`, which is created if it doesn't exist.
+    ///
+    /// The columns are those of [`CONDITION_CSV_COLUMNS`] and [`CONTIG_CSV_COLUMNS`], so both files start with the
+    /// `condition` column, and the rows are in the same order as the CSVs. The names are strings, the
+    /// `off_target_percent` and `mean_coverage` are doubles rounded as in the CSVs, which are null if they aren't a
+    /// finite number, and every other column is an unsigned 64 bit integer.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The directory to write the Parquet files to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created, or the Parquet files cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let summary = _demultiplex_paf(...)?;
+    /// summary.write_parquet("PAK09329_summary")?;
+    /// ```
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet(&self, path: impl AsRef<Path>) -> DynResult<()> {
+        let path = path.as_ref();
+        std::fs::create_dir_all(path)?;
+        write_parquet_table(
+            path.join("conditions.parquet"),
+            typed_columns(CONDITION_CSV_COLUMNS, &self.condition_records())?,
+        )?;
+        write_parquet_table(
+            path.join("contigs.parquet"),
+            typed_columns(CONTIG_CSV_COLUMNS, &self.contig_records())?,
+        )
+    }
+
+    /// Write the conditions and contigs of the summary into a SQLite database, under a run name, so many runs can be
+    /// queried together. The database is created if it doesn't exist.
+    ///
+    /// The schema is created if it doesn't exist, see [`SQL_SCHEMA`]:
+    ///
+    /// * `runs` - One row per run, keyed on `run`.
+    /// * `conditions` - One row per condition of a run, keyed on `(run, condition)`, with the columns of
+    ///   [`CONDITION_CSV_COLUMNS`].
+    /// * `contigs` - One row per contig of a condition, keyed on `(run, condition, contig)`, with the columns of
+    ///   [`CONTIG_CSV_COLUMNS`].
+    /// * `assignments` - One row per alignment of a read, with the columns of [`paf::ASSIGNMENTS_HEADER`], indexed on
+    ///   `(run, read_id)`.
+    ///
+    /// The run is appended to the database, replacing any earlier rows of a run with the same name, and keeping every
+    /// other run. Every row is written in a single transaction, so a run is stored completely or not at all.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the SQLite database.
+    /// * `run_name` - The name the run is stored under.
+    /// * `assignments` - The optional path to the tab separated read assignments written whilst demultiplexing, see
+    ///   [`DemultiplexOptions::assignments_out`], which are loaded into the `assignments` table.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or written to, or if the assignments cannot be read, or
+    /// aren't tab separated with a header of [`paf::ASSIGNMENTS_HEADER`]. Nothing is written on an error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let summary = _demultiplex_paf(...)?;
+    /// summary.write_sqlite("runs.db", "PAK09329", Some("assignments.tsv"))?;
+    /// ```
+    #[cfg(feature = "sqlite")]
+    pub fn write_sqlite(
+        &self,
+        path: impl AsRef<Path>,
+        run_name: &str,
+        assignments: Option<impl AsRef<Path>>,
+    ) -> DynResult<()> {
+        use rusqlite::types::Value;
+        let mut connection = rusqlite::Connection::open(path)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(SQL_SCHEMA)?;
+        for table in ["assignments", "contigs", "conditions"] {
+            transaction.execute(&format!("DELETE FROM {} WHERE run = ?1", table), [run_name])?;
+        }
+        transaction.execute("INSERT OR REPLACE INTO runs (run) VALUES (?1)", [run_name])?;
+        {
+            let mut insert_condition = transaction.prepare(&format!(
+                "INSERT INTO conditions (run, {}) VALUES (?1, ?2, {})",
+                CONDITION_CSV_COLUMNS.join(", "),
+                (3..=CONDITION_CSV_COLUMNS.len() + 1)
+                    .map(|index| format!("?{}", index))
+                    .join(", ")
+            ))?;
+            for [condition_name, values @ ..] in self.condition_records() {
+                insert_condition.execute(rusqlite::params_from_iter(
+                    [
+                        Value::Text(run_name.to_string()),
+                        Value::Text(condition_name),
+                    ]
+                    .into_iter()
+                    .chain(values.iter().map(|value| sqlite_number(value))),
+                ))?;
+            }
+            let mut insert_contig = transaction.prepare(&format!(
+                "INSERT INTO contigs (run, {}) VALUES (?1, ?2, ?3, {})",
+                CONTIG_CSV_COLUMNS.join(", "),
+                (4..=CONTIG_CSV_COLUMNS.len() + 1)
+                    .map(|index| format!("?{}", index))
+                    .join(", ")
+            ))?;
+            for [condition_name, contig_name, values @ ..] in self.contig_records() {
+                insert_contig.execute(rusqlite::params_from_iter(
+                    [
+                        Value::Text(run_name.to_string()),
+                        Value::Text(condition_name),
+                        Value::Text(contig_name),
+                    ]
+                    .into_iter()
+                    .chain(values.iter().map(|value| sqlite_number(value))),
+                ))?;
+            }
+        }
+        if let Some(assignments) = assignments {
+            let assignments = assignments.as_ref();
+            let mut rows = csv::ReaderBuilder::new()
+                .delimiter(b'\t')
+                .from_reader(readfish_io::try_reader(assignments, None)?);
+            let header = rows.headers()?.iter().join("\t");
+            if header != paf::ASSIGNMENTS_HEADER {
+                return Err(format!(
+                    "{} isn't a tab separated file of read assignments, with a header of {}",
+                    assignments.display(),
+                    paf::ASSIGNMENTS_HEADER.replace('\t', ", ")
+                )
+                .into());
+            }
+            let mut insert_assignment = transaction.prepare(
+                "INSERT INTO assignments (run, read_id, condition, on_target, contig) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for row in rows.records() {
+                let row = row?;
+                let on_target: bool = row[2].parse().map_err(|_| {
+                    format!(
+                        "{} has an on_target value of {}, rather than true or false",
+                        assignments.display(),
+                        &row[2]
+                    )
+                })?;
+                insert_assignment.execute(rusqlite::params![
+                    run_name, &row[0], &row[1], on_target, &row[3]
+                ])?;
+            }
+        }
+        transaction.commit()?;
         Ok(())
     }
-}
 
-#[cfg(feature = "pyo3_support")]
-/// Formats the sum of two numbers as string.
-#[pyfunction]
-fn summarise_from_iter(
-    toml_path: PathBuf,
-    paf_path: PathBuf,
-    seq_sum_path: PathBuf,
-) -> PyResult<()> {
-    _demultiplex_paf(
-        toml_path,
-        paf_path,
-        Some(seq_sum_path),
-        true,
-        None::<String>,
-    );
-    Ok(())
-}
+    /// Write the summary as a SQL script that loads the conditions and contigs into a SQLite database, under a run
+    /// name, so many runs can be queried together, e.g. `sqlite3 runs.db < summary.sql`.
+    ///
+    /// This is a fallback for builds without the `sqlite` feature, see [`Summary::write_sqlite`], which also loads
+    /// the read assignments. The schema is created if it doesn't exist, see [`SQL_SCHEMA`]:
+    ///
+    /// * `runs` - One row per run, keyed on `run`.
+    /// * `conditions` - One row per condition of a run, keyed on `(run, condition)`, with the columns of
+    ///   [`CONDITION_CSV_COLUMNS`].
+    /// * `contigs` - One row per contig of a condition, keyed on `(run, condition, contig)`, with the columns of
+    ///   [`CONTIG_CSV_COLUMNS`].
+    ///
+    /// Loading a script appends the run to the database, replacing any earlier rows of a run with the same name, and
+    /// keeping every other run. The inserts are wrapped in a single transaction, so a run is loaded completely or
+    /// not at all.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to write the SQL to.
+    /// * `run_name` - The name the run is stored under.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the SQL cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let summary = _demultiplex_paf(...)?;
+    /// summary.write_sql(File::create("summary.sql")?, "PAK09329")?;
+    /// ```
+    pub fn write_sql(&self, mut w: impl Write, run_name: &str) -> DynResult<()> {
+        let run_name = sql_string(run_name);
+        writeln!(w, "BEGIN TRANSACTION;")?;
+        write!(w, "{}", SQL_SCHEMA)?;
+        writeln!(w, "DELETE FROM assignments WHERE run = {};", run_name)?;
+        writeln!(w, "DELETE FROM contigs WHERE run = {};", run_name)?;
+        writeln!(w, "DELETE FROM conditions WHERE run = {};", run_name)?;
+        writeln!(
+            w,
+            "INSERT OR REPLACE INTO runs (run) VALUES ({});",
+            run_name
+        )?;
+        for [condition_name, values @ ..] in self.condition_records() {
+            writeln!(
+                w,
+                "INSERT INTO conditions (run, {}) VALUES ({}, {}, {});",
+                CONDITION_CSV_COLUMNS.join(", "),
+                run_name,
+                sql_string(&condition_name),
+                sql_numbers(&values)
+            )?;
+        }
+        for [condition_name, contig_name, values @ ..] in self.contig_records() {
+            writeln!(
+                w,
+                "INSERT INTO contigs (run, {}) VALUES ({}, {}, {}, {});",
+                CONTIG_CSV_COLUMNS.join(", "),
+                run_name,
+                sql_string(&condition_name),
+                sql_string(&contig_name),
+                sql_numbers(&values)
+            )?;
+        }
+        writeln!(w, "COMMIT;")?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Finalize every [`ConditionSummary`] in the `Summary`, calculating metrics such as the N50
+    /// that require all reads to have been seen.
+    ///
+    /// If a `.fai` has been loaded with [`Summary::load_fai`], every contig of the reference is first added to each
+    /// condition, and a warning is logged for each contig whose PAF `target_length` disagrees with the `.fai`.
+    pub fn finalize(&mut self) {
+        let length_mismatches: Vec<(String, usize, usize)> = self
+            .conditions
+            .values_mut()
+            .flat_map(|condition_summary| {
+                condition_summary.add_reference_contigs(&self.reference_lengths)
+            })
+            .sorted()
+            .dedup()
+            .collect();
+        for (contig, paf_length, reference_length) in length_mismatches {
+            // Already logged as the alignments were added, if the .fai was loaded first
+            let discrepancy = (contig.clone(), reference_length, paf_length);
+            if self.contig_length_discrepancies.contains(&discrepancy) {
+                continue;
+            }
+            log::warn!(
+                "Contig {} has a length of {} in the PAF, but {} in the .fai, using the .fai length",
+                contig,
+                paf_length,
+                reference_length
+            );
+        }
+        for condition_summary in self.conditions.values_mut() {
+            condition_summary.finalize();
+        }
+    }
+
+    /// Get the summary for the specified condition, or `None` if the condition has no reads.
+    ///
+    /// Unlike [`Summary::conditions_entry`], a missing condition is not added, so this is the method to use when
+    /// querying a finished summary.
+    ///
+    /// # Arguments
+    ///
+    /// * `condition_name` - The name of the region or barcode.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use readfish_tools::Summary;
+    ///
+    /// let summary = Summary::default();
+    /// assert!(summary.get("barcode01").is_none());
+    /// assert!(summary.conditions.is_empty());
+    /// ```
+    pub fn get(&self, condition_name: &str) -> Option<&ConditionSummary> {
+        self.conditions.get(condition_name)
+    }
+
+    /// Get a mutable reference to the summary for the specified condition, or `None` if the condition has no reads.
+    /// A missing condition is not added, see [`Summary::conditions_entry`].
+    ///
+    /// # Arguments
+    ///
+    /// * `condition_name` - The name of the region or barcode.
+    pub fn get_mut(&mut self, condition_name: &str) -> Option<&mut ConditionSummary> {
+        self.conditions.get_mut(condition_name)
+    }
+
+    /// Get the summary for the specified condition, **adding an empty condition if it does not exist**, to update
+    /// it with reads whilst demultiplexing. Use [`Summary::get`] to query a condition without adding it.
+    ///
+    /// # Arguments
+    ///
+    /// * `condition_name`: A `String` representing the name or identifier of the condition.
+    ///
+    /// # Returns
+    ///
+    /// A reference to the `ConditionSummary` for the specified condition.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use std::collections::HashMap;
+    ///
+    /// let mut summary = Summary::new();
+    ///
+    /// // Get or add the condition with the name "Condition A"
+    /// let condition_a = summary.conditions_entry("Condition A".to_string());
+    ///
+    /// // Modify the fields of the condition summary
+    /// condition_a.set_total_reads(10000);
+    /// condition_a.set_on_target_read_count(8000);
+    /// condition_a.set_off_target_read_count(2000);
+    /// // ...
+    ///
+    /// // Get or add another condition
+    /// let condition_b = summary.conditions_entry("Condition B".to_string());
+    /// // ...
+    /// ```
+    pub fn conditions_entry<T: Deref<Target = str>>(
+        &mut self,
+        condition_name: T,
+    ) -> &mut ConditionSummary {
+        // Only allocate the condition name when the condition is new, as this is called for every read
+        if !self.conditions.contains_key(&*condition_name) {
+            let mut condition_summary = ConditionSummary::new(condition_name.to_string());
+            condition_summary.set_keep_read_lengths(self.keep_read_lengths);
+            self.conditions
+                .insert(condition_name.to_string(), condition_summary);
+        }
+        self.conditions.get_mut(&*condition_name).unwrap()
+    }
+}
+/// Options controlling how PAF files are demultiplexed and summarised by [`_demultiplex_paf`].
+///
+/// Every option is off by default, so `DemultiplexOptions::default()` summarises the PAF files on a single thread
+/// without printing or writing anything.
+#[derive(Debug, Clone, Default)]
+pub struct DemultiplexOptions {
+    /// Whether to print the summary tables to stdout.
+    pub print_summary: bool,
+    /// The optional file path to write the per-condition summary CSV to.
+    pub csv_out: Option<PathBuf>,
+    /// Whether malformed PAF lines are an error, rather than being skipped and counted in [`Summary::skipped_lines`].
+    pub strict: bool,
+    /// Whether contig lengths that disagree between alignments or with the `.fai` are an error, rather than being
+    /// counted in [`Summary::contig_length_mismatches`], see [`Summary::set_strict_contig_lengths`].
+    pub strict_contig_lengths: bool,
+    /// Whether reads missing from the sequencing summary are an error, rather than being skipped and counted in
+    /// [`Summary::reads_missing_from_seqsum`].
+    pub strict_seqsum: bool,
+    /// Whether to count each read once in the read counts and yields, by its primary alignment, rather than once per
+    /// PAF line, see [`paf::Paf::set_deduplicate_reads`].
+    pub deduplicate_reads: bool,
+    /// Whether to keep the read length of every alignment, to calculate and render the N50 of each contig, see
+    /// [`Summary::set_keep_read_lengths`].
+    pub keep_read_lengths: bool,
+    /// The optional number of threads to demultiplex with. More than one thread uses
+    /// [`paf::Paf::demultiplex_parallel`], which requires the `rayon` feature. Without it, a warning is logged and the
+    /// files are demultiplexed on a single thread.
+    pub threads: Option<usize>,
+    /// The optional file path to a BED file of targets, which replace the targets in the TOML, see
+    /// [`Conf::load_bed_targets`].
+    pub targets_bed: Option<PathBuf>,
+    /// The optional file path to a samtools faidx `.fai` index of the reference, so that contigs without alignments
+    /// are reported and the enrichment of each condition is calculated, see [`Summary::load_fai`].
+    pub fai_path: Option<PathBuf>,
+    /// The minimum mapping quality, read length and alignment block length alignments must pass to be summarised.
+    /// Failing alignments are counted in [`Summary::filtered_out`], see [`paf::ReadFilter`].
+    pub read_filter: ReadFilter,
+    /// The optional directory to write the PAF records of each condition to, one file per condition, see
+    /// [`paf::ConditionPafWriters`]. The records of every PAF file are appended to the same file per condition.
+    /// Records can't be written out in parallel, so this demultiplexes on a single thread.
+    pub output_dir: Option<PathBuf>,
+    /// The optional file path to stream the assignment of every read to, as a tab separated file with a header of
+    /// [`paf::ASSIGNMENTS_HEADER`] and a row per alignment from every PAF file, so on-target reads can be extracted
+    /// from the original FASTQ later. A path ending in `.ndjson` or `.jsonl` is written as a JSON object per alignment
+    /// instead, see [`paf::AssignmentsFormat::from_path`]. Like `output_dir`, this demultiplexes on a single thread.
+    pub assignments_out: Option<PathBuf>,
+}
+
+/// Demultiplex PAF records based on the specified configuration.
+///
+/// This function takes two file paths as inputs, `toml_path` and `paf_path`, representing
+/// the paths to the TOML configuration file and the PAF file, respectively. The TOML configuration
+/// is read using the `readfish::Conf::from_file` function, and the PAF file is opened and checked using the
+/// `paf::open_paf_for_reading` function. The resulting PAF records are then demultiplexed based on the
+/// information provided in the configuration file.
+///
+/// If there are barcodes present in the Conf TOML file, the barcode for each read is taken from the `ba` tag of
+/// the PAF record, or from the `barcode_arrangement` column of the sequencing summary file.
+///
+/// # Arguments
+///
+/// * `toml_path`: The file path to the TOML configuration file.
+/// * `paf_paths`: The file paths of the PAF files to be demultiplexed, for example one per minimap2 batch. Every file
+///   contributes to the same [`Summary`], so reads from the same condition are summarised together across files.
+///   A path of `-` reads the PAF from stdin, so the output of an aligner can be piped straight in.
+/// * `sequencing_summary_path`: The optional file path to the sequencing summary file. If the run is split across
+///   several PAF files, they should be given in the order the reads were base-called, as the sequencing summary is read in order.
+///   A path of `-` reads the sequencing summary from stdin, as a single forward stream.
+/// * `options`: The [`DemultiplexOptions`] controlling filtering, threading, strictness and what is printed and
+///   written out.
+///
+/// # Returns
+///
+/// The finalized [`Summary`] of the demultiplexed PAF records.
+///
+/// # Errors
+///
+/// Returns an error if the targets BED file, the `.fai` or the sequencing summary cannot be read, if demultiplexing fails, for example when the
+/// TOML has barcodes but a read has neither a `ba` tag nor a `barcode_arrangement` column, or if the CSV cannot be written.
+/// Errors from demultiplexing are prefixed with the path of the PAF file that failed. Returns an error if more than
+/// one input is read from stdin, or if the output directory cannot be created.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use std::path::Path;
+/// let options = DemultiplexOptions {
+///     print_summary: true,
+///     ..Default::default()
+/// };
+/// _demultiplex_paf("config.toml", &["batch_0.paf", "batch_1.paf"], None::<&Path>, &options)?;
+/// ```
+///
+pub fn _demultiplex_paf(
+    toml_path: impl AsRef<Path>,
+    paf_paths: &[impl AsRef<Path>],
+    sequencing_summary_path: Option<impl AsRef<Path>>,
+    options: &DemultiplexOptions,
+) -> DynResult<Summary> {
+    let toml_path = toml_path.as_ref();
+    let sequencing_summary_path = sequencing_summary_path.map(|path| path.as_ref().to_path_buf());
+    let stdin_inputs = paf_paths
+        .iter()
+        .map(|path| path.as_ref())
+        .chain(sequencing_summary_path.as_deref())
+        .filter(|path| *path == Path::new("-"))
+        .count();
+    if stdin_inputs > 1 {
+        return Err(
+            "Only one of the PAF files and the sequencing summary can be read from stdin".into(),
+        );
+    }
+    let mut toml = load_conf(toml_path, options.targets_bed.as_ref())?;
+    let mut seq_sum = open_sequencing_summary(sequencing_summary_path)?;
+    let mut summary = Summary::new();
+    summary.set_strict_contig_lengths(options.strict_contig_lengths);
+    summary.set_keep_read_lengths(options.keep_read_lengths);
+    summary.set_strand_aware(toml.is_strand_aware());
+    if let Some(fai_path) = &options.fai_path {
+        summary.load_fai(fai_path)?;
+    }
+    let assignments_format = match &options.assignments_out {
+        Some(path) => paf::AssignmentsFormat::from_path(path)?,
+        None => paf::AssignmentsFormat::default(),
+    };
+    let mut assignments = options
+        .assignments_out
+        .as_ref()
+        .map(|path| -> DynResult<Box<dyn Write>> {
+            let mut assignments = readfish_io::writer(path)?;
+            if let Some(header) = assignments_format.header() {
+                writeln!(assignments, "{}", header)?;
+            }
+            Ok(assignments)
+        })
+        .transpose()?;
+    // Shared by every PAF file, so their records are appended to the same file per condition
+    let mut output = options
+        .output_dir
+        .as_ref()
+        .map(paf::ConditionPafWriters::new)
+        .transpose()?;
+    for paf_path in paf_paths {
+        let paf_path = paf_path.as_ref();
+        demultiplex_paf_file(
+            &mut toml,
+            paf_path,
+            seq_sum.as_mut(),
+            &mut summary,
+            options,
+            output.as_mut(),
+            assignments
+                .as_mut()
+                .map(|assignments| assignments as &mut dyn Write),
+        )
+        .map_err(|err| format!("Failed to demultiplex {}: {}", paf_path.display(), err))?;
+    }
+    summary.set_target_fractions(&toml);
+    summary.finalize();
+    summary.set_uncovered_targets(&toml);
+    summary.set_target_breadths(&toml);
+    summary.set_target_summaries(&toml);
+    if options.print_summary {
+        println!("{}", summary);
+    }
+    if let Some(csv_out) = &options.csv_out {
+        summary.write_csv(readfish_io::writer(csv_out)?)?;
+    }
+    Ok(summary)
+}
+
+/// Summarise a run from its sequencing summary alone, before the reads have been aligned.
+///
+/// Every record of the sequencing summary is bucketed into a condition by its channel and barcode, see
+/// [`Conf::condition_key`], and added with [`Summary::add_unaligned_read`]. Records that match no region or barcode
+/// are counted as unclassified. As there are no alignments, every read is counted as unmapped, so each condition has
+/// a read count, yield, unmapped mean and median read length, N50 and mean read quality, but no on or off-target
+/// counts.
+///
+/// # Arguments
+///
+/// * `toml_path`: The file path to the TOML configuration file.
+/// * `sequencing_summary_path`: The file path to the sequencing summary file, which may be gzip or bzip2 compressed.
+/// * `print_summary`: Whether to print the summary tables to stdout.
+/// * `csv_out`: The optional file path to write the per-condition summary CSV to.
+///
+/// # Returns
+///
+/// The finalized [`Summary`] of the reads in the sequencing summary.
+///
+/// # Errors
+///
+/// Returns an error if the TOML or sequencing summary cannot be read, if a line of the sequencing summary is
+/// malformed, if the TOML has barcodes but the sequencing summary has no barcode column, or if the CSV cannot be
+/// written. A sequencing summary can't be summarised from stdin, see [`SeqSum::records`].
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// let summary = _summarise_seqsum("config.toml", "sequencing_summary.txt", true, None::<&Path>)?;
+/// ```
+pub fn _summarise_seqsum(
+    toml_path: impl AsRef<Path>,
+    sequencing_summary_path: impl AsRef<Path>,
+    print_summary: bool,
+    csv_out: Option<impl AsRef<Path>>,
+) -> DynResult<Summary> {
+    let toml = Conf::from_file(toml_path.as_ref())?;
+    let seq_sum = SeqSum::from_file(&sequencing_summary_path)?;
+    if !seq_sum.has_barcode && !toml.barcodes().is_empty() {
+        return Err(format!(
+            "The TOML configuration contains barcodes but {} has no `barcode_arrangement` or `barcode` column",
+            sequencing_summary_path.as_ref().display()
+        )
+        .into());
+    }
+    let mut summary = Summary::new();
+    for seq_sum_record in seq_sum.records() {
+        let seq_sum_record = seq_sum_record?;
+        let condition_name = toml
+            .condition_key(seq_sum_record.channel, seq_sum_record.barcode.as_deref())
+            .unwrap_or_else(|_| UNCLASSIFIED_CONDITION.to_string());
+        summary.add_unaligned_read(&condition_name, &seq_sum_record);
+    }
+    summary.finalize();
+    if print_summary {
+        println!("{}", summary);
+    }
+    if let Some(csv_out) = csv_out {
+        summary.write_csv(readfish_io::writer(csv_out)?)?;
+    }
+    Ok(summary)
+}
+
+/// Open the sequencing summary, if one is given, indexing it so reads can be looked up in any order.
+///
+/// Sequencing summaries read from stdin can't be indexed, so are read in order, as are gzipped files,
+/// see [`SeqSum::build_index`].
+///
+/// # Errors
+///
+/// Returns an error if the sequencing summary cannot be read or indexed.
+fn open_sequencing_summary(
+    sequencing_summary_path: Option<impl AsRef<Path>>,
+) -> DynResult<Option<SeqSum>> {
+    let Some(sequencing_summary_path) = sequencing_summary_path else {
+        return Ok(None);
+    };
+    let mut seq_sum = SeqSum::from_file(&sequencing_summary_path)?;
+    if sequencing_summary_path.as_ref() != Path::new("-") {
+        seq_sum.build_index()?;
+    }
+    Ok(Some(seq_sum))
+}
+
+/// Demultiplexes a single PAF file into `summary`, on one thread or in parallel, see [`_demultiplex_paf`].
+///
+/// # Errors
+///
+/// Returns an error if the PAF file cannot be opened, or if demultiplexing fails.
+fn demultiplex_paf_file(
+    toml: &mut Conf,
+    paf_path: &Path,
+    seq_sum: Option<&mut SeqSum>,
+    summary: &mut Summary,
+    options: &DemultiplexOptions,
+    output: Option<&mut paf::ConditionPafWriters>,
+    assignments: Option<&mut dyn Write>,
+) -> DynResult<()> {
+    let mut paf = paf::Paf::try_new(paf_path)?;
+    if let Some(assignments_out) = &options.assignments_out {
+        paf.set_assignments_format(paf::AssignmentsFormat::from_path(assignments_out)?);
+    }
+    paf.set_strict(options.strict);
+    paf.set_strict_seqsum(options.strict_seqsum);
+    paf.set_deduplicate_reads(options.deduplicate_reads);
+    paf.set_keep_read_lengths(options.keep_read_lengths);
+    paf.set_read_filter(options.read_filter);
+    match options.threads {
+        #[cfg(feature = "rayon")]
+        Some(threads) if threads > 1 && output.is_none() && assignments.is_none() => {
+            summary.merge(paf.demultiplex_parallel(toml, seq_sum, threads)?);
+            // The partial summaries of each thread aren't strict, so any mismatches are only found once merged
+            if let Some((contig, expected_length, length)) = summary
+                .contig_length_discrepancies
+                .first()
+                .filter(|_| summary.strict_contig_lengths)
+            {
+                return Err(format!(
+                    "Contig {} has a length of {} in an alignment, but {} elsewhere, were the reads aligned to \
+                     different references?",
+                    contig, length, expected_length
+                )
+                .into());
+            }
+        }
+        _ => {
+            #[cfg(not(feature = "rayon"))]
+            if options.threads.is_some_and(|threads| threads > 1) {
+                log::warn!(
+                    "readfish_tools was built without the rayon feature, demultiplexing on a single thread"
+                );
+            }
+            #[cfg(feature = "rayon")]
+            if options.threads.is_some_and(|threads| threads > 1) {
+                log::warn!(
+                    "Records can't be written out in parallel, demultiplexing on a single thread"
+                );
+            }
+            paf.demultiplex(toml, seq_sum, Some(summary), output, assignments)?;
+        }
+    }
+    Ok(())
+}
+
+/// Initialises logging to stderr, so the messages logged whilst demultiplexing can be seen.
+///
+/// Only warnings and errors are shown by default, so production runs are quiet. Setting `verbose` raises the
+/// level to `debug`. If the `RUST_LOG` environment variable is set, it takes precedence over `verbose`.
+/// The logger is only installed once, but the level can be changed by calling this function again.
+///
+/// # Arguments
+///
+/// * `verbose` - Whether to show debug messages, such as the files being demultiplexed.
+///
+/// # Examples
+///
+/// ```
+/// readfish_tools::init_logging(false);
+/// log::debug!("Not shown");
+/// ```
+pub fn init_logging(verbose: bool) {
+    let rust_log_set = std::env::var_os("RUST_LOG").is_some();
+    // The logger itself lets everything through, and the global max level decides what is shown
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    if !rust_log_set {
+        log::set_max_level(if verbose {
+            LevelFilter::Debug
+        } else {
+            LevelFilter::Warn
+        });
+    }
+}
+
+/// Reads the TOML configuration, replacing its targets with those of a BED file if one is given.
+///
+/// # Arguments
+///
+/// * `toml_path`: The file path to the TOML configuration file.
+/// * `targets_bed`: The optional file path to a BED file of targets, see [`Conf::load_bed_targets`].
+///
+/// # Errors
+///
+/// Returns an error if the BED file cannot be read or is malformed.
+fn load_conf(
+    toml_path: impl AsRef<Path>,
+    targets_bed: Option<impl AsRef<Path>>,
+) -> DynResult<Conf> {
+    let mut conf = Conf::from_file(toml_path.as_ref())?;
+    if let Some(targets_bed) = targets_bed {
+        conf.load_bed_targets(targets_bed)?;
+    }
+    Ok(conf)
+}
+
+/// Demultiplexes a BAM or CRAM file by condition, using the provided TOML configuration and sequencing summary.
+///
+/// Each alignment is wrapped in a [`bam::BamRecord`] and summarised in the same way as in [`_demultiplex_paf`], see
+/// [`bam::demultiplex`].
+///
+/// # Arguments
+///
+/// * `toml_path`: The file path to the TOML configuration file.
+/// * `bam_path`: The file path to the BAM or CRAM file to be demultiplexed.
+/// * `sequencing_summary_path`: The file path to the sequencing summary file, used to look up the channel and barcode of each read.
+/// * `print_summary`: Whether to print the summary tables to stdout.
+/// * `csv_out`: The optional file path to write the per-condition summary CSV to.
+/// * `skip_secondary`: Whether to skip secondary and supplementary alignments.
+/// * `strict_seqsum`: Whether reads missing from the sequencing summary are an error, rather than being skipped and
+///   counted in [`Summary::reads_missing_from_seqsum`].
+/// * `targets_bed`: The optional file path to a BED file of targets, which replace the targets in the TOML, see
+///   [`Conf::load_bed_targets`].
+/// * `fai_path`: The optional file path to a samtools faidx `.fai` index of the reference, so that contigs without
+///   alignments are reported and the enrichment of each condition is calculated, see [`Summary::load_fai`].
+/// * `read_filter`: The filters alignments must pass to be summarised, see [`paf::ReadFilter`].
+/// * `output_dir`: An optional directory to write a coordinate sorted BAM file of the records of each condition to, see
+///   [`bam::demultiplex`].
+/// * `reference_path`: The optional file path to the reference FASTA, indexed with samtools faidx, which is required
+///   to decode a CRAM file. Its `.fai` doubles as the `fai_path` if none is given.
+///
+/// # Returns
+///
+/// The finalized [`Summary`] of the demultiplexed BAM records.
+///
+/// # Errors
+///
+/// Returns an error if the targets BED file or the `.fai` cannot be read, if the sequencing summary is missing or cannot be read, if the BAM file cannot be read,
+/// if a CRAM file has no reference, or the reference doesn't match it, if demultiplexing fails, or if the CSV cannot be written.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// _demultiplex_bam("config.toml", "file.bam", Some("sequencing_summary.txt"), true, None::<String>, true, false, None::<String>, None::<String>, ReadFilter::default(), None::<String>, None::<String>)?;
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn _demultiplex_bam(
+    toml_path: impl AsRef<Path>,
+    bam_path: impl AsRef<Path>,
+    sequencing_summary_path: Option<impl AsRef<Path>>,
+    print_summary: bool,
+    csv_out: Option<impl AsRef<Path>>,
+    skip_secondary: bool,
+    strict_seqsum: bool,
+    targets_bed: Option<impl AsRef<Path>>,
+    fai_path: Option<impl AsRef<Path>>,
+    read_filter: ReadFilter,
+    output_dir: Option<impl AsRef<Path>>,
+    reference_path: Option<impl AsRef<Path>>,
+) -> DynResult<Summary> {
+    let toml = load_conf(toml_path, targets_bed)?;
+    let mut seq_sum = open_sequencing_summary(sequencing_summary_path)?;
+    let mut summary = Summary::new();
+    summary.set_strand_aware(toml.is_strand_aware());
+    if let Some(fai_path) = fai_path {
+        summary.load_fai(fai_path)?;
+    } else if let Some(reference_path) = &reference_path {
+        summary.load_fai(bam::reference_fai_path(reference_path.as_ref()))?;
+    }
+    bam::demultiplex(
+        bam_path,
+        &toml,
+        seq_sum.as_mut(),
+        Some(&mut summary),
+        skip_secondary,
+        strict_seqsum,
+        read_filter,
+        output_dir.as_ref().map(AsRef::as_ref),
+        reference_path.as_ref().map(AsRef::as_ref),
+    )?;
+    summary.set_target_fractions(&toml);
+    summary.set_uncovered_targets(&toml);
+    summary.set_target_breadths(&toml);
+    summary.set_target_summaries(&toml);
+    if print_summary {
+        println!("{}", summary);
+    }
+    if let Some(csv_out) = csv_out {
+        summary.write_csv(readfish_io::writer(csv_out)?)?;
+    }
+    Ok(summary)
+}
+
+// PYTHON PyO3 STuff below ////////////////////////
+#[cfg(feature = "pyo3_support")]
+#[pyclass]
+/// Organise the data and methods for analysing a readfish PAF file.
+pub struct ReadfishSummary {
+    /// Stores the aggregated summary numbers for the readfish run
+    summary: RefCell<Summary>,
+    /// The config TOML file for the readfish tun
+    _conf: Option<Conf>,
+    /// The sequencing summary file
+    _sequencing_summary: Option<SeqSum>,
+    /// The PAF file
+    _paf_file: Option<Paf>,
+}
+#[cfg(not(feature = "pyo3_support"))]
+/// Organise the data and methods for analysing a readfish PAF file.
+pub struct ReadfishSummary {
+    /// Stores the aggregated summary numbers for the readfish run
+    summary: RefCell<Summary>,
+    /// The config TOML file for the readfish tun
+    _conf: Option<Conf>,
+    /// The sequencing summary file
+    _sequencing_summary: Option<SeqSum>,
+    /// The PAF file
+    _paf_file: Option<Paf>,
+}
+
+impl Default for ReadfishSummary {
+    fn default() -> Self {
+        ReadfishSummary::new()
+    }
+}
+
+impl ReadfishSummary {
+    /// Creates a new instance of `ReadfishSummary` with default values.
+    ///
+    /// This function initializes a new `ReadfishSummary` struct with default values
+    /// for all fields. The `summary` field will be initialized with an empty `Summary`
+    /// instance. The `_conf`, `_sequencing_summary`, and `_paf_file` fields will be set
+    /// to `None`, indicating that they have not been initialized with specific values yet.
+    ///
+    /// # Returns
+    ///
+    /// A new `ReadfishSummary` instance with default values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use readfish_tools::ReadfishSummary;
+    ///
+    /// let summary = ReadfishSummary::new();
+    /// assert_eq!(summary.has_conf(), false); // _conf field is not set yet
+    /// assert_eq!(summary.has_sequencing_summary(), false); // _sequencing_summary field is not set yet
+    /// assert_eq!(summary.has_paf_file(), false); // _paf_file field is not set yet
+    /// ```
+    pub fn new() -> Self {
+        ReadfishSummary {
+            summary: RefCell::new(Summary::new()),
+            _conf: None,
+            _sequencing_summary: None,
+            _paf_file: None,
+        }
+    }
+    /// Update a condition on the summary
+    // pub fn update_condition(
+    //     &mut self,
+    //     condition_name: &str,
+    //     paf_record: PafRecord,
+    //     on_target: bool,
+    // ) {
+    //     let condition_summary = self.summary.borrow_mut().conditions_entry(condition_name);
+    //     condition_summary.update(paf_record, on_target).unwrap();
+    // }
+
+    /// Get the config TOML file for the readfish run.
+    pub fn conf(&self) -> Option<&Conf> {
+        self._conf.as_ref()
+    }
+
+    /// Set the config TOML file for the readfish run.
+    pub fn set_conf(&mut self, conf: Conf) {
+        self._conf = Some(conf);
+    }
+
+    /// Parse a path to a config file and add it to the ReadfishSummary
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`readfish::ConfError`] if the config file cannot be read or parsed, see [`Conf::from_file`].
+    pub fn parse_conf_file(&mut self, conf_path: PathBuf) -> readfish::ConfResult<()> {
+        let conf = Conf::from_file(conf_path)?;
+        self._conf = Some(conf);
+        Ok(())
+    }
+
+    /// Get the sequencing summary file.
+    pub fn sequencing_summary(&self) -> Option<&SeqSum> {
+        self._sequencing_summary.as_ref()
+    }
+
+    /// Set the sequencing summary file.
+    pub fn set_sequencing_summary(&mut self, sequencing_summary: SeqSum) {
+        self._sequencing_summary = Some(sequencing_summary);
+    }
+
+    /// Parse a provided sequencing summary file path and set it on the summary
+    pub fn parse_sequencing_summary(&mut self, seq_sum_path: PathBuf) {
+        let sequencing_summary = SeqSum::from_file(seq_sum_path).unwrap();
+        self.set_sequencing_summary(sequencing_summary);
+    }
+
+    /// Get the PAF file.
+    pub fn paf_file(&self) -> Option<&Paf> {
+        self._paf_file.as_ref()
+    }
+
+    /// Set the PAF file.
+    pub fn set_paf_file(&mut self, paf_file: Paf) {
+        self._paf_file = Some(paf_file);
+    }
+
+    /// Check if the config TOML file is set.
+    pub fn has_conf(&self) -> bool {
+        self._conf.is_some()
+    }
+
+    /// Check if the sequencing summary file is set.
+    pub fn has_sequencing_summary(&self) -> bool {
+        self._sequencing_summary.is_some()
+    }
+
+    /// Check if the PAF file is set.
+    pub fn has_paf_file(&self) -> bool {
+        self._paf_file.is_some()
+    }
+}
+
+#[cfg(feature = "pyo3_support")]
+/// Implements methods for interacting with a ReadfishSummary instance from Python.
+#[pymethods]
+impl ReadfishSummary {
+    /// Creates a new instance of ReadfishSummary with default values.
+    /// Returns:
+    ///     A new ReadfishSummary instance.
+    #[new]
+    #[pyo3(signature = ())]
+    fn py_new() -> PyResult<Self> {
+        Ok(ReadfishSummary::default())
+    }
+    /// Adds a PAF record to the ReadfishSummary.
+    /// This method is not implemented and will always return an error.
+    /// Returns:
+    ///     An error indicating that the method is not implemented.
+    fn add_paf_record(&self) -> PyResult<()> {
+        unimplemented!()
+    }
+
+    /// Parses the provided sequencing summary file and sets it on the ReadfishSummary.
+    /// Args:
+    ///     seq_sum_path: PathBuf - The path to the sequencing summary file to be parsed.
+    /// Returns:
+    ///     Ok(()) if successful, or an error if the file could not be parsed or set on the summary.
+    fn with_sequencing_summary(&mut self, seq_sum_path: PathBuf) -> PyResult<()> {
+        self.parse_sequencing_summary(seq_sum_path);
+        Ok(())
+    }
+
+    /// Parses the provided TOML configuration file and sets it on the ReadfishSummary.
+    /// Args:
+    ///     toml_path: PathBuf - The path to the TOML configuration file to be parsed.
+    /// Returns:
+    ///     Ok(()) if successful, or an error if the file could not be parsed or set on the summary.
+    fn with_toml_conf(&mut self, toml_path: PathBuf) -> PyResult<()> {
+        self.parse_conf_file(toml_path)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+    }
+
+    /// The names of the regions in the TOML configuration, in the order they were declared, see
+    /// [`Conf::region_names`].
+    /// Returns:
+    ///     A list of region names, or a `RuntimeError` if no TOML configuration has been set.
+    fn region_names(&self) -> PyResult<Vec<String>> {
+        let conf = self
+            .conf()
+            .ok_or_else(|| PyRuntimeError::new_err("No TOML configuration has been set"))?;
+        Ok(conf.region_names().into_iter().map(String::from).collect())
+    }
+
+    /// The names of the barcodes in the TOML configuration, in the order they were declared, see
+    /// [`Conf::barcode_names`].
+    /// Returns:
+    ///     A list of barcode names, or a `RuntimeError` if no TOML configuration has been set.
+    fn barcode_names(&self) -> PyResult<Vec<String>> {
+        let conf = self
+            .conf()
+            .ok_or_else(|| PyRuntimeError::new_err("No TOML configuration has been set"))?;
+        Ok(conf.barcode_names().into_iter().map(String::from).collect())
+    }
+
+    /// Loads the contig lengths of the reference from a samtools faidx `.fai` index, see [`Summary::load_fai`].
+    /// Args:
+    ///     fai_path: PathBuf - The path to the `.fai` file.
+    /// Returns:
+    ///     Ok(()) if successful, or a `RuntimeError` if the file could not be read.
+    fn with_fai(&mut self, fai_path: PathBuf) -> PyResult<()> {
+        self.summary
+            .borrow_mut()
+            .load_fai(fai_path)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+    }
+
+    /// Parses PAF lines from a Python iterator and updates the ReadfishSummary accordingly.
+    ///
+    /// This method takes a Python iterator that provides PAF lines as strings. It iterates over the lines,
+    /// parses each line to extract relevant information, and updates the ReadfishSummary based on the
+    /// extracted data. The extracted metadata is used to make decisions and update the internal state of
+    /// the ReadfishSummary. Finishes by printing a summary of the parsed PAF files to stdout.
+    ///
+    /// # Arguments
+    ///
+    /// * `iter`: A Python iterator that provides PAF lines as strings.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` indicating success or an error encountered during parsing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// # use pyo3::types::PyIterator;
+    /// # use readfish_tools::{ReadfishSummary, paf::Metadata, paf::_parse_paf_line};
+    ///
+    /// // Assuming we have valid inputs
+    /// let mut readfish_summary = ReadfishSummary::default();
+    /// let paf_lines: Vec<String> = vec![
+    ///     "read123 200 0 200 + contig123 300 0 300 200 200 50 ch=1".to_string(),
+    ///     "read456 150 0 150 - contig456 200 0 200 150 150 45 ch=2 ba=sampleB".to_string(),
+    ///     // Add more PAF lines as needed
+    /// ];
+    /// let py_iter = PyIterator::new(paf_lines.into_iter());
+    ///
+    /// let result = readfish_summary.parse_paf_from_iter(&py_iter);
+    ///
+    /// assert!(result.is_ok());
+    /// ```
+    fn parse_paf_from_iter(&mut self, iter: &PyIterator) -> PyResult<()> {
+        let conf = self.conf().unwrap();
+        for paf_line in iter {
+            let paf_line = paf_line?;
+            let (paf_line, meta_tuple): (String, (String, usize, Option<String>)) =
+                paf_line.extract()?;
+            let mut meta_data: Metadata = meta_tuple.into();
+            let channel = meta_data.channel();
+            let (paf_record, on_target, condition_name, _seq_sum_record) =
+                _parse_paf_line(paf_line, conf, Some(&mut meta_data), None)
+                    .map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+            let targets = if on_target {
+                conf.overlapping_targets(
+                    &condition_name,
+                    paf_record.target_name(),
+                    paf_record.target_start(),
+                    paf_record.target_end(),
+                )
+            } else {
+                0..0
+            };
+            {
+                let mut x = self.summary.borrow_mut();
+                x.add_record(&condition_name, channel, &paf_record, on_target, None)
+                    .map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+                x.add_target_hits(&condition_name, &paf_record, targets);
+            }
+        }
+        Ok(())
+    }
+    /// Prints the summary of the `ReadfishSummary` to the standard output.
+    ///
+    /// This method borrows the `ReadfishSummary` immutably and prints its summary to the standard output.
+    /// The summary is obtained by calling the `borrow` method on the `RefCell<Summary>` field of the
+    /// `ReadfishSummary`.
+    ///
+    /// # Returns
+    ///
+    /// This function returns a `PyResult<()>` to indicate success or failure. If the summary is
+    /// successfully printed, `Ok(())` is returned. If an error occurs during printing, an appropriate
+    /// `PyErr` will be set, and `Err` will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// # use pyo3::prelude::*;
+    /// # use std::cell::RefCell;
+    /// # use std::collections::HashMap;
+    /// # use std::path::PathBuf;
+    /// # use std::error::Error;
+    ///
+    /// # #[pyclass]
+    /// # pub struct ReadfishSummary {
+    /// #     // Fields of ReadfishSummary
+    /// #     // ...
+    /// # }
+    /// #
+    /// # #[pymethods]
+    /// # impl ReadfishSummary {
+    /// #     #[getter]
+    /// #     pub fn summary(&self) -> PyResult<Ref<Summary>> {
+    /// #         unimplemented!()
+    /// #     }
+    /// #
+    /// /// Method to print the summary of ReadfishSummary.
+    /// pub fn print_summary(&self) -> PyResult<()> {
+    ///     println!("{}", self.summary.borrow());
+    ///     Ok(())
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// The method can be called on an instance of `ReadfishSummary` to print its summary.
+    ///
+    /// ```rust,ignore
+    /// # use pyo3::prelude::*;
+    /// # use std::cell::RefCell;
+    /// # use std::collections::HashMap;
+    /// # use std::path::PathBuf;
+    /// # use std::error::Error;
+    /// #
+    /// # #[pyclass]
+    /// # pub struct ReadfishSummary {
+    /// #     // Fields of ReadfishSummary
+    /// #     // ...
+    /// # }
+    /// #
+    /// # #[pymethods]
+    /// # impl ReadfishSummary {
+    /// #     #[getter]
+    /// #     pub fn summary(&self) -> PyResult<Ref<Summary>> {
+    /// #         unimplemented!()
+    /// #     }
+    /// #
+    /// #     /// Method to print the summary of ReadfishSummary.
+    /// #     pub fn print_summary(&self) -> PyResult<()> {
+    /// #         println!("{}", self.summary.borrow());
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// #
+    /// # fn main() -> PyResult<()> {
+    /// #     Python::with_gil(|py| {
+    /// #         let gil = Python::acquire_gil();
+    /// #         let py = gil.python();
+    /// #
+    /// #         // Create an instance of ReadfishSummary and call the print_summary method
+    /// #         let readfish_summary = ReadfishSummary { /* Initialize fields... */ };
+    /// #         readfish_summary.print_summary()?;
+    /// #         Ok(())
+    /// #     })
+    /// # }
+    /// ```
+    pub fn print_summary(&self) -> PyResult<()> {
+        let mut summary = self.summary.borrow_mut();
+        if let Some(conf) = self.conf() {
+            summary.set_target_fractions(conf);
+            summary.set_strand_aware(conf.is_strand_aware());
+        }
+        summary.finalize();
+        if let Some(conf) = self.conf() {
+            summary.set_uncovered_targets(conf);
+            summary.set_target_breadths(conf);
+            summary.set_target_summaries(conf);
+        }
+        println!("{}", summary);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "pyo3_support")]
+/// One or more PAF file paths passed from Python.
+#[derive(FromPyObject)]
+enum PafPaths {
+    /// A single PAF file.
+    One(PathBuf),
+    /// Several PAF files, summarised together.
+    Many(Vec<PathBuf>),
+}
+
+#[cfg(feature = "pyo3_support")]
+impl PafPaths {
+    /// Get the PAF file paths, in the order they were given.
+    fn into_paths(self) -> Vec<PathBuf> {
+        match self {
+            PafPaths::One(path) => vec![path],
+            PafPaths::Many(paths) => paths,
+        }
+    }
+}
+
+#[cfg(feature = "pyo3_support")]
+/// Formats the sum of two numbers as string.
+#[pyfunction]
+fn summarise_from_iter(
+    toml_path: PathBuf,
+    paf_path: PathBuf,
+    seq_sum_path: PathBuf,
+) -> PyResult<()> {
+    _demultiplex_paf(
+        toml_path,
+        &[paf_path],
+        Some(seq_sum_path),
+        &DemultiplexOptions {
+            print_summary: true,
+            ..Default::default()
+        },
+    )
+    .map(|_summary| ())
+    .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+}
+
+#[cfg(feature = "pyo3_support")]
+/// Summarizes the results of demultiplexing a PAF file using the provided TOML file and
+/// prints the summary to stdout.
+///
+/// # Arguments
+///
+/// * `toml_path` - The path to the TOML file containing configuration settings for a readfish experiment.
+/// * `paf_path` - The path to the PAF file containing alignment results, or a list of paths to PAF files that are
+///   summarised together, such as the output of each minimap2 batch.
+/// * `seq_sum_path` - The optional sequencing summary file produced by ONTs guppy. If not given, the channel and
+///   barcode of each read are taken from the `ch` and `ba` tags of the PAF file.
+/// * `strict` - Whether malformed PAF lines raise an error, rather than being skipped. Defaults to `False`.
+/// * `strict_contig_lengths` - Whether contig lengths that disagree between alignments, or with the `.fai`, raise an
+///   error, rather than being counted. Defaults to `False`.
+/// * `strict_seqsum` - Whether reads missing from the sequencing summary raise an error, rather than being skipped.
+///   Defaults to `False`.
+/// * `deduplicate_reads` - Whether to count each read once, by its primary alignment, rather than once per PAF line.
+///   Defaults to `False`.
+/// * `keep_read_lengths` - Whether to keep the read length of every alignment, to calculate the N50 of each contig.
+///   Defaults to `False`.
+/// * `threads` - The number of threads to demultiplex with, if readfish_tools was built with the `rayon` feature.
+///   Defaults to `None`, a single thread.
+/// * `targets_bed` - The path to a BED file of targets, which replace the targets in the TOML. Records whose name
+///   column is a region or barcode are only targets of that condition. Defaults to `None`.
+/// * `fai_path` - The path to a samtools faidx `.fai` index of the reference, so that contigs without alignments are
+///   reported and the enrichment of each condition is calculated. Defaults to `None`.
+/// * `min_mapq` - The minimum mapping quality of an alignment. A missing mapping quality (255) isn't compared.
+///   Defaults to `None`.
+/// * `min_read_length` - The minimum length of a read. Defaults to `None`.
+/// * `min_block_len` - The minimum alignment block length of an alignment. Defaults to `None`.
+/// * `exclude_missing_mapq` - Whether to exclude alignments with a missing mapping quality (255). Defaults to `False`.
+/// * `assignments` - The path to write the read ID, condition, on-target flag and contig of every alignment to, as a
+///   tab separated file, to extract on-target reads from the FASTQ later. A path ending in `.ndjson` is written as a
+///   JSON object per alignment, which also has the query length and identity. Defaults to `None`.
+/// * `verbose` - Whether to log debug messages to stderr. Defaults to `False`, see [`init_logging`].
+///
+/// # Returns
+///
+/// This function returns a `PyResult<()>`, indicating success or an error encountered during processing.
+///
+/// # Errors
+///
+/// Raises a `RuntimeError` if demultiplexing fails, for example if the TOML has barcodes and the
+/// sequencing summary file has no `barcode_arrangement` column, or if no sequencing summary is given and
+/// a read is missing the `ch` tag. The message names the PAF file that failed.
+#[pyfunction]
+#[pyo3(signature = (toml_path, paf_path, seq_sum_path = None, strict = false, strict_contig_lengths = false, strict_seqsum = false, deduplicate_reads = false, keep_read_lengths = false, threads = None, targets_bed = None, fai_path = None, min_mapq = None, min_read_length = None, min_block_len = None, exclude_missing_mapq = false, assignments = None, verbose = false))]
+#[allow(clippy::too_many_arguments)]
+fn summarise_paf(
+    toml_path: PathBuf,
+    paf_path: PafPaths,
+    seq_sum_path: Option<PathBuf>,
+    strict: bool,
+    strict_contig_lengths: bool,
+    strict_seqsum: bool,
+    deduplicate_reads: bool,
+    keep_read_lengths: bool,
+    threads: Option<usize>,
+    targets_bed: Option<PathBuf>,
+    fai_path: Option<PathBuf>,
+    min_mapq: Option<u8>,
+    min_read_length: Option<usize>,
+    min_block_len: Option<usize>,
+    exclude_missing_mapq: bool,
+    assignments: Option<PathBuf>,
+    verbose: bool,
+) -> PyResult<()> {
+    init_logging(verbose);
+    _demultiplex_paf(
+        toml_path,
+        &paf_path.into_paths(),
+        seq_sum_path,
+        &DemultiplexOptions {
+            print_summary: true,
+            strict,
+            strict_contig_lengths,
+            strict_seqsum,
+            deduplicate_reads,
+            keep_read_lengths,
+            threads,
+            targets_bed,
+            fai_path,
+            read_filter: ReadFilter {
+                min_mapq,
+                min_read_length,
+                min_block_len,
+                exclude_missing_mapq,
+            },
+            assignments_out: assignments,
+            ..Default::default()
+        },
+    )
+    .map(|_summary| ())
+    .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+}
+#[cfg(feature = "pyo3_support")]
+/// Demultiplex a PAF file using the provided TOML file, returning the [`Summary`] to Python.
+///
+/// # Arguments
+///
+/// * `toml_path` - The path to the TOML file containing configuration settings for a readfish experiment.
+/// * `paf_path` - The path to the PAF file containing alignment results, or a list of paths to PAF files that are
+///   summarised together, such as the output of each minimap2 batch.
+/// * `seq_sum_path` - The optional sequencing summary file produced by ONTs guppy. If not given, the channel and
+///   barcode of each read are taken from the `ch` and `ba` tags of the PAF file.
+/// * `print` - Whether to also print the summary tables to stdout. Defaults to `False`.
+/// * `strict` - Whether malformed PAF lines raise an error, rather than being skipped and counted in
+///   `Summary.skipped_lines`. Defaults to `False`.
+/// * `strict_contig_lengths` - Whether contig lengths that disagree between alignments, or with the `.fai`, raise an
+///   error, rather than being counted in `Summary.contig_length_mismatches`. Defaults to `False`.
+/// * `strict_seqsum` - Whether reads missing from the sequencing summary raise an error, rather than being skipped and
+///   counted in `Summary.reads_missing_from_seqsum`. Defaults to `False`.
+/// * `deduplicate_reads` - Whether to count each read once, by its primary alignment, rather than once per PAF line.
+///   Defaults to `False`.
+/// * `keep_read_lengths` - Whether to keep the read length of every alignment, to calculate the N50 of each contig.
+///   Defaults to `False`.
+/// * `threads` - The number of threads to demultiplex with, if readfish_tools was built with the `rayon` feature.
+///   Defaults to `None`, a single thread.
+/// * `targets_bed` - The path to a BED file of targets, which replace the targets in the TOML. Records whose name
+///   column is a region or barcode are only targets of that condition. Defaults to `None`.
+/// * `fai_path` - The path to a samtools faidx `.fai` index of the reference, so that contigs without alignments are
+///   reported and the enrichment of each condition is calculated. Defaults to `None`.
+/// * `min_mapq` - The minimum mapping quality of an alignment. A missing mapping quality (255) isn't compared.
+///   Defaults to `None`.
+/// * `min_read_length` - The minimum length of a read. Defaults to `None`.
+/// * `min_block_len` - The minimum alignment block length of an alignment. Defaults to `None`.
+/// * `exclude_missing_mapq` - Whether to exclude alignments with a missing mapping quality (255). Defaults to `False`.
+/// * `assignments` - The path to write the read ID, condition, on-target flag and contig of every alignment to, as a
+///   tab separated file, to extract on-target reads from the FASTQ later. A path ending in `.ndjson` is written as a
+///   JSON object per alignment, which also has the query length and identity. Defaults to `None`.
+/// * `verbose` - Whether to log debug messages to stderr. Defaults to `False`, see [`init_logging`].
+///
+/// # Returns
+///
+/// The [`Summary`], whose conditions and contigs can be inspected from Python.
+///
+/// # Errors
+///
+/// Raises a `RuntimeError` if demultiplexing fails, including when no sequencing summary is given and a
+/// read is missing the `ch` tag. The message names the PAF file that failed.
+#[pyfunction]
+#[pyo3(signature = (toml_path, paf_path, seq_sum_path = None, print = false, strict = false, strict_contig_lengths = false, strict_seqsum = false, deduplicate_reads = false, keep_read_lengths = false, threads = None, targets_bed = None, fai_path = None, min_mapq = None, min_read_length = None, min_block_len = None, exclude_missing_mapq = false, assignments = None, verbose = false))]
+#[allow(clippy::too_many_arguments)]
+fn demultiplex_paf(
+    toml_path: PathBuf,
+    paf_path: PafPaths,
+    seq_sum_path: Option<PathBuf>,
+    print: bool,
+    strict: bool,
+    strict_contig_lengths: bool,
+    strict_seqsum: bool,
+    deduplicate_reads: bool,
+    keep_read_lengths: bool,
+    threads: Option<usize>,
+    targets_bed: Option<PathBuf>,
+    fai_path: Option<PathBuf>,
+    min_mapq: Option<u8>,
+    min_read_length: Option<usize>,
+    min_block_len: Option<usize>,
+    exclude_missing_mapq: bool,
+    assignments: Option<PathBuf>,
+    verbose: bool,
+) -> PyResult<Summary> {
+    init_logging(verbose);
+    _demultiplex_paf(
+        toml_path,
+        &paf_path.into_paths(),
+        seq_sum_path,
+        &DemultiplexOptions {
+            print_summary: print,
+            strict,
+            strict_contig_lengths,
+            strict_seqsum,
+            deduplicate_reads,
+            keep_read_lengths,
+            threads,
+            targets_bed,
+            fai_path,
+            read_filter: ReadFilter {
+                min_mapq,
+                min_read_length,
+                min_block_len,
+                exclude_missing_mapq,
+            },
+            assignments_out: assignments,
+            ..Default::default()
+        },
+    )
+    .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+}
+
+#[cfg(feature = "pyo3_support")]
+/// Demultiplex a BAM file using the provided TOML file, returning the [`Summary`] to Python.
+///
+/// # Arguments
+///
+/// * `toml_path` - The path to the TOML file containing configuration settings for a readfish experiment.
+/// * `bam_path` - The path to the BAM or CRAM file containing alignment results.
+/// * `seq_sum_path` - The sequencing summary file produced by ONTs guppy.
+/// * `print` - Whether to also print the summary tables to stdout. Defaults to `False`.
+/// * `skip_secondary` - Whether to skip secondary and supplementary alignments. Defaults to `True`.
+/// * `strict_seqsum` - Whether reads missing from the sequencing summary raise an error, rather than being skipped and
+///   counted in `Summary.reads_missing_from_seqsum`. Defaults to `False`.
+/// * `targets_bed` - The path to a BED file of targets, which replace the targets in the TOML. Records whose name
+///   column is a region or barcode are only targets of that condition. Defaults to `None`.
+/// * `fai_path` - The path to a samtools faidx `.fai` index of the reference, so that contigs without alignments are
+///   reported and the enrichment of each condition is calculated. Defaults to `None`.
+/// * `min_mapq` - The minimum mapping quality of an alignment. A missing mapping quality (255) isn't compared.
+///   Defaults to `None`.
+/// * `min_read_length` - The minimum length of a read. Defaults to `None`.
+/// * `min_block_len` - The minimum alignment block length of an alignment. Defaults to `None`.
+/// * `exclude_missing_mapq` - Whether to exclude alignments with a missing mapping quality (255). Defaults to `False`.
+/// * `verbose` - Whether to log debug messages to stderr. Defaults to `False`, see [`init_logging`].
+/// * `out_dir` - A directory to write a coordinate sorted BAM file of the records of each condition to. Defaults to
+///   `None`, which writes no BAM files.
+/// * `reference_path` - The path to the reference FASTA, indexed with samtools faidx, that a CRAM file was compressed
+///   against, which is required to decode it. Its `.fai` is used as the `fai_path` if that isn't given. Defaults to
+///   `None`.
+///
+/// # Returns
+///
+/// The [`Summary`], whose conditions and contigs can be inspected from Python.
+///
+/// # Errors
+///
+/// Raises a `RuntimeError` if demultiplexing fails.
+#[pyfunction]
+#[pyo3(signature = (toml_path, bam_path, seq_sum_path, print = false, skip_secondary = true, strict_seqsum = false, targets_bed = None, fai_path = None, min_mapq = None, min_read_length = None, min_block_len = None, exclude_missing_mapq = false, verbose = false, out_dir = None, reference_path = None))]
+#[allow(clippy::too_many_arguments)]
+fn demultiplex_bam(
+    toml_path: PathBuf,
+    bam_path: PathBuf,
+    seq_sum_path: PathBuf,
+    print: bool,
+    skip_secondary: bool,
+    strict_seqsum: bool,
+    targets_bed: Option<PathBuf>,
+    fai_path: Option<PathBuf>,
+    min_mapq: Option<u8>,
+    min_read_length: Option<usize>,
+    min_block_len: Option<usize>,
+    exclude_missing_mapq: bool,
+    verbose: bool,
+    out_dir: Option<PathBuf>,
+    reference_path: Option<PathBuf>,
+) -> PyResult<Summary> {
+    init_logging(verbose);
+    _demultiplex_bam(
+        toml_path,
+        bam_path,
+        Some(seq_sum_path),
+        print,
+        None::<String>,
+        skip_secondary,
+        strict_seqsum,
+        targets_bed,
+        fai_path,
+        ReadFilter {
+            min_mapq,
+            min_read_length,
+            min_block_len,
+            exclude_missing_mapq,
+        },
+        out_dir,
+        reference_path,
+    )
+    .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+}
+
+#[cfg(feature = "pyo3_support")]
+/// Summarise a run from its sequencing summary alone, before it has been aligned, returning the [`Summary`] to
+/// Python. Every read is counted as unmapped in the condition of its channel and barcode, see [`_summarise_seqsum`].
+///
+/// # Arguments
+///
+/// * `toml_path` - The path to the TOML file containing configuration settings for a readfish experiment.
+/// * `seq_sum_path` - The sequencing summary file produced by ONTs guppy.
+/// * `print` - Whether to also print the summary tables to stdout. Defaults to `False`.
+/// * `verbose` - Whether to log debug messages to stderr. Defaults to `False`, see [`init_logging`].
+///
+/// # Returns
+///
+/// The [`Summary`], with the read count, yield, read lengths and read quality of each condition.
+///
+/// # Errors
+///
+/// Raises a `RuntimeError` if the sequencing summary cannot be read or has a malformed line, or if the TOML has
+/// barcodes and the sequencing summary has no barcode column.
+#[pyfunction]
+#[pyo3(signature = (toml_path, seq_sum_path, print = false, verbose = false))]
+fn summarise_seqsum(
+    toml_path: PathBuf,
+    seq_sum_path: PathBuf,
+    print: bool,
+    verbose: bool,
+) -> PyResult<Summary> {
+    init_logging(verbose);
+    _summarise_seqsum(toml_path, seq_sum_path, print, None::<String>)
+        .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+}
+
+#[cfg(feature = "pyo3_support")]
+/// Format a number of bases into a human readable string, such as `1.63 Mb`, the same as the summary tables.
+/// See [`nanopore::format_bases_with`].
+///
+/// # Arguments
+///
+/// * `number` - The number of bases to format.
+/// * `precision` - The number of decimal places to write. Defaults to 2.
+/// * `binary` - Whether each unit is 1024 times the last (Kib, Mib, ...), rather than 1000 (Kb, Mb, ...). Defaults to
+///   `False`.
+#[pyfunction]
+#[pyo3(name = "format_bases", signature = (number, precision = 2, binary = false))]
+fn py_format_bases(number: usize, precision: usize, binary: bool) -> String {
+    let scale = if binary {
+        nanopore::BaseScale::Binary
+    } else {
+        nanopore::BaseScale::Si
+    };
+    nanopore::format_bases_with(number, precision, scale)
+}
+
+#[cfg(feature = "pyo3_support")]
+#[pymethods]
+impl Summary {
+    /// Render the summary tables, so that `print(summary)` in Python shows the same report as Rust.
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+
+    /// Get the stats of every channel with reads, with the `(column, row)` of the channel on the flowcell, for
+    /// plotting a heatmap of the flowcell. See [`Summary::channel_coords`].
+    ///
+    /// # Errors
+    ///
+    /// Raises a `RuntimeError` if a channel is not on a flowcell of `flowcell_size` channels.
+    #[pyo3(name = "channel_coords")]
+    fn py_channel_coords(
+        &self,
+        flowcell_size: usize,
+    ) -> PyResult<Vec<ChannelCoords<ChannelStats>>> {
+        self.channel_coords(flowcell_size)
+            .map(|channels| {
+                channels
+                    .into_iter()
+                    .map(|(channel, coords, channel_stats)| {
+                        (channel, coords, channel_stats.clone())
+                    })
+                    .collect()
+            })
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+    }
+
+    /// Get the grand total of every condition, without contigs. See [`Summary::total`].
+    #[pyo3(name = "total")]
+    fn py_total(&self) -> ConditionSummary {
+        self.total()
+    }
+
+    /// Compare this summary against a baseline summary, such as a control run, giving the change in yield, on-target
+    /// percentage and enrichment of each condition. See [`Summary::diff`].
+    #[pyo3(name = "diff")]
+    fn py_diff(&self, baseline: PyRef<Summary>) -> SummaryDiff {
+        self.diff(&baseline)
+    }
+
+    /// Normalise the on-target fraction of each condition against a control run, giving a control-corrected
+    /// enrichment. See [`Summary::normalize_against`].
+    #[pyo3(name = "normalize_against")]
+    fn py_normalize_against(&self, control: PyRef<Summary>) -> NormalizedSummary {
+        self.normalize_against(&control)
+    }
+
+    /// Set the approximate size of the genome of every condition, so that the estimated coverage is displayed. See
+    /// [`Summary::set_genome_size`].
+    #[pyo3(name = "set_genome_size")]
+    fn py_set_genome_size(&mut self, genome_size: usize) {
+        self.set_genome_size(genome_size)
+    }
+
+    /// Write the summary as a SQL script that loads it into a SQLite database under `run_name`, replacing any earlier
+    /// rows of the same run. See [`Summary::write_sql`].
+    ///
+    /// # Errors
+    ///
+    /// Raises a `RuntimeError` if the file cannot be written.
+    #[pyo3(name = "write_sql")]
+    fn py_write_sql(&self, path: PathBuf, run_name: &str) -> PyResult<()> {
+        let file =
+            std::fs::File::create(path).map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+        self.write_sql(std::io::BufWriter::new(file), run_name)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+    }
+
+    /// Write the conditions and contigs as `conditions.parquet` and `contigs.parquet` in the directory `path`. See
+    /// [`Summary::write_parquet`].
+    ///
+    /// # Errors
+    ///
+    /// Raises a `RuntimeError` if the Parquet files cannot be written.
+    #[cfg(feature = "parquet")]
+    #[pyo3(name = "write_parquet")]
+    fn py_write_parquet(&self, path: PathBuf) -> PyResult<()> {
+        self.write_parquet(path)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+    }
+
+    /// Write the summary into a SQLite database under `run_name`, replacing any earlier rows of the same run, and
+    /// loading the tab separated read `assignments` if given. See [`Summary::write_sqlite`].
+    ///
+    /// # Errors
+    ///
+    /// Raises a `RuntimeError` if the database cannot be written, or the assignments cannot be read.
+    #[cfg(feature = "sqlite")]
+    #[pyo3(name = "write_sqlite", signature = (path, run_name, assignments = None))]
+    fn py_write_sqlite(
+        &self,
+        path: PathBuf,
+        run_name: &str,
+        assignments: Option<PathBuf>,
+    ) -> PyResult<()> {
+        self.write_sqlite(path, run_name, assignments)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+    }
+
+    /// Get how many reads were assigned to a condition, and why the rest were dropped. See
+    /// [`Summary::read_reconciliation`].
+    #[pyo3(name = "read_reconciliation")]
+    fn py_read_reconciliation(&self) -> ReadReconciliation {
+        self.read_reconciliation()
+    }
+}
+
+#[cfg(feature = "pyo3_support")]
+#[pymethods]
+impl SummaryDiff {
+    /// Render the diff table, so that `print(diff)` in Python shows the same table as Rust.
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(feature = "pyo3_support")]
+#[pymethods]
+impl NormalizedSummary {
+    /// Render the normalised table, so that `print(normalized)` in Python shows the same table as Rust.
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(feature = "pyo3_support")]
+/// A Python module implemented in Rust.
+#[pymodule]
+fn readfish_tools(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(summarise_paf, m)?)?;
+    m.add_function(wrap_pyfunction!(demultiplex_paf, m)?)?;
+    m.add_function(wrap_pyfunction!(demultiplex_bam, m)?)?;
+    m.add_function(wrap_pyfunction!(summarise_seqsum, m)?)?;
+    m.add_function(wrap_pyfunction!(py_format_bases, m)?)?;
+    m.add_class::<ReadfishSummary>()?;
+    m.add_class::<Summary>()?;
+    m.add_class::<ConditionSummary>()?;
+    m.add_class::<ContigSummary>()?;
+    m.add_class::<ChannelStats>()?;
+    m.add_class::<TargetSummary>()?;
+    m.add_class::<ReadReconciliation>()?;
+    m.add_class::<SummaryDiff>()?;
+    m.add_class::<ConditionDiff>()?;
+    m.add_class::<DiffMetrics>()?;
+    m.add_class::<NormalizedSummary>()?;
+    m.add_class::<NormalizedCondition>()?;
+    m.add_class::<MeanReadLengths>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        paf::PafRecord,
+        readfish::{Action, Region},
+    };
+
+    use super::*;
+    use std::path::PathBuf;
+    fn get_resource_dir() -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("resources");
+        path
+    }
+
+    fn get_test_file(file: &str) -> PathBuf {
+        let mut path = get_resource_dir();
+        path.push(file);
+        path
+    }
+
+    #[test]
+    fn test_update_lengths() {
+        // Create a PAF record with a query length of 100
+        let paf = PafRecord::new(
+            "read123 100 0 100 + contig123 300 0 300 200 200 50 ch=1"
+                .split(' ')
+                .collect(),
+        )
+        .unwrap();
+
+        // Create a MeanReadLengths instance
+        let mut mean_lengths = MeanReadLengths::new();
+
+        // Initially, all mean lengths should be zero
+        assert_eq!(mean_lengths.on_target, 0);
+        assert_eq!(mean_lengths.off_target, 0);
+        assert_eq!(mean_lengths.total, 0);
+
+        // Update with an on-target read
+        mean_lengths.update_lengths(&paf, true);
+
+        // After the update, only on_target and total should be updated
+        assert_eq!(mean_lengths.on_target, 100);
+        assert_eq!(mean_lengths.off_target, 0);
+        assert_eq!(mean_lengths.total, 100);
+
+        // Update with an off-target read
+        mean_lengths.update_lengths(&paf, false);
+
+        // After the update, off_target and total should be updated
+        assert_eq!(mean_lengths.on_target, 100);
+        assert_eq!(mean_lengths.off_target, 100);
+        assert_eq!(mean_lengths.total, 100);
+        // Create a PAF record with a query length of 100
+        let paf = PafRecord::new(
+            "read123 150 0 100 + contig123 300 0 300 200 200 50 ch=1"
+                .split(' ')
+                .collect(),
+        )
+        .unwrap();
+        // Update with an off-target read with a different length
+        mean_lengths.update_lengths(&paf, false);
+
+        // After the update, off_target and total should be updated
+        assert_eq!(mean_lengths.on_target, 100);
+        assert_eq!(mean_lengths.off_target, 125);
+        assert_eq!(mean_lengths.total, 116);
+    }
+
+    #[test]
+    fn test_condition_summary_mean_identity() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        for (line, on_target) in [
+            ("read1 100 0 100 + contig1 300 0 100 90 100 60", true),
+            ("read2 100 0 100 + contig1 300 0 100 80 100 60", true),
+            (
+                "read3 100 0 100 + contig1 300 0 100 50 100 60 de:f:0.25",
+                false,
+            ),
+            ("read4 100 0 100 + contig1 300 0 100 0 0 60", false),
+        ] {
+            let paf = PafRecord::from_line(line).unwrap();
+            condition_summary.update(&paf, on_target).unwrap();
+        }
+        assert!((condition_summary.on_target_mean_identity() - 0.85).abs() < 1e-9);
+        assert!((condition_summary.off_target_mean_identity() - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_condition_summary_n50() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        for (length, on_target) in [
+            (100, true),
+            (400, true),
+            (50, false),
+            (300, false),
+            (200, true),
+        ] {
+            let line =
+                format!("read {length} 0 {length} + contig1 1000 0 {length} {length} {length} 60");
+            let paf = PafRecord::from_line(&line).unwrap();
+            condition_summary.update(&paf, on_target).unwrap();
+        }
+        condition_summary.finalize();
+        assert_eq!(condition_summary.n50(), 300);
+        assert_eq!(condition_summary.on_target_n50(), 400);
+        assert_eq!(condition_summary.off_target_n50(), 300);
+    }
+
+    #[test]
+    fn test_contig_summary_n50() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        condition_summary.set_keep_read_lengths(true);
+        // Read lengths aren't kept by default, so the contig N50 is left at 0
+        let mut default = ConditionSummary::new("test".to_string());
+        for (length, contig) in [
+            (100, "contig1"),
+            (400, "contig1"),
+            (200, "contig1"),
+            (50, "contig2"),
+            (300, "contig2"),
+        ] {
+            let line =
+                format!("read {length} 0 {length} + {contig} 1000 0 {length} {length} {length} 60");
+            let paf = PafRecord::from_line(&line).unwrap();
+            condition_summary.update(&paf, true).unwrap();
+            default.update(&paf, true).unwrap();
+        }
+        default.finalize();
+        assert_eq!(default.get_or_add_contig("contig1", 1000).n50, 0);
+        assert!(default
+            .get_or_add_contig("contig1", 1000)
+            .read_lengths
+            .is_empty());
+        assert_eq!(default.n50(), 300);
+        let mut summary = Summary::new();
+        summary.set_keep_read_lengths(true);
+        assert!(summary.conditions_entry("test").keep_read_lengths);
+        let mut other = ConditionSummary::new("test".to_string());
+        other.set_keep_read_lengths(true);
+        let paf =
+            PafRecord::from_line("read 1000 0 1000 + contig2 1000 0 1000 1000 1000 60").unwrap();
+        other.update(&paf, false).unwrap();
+        condition_summary.finalize();
+        assert_eq!(
+            condition_summary.get_or_add_contig("contig1", 1000).n50,
+            400
+        );
+        assert_eq!(
+            condition_summary.get_or_add_contig("contig2", 1000).n50,
+            300
+        );
+        condition_summary.merge(&other);
+        condition_summary.finalize();
+        assert_eq!(
+            condition_summary.get_or_add_contig("contig2", 1000).n50,
+            1000
+        );
+        assert_eq!(condition_summary.n50(), 400);
+    }
+
+    /// Reads for the merge tests, as `(condition, PAF line, on target, mean qscore)`.
+    const MERGE_READS: [(&str, &str, bool, f64); 7] = [
+        (
+            "cond1",
+            "read1 200 0 200 + contig1 1000 0 200 180 200 60",
+            true,
+            12.5,
+        ),
+        (
+            "cond1",
+            "read2 450 0 450 + contig1 1000 100 550 400 450 60",
+            false,
+            9.0,
+        ),
+        (
+            "cond1",
+            "read3 1000 0 1000 - contig2 5000 0 1000 950 1000 60",
+            true,
+            14.0,
+        ),
+        (
+            "cond2",
+            "read4 300 0 300 + contig1 1000 500 800 250 300 60",
+            false,
+            8.25,
+        ),
+        (
+            "cond1",
+            "read5 75 0 75 + contig1 1000 900 975 70 75 60",
+            false,
+            11.0,
+        ),
+        (
+            "cond1",
+            "read6 3000 0 3000 + contig2 5000 1000 4000 2900 3000 60",
+            true,
+            13.0,
+        ),
+        (
+            "cond3",
+            "read7 125 0 125 + contig3 2000 0 125 120 125 60",
+            true,
+            10.0,
+        ),
+    ];
+
+    /// Summarise a slice of the merge test reads in a single pass.
+    fn summarise_merge_reads(reads: &[(&str, &str, bool, f64)]) -> Summary {
+        let mut summary = Summary::new();
+        for (condition, line, on_target, mean_qscore) in reads {
+            let condition_summary = summary.conditions_entry(*condition);
+            condition_summary
+                .update(&PafRecord::from_line(line).unwrap(), *on_target)
+                .unwrap();
+            condition_summary.update_mean_read_quality(*mean_qscore, *on_target);
+        }
+        summary.finalize();
+        summary
+    }
+
+    /// Assert that two condition summaries are the same, up to floating point error in the means.
+    fn assert_condition_summaries_eq(merged: &ConditionSummary, expected: &ConditionSummary) {
+        assert!(
+            merged.approx_eq(expected, 1e-9),
+            "{:#?}\n!=\n{:#?}",
+            merged,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_condition_summary_merge() {
+        let (first, second) = MERGE_READS.split_at(3);
+        let first: Vec<_> = first
+            .iter()
+            .filter(|read| read.0 == "cond1")
+            .copied()
+            .collect();
+        let second: Vec<_> = second
+            .iter()
+            .filter(|read| read.0 == "cond1")
+            .copied()
+            .collect();
+        let mut merged = summarise_merge_reads(&first).conditions["cond1"].clone();
+        merged.merge(&summarise_merge_reads(&second).conditions["cond1"]);
+        merged.finalize();
+
+        let expected = summarise_merge_reads(&MERGE_READS);
+        assert_condition_summaries_eq(&merged, &expected.conditions["cond1"]);
+        // Means come from the combined totals, not the average of the two partial means
+        assert_eq!(merged.on_target_mean_read_length(), 1400);
+    }
+
+    #[test]
+    fn test_summary_merge() {
+        let expected = summarise_merge_reads(&MERGE_READS);
+        for split in 0..=MERGE_READS.len() {
+            let (first, second) = MERGE_READS.split_at(split);
+            let mut merged = summarise_merge_reads(first);
+            merged.merge(summarise_merge_reads(second));
+            merged.finalize();
+            assert_eq!(merged.conditions.len(), expected.conditions.len());
+            for (condition_name, expected_condition) in &expected.conditions {
+                assert_condition_summaries_eq(
+                    &merged.conditions[condition_name],
+                    expected_condition,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_summary_total() {
+        let summary = summarise_merge_reads(&MERGE_READS);
+        let total = summary.total();
+        // The same as summarising every read under a single condition, but without contigs
+        let single_condition: Vec<_> = MERGE_READS
+            .iter()
+            .map(|(_, line, on_target, mean_qscore)| ("all", *line, *on_target, *mean_qscore))
+            .collect();
+        let mut expected = summarise_merge_reads(&single_condition).conditions["all"].clone();
+        expected.name = TOTAL_CONDITION.to_string();
+        expected.contigs.clear();
+        assert_eq!(total.name(), TOTAL_CONDITION);
+        assert_condition_summaries_eq(&total, &expected);
+        assert!(total.contigs.is_empty());
+        assert_eq!(
+            total.total_reads,
+            summary
+                .conditions
+                .values()
+                .map(|condition| condition.total_reads)
+                .sum::<usize>()
+        );
+
+        let mut rendered = Vec::new();
+        summary
+            .render_with_options(
+                &mut rendered,
+                &RenderOptions {
+                    color: false,
+                    max_contigs: None,
+                    show_targets: false,
+                },
+            )
+            .unwrap();
+        let rendered = String::from_utf8(rendered).unwrap();
+        assert!(rendered.contains(&format!("| {} ", TOTAL_CONDITION)));
+    }
+
+    #[test]
+    fn test_summary_merge_counts() {
+        let mut summary = Summary::new();
+        summary.unclassified();
+        summary.skipped_lines = 2;
+        let mut other = Summary::new();
+        other.unclassified();
+        other.skipped_lines = 3;
+        summary.merge(other);
+        assert_eq!(summary.unclassified_read_count, 2);
+        assert_eq!(summary.skipped_lines, 5);
+    }
+
+    #[test]
+    fn test_condition_summary_mean_read_quality() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        condition_summary.update_mean_read_quality(10.0, true);
+        condition_summary.update_mean_read_quality(14.0, true);
+        condition_summary.update_mean_read_quality(7.0, false);
+        assert!((condition_summary.on_target_mean_read_quality() - 12.0).abs() < 1e-9);
+        assert!((condition_summary.off_target_mean_read_quality() - 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_condition_summary_median_read_length() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        assert_eq!(condition_summary.median_read_length(), 0);
+        assert_eq!(condition_summary.on_target_median_read_length(), 0);
+        for (length, on_target) in [
+            (100, true),
+            (400, true),
+            (50, false),
+            (300, false),
+            (200, true),
+        ] {
+            let line =
+                format!("read {length} 0 {length} + contig1 1000 0 {length} {length} {length} 60");
+            let paf = PafRecord::from_line(&line).unwrap();
+            condition_summary.update(&paf, on_target).unwrap();
+        }
+        assert_eq!(condition_summary.median_read_length(), 200);
+        assert_eq!(condition_summary.on_target_median_read_length(), 200);
+        assert_eq!(condition_summary.off_target_median_read_length(), 175);
+    }
+
+    #[test]
+    fn test_condition_summary_read_length_spread() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        assert_eq!(condition_summary.read_length_std_dev(), 0.0);
+        assert_eq!(condition_summary.read_length_quartiles(), (0, 0));
+        for (length, on_target) in [
+            (100, true),
+            (400, true),
+            (50, false),
+            (300, true),
+            (200, true),
+        ] {
+            let line =
+                format!("read {length} 0 {length} + contig1 1000 0 {length} {length} {length} 60");
+            let paf = PafRecord::from_line(&line).unwrap();
+            condition_summary.update(&paf, on_target).unwrap();
+        }
+        // A single off-target read has a standard deviation of 0, not NaN
+        assert_eq!(condition_summary.off_target_read_length_std_dev(), 0.0);
+        assert_eq!(
+            condition_summary.off_target_read_length_quartiles(),
+            (50, 50)
+        );
+        assert!(
+            (condition_summary.on_target_read_length_std_dev() - (50_000.0_f64 / 3.0).sqrt()).abs()
+                < 1e-9
+        );
+        assert_eq!(
+            condition_summary.on_target_read_length_quartiles(),
+            (175, 325)
+        );
+        assert!((condition_summary.read_length_std_dev() - 143.17821063276352).abs() < 1e-9);
+        assert_eq!(condition_summary.read_length_quartiles(), (100, 300));
+        assert!(condition_summary
+            .to_string()
+            .contains("Off-Target Read Length SD: 0.00"));
+    }
+
+    #[test]
+    fn test_condition_summary_read_length_histogram() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        assert!(condition_summary.read_length_histogram(100).is_empty());
+        for (length, on_target) in [
+            (100, true),
+            (400, true),
+            (50, false),
+            (300, false),
+            (150, true),
+        ] {
+            let line =
+                format!("read {length} 0 {length} + contig1 1000 0 {length} {length} {length} 60");
+            let paf = PafRecord::from_line(&line).unwrap();
+            condition_summary.update(&paf, on_target).unwrap();
+        }
+        assert_eq!(
+            condition_summary.read_length_histogram(100),
+            vec![(0, 1), (100, 2), (200, 0), (300, 1), (400, 1)]
+        );
+        assert_eq!(
+            condition_summary.on_target_read_length_histogram(200),
+            vec![(0, 2), (200, 0), (400, 1)]
+        );
+        assert_eq!(
+            condition_summary.off_target_read_length_histogram(200),
+            vec![(0, 1), (200, 1)]
+        );
+        assert_eq!(condition_summary.read_length_histogram(0).len(), 401);
+    }
+
+    #[test]
+    fn test_contig_summary_coverage() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        for line in [
+            "read1 100 0 100 + contig1 1000 0 100 100 100 60",
+            "read2 100 0 100 + contig1 1000 50 150 100 100 60",
+            "read3 100 0 100 + contig1 1000 500 600 100 100 60",
+            "read4 100 0 100 + contig2 0 0 100 100 100 60",
+        ] {
+            let paf = PafRecord::from_line(line).unwrap();
+            condition_summary.update(&paf, true).unwrap();
+        }
+        condition_summary.finalize();
+        let contig = condition_summary.get_or_add_contig("contig1", 1000);
+        assert!((contig.mean_coverage - 0.3).abs() < 1e-9);
+        assert!((contig.breadth_of_coverage - 0.25).abs() < 1e-9);
+        // Every alignment is on target, with overlapping and disjoint spans
+        assert!((contig.on_target_breadth - 0.25).abs() < 1e-9);
+        contig.set_targets(vec![(0, 120), (550, 650)]);
+        assert!((contig.on_target_breadth - 170.0 / 220.0).abs() < 1e-9);
+        let contig = condition_summary.get_or_add_contig("contig2", 0);
+        assert_eq!(contig.mean_coverage, 0.0);
+        assert_eq!(contig.breadth_of_coverage, 0.0);
+        assert_eq!(contig.on_target_breadth, 0.0);
+    }
+
+    #[test]
+    fn test_contig_summary_compacts_aligned_intervals() {
+        // Many alignments over the same two regions only keep a bounded number of spans
+        let mut contig = ContigSummary::new("contig1".to_string(), 10_000);
+        for read in 0..100_000 {
+            let start = if read % 2 == 0 { 0 } else { 5_000 };
+            let line = format!(
+                "read{read} 100 0 100 + contig1 10000 {} {} 100 100 60",
+                start + read % 50,
+                start + read % 50 + 100
+            );
+            contig.add_alignment_span(&PafRecord::from_line(&line).unwrap());
+            assert!(contig.aligned_intervals.len() <= MIN_INTERVALS_TO_COMPACT);
+        }
+        let mut other = contig.clone();
+        contig.merge(&other);
+        assert!(contig.aligned_intervals.len() <= MIN_INTERVALS_TO_COMPACT);
+        contig.finalize();
+        other.finalize();
+        assert_eq!(contig.aligned_intervals, vec![(0, 148), (5_001, 5_149)]);
+        assert!((contig.breadth_of_coverage - 296.0 / 10_000.0).abs() < 1e-9);
+        assert_eq!(contig.breadth_of_coverage, other.breadth_of_coverage);
+    }
+
+    #[test]
+    fn test_condition_summary_on_target_spans() {
+        // The on-target spans are merged as they accumulate
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        let paf = PafRecord::from_line("read1 100 0 100 + contig1 1000 0 100 100 100 60").unwrap();
+        for _ in 0..10_000 {
+            condition_summary.update(&paf, true).unwrap();
+        }
+        let contig = &condition_summary.contigs["contig1"];
+        assert!(contig.on_target_intervals.len() <= MIN_INTERVALS_TO_COMPACT);
+    }
+
+    #[test]
+    fn test_contig_summary_target_hits() {
+        let mut contig = ContigSummary::new("contig1".to_string(), 10_000);
+        assert_eq!(contig.target_read_counts(2), vec![(0, 0), (0, 0)]);
+        // An alignment spanning two targets is counted in both
+        contig.add_target_hits(0..2, 200);
+        contig.add_target_hits(1..2, 300);
+        contig.add_target_hits(0..0, 400);
+        assert_eq!(
+            contig.target_read_counts(3),
+            vec![(1, 200), (2, 500), (0, 0)]
+        );
+        let mut other = ContigSummary::new("contig1".to_string(), 10_000);
+        other.add_target_hits(2..3, 100);
+        contig.merge(&other);
+        assert_eq!(
+            contig.target_read_counts(3),
+            vec![(1, 200), (2, 500), (1, 100)]
+        );
+    }
+
+    #[test]
+    fn test_contig_summary_on_off_target_yield() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        for (line, on_target) in [
+            ("read1 200 0 200 + contig1 5000 0 200 200 200 60", true),
+            ("read2 400 0 400 + contig1 5000 0 400 400 400 60", true),
+            ("read3 100 0 100 + contig1 5000 0 100 100 100 60", false),
+            ("read4 300 0 300 + contig2 5000 0 300 300 300 60", false),
+        ] {
+            let paf = PafRecord::from_line(line).unwrap();
+            condition_summary.update(&paf, on_target).unwrap();
+        }
+        let contig = condition_summary.get_or_add_contig("contig1", 5000);
+        assert_eq!(contig.yield_on_target, 600);
+        assert_eq!(contig.yield_off_target, 100);
+        assert_eq!(contig.total_bases, 700);
+        assert_eq!(contig.on_target_mean_read_length(), 300);
+        let contig = condition_summary.get_or_add_contig("contig2", 5000);
+        assert_eq!(contig.yield_on_target, 0);
+        assert_eq!(contig.yield_off_target, 300);
+    }
+
+    #[test]
+    fn test_condition_summary_enrichment() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        assert_eq!(condition_summary.enrichment(0.1), 0.0);
+        for (line, on_target) in [
+            ("read1 200 0 200 + contig1 5000 0 200 200 200 60", true),
+            ("read2 800 0 800 + contig1 5000 0 800 800 800 60", false),
+        ] {
+            let paf = PafRecord::from_line(line).unwrap();
+            condition_summary.update(&paf, on_target).unwrap();
+        }
+        assert!((condition_summary.enrichment(0.05) - 4.0).abs() < 1e-9);
+        assert_eq!(condition_summary.enrichment(0.0), 0.0);
+    }
+
+    #[test]
+    fn test_condition_summary_estimated_coverage() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        for (line, on_target) in [
+            ("read1 200 0 200 + contig1 5000 0 200 200 200 60", true),
+            ("read2 800 0 800 + contig1 5000 0 800 800 800 60", false),
+        ] {
+            let paf = PafRecord::from_line(line).unwrap();
+            condition_summary.update(&paf, on_target).unwrap();
+        }
+        assert!((condition_summary.estimated_coverage(500) - 2.0).abs() < 1e-9);
+        assert_eq!(condition_summary.estimated_coverage(0), 0.0);
+        // 200 bases over 100 targeted bases, 800 over the other 400
+        assert!((condition_summary.on_target_estimated_coverage(500, 0.2) - 2.0).abs() < 1e-9);
+        assert!((condition_summary.off_target_estimated_coverage(500, 0.2) - 2.0).abs() < 1e-9);
+        assert_eq!(
+            condition_summary.on_target_estimated_coverage(500, 0.0),
+            0.0
+        );
+        assert_eq!(
+            condition_summary.off_target_estimated_coverage(500, 1.0),
+            0.0
+        );
+        assert_eq!(condition_summary.on_target_estimated_coverage(0, 0.2), 0.0);
+
+        let mut summary = Summary::new();
+        summary
+            .conditions
+            .insert("test".to_string(), condition_summary);
+        let rendered = summary.to_string();
+        assert!(!rendered.contains("2.00x"));
+        summary.set_genome_size(500);
+        assert_eq!(summary.conditions["test"].genome_size, Some(500));
+        let rendered = summary.to_string();
+        assert!(rendered.contains("2.00x"));
+    }
+
+    #[test]
+    fn test_condition_summary_yield_over_time() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        assert!(condition_summary.yield_over_time(60).is_empty());
+        condition_summary.update_start_time(Some(10.0), 100);
+        condition_summary.update_start_time(Some(70.5), 200);
+        condition_summary.update_start_time(Some(50.0), 300);
+        condition_summary.update_start_time(Some(190.0), 400);
+        condition_summary.update_start_time(None, 500);
+        assert_eq!(
+            condition_summary.yield_over_time(60),
+            vec![(0, 400), (60, 600), (120, 600), (180, 1000)]
+        );
+        assert_eq!(condition_summary.reads_without_start_time(), 1);
+    }
+
+    #[test]
+    fn test_condition_summary_time_to_coverage() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        assert_eq!(condition_summary.time_to_coverage(2.0, 1_000, 60), None);
+        for minute in 0..4 {
+            condition_summary.update_start_time(Some(minute as f64 * 60.0), 600);
+        }
+        // 600 bases a minute, with 2,400 bases sequenced of the 4,000 needed
+        assert_eq!(
+            condition_summary.time_to_coverage(2.0, 2_000, 60),
+            Some(std::time::Duration::from_secs(160))
+        );
+    }
 
-#[cfg(feature = "pyo3_support")]
-/// Summarizes the results of demultiplexing a PAF file using the provided TOML file and
-/// prints the summary to stdout.
-///
-/// # Arguments
-///
-/// * `toml_path` - The path to the TOML file containing configuration settings for a readfish experiment.
-/// * `paf_path` - The path to the PAF file containing alignment results.
-/// * `seq_sum_path` - The sequencing summary file produced by ONTs guppy.
-///
-/// # Returns
-///
-/// This function returns a `PyResult<()>`, indicating success or an error encountered during processing.
-///
-/// # Panics
-///
-/// This function may panic if there are any critical errors during demultiplexing or file operations.
-/// It's recommended to handle the possible panics in the calling code if necessary.
-#[pyfunction]
-fn summarise_paf(toml_path: PathBuf, paf_path: PathBuf, seq_sum_path: PathBuf) -> PyResult<()> {
-    _demultiplex_paf(
-        toml_path,
-        paf_path,
-        Some(seq_sum_path),
-        true,
-        None::<String>,
-    );
-    Ok(())
-}
-#[cfg(feature = "pyo3_support")]
-/// A Python module implemented in Rust.
-#[pymodule]
-fn readfish_tools(_py: Python, m: &PyModule) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(summarise_paf, m)?)?;
-    m.add_class::<ReadfishSummary>()?;
-    Ok(())
-}
+    #[test]
+    fn test_summary_unblock_end_reasons() {
+        let mut summary = Summary::new();
+        assert!(summary.is_unblocked("data_service_unblock_mux_change"));
+        assert!(!summary.is_unblocked("signal_positive"));
+        summary.set_unblock_end_reasons(vec!["unblock_mux_change".to_string()]);
+        assert!(summary.is_unblocked("unblock_mux_change"));
+        assert!(!summary.is_unblocked("data_service_unblock_mux_change"));
+        let condition_summary = summary.conditions_entry("test");
+        condition_summary.update_unblocked(300);
+        condition_summary.update_unblocked(200);
+        assert_eq!(condition_summary.unblocked_read_count, 2);
+        assert_eq!(condition_summary.unblocked_yield, 500);
+    }
 
-#[cfg(test)]
-mod tests {
-    use crate::readfish::Region;
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_summary_to_json_writer() {
+        let mut summary = Summary::new();
+        let condition_summary = summary.conditions_entry("barcode01");
+        for (line, on_target) in [
+            ("read1 200 0 200 + contig1 5000 0 200 200 200 60", true),
+            ("read2 400 0 400 + contig2 5000 0 400 400 400 60", false),
+        ] {
+            let paf = PafRecord::from_line(line).unwrap();
+            condition_summary.update(&paf, on_target).unwrap();
+        }
+        summary.finalize();
+        let mut json = Vec::new();
+        summary.to_json_writer(&mut json).unwrap();
+        let parsed: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let condition = &parsed["conditions"]["barcode01"];
+        assert_eq!(condition["total_reads"], 2);
+        assert_eq!(condition["on_target_yield"], 200);
+        assert_eq!(condition["n50"], 400);
+        assert_eq!(condition["contigs"]["contig1"]["yield_on_target"], 200);
+        assert_eq!(condition["contigs"]["contig2"]["yield_off_target"], 400);
+        assert!(condition.get("on_target_read_lengths").is_none());
+    }
 
-    use super::*;
-    use std::path::PathBuf;
-    fn get_resource_dir() -> PathBuf {
-        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        path.push("resources");
-        path
+    #[test]
+    fn test_condition_summary_unmapped_reads() {
+        let mut condition_summary = ConditionSummary::new("barcode01".to_string());
+        for (line, on_target) in [
+            ("read1 200 0 200 + contig1 1000 0 200 200 200 60", true),
+            ("read2 300 0 300 + contig1 1000 300 600 300 300 60", false),
+            ("read3 500 0 0 * * 0 0 0 0 0 0", false),
+            ("read4 100 0 100 + contig1 1000 0 100 0 0 0", false),
+        ] {
+            condition_summary
+                .update(&PafRecord::from_line(line).unwrap(), on_target)
+                .unwrap();
+        }
+        assert_eq!(condition_summary.total_reads, 4);
+        assert_eq!(condition_summary.unmapped_read_count, 2);
+        assert_eq!(condition_summary.unmapped_yield, 600);
+        assert_eq!(condition_summary.on_target_read_count, 1);
+        assert_eq!(condition_summary.off_target_read_count, 1);
+        assert_eq!(condition_summary.total_yield(), 1100);
+        assert_eq!(condition_summary.on_target_percent(), 25.0);
+        assert_eq!(condition_summary.off_target_percent, 25.0);
+        assert_eq!(condition_summary.on_to_off_ratio(), Some(1.0));
+        assert!((condition_summary.on_target_yield_percent() - 200.0 / 11.0).abs() < 1e-9);
+        assert!(!condition_summary.contigs.contains_key("*"));
+        assert_eq!(condition_summary.contigs["contig1"].total_bases, 500);
+
+        let mut merged = ConditionSummary::new("barcode01".to_string());
+        merged.merge(&condition_summary);
+        merged.finalize();
+        assert_eq!(merged.unmapped_read_count, 2);
+        assert_eq!(merged.unmapped_yield, 600);
+        assert_eq!(merged.unmapped_n50, 500);
+        assert_eq!(merged.unmapped_mean_read_length(), 300);
+        assert_eq!(merged.n50, 300);
+        let display = condition_summary.to_string();
+        assert!(display.contains("Unmapped Read Count: 2"));
+        assert!(display.contains("On-Target Percent: 25.00%"));
+        assert!(display.contains("On/Off-Target Ratio: 1.00"));
+        assert!(display.contains("On-Target Yield Percent: 18.18%"));
+
+        // Nothing to divide by in an empty summary
+        let empty = ConditionSummary::new("barcode02".to_string());
+        assert_eq!(empty.on_target_percent(), 0.0);
+        assert_eq!(empty.on_to_off_ratio(), None);
+        assert_eq!(empty.on_target_yield_percent(), 0.0);
+        assert!(empty.to_string().contains("On/Off-Target Ratio: -"));
     }
 
-    fn get_test_file(file: &str) -> PathBuf {
-        let mut path = get_resource_dir();
-        path.push(file);
-        path
+    #[test]
+    fn test_summarise_seqsum() {
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let first_condition = conf.condition_key(1, None::<&str>).unwrap();
+        let seq_sum_path = std::env::temp_dir().join("readfish_tools_test_summarise_seqsum.txt");
+        std::fs::write(
+            &seq_sum_path,
+            "read_id\tchannel\tstart_time\tsequence_length_template\tmean_qscore_template\tend_reason\n\
+             read1\t1\t10.0\t100\t10.0\tdata_service_unblock_mux_change\n\
+             read2\t1\t20.0\t300\t20.0\tsignal_positive\n\
+             read3\t1\t30.0\t600\t12.0\tsignal_positive\n\
+             read4\t9999\t40.0\t50\t8.0\tsignal_positive\n",
+        )
+        .unwrap();
+        let summary = _summarise_seqsum(
+            get_test_file("RAPID_CNS2.toml"),
+            &seq_sum_path,
+            false,
+            None::<PathBuf>,
+        )
+        .unwrap();
+        let condition_summary = &summary.conditions[&first_condition];
+        assert_eq!(condition_summary.total_reads, 3);
+        assert_eq!(condition_summary.unmapped_read_count, 3);
+        assert_eq!(condition_summary.unmapped_yield, 1000);
+        assert_eq!(condition_summary.total_yield(), 1000);
+        assert_eq!(condition_summary.on_target_read_count, 0);
+        assert_eq!(condition_summary.off_target_read_count, 0);
+        assert_eq!(condition_summary.unmapped_mean_read_length(), 333);
+        assert_eq!(condition_summary.unmapped_median_read_length(), 300);
+        assert_eq!(condition_summary.unmapped_n50, 600);
+        assert!((condition_summary.unmapped_mean_read_quality - 14.0).abs() < 1e-9);
+        assert_eq!(condition_summary.unblocked_read_count, 1);
+        assert_eq!(condition_summary.end_reasons["signal_positive"], 2);
+        assert_eq!(summary.channels[&1].read_count, 3);
+        assert_eq!(summary.channels[&1].total_yield, 1000);
+        // A channel in no region is unclassified
+        assert_eq!(summary.unclassified_read_count, 1);
+        assert_eq!(
+            summary.conditions[UNCLASSIFIED_CONDITION].unmapped_yield,
+            50
+        );
+
+        // The reads of the full sequencing summary are all counted, with barcodes taken from its barcode column
+        let summary = _summarise_seqsum(
+            get_test_file("human_barcode.toml"),
+            get_test_file("seq_sum_PAK09329.txt"),
+            false,
+            None::<PathBuf>,
+        )
+        .unwrap();
+        let total_reads: usize = summary
+            .conditions
+            .values()
+            .map(|condition_summary| condition_summary.total_reads)
+            .sum();
+        assert_eq!(total_reads, 150_000);
+
+        // Barcodes can't be assigned without a barcode column
+        let err = _summarise_seqsum(
+            get_test_file("human_barcode.toml"),
+            &seq_sum_path,
+            false,
+            None::<PathBuf>,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("barcode"));
+        std::fs::remove_file(seq_sum_path).unwrap();
     }
 
     #[test]
-    fn test_update_lengths() {
-        // Create a PAF record with a query length of 100
-        let paf = PafRecord::new(
-            "read123 100 0 100 + contig123 300 0 300 200 200 50 ch=1"
-                .split(' ')
-                .collect(),
+    fn test_summary_channel_stats() {
+        let mut summary = Summary::new();
+        for (channel, line, on_target) in [
+            (1, "read1 200 0 200 + contig1 1000 0 200 200 200 60", true),
+            (
+                1,
+                "read2 300 0 300 + contig1 1000 300 600 300 300 60",
+                false,
+            ),
+            (2, "read3 500 0 0 * * 0 0 0 0 0 0", false),
+        ] {
+            summary
+                .add_record(
+                    "barcode01",
+                    channel,
+                    &PafRecord::from_line(line).unwrap(),
+                    on_target,
+                    None,
+                )
+                .unwrap();
+        }
+        let channel_one = &summary.channels[&1];
+        assert_eq!(channel_one.read_count, 2);
+        assert_eq!(channel_one.total_yield, 500);
+        assert_eq!(channel_one.on_target_read_count, 1);
+        assert_eq!(channel_one.off_target_read_count, 1);
+        // Unmapped reads are counted, but neither on nor off-target
+        let channel_two = &summary.channels[&2];
+        assert_eq!(channel_two.read_count, 1);
+        assert_eq!(
+            channel_two.on_target_read_count + channel_two.off_target_read_count,
+            0
+        );
+
+        let coords = summary.channel_coords(512).unwrap();
+        assert_eq!(coords.len(), 2);
+        assert_eq!((coords[0].0, coords[0].1), (1, (31, 0)));
+        assert_eq!((coords[1].0, coords[1].1), (2, (31, 1)));
+        assert!(summary.channel_coords(1).is_err());
+
+        let mut merged = Summary::new();
+        merged.merge(summary.clone());
+        merged.merge(summary);
+        assert_eq!(merged.channels[&1].read_count, 4);
+        assert_eq!(merged.channels[&2].total_yield, 1000);
+    }
+
+    #[test]
+    fn test_summary_uncovered_targets() {
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let mut summary = Summary::new();
+        for (line, on_target) in [
+            ("read1 200 0 200 - chr2 10000 3500 3600 100 100 60", true),
+            // Off-target alignments don't cover a target
+            ("read2 200 0 200 + chr20 10000 3000 3100 100 100 60", false),
+        ] {
+            summary
+                .add_record(
+                    "Direct_CNS",
+                    1,
+                    &PafRecord::from_line(line).unwrap(),
+                    on_target,
+                    None,
+                )
+                .unwrap();
+        }
+        summary.finalize();
+        let uncovered = summary.uncovered_targets(&conf);
+        assert_eq!(
+            uncovered["Direct_CNS"],
+            vec![("chr20".to_string(), 3000, 4000)]
+        );
+        // 100 bases of the 1,000 base target on chr2 are covered
+        summary.set_target_breadths(&conf);
+        let direct_cns = &summary.get("Direct_CNS").unwrap().contigs;
+        assert!((direct_cns["chr2"].on_target_breadth - 0.1).abs() < 1e-9);
+        assert_eq!(direct_cns["chr20"].on_target_breadth, 0.0);
+        summary.set_uncovered_targets(&conf);
+        let mut rendered = Vec::new();
+        summary
+            .render_with_options(
+                &mut rendered,
+                &RenderOptions {
+                    color: false,
+                    max_contigs: None,
+                    show_targets: false,
+                },
+            )
+            .unwrap();
+        assert!(String::from_utf8(rendered)
+            .unwrap()
+            .contains("Direct_CNS: 1 targets with zero coverage"));
+    }
+
+    #[test]
+    fn test_summary_strand_on_target_counts() {
+        let mut summary = Summary::new();
+        for (line, on_target) in [
+            ("read1 200 0 200 + chr2 10000 3500 3700 200 200 60", true),
+            ("read2 200 0 200 + chr2 10000 3500 3700 200 200 60", true),
+            ("read3 200 0 200 - chr2 10000 3500 3700 200 200 60", true),
+            // Off-target reads aren't split by strand
+            ("read4 200 0 200 - chr2 10000 3500 3700 200 200 60", false),
+            ("read5 200 0 0 * * 0 0 0 0 0 0", false),
+        ] {
+            summary
+                .add_record(
+                    "Direct_CNS",
+                    1,
+                    &PafRecord::from_line(line).unwrap(),
+                    on_target,
+                    None,
+                )
+                .unwrap();
+        }
+        summary.finalize();
+        let condition_summary = summary.get("Direct_CNS").unwrap();
+        assert_eq!(condition_summary.forward_on_target_read_count, 2);
+        assert_eq!(condition_summary.reverse_on_target_read_count, 1);
+        let mut merged = condition_summary.clone();
+        merged.merge(condition_summary);
+        assert_eq!(merged.forward_on_target_read_count, 4);
+        assert_eq!(merged.reverse_on_target_read_count, 2);
+
+        let render = |summary: &Summary| {
+            let mut rendered = Vec::new();
+            summary
+                .render_with_options(
+                    &mut rendered,
+                    &RenderOptions {
+                        color: false,
+                        max_contigs: None,
+                        show_targets: false,
+                    },
+                )
+                .unwrap();
+            String::from_utf8(rendered).unwrap()
+        };
+        let line = "Direct_CNS: on-target reads by strand: 2 forward (+), 1 reverse (-)";
+        assert!(!render(&summary).contains(line));
+        summary.set_strand_aware(true);
+        assert!(render(&summary).contains(line));
+    }
+
+    #[test]
+    fn test_summary_target_summaries() {
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let mut summary = Summary::new();
+        for (line, on_target) in [
+            ("read1 200 0 200 - chr2 10000 3500 3600 100 100 60", true),
+            ("read2 300 0 300 - chr2 10000 3900 4200 300 300 60", true),
+            // Off-target alignments aren't counted in a target
+            ("read3 200 0 200 + chr2 10000 3000 3100 100 100 60", false),
+        ] {
+            let record = PafRecord::from_line(line).unwrap();
+            let targets = if on_target {
+                conf.overlapping_targets(
+                    "Direct_CNS",
+                    &record.target_name,
+                    record.target_start,
+                    record.target_end,
+                )
+            } else {
+                0..0
+            };
+            summary
+                .add_record("Direct_CNS", 1, &record, on_target, None)
+                .unwrap();
+            summary.add_target_hits("Direct_CNS", &record, targets);
+        }
+        summary.finalize();
+        // The targets are counted without keeping the read lengths
+        assert!(!summary.keep_read_lengths);
+        let target_summaries = summary.target_summaries(&conf);
+        assert_eq!(
+            target_summaries["Direct_CNS"],
+            vec![
+                TargetSummary {
+                    contig: "chr2".to_string(),
+                    start: 3000,
+                    stop: 4000,
+                    read_count: 2,
+                    total_bases: 500,
+                },
+                // Targets without reads are kept, so uncovered genes stand out
+                TargetSummary {
+                    contig: "chr20".to_string(),
+                    start: 3000,
+                    stop: 4000,
+                    read_count: 0,
+                    total_bases: 0,
+                },
+            ]
+        );
+
+        let mut rendered = Vec::new();
+        summary
+            .render_with_options(
+                &mut rendered,
+                &RenderOptions {
+                    color: false,
+                    max_contigs: None,
+                    show_targets: true,
+                },
+            )
+            .unwrap();
+        // Not rendered until the targets have been counted
+        assert!(!String::from_utf8(rendered).unwrap().contains("Targets:"));
+        summary.set_target_summaries(&conf);
+        assert_eq!(
+            summary.get("Direct_CNS").unwrap().targets.as_ref(),
+            target_summaries.get("Direct_CNS")
+        );
+        let mut rendered = Vec::new();
+        summary
+            .render_with_options(
+                &mut rendered,
+                &RenderOptions {
+                    color: false,
+                    max_contigs: None,
+                    show_targets: true,
+                },
+            )
+            .unwrap();
+        let rendered = String::from_utf8(rendered).unwrap();
+        assert!(rendered.contains("Targets:"));
+        assert!(rendered.contains("chr20"));
+    }
+
+    #[test]
+    fn test_summary_end_reasons() {
+        let mut summary = Summary::new();
+        for (read_id, end_reason) in [
+            ("read1", Some("signal_positive")),
+            ("read2", Some("data_service_unblock_mux_change")),
+            ("read3", Some("data_service_unblock_mux_change")),
+            ("read4", Some("analysis_config_change")),
+            ("read5", None),
+        ] {
+            let seq_sum_record = SeqSumRecord {
+                read_id: read_id.to_string(),
+                channel: 1,
+                barcode: None,
+                length: None,
+                mean_qscore: None,
+                start_time: None,
+                end_reason: end_reason.map(EndReason::from),
+            };
+            summary
+                .add_record(
+                    "barcode01",
+                    1,
+                    &PafRecord::from_line(&format!(
+                        "{} 200 0 200 + contig1 5000 0 200 200 200 60",
+                        read_id
+                    ))
+                    .unwrap(),
+                    true,
+                    Some(seq_sum_record),
+                )
+                .unwrap();
+        }
+        let condition_summary = &summary.conditions["barcode01"];
+        assert_eq!(condition_summary.end_reasons.len(), 3);
+        assert_eq!(condition_summary.end_reasons["signal_positive"], 1);
+        assert_eq!(
+            condition_summary.end_reasons["data_service_unblock_mux_change"],
+            2
+        );
+        assert_eq!(condition_summary.end_reasons["analysis_config_change"], 1);
+        assert_eq!(condition_summary.unblocked_read_count, 2);
+
+        let mut merged = summary.clone();
+        merged.merge(summary.clone());
+        assert_eq!(
+            merged.conditions["barcode01"].end_reasons["data_service_unblock_mux_change"],
+            4
+        );
+
+        let mut rendered = Vec::new();
+        summary
+            .render_with_options(
+                &mut rendered,
+                &RenderOptions {
+                    color: false,
+                    max_contigs: None,
+                    show_targets: false,
+                },
+            )
+            .unwrap();
+        let rendered = String::from_utf8(rendered).unwrap();
+        assert!(rendered.contains("End reasons:"));
+        assert!(rendered.contains("| data_service_unblock_mux_change |"));
+        assert!(rendered.contains("| 2 (50.00%) "));
+        assert!(rendered.contains("| analysis_config_change "));
+    }
+
+    #[test]
+    fn test_summary_contig_length_mismatches() {
+        let records = [
+            "read1 2000 0 2000 + chr1 248956422 0 2000 2000 2000 60",
+            // chr1 of an older reference version
+            "read2 2000 0 2000 + chr1 249250621 0 2000 2000 2000 60",
+            "read3 2000 0 2000 + chr1 249250621 0 2000 2000 2000 60",
+            "read4 2000 0 2000 + chr2 242193529 0 2000 2000 2000 60",
+            "read5 2000 0 2000 + * 0 0 0 0 0 0",
+        ]
+        .map(|line| PafRecord::from_line(line).unwrap());
+        let mut summary = Summary::new();
+        for record in &records {
+            summary
+                .add_record("barcode01", 1, record, false, None)
+                .unwrap();
+        }
+        assert_eq!(summary.contig_length_mismatches, 2);
+        // The disagreement is only recorded once
+        assert_eq!(
+            summary.contig_length_discrepancies,
+            vec![("chr1".to_string(), 248_956_422, 249_250_621)]
+        );
+        assert_eq!(summary.contig_lengths["chr1"], 248_956_422);
+        assert!(summary.to_string().contains(
+            "Alignments whose contig length disagrees with the reference: 2 (contigs: chr1)"
+        ));
+
+        // A contig whose first length differs between merged summaries is a mismatch
+        let mut other = Summary::new();
+        other
+            .add_record("barcode01", 1, &records[3], false, None)
+            .unwrap();
+        other.contig_lengths.insert("chr2".to_string(), 1000);
+        summary.merge(other);
+        assert_eq!(summary.contig_length_mismatches, 3);
+        assert_eq!(summary.contig_length_discrepancies.len(), 2);
+
+        let mut strict = Summary::new();
+        strict.set_strict_contig_lengths(true);
+        strict
+            .add_record("barcode01", 1, &records[0], false, None)
+            .unwrap();
+        let err = strict
+            .add_record("barcode01", 1, &records[1], false, None)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Contig chr1 has a length of 249250621"));
+        assert!(err.to_string().contains("read2"));
+
+        // Secondary alignments are checked as well
+        let mut summary = Summary::new();
+        summary
+            .add_record("barcode01", 1, &records[0], false, None)
+            .unwrap();
+        summary
+            .add_secondary_alignment("barcode01", &records[1], false)
+            .unwrap();
+        assert_eq!(summary.contig_length_mismatches, 1);
+        summary.set_strict_contig_lengths(true);
+        assert!(summary
+            .add_secondary_alignment("barcode01", &records[2], false)
+            .is_err());
+    }
+
+    #[test]
+    fn test_summary_load_fai() {
+        let fai_path = std::env::temp_dir().join("readfish_tools_test_reference.fa.fai");
+        std::fs::write(
+            &fai_path,
+            "chr2\t10000\t6\t60\t61\nchr3\t5000\t10178\t60\t61\n",
         )
         .unwrap();
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let mut summary = Summary::new();
+        summary.load_fai(&fai_path).unwrap();
+        // The PAF length of chr2 disagrees with the .fai
+        summary
+            .add_record(
+                "Direct_CNS",
+                1,
+                &PafRecord::from_line("read1 2000 0 2000 - chr2 12000 3000 5000 2000 2000 60")
+                    .unwrap(),
+                true,
+                None,
+            )
+            .unwrap();
+        summary.set_target_fractions(&conf);
+        summary.finalize();
+        let condition = &summary.conditions["Direct_CNS"];
+        assert_eq!(condition.contigs.len(), 2);
+        assert_eq!(condition.contigs["chr2"].length, 10_000);
+        assert!((condition.contigs["chr2"].mean_coverage - 0.2).abs() < 1e-9);
+        // Contigs without alignments are reported with zero coverage
+        assert_eq!(condition.contigs["chr3"].length, 5000);
+        assert_eq!(condition.contigs["chr3"].total_reads(), 0);
+        assert_eq!(condition.contigs["chr3"].mean_coverage, 0.0);
+        assert_eq!(summary.contig_length_mismatches, 1);
+        assert_eq!(
+            summary.contig_length_discrepancies,
+            vec![("chr2".to_string(), 10_000, 12_000)]
+        );
+        // chr2:3000-4000 of a 15 Kb reference
+        assert!((condition.target_fraction.unwrap() - 1.0 / 15.0).abs() < 1e-9);
+        assert!(summary.load_fai(get_test_file("RAPID_CNS2.toml")).is_err());
+        std::fs::remove_file(fai_path).unwrap();
+    }
 
-        // Create a MeanReadLengths instance
-        let mut mean_lengths = MeanReadLengths::new();
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_cram_reference_lengths() {
+        // The .fai of the reference a CRAM file is decoded against doubles as the contig lengths
+        let summary = _demultiplex_bam(
+            get_test_file("RAPID_CNS2.toml"),
+            get_test_file("test_alignments.cram"),
+            Some(get_test_file("sequencing_summary_0.txt")),
+            false,
+            None::<&Path>,
+            true,
+            false,
+            None::<&Path>,
+            None::<&Path>,
+            ReadFilter::default(),
+            None::<&Path>,
+            Some(get_test_file("test_reference.fa")),
+        )
+        .unwrap();
+        assert_eq!(
+            summary.reference_lengths,
+            HashMap::from([("chr1".to_string(), 5000), ("chr2".to_string(), 5000)])
+        );
+        assert_eq!(
+            summary
+                .conditions
+                .values()
+                .map(|condition| condition.total_reads)
+                .sum::<usize>(),
+            4
+        );
+    }
 
-        // Initially, all mean lengths should be zero
-        assert_eq!(mean_lengths.on_target, 0);
-        assert_eq!(mean_lengths.off_target, 0);
-        assert_eq!(mean_lengths.total, 0);
+    #[test]
+    fn test_summary_write_csv() {
+        let mut summary = Summary::new();
+        for (condition_name, line, on_target) in [
+            (
+                "barcode10",
+                "read1 200 0 200 + contig1 5000 0 200 200 200 60",
+                true,
+            ),
+            (
+                "barcode02",
+                "read2 400 0 400 + contig2 5000 0 400 400 400 60",
+                false,
+            ),
+        ] {
+            let paf = PafRecord::from_line(line).unwrap();
+            summary
+                .conditions_entry(condition_name)
+                .update(&paf, on_target)
+                .unwrap();
+        }
+        summary.finalize();
+        let mut csv = Vec::new();
+        summary.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("\"condition\",\"total_reads\""));
+        assert_eq!(
+            lines[1],
+            "\"barcode02\",1,0,1,400,0,400,100.00,400,0,400,0,0"
+        );
+        assert_eq!(lines[2], "\"barcode10\",1,1,0,200,200,0,0.00,200,200,0,0,0");
 
-        // Update with an on-target read
-        mean_lengths.update_lengths(&paf, true);
+        let mut contig_csv = Vec::new();
+        summary.write_contig_csv(&mut contig_csv).unwrap();
+        let contig_csv = String::from_utf8(contig_csv).unwrap();
+        let lines: Vec<&str> = contig_csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "\"barcode02\",\"contig2\",5000,1,0,1,400,0,400,400,0.08"
+        );
+    }
 
-        // After the update, only on_target and total should be updated
-        assert_eq!(mean_lengths.on_target, 100);
-        assert_eq!(mean_lengths.off_target, 0);
-        assert_eq!(mean_lengths.total, 100);
+    #[test]
+    fn test_summary_write_sql() {
+        let mut summary = Summary::new();
+        let paf = PafRecord::from_line("read1 200 0 200 + chr'1 5000 0 200 200 200 60").unwrap();
+        summary
+            .conditions_entry("barcode01")
+            .update(&paf, true)
+            .unwrap();
+        summary.conditions_entry("barcode02");
+        summary.finalize();
+        // Percentages that aren't a number are stored as NULL
+        summary
+            .conditions_entry("barcode02")
+            .set_off_target_percent(f64::NAN);
+        let mut sql = Vec::new();
+        summary.write_sql(&mut sql, "run'1").unwrap();
+        let sql = String::from_utf8(sql).unwrap();
+        assert!(sql.starts_with("BEGIN TRANSACTION;\nCREATE TABLE IF NOT EXISTS runs"));
+        assert!(sql.ends_with("COMMIT;\n"));
+        assert!(sql.contains("DELETE FROM conditions WHERE run = 'run''1';\n"));
+        assert!(sql.contains(
+            "INSERT INTO conditions (run, condition, total_reads, on_target_read_count, off_target_read_count, \
+             total_yield, on_target_yield, off_target_yield, off_target_percent, n50, on_target_n50, off_target_n50, \
+             unmapped_read_count, unmapped_yield) VALUES ('run''1', 'barcode01', 1, 1, 0, 200, 200, 0, 0.00, 200, \
+             200, 0, 0, 0);\n"
+        ));
+        assert!(sql
+            .contains("VALUES ('run''1', 'barcode02', 0, 0, 0, 0, 0, 0, NULL, 0, 0, 0, 0, 0);\n"));
+        assert!(sql.contains(
+            "VALUES ('run''1', 'barcode01', 'chr''1', 5000, 1, 1, 0, 200, 200, 0, 200, 0.04);\n"
+        ));
+    }
 
-        // Update with an off-target read
-        mean_lengths.update_lengths(&paf, false);
+    #[test]
+    #[cfg(feature = "polars")]
+    fn test_summary_to_dataframe() {
+        use polars::prelude::DataType;
 
-        // After the update, off_target and total should be updated
-        assert_eq!(mean_lengths.on_target, 100);
-        assert_eq!(mean_lengths.off_target, 100);
-        assert_eq!(mean_lengths.total, 100);
-        // Create a PAF record with a query length of 100
-        let paf = PafRecord::new(
-            "read123 150 0 100 + contig123 300 0 300 200 200 50 ch=1"
-                .split(' ')
-                .collect(),
+        let mut summary = Summary::new();
+        let paf = PafRecord::from_line("read1 200 0 200 + contig1 5000 0 200 200 200 60").unwrap();
+        summary
+            .conditions_entry("barcode01")
+            .update(&paf, true)
+            .unwrap();
+        summary.conditions_entry("barcode02");
+        summary.finalize();
+        summary
+            .conditions_entry("barcode02")
+            .set_off_target_percent(f64::NAN);
+
+        // The columns are those of the CSVs, in the same order
+        let mut csv = Vec::new();
+        summary.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let conditions = summary.to_condition_dataframe().unwrap();
+        let names: Vec<&str> = conditions
+            .get_column_names()
+            .into_iter()
+            .map(|name| name.as_str())
+            .collect();
+        assert_eq!(names, CONDITION_CSV_COLUMNS);
+        assert_eq!(
+            csv.lines().next().unwrap(),
+            names.iter().map(|name| format!("\"{}\"", name)).join(",")
+        );
+        assert_eq!(conditions.height(), csv.lines().count() - 1);
+        for (name, dtype) in CONDITION_CSV_COLUMNS.iter().zip(conditions.dtypes()) {
+            let expected = match *name {
+                "condition" => DataType::String,
+                "off_target_percent" => DataType::Float64,
+                _ => DataType::UInt64,
+            };
+            assert_eq!(dtype, expected, "{}", name);
+        }
+        let condition_names = conditions.column("condition").unwrap().str().unwrap();
+        assert_eq!(condition_names.get(0), Some("barcode01"));
+        let on_target_yield = conditions.column("on_target_yield").unwrap().u64().unwrap();
+        assert_eq!(on_target_yield.get(0), Some(200));
+        // Percentages that aren't a number are null
+        let off_target_percent = conditions
+            .column("off_target_percent")
+            .unwrap()
+            .f64()
+            .unwrap();
+        assert_eq!(off_target_percent.get(0), Some(0.0));
+        assert_eq!(off_target_percent.get(1), None);
+
+        let mut csv = Vec::new();
+        summary.write_contig_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let contigs = summary.to_contig_dataframe().unwrap();
+        let names: Vec<&str> = contigs
+            .get_column_names()
+            .into_iter()
+            .map(|name| name.as_str())
+            .collect();
+        assert_eq!(names, CONTIG_CSV_COLUMNS);
+        assert_eq!(
+            csv.lines().next().unwrap(),
+            names.iter().map(|name| format!("\"{}\"", name)).join(",")
+        );
+        assert_eq!(contigs.height(), csv.lines().count() - 1);
+        for (name, dtype) in CONTIG_CSV_COLUMNS.iter().zip(contigs.dtypes()) {
+            let expected = match *name {
+                "condition" | "contig" => DataType::String,
+                "mean_coverage" => DataType::Float64,
+                _ => DataType::UInt64,
+            };
+            assert_eq!(dtype, expected, "{}", name);
+        }
+        let mean_coverage = contigs.column("mean_coverage").unwrap().f64().unwrap();
+        assert_eq!(mean_coverage.get(0), Some(0.04));
+    }
+
+    #[test]
+    #[cfg(feature = "parquet")]
+    fn test_summary_write_parquet() {
+        use arrow::{
+            array::{Array, Float64Array, StringArray, UInt64Array},
+            datatypes::DataType,
+        };
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let mut summary = Summary::new();
+        let paf = PafRecord::from_line("read1 200 0 200 + contig1 5000 0 200 200 200 60").unwrap();
+        summary
+            .conditions_entry("barcode01")
+            .update(&paf, true)
+            .unwrap();
+        summary.conditions_entry("barcode02");
+        summary.finalize();
+        summary
+            .conditions_entry("barcode02")
+            .set_off_target_percent(f64::NAN);
+        let out_dir = std::env::temp_dir().join("readfish_tools_test_parquet");
+        summary.write_parquet(&out_dir).unwrap();
+        let read_batch = |file_name: &str| {
+            let file = std::fs::File::open(out_dir.join(file_name)).unwrap();
+            let mut reader = ParquetRecordBatchReaderBuilder::try_new(file)
+                .unwrap()
+                .build()
+                .unwrap();
+            reader.next().unwrap().unwrap()
+        };
+
+        let conditions = read_batch("conditions.parquet");
+        let schema = conditions.schema();
+        let names: Vec<&str> = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(names, CONDITION_CSV_COLUMNS);
+        assert_eq!(schema.field(0).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(1).data_type(), &DataType::UInt64);
+        assert_eq!(schema.field(7).data_type(), &DataType::Float64);
+        let condition_names = conditions
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(condition_names.value(0), "barcode01");
+        assert_eq!(condition_names.value(1), "barcode02");
+        let on_target_yield = conditions
+            .column(5)
+            .as_any()
+            .downcast_ref::<UInt64Array>()
+            .unwrap();
+        assert_eq!(on_target_yield.value(0), 200);
+        let off_target_percent = conditions
+            .column(7)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(off_target_percent.value(0), 0.0);
+        // Percentages that aren't a number are null
+        assert!(off_target_percent.is_null(1));
+
+        let contigs = read_batch("contigs.parquet");
+        let schema = contigs.schema();
+        let names: Vec<&str> = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(names, CONTIG_CSV_COLUMNS);
+        assert_eq!(contigs.num_rows(), 1);
+        let contig_names = contigs
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(contig_names.value(0), "contig1");
+        let mean_coverage = contigs
+            .column(10)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(mean_coverage.value(0), 0.04);
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite")]
+    fn test_summary_write_sqlite() {
+        let mut summary = Summary::new();
+        let paf = PafRecord::from_line("read1 200 0 200 + chr'1 5000 0 200 200 200 60").unwrap();
+        summary
+            .conditions_entry("barcode01")
+            .update(&paf, true)
+            .unwrap();
+        summary.conditions_entry("barcode02");
+        summary.finalize();
+        summary
+            .conditions_entry("barcode02")
+            .set_off_target_percent(f64::NAN);
+        let db_path = std::env::temp_dir().join("readfish_tools_test_summary.db");
+        let assignments_path = std::env::temp_dir().join("readfish_tools_test_sqlite.tsv");
+        let _ = std::fs::remove_file(&db_path);
+        std::fs::write(
+            &assignments_path,
+            format!(
+                "{}\nread1\tbarcode01\ttrue\tchr'1\n",
+                paf::ASSIGNMENTS_HEADER
+            ),
         )
         .unwrap();
-        // Update with an off-target read with a different length
-        mean_lengths.update_lengths(&paf, false);
+        summary
+            .write_sqlite(&db_path, "run1", Some(&assignments_path))
+            .unwrap();
+        // Writing a run again replaces its rows, and other runs are kept
+        summary
+            .write_sqlite(&db_path, "run1", Some(&assignments_path))
+            .unwrap();
+        summary
+            .write_sqlite(&db_path, "run2", None::<&Path>)
+            .unwrap();
+        let connection = rusqlite::Connection::open(&db_path).unwrap();
+        let count =
+            |query: &str| -> i64 { connection.query_row(query, [], |row| row.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM runs"), 2);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM conditions WHERE run = 'run1'"),
+            2
+        );
+        assert_eq!(count("SELECT COUNT(*) FROM contigs"), 2);
+        assert_eq!(count("SELECT COUNT(*) FROM assignments"), 1);
+        assert_eq!(
+            count("SELECT on_target_yield FROM conditions WHERE condition = 'barcode01' AND run = 'run1'"),
+            200
+        );
+        let off_target_percent: Option<f64> = connection
+            .query_row(
+                "SELECT off_target_percent FROM conditions WHERE condition = 'barcode02'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(off_target_percent, None);
+        let (contig, on_target): (String, bool) = connection
+            .query_row(
+                "SELECT contig, on_target FROM assignments WHERE read_id = 'read1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((contig.as_str(), on_target), ("chr'1", true));
+        // Nothing is written if the assignments aren't a TSV of assignments
+        assert!(summary
+            .write_sqlite(&db_path, "run3", Some(get_test_file("RAPID_CNS2.toml")))
+            .is_err());
+        assert_eq!(count("SELECT COUNT(*) FROM runs"), 2);
+        drop(connection);
+        std::fs::remove_file(db_path).unwrap();
+        std::fs::remove_file(assignments_path).unwrap();
+    }
 
-        // After the update, off_target and total should be updated
-        assert_eq!(mean_lengths.on_target, 100);
-        assert_eq!(mean_lengths.off_target, 125);
-        assert_eq!(mean_lengths.total, 116);
+    #[test]
+    fn test_summary_render() {
+        let mut summary = Summary::new();
+        let paf = PafRecord::from_line("read1 200 0 200 + contig1 5000 0 200 200 200 60").unwrap();
+        summary
+            .conditions_entry("barcode01")
+            .update(&paf, true)
+            .unwrap();
+        summary.finalize();
+        let mut buffer = Vec::new();
+        summary.render(&mut buffer).unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+        assert!(rendered.contains("barcode01"));
+        assert!(rendered.contains("Contigs:"));
+        assert!(rendered.contains("contig1"));
+        assert_eq!(rendered, summary.to_string());
+    }
+
+    #[test]
+    fn test_init_logging() {
+        if std::env::var_os("RUST_LOG").is_none() {
+            init_logging(true);
+            assert_eq!(log::max_level(), LevelFilter::Debug);
+            init_logging(false);
+            assert_eq!(log::max_level(), LevelFilter::Warn);
+        }
+    }
+
+    #[test]
+    fn test_summary_unclassified() {
+        let mut summary = Summary::new();
+        assert_eq!(summary.unclassified_percent(), 0.0);
+        for read_id in ["read1", "read2", "read3"] {
+            let paf = PafRecord::from_line(&format!(
+                "{read_id} 200 0 200 + contig1 5000 0 200 200 200 60"
+            ))
+            .unwrap();
+            summary
+                .conditions_entry("barcode01")
+                .update(&paf, true)
+                .unwrap();
+        }
+        let paf = PafRecord::from_line("read4 200 0 200 + contig1 5000 0 200 200 200 60").unwrap();
+        summary.unclassified().update(&paf, false).unwrap();
+        assert_eq!(summary.unclassified_read_count, 1);
+        assert_eq!(summary.conditions[UNCLASSIFIED_CONDITION].total_reads, 1);
+        assert_eq!(summary.unclassified_percent(), 25.0);
+        let mut buffer = Vec::new();
+        summary
+            .render_with_options(
+                &mut buffer,
+                &RenderOptions {
+                    color: false,
+                    max_contigs: None,
+                    show_targets: false,
+                },
+            )
+            .unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+        assert!(rendered.contains("Unclassified reads: 1 (25.00%)"));
+    }
+
+    #[test]
+    fn test_summary_render_without_color() {
+        let mut summary = Summary::new();
+        let paf = PafRecord::from_line("read1 200 0 200 + contig1 5000 0 200 200 200 60").unwrap();
+        summary
+            .conditions_entry("barcode01")
+            .update(&paf, true)
+            .unwrap();
+        let mut buffer = Vec::new();
+        summary
+            .render_with_options(
+                &mut buffer,
+                &RenderOptions {
+                    color: false,
+                    max_contigs: None,
+                    show_targets: false,
+                },
+            )
+            .unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+        assert!(rendered.contains("Condition Name"));
+        assert!(!rendered.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_summary_default_and_eq() {
+        assert_eq!(Summary::default(), Summary::new());
+        assert_eq!(
+            ConditionSummary::default(),
+            ConditionSummary::new(String::new())
+        );
+        assert_eq!(
+            ContigSummary::default(),
+            ContigSummary::new(String::new(), 0)
+        );
+
+        let summary = summarise_merge_reads(&MERGE_READS);
+        assert_eq!(summary.clone(), summary);
+        let condition_summary = &summary.conditions["cond1"];
+        let mut other = condition_summary.clone();
+        other.on_target_mean_read_quality += 1e-12;
+        assert_ne!(&other, condition_summary);
+        assert!(other.approx_eq(condition_summary, 1e-9));
+        assert!(!other.approx_eq(condition_summary, 0.0));
+        other.total_reads += 1;
+        assert!(!other.approx_eq(condition_summary, 1e-9));
+
+        let contig_name = condition_summary.contigs.keys().next().unwrap();
+        let mut other = condition_summary.clone();
+        other
+            .contigs_mut()
+            .get_mut(contig_name)
+            .unwrap()
+            .mean_coverage += 1.0;
+        assert!(!other.approx_eq(condition_summary, 1e-9));
+    }
+
+    #[test]
+    fn test_summary_get() {
+        let mut summary = Summary::default();
+        assert!(summary.get("barcode01").is_none());
+        assert!(summary.get_mut("barcode01").is_none());
+        assert!(summary.conditions.is_empty());
+        let paf = PafRecord::from_line("read1 200 0 200 + contig1 5000 0 200 200 200 60").unwrap();
+        summary
+            .conditions_entry("barcode01")
+            .update(&paf, true)
+            .unwrap();
+        assert_eq!(summary.get("barcode01").unwrap().total_reads, 1);
+        summary.get_mut("barcode01").unwrap().total_reads = 2;
+        assert_eq!(summary.conditions["barcode01"].total_reads, 2);
+        assert!(summary.get("barcode02").is_none());
+        assert_eq!(summary.conditions.len(), 1);
+    }
+
+    #[test]
+    fn test_summary_diff() {
+        let summarise = |reads: &[(&str, &str, bool)]| {
+            let mut summary = Summary::new();
+            for (condition, line, on_target) in reads {
+                let paf = PafRecord::from_line(line).unwrap();
+                summary
+                    .conditions_entry(*condition)
+                    .update(&paf, *on_target)
+                    .unwrap();
+            }
+            summary
+        };
+        let mut run = summarise(&[
+            (
+                "barcode01",
+                "read1 300 0 300 + contig1 5000 0 300 300 300 60",
+                true,
+            ),
+            (
+                "barcode01",
+                "read2 100 0 100 + contig1 5000 0 100 100 100 60",
+                false,
+            ),
+            (
+                "barcode02",
+                "read3 100 0 100 + contig1 5000 0 100 100 100 60",
+                true,
+            ),
+        ]);
+        let mut baseline = summarise(&[
+            (
+                "barcode01",
+                "read1 100 0 100 + contig1 5000 0 100 100 100 60",
+                true,
+            ),
+            (
+                "barcode01",
+                "read2 100 0 100 + contig1 5000 0 100 100 100 60",
+                false,
+            ),
+            (
+                "barcode03",
+                "read3 100 0 100 + contig1 5000 0 100 100 100 60",
+                true,
+            ),
+        ]);
+        run.conditions_entry("barcode01").set_target_fraction(0.5);
+        baseline
+            .conditions_entry("barcode01")
+            .set_target_fraction(0.5);
+
+        let diff = run.diff(&baseline);
+        let names: Vec<&str> = diff
+            .conditions
+            .iter()
+            .map(|condition| condition.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["barcode01", "barcode02", "barcode03"]);
+
+        let barcode01 = &diff.conditions[0];
+        assert_eq!(barcode01.yield_delta(), Some(200));
+        assert_eq!(barcode01.yield_change_percent(), Some(100.0));
+        assert_eq!(barcode01.on_target_percent_delta(), Some(0.0));
+        // 0.75 / 0.5 against 0.5 / 0.5
+        assert!((barcode01.enrichment_delta().unwrap() - 0.5).abs() < 1e-9);
+        assert!((barcode01.enrichment_change_percent().unwrap() - 50.0).abs() < 1e-9);
+
+        let barcode02 = &diff.conditions[1];
+        assert!(barcode02.run.is_some() && barcode02.baseline.is_none());
+        assert_eq!(barcode02.yield_delta(), None);
+        assert_eq!(barcode02.run.unwrap().enrichment, None);
+        let barcode03 = &diff.conditions[2];
+        assert!(barcode03.run.is_none() && barcode03.baseline.is_some());
+
+        let mut buffer = Vec::new();
+        diff.render_with_options(
+            &mut buffer,
+            &RenderOptions {
+                color: false,
+                max_contigs: None,
+                show_targets: false,
+            },
+        )
+        .unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+        assert!(rendered.contains("+200 b (+100.00%)"));
+        assert!(rendered.contains("+0.00 pp"));
+        assert!(rendered.contains("+0.50x (+50.00%)"));
+        assert!(rendered.contains("only in run"));
+        assert!(rendered.contains("only in baseline"));
+    }
+
+    #[test]
+    fn test_summary_normalize_against() {
+        let summarise = |reads: &[(&str, &str, bool)]| {
+            let mut summary = Summary::new();
+            for (condition, line, on_target) in reads {
+                let paf = PafRecord::from_line(line).unwrap();
+                summary
+                    .conditions_entry(*condition)
+                    .update(&paf, *on_target)
+                    .unwrap();
+            }
+            summary
+        };
+        let run = summarise(&[
+            (
+                "barcode01",
+                "read1 300 0 300 + contig1 5000 0 300 300 300 60",
+                true,
+            ),
+            (
+                "barcode01",
+                "read2 100 0 100 + contig1 5000 0 100 100 100 60",
+                false,
+            ),
+            (
+                "barcode02",
+                "read3 100 0 100 + contig1 5000 0 100 100 100 60",
+                true,
+            ),
+            (
+                "barcode10",
+                "read4 100 0 100 + contig1 5000 0 100 100 100 60",
+                true,
+            ),
+        ]);
+        let control = summarise(&[
+            (
+                "barcode01",
+                "read1 100 0 100 + contig1 5000 0 100 100 100 60",
+                true,
+            ),
+            (
+                "barcode01",
+                "read2 300 0 300 + contig1 5000 0 300 300 300 60",
+                false,
+            ),
+            (
+                "barcode10",
+                "read3 100 0 100 + contig1 5000 0 100 100 100 60",
+                false,
+            ),
+            (
+                "barcode03",
+                "read4 100 0 100 + contig1 5000 0 100 100 100 60",
+                true,
+            ),
+        ]);
+
+        let normalized = run.normalize_against(&control);
+        let names: Vec<&str> = normalized
+            .conditions
+            .iter()
+            .map(|condition| condition.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["barcode01", "barcode02", "barcode10"]);
+
+        let barcode01 = &normalized.conditions[0];
+        assert!(barcode01.in_control);
+        assert!((barcode01.on_target_fraction - 0.75).abs() < 1e-9);
+        assert_eq!(barcode01.control_on_target_fraction, Some(0.25));
+        // 0.75 / 0.25
+        assert!((barcode01.enrichment.unwrap() - 3.0).abs() < 1e-9);
+
+        let barcode02 = &normalized.conditions[1];
+        assert!(!barcode02.in_control);
+        assert_eq!(barcode02.control_on_target_fraction, None);
+        assert_eq!(barcode02.enrichment, None);
+
+        // No on-target yield in the control, so no enrichment
+        let barcode10 = &normalized.conditions[2];
+        assert!(barcode10.in_control);
+        assert_eq!(barcode10.control_on_target_fraction, Some(0.0));
+        assert_eq!(barcode10.enrichment, None);
+
+        let mut buffer = Vec::new();
+        normalized
+            .render_with_options(
+                &mut buffer,
+                &RenderOptions {
+                    color: false,
+                    max_contigs: None,
+                    show_targets: false,
+                },
+            )
+            .unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+        assert!(rendered.contains("3.00x"));
+        assert!(rendered.contains("25.00%"));
+        assert!(rendered.contains("not in control"));
+        assert!(!rendered.contains("barcode03"));
+    }
+
+    #[test]
+    fn test_read_reconciliation() {
+        let mut summary = Summary::new();
+        assert_eq!(summary.read_reconciliation(), ReadReconciliation::default());
+        assert_eq!(
+            summary.read_reconciliation().to_string(),
+            "0 reads in, 0 assigned (0.00%)"
+        );
+        for line in [
+            "read1 200 0 200 + contig1 5000 0 200 200 200 60",
+            "read2 200 0 200 + contig1 5000 0 200 200 200 60",
+            "read3 200 0 0 * * 0 0 0 0 0 0",
+        ] {
+            let paf = PafRecord::from_line(line).unwrap();
+            summary
+                .conditions_entry("barcode01")
+                .update(&paf, true)
+                .unwrap();
+        }
+        let paf = PafRecord::from_line("read4 200 0 200 + contig1 5000 0 200 200 200 60").unwrap();
+        summary.unclassified().update(&paf, false).unwrap();
+        summary.filtered_out = 2;
+        summary.reads_missing_from_seqsum = 1;
+        summary.skipped_lines = 1;
+        let reconciliation = summary.read_reconciliation();
+        assert_eq!(
+            reconciliation,
+            ReadReconciliation {
+                reads_in: 8,
+                assigned: 2,
+                unclassified: 1,
+                unmapped: 1,
+                filtered_out: 2,
+                missing_from_seqsum: 1,
+                skipped_lines: 1,
+            }
+        );
+        assert_eq!(reconciliation.dropped(), 6);
+        assert_eq!(reconciliation.assigned_percent(), 25.0);
+        assert_eq!(
+            reconciliation.to_string(),
+            "8 reads in, 2 assigned (25.00%); dropped 1 unclassified, 1 unmapped, 2 filtered out, \
+             1 missing from the sequencing summary, 1 malformed"
+        );
+    }
+
+    #[test]
+    fn test_top_contigs() {
+        let mut summary = Summary::new();
+        for line in [
+            "read1 300 0 300 + contig2 5000 0 300 300 300 60",
+            "read2 100 0 100 + contig10 5000 0 100 100 100 60",
+            "read3 100 0 100 + contig10 5000 0 100 100 100 60",
+            "read4 100 0 100 + contig1 5000 0 100 100 100 60",
+            "read5 200 0 200 + contig3 5000 0 200 200 200 60",
+        ] {
+            let paf = PafRecord::from_line(line).unwrap();
+            summary
+                .conditions_entry("barcode01")
+                .update(&paf, false)
+                .unwrap();
+        }
+        let condition_summary = &summary.conditions["barcode01"];
+        let names = |contigs: Vec<&ContigSummary>| -> Vec<String> {
+            contigs
+                .into_iter()
+                .map(|contig| contig.name.clone())
+                .collect()
+        };
+        // contig3 and contig10 have the same yield
+        assert_eq!(
+            names(condition_summary.top_contigs_by_yield(3)),
+            vec!["contig2", "contig3", "contig10"]
+        );
+        assert_eq!(
+            names(condition_summary.top_contigs_by_reads(2)),
+            vec!["contig10", "contig1"]
+        );
+        assert_eq!(condition_summary.top_contigs_by_yield(10).len(), 4);
+        assert!(condition_summary.top_contigs_by_reads(0).is_empty());
+
+        let mut buffer = Vec::new();
+        summary
+            .render_with_options(
+                &mut buffer,
+                &RenderOptions {
+                    color: false,
+                    max_contigs: Some(2),
+                    show_targets: false,
+                },
+            )
+            .unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+        assert!(rendered.contains("| contig2 "));
+        assert!(rendered.contains("| contig3 "));
+        assert!(!rendered.contains("| contig1 "));
+        assert!(!rendered.contains("| contig10 "));
+        assert!(rendered.contains("... and 2 more contigs"));
+    }
+
+    #[test]
+    fn test_contig_summary_mean_read_lengths() {
+        let mut condition_summary = ConditionSummary::new("test".to_string());
+        let contig = condition_summary.get_or_add_contig("contig1", 300);
+        assert_eq!(contig.on_target_mean_read_length(), 0);
+        assert_eq!(contig.off_target_mean_read_length(), 0);
+        for (line, on_target) in [
+            ("read1 200 0 200 + contig1 300 0 200 200 200 60", true),
+            ("read2 400 0 400 + contig1 300 0 300 300 300 60", true),
+            ("read3 100 0 100 + contig1 300 0 100 100 100 60", false),
+            ("read4 50 0 50 + contig1 300 0 50 50 50 60", false),
+        ] {
+            let paf = PafRecord::from_line(line).unwrap();
+            condition_summary.update(&paf, on_target).unwrap();
+        }
+        let contig = condition_summary.get_or_add_contig("contig1", 300);
+        assert_eq!(contig.on_target_mean_read_length(), 300);
+        assert_eq!(contig.off_target_mean_read_length(), 75);
+        // 750 bases over 4 reads
+        assert_eq!(contig.mean_read_length(), 187);
     }
 
     #[test]
@@ -1330,7 +7909,7 @@ mod tests {
         let mut summary = ReadfishSummary::new();
 
         // Parse the config file and update the summary
-        summary.parse_conf_file(conf_file);
+        summary.parse_conf_file(conf_file).unwrap();
 
         // Check that the _conf field is now set with the parsed config
         assert!(summary.has_conf());
@@ -1344,11 +7923,11 @@ mod tests {
         assert_eq!(region.condition.min_chunks, 1);
         assert_eq!(region.condition.max_chunks, 4);
 
-        assert_eq!(region.condition.single_off, "unblock".into());
-        assert_eq!(region.condition.multi_off, "unblock".into());
-        assert_eq!(region.condition.single_on, "stop_receiving".into());
-        assert_eq!(region.condition.multi_on, "stop_receiving".into());
-        assert_eq!(region.condition.no_seq, "proceed".into());
-        assert_eq!(region.condition.no_map, "proceed".into());
+        assert_eq!(region.condition.single_off, Action::Unblock);
+        assert_eq!(region.condition.multi_off, Action::Unblock);
+        assert_eq!(region.condition.single_on, Action::StopReceiving);
+        assert_eq!(region.condition.multi_on, Action::StopReceiving);
+        assert_eq!(region.condition.no_seq, Action::Proceed);
+        assert_eq!(region.condition.no_map, Action::Proceed);
     }
 }
@@ -16,12 +16,71 @@
 //! println!("Flowcell layout: {:?}", flowcell_layout);
 //!
 //! // Divide the flowcell into sections
-//! let divided_flowcell = generate_flowcell(512, 4, 0, false);
+//! let divided_flowcell = generate_flowcell(512, 4, 0, false).unwrap();
 //! println!("Divided flowcell: {:?}", divided_flowcell);
 //! ```
 //!
-use crate::channels::{FLONGLE_CHANNELS, MINION_CHANNELS};
-use ndarray::{s, Array, Array2, Axis};
+use crate::channels::{CUSTOM_CHANNELS, FLONGLE_CHANNELS, MINION_CHANNELS};
+use itertools::Itertools;
+use ndarray::{s, Array, Array2, Axis, Slice};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+/// Registers a custom flowcell layout, so that flowcells other than Flongle (126), MinION (512) and
+/// PromethION (3000) can be used with [`get_coords`] and the functions built on it.
+///
+/// The registry is global and guarded by a `RwLock`, so it is safe to register flowcells from any thread,
+/// and lookups from multiple threads can proceed concurrently. Registering a size again replaces the existing layout.
+///
+/// # Arguments
+///
+/// * `flowcell_size` - The total number of channels on the flowcell.
+/// * `channels` - A map of every channel number, from 1 to `flowcell_size`, to its (column, row) coordinates.
+///
+/// # Errors
+///
+/// This function may return an error in the following cases:
+///
+/// * If `flowcell_size` is one of the built in sizes (126, 512 or 3000).
+/// * If `channels` does not contain exactly the channels 1 to `flowcell_size`.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use readfish_tools::nanopore::{get_coords, register_flowcell};
+///
+/// let channels: HashMap<usize, (usize, usize)> = (1..=4).map(|channel| (channel, ((channel - 1) % 2, (channel - 1) / 2))).collect();
+/// register_flowcell(4, channels).unwrap();
+/// assert_eq!(get_coords(3, 4), Ok((0, 1)));
+/// ```
+pub fn register_flowcell(
+    flowcell_size: usize,
+    channels: HashMap<usize, (usize, usize)>,
+) -> Result<(), String> {
+    if [126, 512, 3000].contains(&flowcell_size) {
+        return Err(format!(
+            "flowcell_size {} is built in and cannot be registered",
+            flowcell_size
+        ));
+    }
+    if (channels.len() != flowcell_size)
+        | channels
+            .keys()
+            .any(|&channel| (channel == 0) | (channel > flowcell_size))
+    {
+        return Err(format!(
+            "channels must contain exactly the channels 1 to {}",
+            flowcell_size
+        ));
+    }
+    CUSTOM_CHANNELS
+        .write()
+        .map_err(|_| "custom flowcell registry is poisoned".to_string())?
+        .insert(flowcell_size, channels);
+    Ok(())
+}
+
 /// Returns the coordinates (column, row) of a channel on a flowcell.
 ///
 /// # Arguments
@@ -39,7 +98,7 @@ use ndarray::{s, Array, Array2, Axis};
 /// This function may return an error in the following cases:
 ///
 /// * If the `channel` is greater than the `flowcell_size`.
-/// * If the `flowcell_size` is not recognized (not equal to 3000, 126, or 512).
+/// * If the `flowcell_size` is not recognized (not equal to 3000, 126, or 512, or registered with [`register_flowcell`]).
 /// * If the `channel` is not found in the predefined coordinate dictionaries for Flongle or MinION channels.
 ///
 /// # Examples
@@ -82,7 +141,85 @@ pub fn get_coords(channel: usize, flowcell_size: usize) -> Result<(usize, usize)
             None => Err("channel not found in MINION_CHANNELS".to_string()),
         }
     } else {
-        Err("flowcell_size is not recognized".to_string())
+        let custom_channels = CUSTOM_CHANNELS
+            .read()
+            .map_err(|_| "custom flowcell registry is poisoned".to_string())?;
+        match custom_channels.get(&flowcell_size) {
+            Some(channels) => match channels.get(&channel) {
+                Some(coordinates) => Ok(*coordinates),
+                None => Err("channel not found in registered flowcell".to_string()),
+            },
+            None => Err("flowcell_size is not recognized".to_string()),
+        }
+    }
+}
+
+/// Returns the channel at the given coordinates (column, row) on a flowcell. The inverse of [`get_coords`].
+///
+/// # Arguments
+///
+/// * `col` - The column of the channel on the flowcell.
+/// * `row` - The row of the channel on the flowcell.
+/// * `flowcell_size` - The total number of channels on the flowcell.
+///
+/// # Returns
+///
+/// * `Ok(channel)` - The channel number at the coordinates.
+/// * `Err(error_message)` - An error message indicating the reason for failure.
+///
+/// # Errors
+///
+/// This function may return an error in the following cases:
+///
+/// * If the coordinates are outside of the flowcell.
+/// * If the `flowcell_size` is not recognized (not equal to 3000, 126, or 512, or registered with [`register_flowcell`]).
+///
+/// # Examples
+///
+/// ```
+/// use readfish_tools::nanopore::channel_from_coords;
+///
+/// let result = channel_from_coords(4, 9, 126);
+/// assert_eq!(result, Ok(5));
+///
+/// let result = channel_from_coords(19, 4, 3000);
+/// assert_eq!(result, Ok(300));
+///
+/// let result = channel_from_coords(32, 0, 512);
+/// assert_eq!(result, Err("coordinates not found in MINION_CHANNELS".to_string()));
+/// ```
+pub fn channel_from_coords(col: usize, row: usize, flowcell_size: usize) -> Result<usize, String> {
+    if flowcell_size == 3000 {
+        // 12 blocks of 10 columns by 25 rows
+        if (col >= 120) | (row >= 25) {
+            return Err("coordinates are outside of the flowcell".to_string());
+        }
+        let block = col / 10;
+        Ok(block * 250 + row * 10 + col % 10 + 1)
+    } else if flowcell_size == 126 {
+        FLONGLE_CHANNELS
+            .iter()
+            .find(|(_, &coordinates)| coordinates == (col, row))
+            .map(|(&channel, _)| channel)
+            .ok_or_else(|| "coordinates not found in FLONGLE_CHANNELS".to_string())
+    } else if flowcell_size == 512 {
+        MINION_CHANNELS
+            .iter()
+            .find(|(_, &coordinates)| coordinates == (col, row))
+            .map(|(&channel, _)| channel)
+            .ok_or_else(|| "coordinates not found in MINION_CHANNELS".to_string())
+    } else {
+        let custom_channels = CUSTOM_CHANNELS
+            .read()
+            .map_err(|_| "custom flowcell registry is poisoned".to_string())?;
+        match custom_channels.get(&flowcell_size) {
+            Some(channels) => channels
+                .iter()
+                .find(|(_, &coordinates)| coordinates == (col, row))
+                .map(|(&channel, _)| channel)
+                .ok_or_else(|| "coordinates not found in registered flowcell".to_string()),
+            None => Err("flowcell_size is not recognized".to_string()),
+        }
     }
 }
 
@@ -160,12 +297,14 @@ fn get_flowcell_array(flowcell_size: usize) -> Array2<usize> {
 /// * `axis` - The axis along which to split the flowcell (0 for rows, 1 for columns).
 /// * `odd_even` - Specifies whether to return the flowcell divided into odd and even channels.
 ///
-/// # Panics
+/// # Errors
 ///
-/// This function may panic in the following cases:
+/// This function returns an error in the following cases:
 ///
 /// * If `split` is 0, indicating an invalid value for the number of sections.
+/// * If `axis` is neither 0 nor 1.
 /// * If the target axis dimension cannot be evenly divided by `split`, resulting in an uneven split.
+/// * If the `flowcell_size` is not recognized (not equal to 3000, 126, or 512).
 ///
 /// # Examples
 ///
@@ -173,7 +312,7 @@ fn get_flowcell_array(flowcell_size: usize) -> Array2<usize> {
 /// use crate::{generate_flowcell, get_flowcell_array};
 /// use ndarray::array;
 ///
-/// generate_flowcell(512, 4, 1, false);
+/// generate_flowcell(512, 4, 1, false)?;
 /// // splits the flowcell into vertical quarters
 /// // ########........................
 /// // ########........................
@@ -181,7 +320,7 @@ fn get_flowcell_array(flowcell_size: usize) -> Array2<usize> {
 /// // ########........................
 ///
 /// // moving across the flowcell quarters, where # is the a channel in this split.
-/// generate_flowcell(512, 4, 1, false);
+/// generate_flowcell(512, 4, 1, false)?;
 /// // [
 /// //    [121, 113, 105, 97, 185, 177, 169, 161, ... 129],
 /// //    [249, 241, 233, 225, 313, 305, 297, 289, ... 257],
@@ -189,7 +328,7 @@ fn get_flowcell_array(flowcell_size: usize) -> Array2<usize> {
 /// //    [505, 497, 489, 481, 57, 49, 41, 33, 506, ... 1]
 /// // ]
 ///
-/// generate_flowcell(512, 2, 1, true);
+/// generate_flowcell(512, 2, 1, true)?;
 /// // [
 /// //    [1, 3, 5, 7, 9, 11, 13,...
 /// //    [2, 4, 6, 8, 10, 12, 14,...
@@ -199,25 +338,35 @@ pub fn generate_flowcell(
     split: usize,
     axis: usize,
     odd_even: bool,
-) -> Vec<Vec<usize>> {
+) -> Result<Vec<Vec<usize>>, String> {
     if odd_even {
-        return vec![
+        return Ok(vec![
             (1..=flowcell_size).step_by(2).collect(),
             (2..=flowcell_size).step_by(2).collect(),
-        ];
+        ]);
     }
 
-    let arr: Array2<usize> = get_flowcell_array(flowcell_size);
-
     if split == 0 {
-        panic!("split must be a positive integer");
+        return Err("split must be a positive integer".to_string());
+    }
+    if axis > 1 {
+        return Err(format!(
+            "axis must be 0 for rows or 1 for columns, not {}",
+            axis
+        ));
     }
+    // Check the flowcell size is recognised before building the array
+    get_coords(1, flowcell_size)?;
+    let arr: Array2<usize> = get_flowcell_array(flowcell_size);
 
     let (dim1, dim2) = arr.dim();
     let target_dim = if axis == 0 { dim1 } else { dim2 };
 
     if target_dim % split != 0 {
-        panic!("The flowcell cannot be split evenly");
+        return Err(format!(
+            "The flowcell cannot be split evenly along axis {} (size {}, split {})",
+            axis, target_dim, split
+        ));
     }
     let axis_ = Axis(axis);
     let split_flowcell = arr
@@ -225,10 +374,284 @@ pub fn generate_flowcell(
         .map(|x| x.iter().cloned().collect())
         .collect::<Vec<Vec<usize>>>();
 
-    split_flowcell
+    Ok(split_flowcell)
+}
+
+/// Generates a flowcell divided into sections of unequal size, such as a 30/70 split, rather than the even split of
+/// [`generate_flowcell`].
+///
+/// The rows (axis 0) or columns (axis 1) of the flowcell layout are allocated to each section in turn, as contiguous
+/// blocks. Each section gets `proportion * number of rows or columns`, rounded down, and the last section gets any
+/// remainder. As in [`generate_flowcell`], positions in the layout with no channel are included as 0.
+///
+/// # Arguments
+///
+/// * `flowcell_size` - The total number of channels on the flowcell.
+/// * `proportions` - The fraction of the flowcell given to each section, which must sum to 1.0.
+/// * `axis` - The axis along which to split the flowcell (0 for rows, 1 for columns).
+///
+/// # Errors
+///
+/// This function returns an error in the following cases:
+///
+/// * If `proportions` is empty, contains a value that isn't positive, or doesn't sum to 1.0.
+/// * If `axis` is not 0 or 1.
+/// * If a section is too small to be given a whole row or column.
+/// * If the `flowcell_size` is not recognised, see [`get_coords`].
+///
+/// # Examples
+///
+/// ```
+/// use readfish_tools::nanopore::generate_flowcell_proportional;
+///
+/// // 9 of the 32 columns of a MinION flowcell, and the remaining 23
+/// let sections = generate_flowcell_proportional(512, &[0.3, 0.7], 1).unwrap();
+/// assert_eq!(sections[0].len(), 9 * 16);
+/// assert_eq!(sections[1].len(), 23 * 16);
+/// assert!(generate_flowcell_proportional(512, &[0.3, 0.6], 1).is_err());
+/// ```
+pub fn generate_flowcell_proportional(
+    flowcell_size: usize,
+    proportions: &[f64],
+    axis: usize,
+) -> Result<Vec<Vec<usize>>, String> {
+    if proportions.is_empty() {
+        return Err("proportions must contain at least one section".to_string());
+    }
+    if let Some(proportion) = proportions
+        .iter()
+        .find(|proportion| !(proportion.is_finite() && **proportion > 0.0))
+    {
+        return Err(format!("proportion {} must be positive", proportion));
+    }
+    let total: f64 = proportions.iter().sum();
+    if (total - 1.0).abs() > 1e-6 {
+        return Err(format!("proportions must sum to 1.0, not {}", total));
+    }
+    if axis > 1 {
+        return Err(format!(
+            "axis must be 0 for rows or 1 for columns, not {}",
+            axis
+        ));
+    }
+    // Check the flowcell size is recognised before building the array
+    get_coords(1, flowcell_size)?;
+    let arr: Array2<usize> = get_flowcell_array(flowcell_size);
+    let axis_ = Axis(axis);
+    let target_dim = arr.len_of(axis_);
+
+    let mut start = 0;
+    let mut sections = Vec::with_capacity(proportions.len());
+    for (index, proportion) in proportions.iter().enumerate() {
+        let end = if index == proportions.len() - 1 {
+            target_dim
+        } else {
+            start + (proportion * target_dim as f64).floor() as usize
+        };
+        if end <= start || end > target_dim {
+            return Err(format!(
+                "proportion {} is too small to be given a whole row or column along axis {} (size {})",
+                proportion, axis, target_dim
+            ));
+        }
+        sections.push(
+            arr.slice_axis(axis_, Slice::from(start..end))
+                .iter()
+                .cloned()
+                .collect(),
+        );
+        start = end;
+    }
+    Ok(sections)
+}
+
+/// Maps each channel on a flowcell to the index of the section it is in, using the same split as [`generate_flowcell`].
+///
+/// # Arguments
+///
+/// * `flowcell_size` - The total number of channels on the flowcell.
+/// * `split` - The number of sections to split the flowcell into.
+/// * `axis` - The axis along which to split the flowcell (0 for rows, 1 for columns).
+/// * `odd_even` - Specifies whether to split the flowcell into odd and even channels.
+///
+/// # Returns
+///
+/// A `HashMap` of channel number to the index of its section in the output of [`generate_flowcell`].
+///
+/// # Errors
+///
+/// Returns an error in the same cases as [`generate_flowcell`].
+///
+/// # Examples
+///
+/// ```
+/// use readfish_tools::nanopore::channel_section_map;
+///
+/// let sections = channel_section_map(512, 2, 1, true).unwrap();
+/// assert_eq!(sections[&1], 0);
+/// assert_eq!(sections[&2], 1);
+/// ```
+pub fn channel_section_map(
+    flowcell_size: usize,
+    split: usize,
+    axis: usize,
+    odd_even: bool,
+) -> Result<HashMap<usize, usize>, String> {
+    Ok(generate_flowcell(flowcell_size, split, axis, odd_even)?
+        .into_iter()
+        .enumerate()
+        .flat_map(|(section, channels)| channels.into_iter().map(move |channel| (channel, section)))
+        // Padding positions in the flowcell array that have no channel are 0
+        .filter(|(channel, _)| *channel != 0)
+        .collect())
+}
+
+/// The characters used to draw each section of a flowcell in [`render_flowcell`], cycled if there are more sections.
+const SECTION_CHARACTERS: [char; 10] = ['#', '.', '+', 'o', '*', 'x', '=', '~', '@', '%'];
+
+/// Draws the flowcell grid as text, with a different character for the channels in each section of the split.
+///
+/// The grid has the same orientation as [`get_flowcell_array`], and the sections are those of [`generate_flowcell`],
+/// so the first section is drawn with `#`, the second with `.` and so on. Positions in the grid with no channel are
+/// drawn as spaces.
+///
+/// # Arguments
+///
+/// * `flowcell_size` - The total number of channels on the flowcell.
+/// * `split` - The number of sections to split the flowcell into.
+/// * `axis` - The axis along which to split the flowcell (0 for rows, 1 for columns).
+/// * `odd_even` - Specifies whether to split the flowcell into odd and even channels.
+///
+/// # Errors
+///
+/// Returns an error in the same cases as [`generate_flowcell`].
+///
+/// # Examples
+///
+/// ```
+/// use readfish_tools::nanopore::render_flowcell;
+///
+/// let flowcell = render_flowcell(512, 4, 1, false).unwrap();
+/// assert_eq!(flowcell.lines().next(), Some("########........++++++++oooooooo"));
+/// ```
+pub fn render_flowcell(
+    flowcell_size: usize,
+    split: usize,
+    axis: usize,
+    odd_even: bool,
+) -> Result<String, String> {
+    let sections = channel_section_map(flowcell_size, split, axis, odd_even)?;
+    let arr = get_flowcell_array(flowcell_size);
+    let rendered = arr
+        .rows()
+        .into_iter()
+        .map(|row| {
+            row.iter()
+                .map(|channel| match sections.get(channel) {
+                    Some(section) => SECTION_CHARACTERS[section % SECTION_CHARACTERS.len()],
+                    None => ' ',
+                })
+                .collect::<String>()
+        })
+        .join("\n");
+    Ok(rendered)
+}
+
+/// A readfish `[[regions]]` entry, with only the name and channels of the region, written by [`regions_toml`].
+#[derive(Debug, Serialize)]
+struct RegionChannels<'a> {
+    /// The name of the region.
+    name: &'a str,
+    /// The channels of the region, in ascending order.
+    channels: Vec<usize>,
+}
+
+/// The `[[regions]]` array of tables of a readfish TOML, written by [`regions_toml`].
+#[derive(Debug, Serialize)]
+struct RegionsToml<'a> {
+    /// One entry per section of the flowcell.
+    regions: Vec<RegionChannels<'a>>,
+}
+
+/// Converts a flowcell split, from [`generate_flowcell`], into readfish `[[regions]]` TOML entries, each with a
+/// `name` and a `channels` list, ready to be pasted into a readfish TOML and filled in with targets.
+///
+/// # Arguments
+///
+/// * `flowcell_size` - The total number of channels on the flowcell.
+/// * `sections` - The channels of each section of the flowcell. Padding channels of 0 are ignored.
+/// * `names` - The name of the region for each section, in the same order as `sections`.
+///
+/// # Errors
+///
+/// Returns an error if the number of names differs from the number of sections, or if the sections don't cover
+/// every channel from 1 to `flowcell_size` exactly once.
+///
+/// # Examples
+///
+/// ```
+/// use readfish_tools::nanopore::{generate_flowcell, regions_toml};
+///
+/// let sections = generate_flowcell(126, 2, 0, true).unwrap();
+/// let toml = regions_toml(126, &sections, &["control", "analysis"]).unwrap();
+/// assert!(toml.starts_with("[[regions]]\nname = \"control\"\nchannels = [1, 3, 5,"));
+/// assert!(regions_toml(126, &sections, &["control"]).is_err());
+/// ```
+pub fn regions_toml(
+    flowcell_size: usize,
+    sections: &[Vec<usize>],
+    names: &[impl AsRef<str>],
+) -> Result<String, String> {
+    if names.len() != sections.len() {
+        return Err(format!(
+            "{} region names were given for {} sections",
+            names.len(),
+            sections.len()
+        ));
+    }
+    let mut seen = vec![false; flowcell_size];
+    let mut regions = Vec::with_capacity(sections.len());
+    for (name, channels) in names.iter().zip(sections) {
+        let channels: Vec<usize> = channels
+            .iter()
+            .copied()
+            .filter(|channel| *channel != 0)
+            .sorted()
+            .collect();
+        for &channel in &channels {
+            if channel > flowcell_size {
+                return Err(format!(
+                    "channel {} is above flowcell_size {}",
+                    channel, flowcell_size
+                ));
+            }
+            if std::mem::replace(&mut seen[channel - 1], true) {
+                return Err(format!("channel {} is in more than one region", channel));
+            }
+        }
+        regions.push(RegionChannels {
+            name: name.as_ref(),
+            channels,
+        });
+    }
+    if let Some(missing) = seen.iter().position(|seen| !seen) {
+        return Err(format!("channel {} is not in any region", missing + 1));
+    }
+    toml::to_string(&RegionsToml { regions }).map_err(|err| err.to_string())
+}
+
+/// The scaling between the units used by [`format_bases_with`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BaseScale {
+    /// Powers of 1000, with units of Kb, Mb, Gb and Tb.
+    #[default]
+    Si,
+    /// Powers of 1024, with units of Kib, Mib, Gib and Tib.
+    Binary,
 }
 
-/// Formats a given number of bases into a human-readable string with appropriate units (Kb, Mb, Gb, etc.).
+/// Formats a given number of bases into a human-readable string with appropriate units (Kb, Mb, Gb or Tb), to 2
+/// decimal places. See [`format_bases_with`] to choose the precision and scaling.
 ///
 /// # Arguments
 ///
@@ -240,30 +663,59 @@ pub fn generate_flowcell(
 ///
 /// # Examples
 ///
-/// ```rust,ignore
+/// ```rust
+/// use readfish_tools::nanopore::format_bases;
+/// assert_eq!(format_bases(999), "999 b");
 /// assert_eq!(format_bases(1_000), "1.00 Kb");
-/// assert_eq!(format_bases(1_000_000), "1.00 Mb");
 /// assert_eq!(format_bases(1_630_000), "1.63 Mb");
 /// assert_eq!(format_bases(1_000_000_000), "1.00 Gb");
 /// ```
 pub fn format_bases(number: usize) -> String {
-    let number = number as f64;
-    let units = ["", "K", "M", "G", "T", "P", "E", "Z", "Y"];
-    let base = 1000.0;
+    format_bases_with(number, 2, BaseScale::Si)
+}
 
-    if number.abs() < base {
+/// Formats a given number of bases into a human-readable string, with the largest unit up to Tb that the number
+/// reaches. Numbers below the first unit are written as whole bases.
+///
+/// A number that rounds up to the next unit at the given precision is written in the next unit, so 999,999 bases are
+/// `1.00 Mb` rather than `1000.00 Kb`. Numbers of 1000 Tb or more stay in Tb.
+///
+/// # Arguments
+///
+/// * `number` - The number of bases to be formatted.
+/// * `precision` - The number of decimal places to write.
+/// * `scale` - Whether each unit is 1000 ([`BaseScale::Si`]) or 1024 ([`BaseScale::Binary`]) times the last.
+///
+/// # Returns
+///
+/// A string representing the formatted number of bases with the appropriate unit.
+///
+/// # Examples
+///
+/// ```rust
+/// use readfish_tools::nanopore::{format_bases_with, BaseScale};
+/// assert_eq!(format_bases_with(1_630_000, 1, BaseScale::Si), "1.6 Mb");
+/// assert_eq!(format_bases_with(1_024, 2, BaseScale::Binary), "1.00 Kib");
+/// assert_eq!(format_bases_with(1_000, 2, BaseScale::Binary), "1000 b");
+/// ```
+pub fn format_bases_with(number: usize, precision: usize, scale: BaseScale) -> String {
+    let (base, units) = match scale {
+        BaseScale::Si => (1000.0, ["Kb", "Mb", "Gb", "Tb"]),
+        BaseScale::Binary => (1024.0, ["Kib", "Mib", "Gib", "Tib"]),
+    };
+    if (number as f64) < base {
         return format!("{} b", number);
     }
-
-    let exponent = (number.abs().log(base)).floor() as i32;
-    let unit_idx = if exponent >= 0 {
-        std::cmp::min(exponent as usize, units.len() - 1)
-    } else {
-        std::cmp::max(exponent as usize + units.len(), 0)
-    };
-
-    let formatted_number = number / base.powi(exponent);
-    format!("{:.2} {}b", formatted_number, units[unit_idx])
+    // Rounding to the precision can carry a number up to the base, so compare the rounded number
+    let rounds_below_base =
+        |value: f64| format!("{:.*}", precision, value).parse::<f64>().unwrap() < base;
+    let mut value = number as f64 / base;
+    let mut unit_idx = 0;
+    while unit_idx < units.len() - 1 && !rounds_below_base(value) {
+        value /= base;
+        unit_idx += 1;
+    }
+    format!("{:.*} {}", precision, value, units[unit_idx])
 }
 
 /// Calculate the running mean incrementally.
@@ -293,6 +745,392 @@ pub fn running_mean(mean: &mut isize, count: &mut isize, value: &mut isize) {
     *mean += (*value - *mean) / *count; // Update the running mean incrementally
 }
 
+/// Calculate the running mean incrementally for floating point values.
+///
+/// Behaves the same as [`running_mean`], but without the integer truncation.
+///
+/// # Arguments
+///
+/// * `mean`: A mutable reference to the current running mean.
+/// * `count`: A mutable reference to the count of elements seen so far.
+/// * `value`: The new value to be included in the running mean calculation.
+///
+/// # Example
+///
+/// ```
+/// use readfish_tools::nanopore::running_mean_f64;
+/// let mut mean = 0.0;
+/// let mut count = 0;
+/// running_mean_f64(&mut mean, &mut count, 0.5);
+/// running_mean_f64(&mut mean, &mut count, 1.0);
+/// assert_eq!(mean, 0.75);
+/// assert_eq!(count, 2);
+/// ```
+pub fn running_mean_f64(mean: &mut f64, count: &mut usize, value: f64) {
+    *count += 1;
+    *mean += (value - *mean) / *count as f64;
+}
+
+/// Merge a mean calculated over another set of values into a running mean, weighting each mean by its count.
+///
+/// # Arguments
+///
+/// * `mean`: A mutable reference to the running mean to merge into.
+/// * `count`: A mutable reference to the count of elements in the running mean.
+/// * `other_mean`: The mean of the other set of values.
+/// * `other_count`: The count of elements in the other set of values.
+///
+/// # Example
+///
+/// ```
+/// use readfish_tools::nanopore::merge_means_f64;
+/// let mut mean = 1.0;
+/// let mut count = 1;
+/// merge_means_f64(&mut mean, &mut count, 0.25, 3);
+/// assert_eq!(mean, 0.4375);
+/// assert_eq!(count, 4);
+/// ```
+pub fn merge_means_f64(mean: &mut f64, count: &mut usize, other_mean: f64, other_count: usize) {
+    let total_count = *count + other_count;
+    if total_count > 0 {
+        *mean = (*mean * *count as f64 + other_mean * other_count as f64) / total_count as f64;
+    }
+    *count = total_count;
+}
+
+/// Calculate the N50 of a set of read lengths.
+///
+/// The lengths are sorted in descending order, and the N50 is the length of the read at which
+/// the cumulative sum of lengths first reaches half of the total yield.
+///
+/// # Arguments
+///
+/// * `lengths`: A mutable slice of read lengths, which is sorted in place.
+///
+/// # Returns
+///
+/// The N50 of the read lengths, or 0 if `lengths` is empty.
+///
+/// # Example
+///
+/// ```
+/// use readfish_tools::nanopore::n50;
+/// let mut lengths = vec![2, 3, 4, 5, 6, 7, 8, 9, 10];
+/// assert_eq!(n50(&mut lengths), 8);
+/// assert_eq!(n50(&mut []), 0);
+/// ```
+pub fn n50(lengths: &mut [usize]) -> usize {
+    lengths.sort_unstable_by(|a, b| b.cmp(a));
+    let half_total = lengths.iter().sum::<usize>() as f64 / 2.0;
+    let mut cumulative = 0;
+    for length in lengths.iter() {
+        cumulative += length;
+        if cumulative as f64 >= half_total {
+            return *length;
+        }
+    }
+    0
+}
+
+/// Calculate the median of a set of read lengths.
+///
+/// For an even number of lengths, the mean of the two middle lengths is returned,
+/// truncated to a whole number of bases.
+///
+/// # Arguments
+///
+/// * `lengths`: A slice of read lengths, which need not be sorted.
+///
+/// # Returns
+///
+/// The median of the read lengths, or 0 if `lengths` is empty.
+///
+/// # Example
+///
+/// ```
+/// use readfish_tools::nanopore::median;
+/// assert_eq!(median(&[5, 1, 3]), 3);
+/// assert_eq!(median(&[4, 1, 3, 2]), 2);
+/// assert_eq!(median(&[]), 0);
+/// ```
+pub fn median(lengths: &[usize]) -> usize {
+    if lengths.is_empty() {
+        return 0;
+    }
+    let mut sorted_lengths = lengths.to_vec();
+    sorted_lengths.sort_unstable();
+    let middle = sorted_lengths.len() / 2;
+    if sorted_lengths.len() % 2 == 1 {
+        sorted_lengths[middle]
+    } else {
+        (sorted_lengths[middle - 1] + sorted_lengths[middle]) / 2
+    }
+}
+
+/// Calculate the sample standard deviation of a set of read lengths.
+///
+/// # Arguments
+///
+/// * `lengths`: A slice of read lengths.
+///
+/// # Returns
+///
+/// The standard deviation of the read lengths, with a denominator of `n - 1`, or 0 if there are fewer than two
+/// lengths, rather than NaN.
+///
+/// # Example
+///
+/// ```
+/// use readfish_tools::nanopore::standard_deviation;
+/// assert_eq!(standard_deviation(&[2, 4, 4, 4, 5, 5, 7, 9]), (32.0_f64 / 7.0).sqrt());
+/// assert_eq!(standard_deviation(&[100]), 0.0);
+/// assert_eq!(standard_deviation(&[]), 0.0);
+/// ```
+pub fn standard_deviation(lengths: &[usize]) -> f64 {
+    if lengths.len() < 2 {
+        return 0.0;
+    }
+    let mean = lengths.iter().sum::<usize>() as f64 / lengths.len() as f64;
+    let sum_of_squares: f64 = lengths
+        .iter()
+        .map(|&length| (length as f64 - mean).powi(2))
+        .sum();
+    (sum_of_squares / (lengths.len() - 1) as f64).sqrt()
+}
+
+/// Calculate the first and third quartiles of a set of read lengths.
+///
+/// The quartiles are interpolated linearly between the closest ranks, as numpy's default `percentile` does, and
+/// truncated to a whole number of bases, as [`median`] is.
+///
+/// # Arguments
+///
+/// * `lengths`: A slice of read lengths, which need not be sorted.
+///
+/// # Returns
+///
+/// The `(Q1, Q3)` quartiles of the read lengths, or `(0, 0)` if `lengths` is empty.
+///
+/// # Example
+///
+/// ```
+/// use readfish_tools::nanopore::quartiles;
+/// assert_eq!(quartiles(&[5, 1, 4, 2, 3]), (2, 4));
+/// assert_eq!(quartiles(&[100, 200, 300, 400]), (175, 325));
+/// assert_eq!(quartiles(&[100]), (100, 100));
+/// assert_eq!(quartiles(&[]), (0, 0));
+/// ```
+pub fn quartiles(lengths: &[usize]) -> (usize, usize) {
+    if lengths.is_empty() {
+        return (0, 0);
+    }
+    let mut sorted_lengths = lengths.to_vec();
+    sorted_lengths.sort_unstable();
+    let quantile = |q: f64| {
+        let rank = q * (sorted_lengths.len() - 1) as f64;
+        let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+        let fraction = rank - lower as f64;
+        (sorted_lengths[lower] as f64
+            + (sorted_lengths[upper] as f64 - sorted_lengths[lower] as f64) * fraction)
+            as usize
+    };
+    (quantile(0.25), quantile(0.75))
+}
+
+/// Bin a set of read lengths into a histogram.
+///
+/// Bins are `bin_size` wide and cover 0 up to and including the longest read length.
+/// A `bin_size` of 0 is clamped to 1.
+///
+/// # Arguments
+///
+/// * `lengths`: A slice of read lengths.
+/// * `bin_size`: The width of each bin in bases.
+///
+/// # Returns
+///
+/// A vector of `(bin_start, count)` pairs, ordered by `bin_start`. Empty if `lengths` is empty.
+///
+/// # Example
+///
+/// ```
+/// use readfish_tools::nanopore::histogram;
+/// assert_eq!(histogram(&[5, 12, 18, 25], 10), vec![(0, 1), (10, 2), (20, 1)]);
+/// assert!(histogram(&[], 10).is_empty());
+/// ```
+pub fn histogram(lengths: &[usize], bin_size: usize) -> Vec<(usize, usize)> {
+    let bin_size = bin_size.max(1);
+    let max_length = match lengths.iter().max() {
+        Some(max_length) => *max_length,
+        None => return Vec::new(),
+    };
+    let mut counts = vec![0; max_length / bin_size + 1];
+    for length in lengths {
+        counts[length / bin_size] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(bin, count)| (bin * bin_size, count))
+        .collect()
+}
+
+/// Estimate the additional run time until a mean depth of coverage is reached, by extrapolating the yield linearly.
+///
+/// The yield rate is the least squares slope of the cumulative yield against time, so a series binned by
+/// [`ConditionSummary::yield_over_time`](crate::ConditionSummary::yield_over_time) can be passed straight in. Yield
+/// usually slows as pores are lost over a run, so pass only the latest part of the series to extrapolate from the
+/// current rate rather than the average rate.
+///
+/// # Arguments
+///
+/// * `yield_over_time`: `(seconds, cumulative_bases)` pairs, ordered by time.
+/// * `target_coverage`: The desired mean depth of coverage.
+/// * `genome_size`: The size in bases of the genome, or of the targeted regions, that the yield is spread over.
+///
+/// # Returns
+///
+/// The estimated run time after the last point of the series until the coverage is reached, which is zero if it has
+/// already been reached. `None` if the genome size is 0, there are fewer than 2 points, or the yield is flat or
+/// declining.
+///
+/// # Example
+///
+/// ```
+/// use readfish_tools::nanopore::time_to_coverage;
+/// use std::time::Duration;
+/// // 1,000 bases a second, and 100,000 more bases are needed for 2x coverage of 100,000 bases
+/// let yield_over_time = [(0, 0), (60, 60_000), (120, 120_000)];
+/// assert_eq!(time_to_coverage(&yield_over_time, 2.0, 100_000), Some(Duration::from_secs(80)));
+/// assert_eq!(time_to_coverage(&[(0, 500), (60, 500)], 2.0, 100_000), None);
+/// ```
+pub fn time_to_coverage(
+    yield_over_time: &[(u64, usize)],
+    target_coverage: f64,
+    genome_size: usize,
+) -> Option<Duration> {
+    let &(_, current_yield) = yield_over_time.last()?;
+    if genome_size == 0 {
+        return None;
+    }
+    let remaining_bases = target_coverage * genome_size as f64 - current_yield as f64;
+    if remaining_bases <= 0.0 {
+        return Some(Duration::ZERO);
+    }
+    if yield_over_time.len() < 2 {
+        return None;
+    }
+    let count = yield_over_time.len() as f64;
+    let mean_time = yield_over_time
+        .iter()
+        .map(|(time, _)| *time as f64)
+        .sum::<f64>()
+        / count;
+    let mean_yield = yield_over_time
+        .iter()
+        .map(|(_, bases)| *bases as f64)
+        .sum::<f64>()
+        / count;
+    let (covariance, variance) =
+        yield_over_time
+            .iter()
+            .fold((0.0, 0.0), |(covariance, variance), (time, bases)| {
+                let time_offset = *time as f64 - mean_time;
+                (
+                    covariance + time_offset * (*bases as f64 - mean_yield),
+                    variance + time_offset * time_offset,
+                )
+            });
+    if variance == 0.0 {
+        return None;
+    }
+    let bases_per_sec = covariance / variance;
+    if bases_per_sec <= 0.0 {
+        return None;
+    }
+    Some(Duration::from_secs_f64(remaining_bases / bases_per_sec))
+}
+
+/// Calculate the number of positions covered by a set of half-open intervals.
+///
+/// The intervals are sorted once by start position and merged in a single linear sweep,
+/// so overlapping intervals are only counted once.
+///
+/// # Arguments
+///
+/// * `intervals`: A mutable slice of `(start, end)` intervals, which is sorted in place.
+///
+/// # Returns
+///
+/// The total length covered by the merged intervals.
+///
+/// # Example
+///
+/// ```
+/// use readfish_tools::nanopore::merged_interval_length;
+/// let mut intervals = vec![(10, 20), (0, 5), (15, 30)];
+/// assert_eq!(merged_interval_length(&mut intervals), 25);
+/// ```
+pub fn merged_interval_length(intervals: &mut [(usize, usize)]) -> usize {
+    intervals.sort_unstable();
+    let mut covered = 0;
+    let mut current: Option<(usize, usize)> = None;
+    for &(start, end) in intervals.iter() {
+        match current {
+            Some((current_start, current_end)) if start <= current_end => {
+                current = Some((current_start, current_end.max(end)));
+            }
+            Some((current_start, current_end)) => {
+                covered += current_end - current_start;
+                current = Some((start, end));
+            }
+            None => current = Some((start, end)),
+        }
+    }
+    if let Some((current_start, current_end)) = current {
+        covered += current_end - current_start;
+    }
+    covered
+}
+
+/// Calculate the number of positions covered by both of two sets of merged half-open intervals.
+///
+/// Both sets must be sorted and non-overlapping, as left by [`crate::readfish::merge_intervals`], so the overlap is
+/// found in a single linear sweep over both, advancing past whichever interval ends first.
+///
+/// # Arguments
+///
+/// * `intervals`: Sorted, non-overlapping `(start, end)` intervals, such as the spans of alignments.
+/// * `other`: Sorted, non-overlapping `(start, end)` intervals, such as targets.
+///
+/// # Returns
+///
+/// The total length of the intersection of the two sets.
+///
+/// # Example
+///
+/// ```
+/// use readfish_tools::nanopore::intersection_length;
+/// let alignments = vec![(100, 260), (500, 600)];
+/// let targets = vec![(0, 200), (550, 650)];
+/// assert_eq!(intersection_length(&alignments, &targets), 150);
+/// ```
+pub fn intersection_length(intervals: &[(usize, usize)], other: &[(usize, usize)]) -> usize {
+    let (mut i, mut j) = (0, 0);
+    let mut covered = 0;
+    while i < intervals.len() && j < other.len() {
+        let (start, end) = intervals[i];
+        let (other_start, other_end) = other[j];
+        covered += end.min(other_end).saturating_sub(start.max(other_start));
+        if end < other_end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    covered
+}
+
 // Tests
 #[cfg(test)]
 mod tests {
@@ -331,18 +1169,58 @@ mod tests {
         assert_eq!(format_bases(1_000_000), "1.00 Mb");
         assert_eq!(format_bases(1_630_000), "1.63 Mb");
         assert_eq!(format_bases(1_000_000_000), "1.00 Gb");
+        assert_eq!(format_bases(0), "0 b");
+        assert_eq!(format_bases(999), "999 b");
+        assert_eq!(format_bases(999_999), "1.00 Mb");
+        assert_eq!(format_bases(999_994), "999.99 Kb");
+        assert_eq!(format_bases(1_000_000_000_000), "1.00 Tb");
+        assert_eq!(format_bases(2_000_000_000_000_000), "2000.00 Tb");
     }
+
+    #[test]
+    fn test_format_bases_with() {
+        assert_eq!(format_bases_with(1_630_000, 0, BaseScale::Si), "2 Mb");
+        assert_eq!(format_bases_with(1_634_000, 3, BaseScale::Si), "1.634 Mb");
+        assert_eq!(format_bases_with(1_023, 2, BaseScale::Binary), "1023 b");
+        assert_eq!(format_bases_with(1_024, 2, BaseScale::Binary), "1.00 Kib");
+        assert_eq!(
+            format_bases_with(1_048_576, 1, BaseScale::Binary),
+            "1.0 Mib"
+        );
+        assert_eq!(format_bases_with(1 << 40, 2, BaseScale::Binary), "1.00 Tib");
+    }
+
     #[test]
     fn test_generate_flowcell() {
-        let x = generate_flowcell(512, 2, 1, false);
+        let x = generate_flowcell(512, 2, 1, false).unwrap();
         assert_eq!(x.len(), 2);
         assert_eq!(x[0][0], 121_usize);
         assert_eq!(x[1][0], 377_usize)
     }
 
+    #[test]
+    fn test_generate_flowcell_errors() {
+        assert_eq!(
+            generate_flowcell(512, 0, 1, false),
+            Err("split must be a positive integer".to_string())
+        );
+        assert_eq!(
+            generate_flowcell(512, 5, 1, false),
+            Err("The flowcell cannot be split evenly along axis 1 (size 32, split 5)".to_string())
+        );
+        assert_eq!(
+            generate_flowcell(513, 2, 1, false),
+            Err("flowcell_size is not recognized".to_string())
+        );
+        assert_eq!(
+            generate_flowcell(512, 2, 2, false),
+            Err("axis must be 0 for rows or 1 for columns, not 2".to_string())
+        );
+    }
+
     #[test]
     fn test_generate_flowcell_odd_even() {
-        let x = generate_flowcell(512, 0, 0, true);
+        let x = generate_flowcell(512, 0, 0, true).unwrap();
         assert_eq!(x.len(), 2);
         assert_eq!(x[0][0], 1);
         assert_eq!(x[1][0], 2)
@@ -354,6 +1232,89 @@ mod tests {
         assert_eq!(fa.get((0, 0)).unwrap(), &121_usize)
     }
 
+    #[test]
+    fn test_channel_section_map() {
+        let sections = channel_section_map(512, 4, 1, false).unwrap();
+        assert_eq!(sections.len(), 512);
+        let split_flowcell = generate_flowcell(512, 4, 1, false).unwrap();
+        for (section, channels) in split_flowcell.iter().enumerate() {
+            for channel in channels {
+                assert_eq!(sections[channel], section);
+            }
+        }
+        assert_eq!(sections[&121], 0);
+        assert_eq!(sections[&1], 3);
+        let sections = channel_section_map(126, 2, 0, true).unwrap();
+        assert_eq!(sections.len(), 126);
+        assert!(!sections.contains_key(&0));
+    }
+
+    #[test]
+    fn test_render_flowcell() {
+        let flowcell = render_flowcell(512, 2, 0, false).unwrap();
+        let rows: Vec<&str> = flowcell.lines().collect();
+        assert_eq!(rows.len(), 16);
+        assert!(rows[..8].iter().all(|row| *row == "#".repeat(32)));
+        assert!(rows[8..].iter().all(|row| *row == ".".repeat(32)));
+        let flowcell = render_flowcell(512, 2, 1, true).unwrap();
+        assert_eq!(flowcell.matches('#').count(), 256);
+        assert_eq!(flowcell.matches('.').count(), 256);
+        assert!(render_flowcell(512, 5, 1, false).is_err());
+    }
+
+    #[test]
+    fn test_generate_flowcell_proportional() {
+        // Even proportions match the even split
+        let sections = generate_flowcell_proportional(512, &[0.25; 4], 1).unwrap();
+        assert_eq!(sections, generate_flowcell(512, 4, 1, false).unwrap());
+
+        // 30/70 across the 16 rows of a MinION flowcell, the remainder goes to the last section
+        let sections = generate_flowcell_proportional(512, &[0.3, 0.7], 0).unwrap();
+        assert_eq!(sections[0].len(), 4 * 32);
+        assert_eq!(sections[1].len(), 12 * 32);
+        let mut channels: Vec<usize> = sections.concat();
+        channels.sort();
+        assert_eq!(channels, (1..=512).collect::<Vec<usize>>());
+
+        assert!(generate_flowcell_proportional(512, &[], 1).is_err());
+        assert!(generate_flowcell_proportional(512, &[0.5, 0.6], 1).is_err());
+        assert!(generate_flowcell_proportional(512, &[1.5, -0.5], 1).is_err());
+        assert!(generate_flowcell_proportional(512, &[0.5, 0.5], 2).is_err());
+        assert!(generate_flowcell_proportional(512, &[0.01, 0.99], 0).is_err());
+        assert!(generate_flowcell_proportional(513, &[1.0], 0).is_err());
+    }
+
+    #[test]
+    fn test_regions_toml() {
+        let sections = generate_flowcell(512, 4, 1, false).unwrap();
+        let names = ["a", "b", "c", "d \"quoted\""];
+        let regions = regions_toml(512, &sections, &names).unwrap();
+        let parsed: toml::Value = toml::from_str(&regions).unwrap();
+        let parsed_regions = parsed["regions"].as_array().unwrap();
+        assert_eq!(parsed_regions.len(), 4);
+        assert_eq!(parsed_regions[3]["name"].as_str(), Some("d \"quoted\""));
+        let mut channels: Vec<i64> = parsed_regions
+            .iter()
+            .flat_map(|region| region["channels"].as_array().unwrap())
+            .map(|channel| channel.as_integer().unwrap())
+            .collect();
+        assert_eq!(channels.len(), 512);
+        channels.sort();
+        assert_eq!(channels, (1..=512).collect::<Vec<i64>>());
+
+        // Overlapping, missing and out of range channels are rejected
+        assert_eq!(
+            regions_toml(4, &[vec![1, 2], vec![2, 3, 4]], &["a", "b"]),
+            Err("channel 2 is in more than one region".to_string())
+        );
+        assert_eq!(
+            regions_toml(4, &[vec![1, 2], vec![0, 4]], &["a", "b"]),
+            Err("channel 3 is not in any region".to_string())
+        );
+        assert!(regions_toml(4, &[vec![1, 2], vec![3, 5]], &["a", "b"]).is_err());
+        assert!(regions_toml(4, &[vec![1, 2, 3, 4]], &["a", "b"]).is_err());
+    }
+
     #[test]
     #[should_panic]
     fn test_get_flowcell_array_panic() {
@@ -368,6 +1329,60 @@ mod tests {
         assert_eq!(get_coords(2, 3000).unwrap(), (1_usize, 0_usize));
     }
 
+    #[test]
+    fn test_channel_from_coords() {
+        assert_eq!(channel_from_coords(31, 1, 512).unwrap(), 2_usize);
+        assert_eq!(channel_from_coords(1, 9, 126).unwrap(), 2_usize);
+        assert_eq!(channel_from_coords(1, 0, 3000).unwrap(), 2_usize);
+    }
+
+    #[test]
+    fn test_channel_from_coords_round_trip() {
+        for flowcell_size in [126, 512, 3000] {
+            for channel in 1..=flowcell_size {
+                let (col, row) = get_coords(channel, flowcell_size).unwrap();
+                assert_eq!(
+                    channel_from_coords(col, row, flowcell_size).unwrap(),
+                    channel
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_channel_from_coords_panics() {
+        channel_from_coords(120, 0, 3000).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_channel_from_coords_panics_size() {
+        channel_from_coords(0, 0, 127).unwrap();
+    }
+
+    #[test]
+    fn test_register_flowcell() {
+        // 2 columns by 5 rows
+        let channels: HashMap<usize, (usize, usize)> = (1..=10)
+            .map(|channel| (channel, ((channel - 1) / 5, (channel - 1) % 5)))
+            .collect();
+        register_flowcell(10, channels).unwrap();
+        assert_eq!(get_coords(7, 10).unwrap(), (1, 1));
+        assert_eq!(channel_from_coords(1, 1, 10).unwrap(), 7);
+        let split_flowcell = generate_flowcell(10, 2, 1, false).unwrap();
+        assert_eq!(split_flowcell[0], vec![5, 4, 3, 2, 1]);
+        assert!(get_coords(11, 10).is_err());
+    }
+
+    #[test]
+    fn test_register_flowcell_errors() {
+        assert!(register_flowcell(512, HashMap::new()).is_err());
+        let channels: HashMap<usize, (usize, usize)> =
+            (0..4).map(|channel| (channel, (channel, 0))).collect();
+        assert!(register_flowcell(4, channels).is_err());
+    }
+
     #[test]
     #[should_panic]
     fn test_get_coords_panics() {
@@ -381,4 +1396,49 @@ mod tests {
         // Code that is expected to panic
         get_coords(10, 127).unwrap();
     }
+
+    #[test]
+    fn test_time_to_coverage() {
+        let yield_over_time = [(0, 0), (60, 60_000), (120, 120_000)];
+        assert_eq!(
+            time_to_coverage(&yield_over_time, 2.0, 100_000),
+            Some(Duration::from_secs(80))
+        );
+        // Already reached
+        assert_eq!(
+            time_to_coverage(&yield_over_time, 1.0, 100_000),
+            Some(Duration::ZERO)
+        );
+        // The slope is fitted through noisy points
+        let noisy = [(0, 0), (60, 70_000), (120, 110_000), (180, 180_000)];
+        let estimate = time_to_coverage(&noisy, 3.0, 100_000).unwrap();
+        assert!((estimate.as_secs_f64() - 120_000.0 / (17_400_000.0 / 18_000.0)).abs() < 1e-6);
+        // Flat, declining, too short or no genome
+        assert_eq!(time_to_coverage(&[(0, 500), (60, 500)], 2.0, 100_000), None);
+        assert_eq!(time_to_coverage(&[(0, 500), (60, 400)], 2.0, 100_000), None);
+        assert_eq!(time_to_coverage(&[(60, 500)], 2.0, 100_000), None);
+        assert_eq!(time_to_coverage(&[], 2.0, 100_000), None);
+        assert_eq!(time_to_coverage(&yield_over_time, 2.0, 0), None);
+    }
+
+    #[test]
+    fn test_intersection_length() {
+        // Thousands of overlapping spans, merged once, against disjoint targets
+        let mut spans: Vec<(usize, usize)> = (0..5000)
+            .map(|start| (start * 10, start * 10 + 100))
+            .chain([(100_000, 100_050)])
+            .collect();
+        crate::readfish::merge_intervals(&mut spans);
+        assert_eq!(spans, vec![(0, 50_090), (100_000, 100_050)]);
+        let targets = vec![(0, 10), (50_000, 60_000), (100_040, 200_000)];
+        assert_eq!(intersection_length(&spans, &targets), 10 + 90 + 10);
+        // Adjacent intervals don't intersect
+        assert_eq!(intersection_length(&[(0, 10)], &[(10, 20)]), 0);
+        assert_eq!(intersection_length(&[(0, 10)], &[]), 0);
+        // A target spanning several intervals
+        assert_eq!(
+            intersection_length(&[(0, 10), (20, 30), (40, 50)], &[(5, 45)]),
+            5 + 10 + 5
+        );
+    }
 }
@@ -5,23 +5,117 @@
 
 use crate::{
     readfish::Conf,
-    readfish_io::{reader, DynResult},
-    sequencing_summary::SeqSum,
-    Summary,
+    readfish_io::{try_reader, writer, DynResult},
+    sequencing_summary::{ChannelSource, SeqSum, SeqSumError, SeqSumRecord},
+    Summary, UNCLASSIFIED_CONDITION,
 };
 use lazy_static::lazy_static;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use regex::Regex;
+#[cfg(feature = "serde")]
+use serde::Serialize;
 use std::{
-    io::BufRead,
+    collections::HashMap,
+    fmt,
+    fs::create_dir_all,
+    io::{BufRead, Lines, Write},
+    ops::Range,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 lazy_static! {
     static ref PAF_TAG: Regex = Regex::new("(..):(.):(.*)").unwrap();
 }
 
+/// The header of the tab separated read assignments written by [`Paf::demultiplex`], with a row per alignment of its
+/// read ID, condition, whether it is on target (`true` or `false`) and the contig it aligned to, or `*` if unmapped.
+pub const ASSIGNMENTS_HEADER: &str = "read_id\tcondition\ton_target\tcontig";
+
+/// The format the read assignments are written in by [`Paf::demultiplex`], see [`Paf::set_assignments_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AssignmentsFormat {
+    /// Tab separated rows, under [`ASSIGNMENTS_HEADER`].
+    #[default]
+    Tsv,
+    /// Newline delimited JSON, with a [`ReadAssignment`] object per line, so consumers can parse each read as it is
+    /// written.
+    #[cfg(feature = "serde")]
+    Ndjson,
+}
+
+impl AssignmentsFormat {
+    /// Choose the format from the extension of the file the assignments are written to, ignoring a `.gz` suffix.
+    /// Files ending in `.ndjson` or `.jsonl` are written as [`AssignmentsFormat::Ndjson`], and any other file as
+    /// [`AssignmentsFormat::Tsv`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for a `.ndjson` or `.jsonl` file if readfish_tools was built without the `serde` feature.
+    pub fn from_path(path: impl AsRef<Path>) -> DynResult<AssignmentsFormat> {
+        let path = path.as_ref();
+        let path = match path.extension() {
+            Some(extension) if extension == "gz" => path.with_extension(""),
+            _ => path.to_path_buf(),
+        };
+        match path.extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature = "serde")]
+            Some("ndjson" | "jsonl") => Ok(AssignmentsFormat::Ndjson),
+            #[cfg(not(feature = "serde"))]
+            Some("ndjson" | "jsonl") => {
+                Err("Writing NDJSON assignments requires the serde feature".into())
+            }
+            _ => Ok(AssignmentsFormat::Tsv),
+        }
+    }
+
+    /// The header line to write before the assignments, if the format has one.
+    pub fn header(&self) -> Option<&'static str> {
+        match self {
+            AssignmentsFormat::Tsv => Some(ASSIGNMENTS_HEADER),
+            #[cfg(feature = "serde")]
+            AssignmentsFormat::Ndjson => None,
+        }
+    }
+}
+
+/// The assignment of an alignment of a read to a condition, written as a JSON object per line by
+/// [`AssignmentsFormat::Ndjson`].
+///
+/// The fields are serialised in this order, and are stable, so consumers can rely on them:
+/// - `read_id`: The read ID.
+/// - `condition`: The condition the read was assigned to.
+/// - `contig`: The contig the read aligned to, or `*` if it is unmapped.
+/// - `on_target`: Whether the alignment is on target.
+/// - `query_length`: The length of the read.
+/// - `identity`: The identity of the alignment, see [`PafRecord::identity`], or `null` if it has no alignment block.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ReadAssignment<'a> {
+    /// The read ID.
+    pub read_id: &'a str,
+    /// The condition the read was assigned to.
+    pub condition: &'a str,
+    /// The contig the read aligned to, or `*` if it is unmapped.
+    pub contig: &'a str,
+    /// Whether the alignment is on target.
+    pub on_target: bool,
+    /// The length of the read.
+    pub query_length: usize,
+    /// The identity of the alignment, see [`PafRecord::identity`].
+    pub identity: Option<f64>,
+}
+
+/// The number of lines read into memory at once by [`Paf::demultiplex_parallel`].
+#[cfg(feature = "rayon")]
+pub const PARALLEL_CHUNK_SIZE: usize = 100_000;
+
+/// Mapping quality value used by the PAF specification to denote a missing mapping quality.
+pub const MAPQ_MISSING: u8 = 255;
+
 /// Store metadata that is provided by a tuple in a call to parse_paf_by_iter in lib.rs.
-/// See also `[sequencing_summary::SeqSumInfo]`.
+/// See also [`crate::sequencing_summary::SeqSumRecord`].
 #[derive(Debug)]
 pub struct Metadata {
     /// The identifier for the read.
@@ -59,8 +153,161 @@ impl Metadata {
     }
 }
 
+/// The typed value of an optional SAM-like PAF tag, e.g. `tp:A:P` or `NM:i:12`, chosen by the type field of the tag.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    /// An integer tag (`i`), e.g. `cm:i:66`.
+    Int(i64),
+    /// A floating point tag (`f`), e.g. `de:f:0.0219`.
+    Float(f64),
+    /// A single printable character tag (`A`), e.g. `tp:A:P`.
+    Char(char),
+    /// A string tag (`Z`), or any other type, kept as written, e.g. `cg:Z:200M`.
+    Str(String),
+}
+
+impl Tag {
+    /// Parse the value of a tag according to its type field. A value that doesn't match its type, such as
+    /// `NM:i:one`, is kept as a [`Tag::Str`].
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_type` - The type field of the tag, the middle field of `XX:T:VALUE`.
+    /// * `value` - The value of the tag.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::Tag;
+    /// assert_eq!(Tag::parse("i", "12"), Tag::Int(12));
+    /// assert_eq!(Tag::parse("A", "P"), Tag::Char('P'));
+    /// assert_eq!(Tag::parse("i", "twelve"), Tag::Str("twelve".to_string()));
+    /// ```
+    pub fn parse(tag_type: &str, value: &str) -> Tag {
+        let typed = match tag_type {
+            "i" => value.parse().ok().map(Tag::Int),
+            "f" => value.parse().ok().map(Tag::Float),
+            "A" => value.parse().ok().map(Tag::Char),
+            _ => None,
+        };
+        typed.unwrap_or_else(|| Tag::Str(value.to_string()))
+    }
+
+    /// The value of the tag as an integer. A string tag is parsed as an integer, so `ch:Z:12` gives `12`, but a
+    /// floating point or character tag is a type mismatch.
+    ///
+    /// # Returns
+    ///
+    /// The integer value, or `None` if the tag isn't an integer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::Tag;
+    /// assert_eq!(Tag::Int(12).as_i64(), Some(12));
+    /// assert_eq!(Tag::Str("12".to_string()).as_i64(), Some(12));
+    /// assert_eq!(Tag::Float(0.5).as_i64(), None);
+    /// ```
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Tag::Int(value) => Some(*value),
+            Tag::Str(value) => value.parse().ok(),
+            Tag::Float(_) | Tag::Char(_) => None,
+        }
+    }
+
+    /// The value of the tag as a floating point number. Integer tags are widened, and string tags are parsed, but a
+    /// character tag is a type mismatch.
+    ///
+    /// # Returns
+    ///
+    /// The floating point value, or `None` if the tag isn't a number.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::Tag;
+    /// assert_eq!(Tag::Float(0.5).as_f64(), Some(0.5));
+    /// assert_eq!(Tag::Int(3).as_f64(), Some(3.0));
+    /// assert_eq!(Tag::Char('P').as_f64(), None);
+    /// ```
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Tag::Float(value) => Some(*value),
+            Tag::Int(value) => Some(*value as f64),
+            Tag::Str(value) => value.parse().ok(),
+            Tag::Char(_) => None,
+        }
+    }
+
+    /// The value of a string tag. Numbers and characters are a type mismatch, rather than being formatted, which
+    /// [`Tag`]'s `Display` implementation does.
+    ///
+    /// # Returns
+    ///
+    /// The string, or `None` if the tag isn't a string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::Tag;
+    /// assert_eq!(Tag::Str("200M".to_string()).as_str(), Some("200M"));
+    /// assert_eq!(Tag::Int(12).as_str(), None);
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::Str(value) => Some(value),
+            Tag::Int(_) | Tag::Float(_) | Tag::Char(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    /// Write the value of the tag, without its name or type.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tag::Int(value) => write!(f, "{}", value),
+            Tag::Float(value) => write!(f, "{}", value),
+            Tag::Char(value) => write!(f, "{}", value),
+            Tag::Str(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// An alignment of a read, from a line of a PAF file or a record of a BAM file, with the fields needed to classify the
+/// read and add it to a [`Summary`].
+///
+/// Classifying reads as on or off target, filtering them and updating the summary only go through this trait, so the
+/// logic is shared by [`PafRecord`] and the [`crate::bam::BamRecord`] wrapper over BAM records.
+pub trait AlignmentRecord {
+    /// The name of the query sequence (read).
+    fn query_name(&self) -> &str;
+    /// The length of the query sequence (read), including clipped bases.
+    fn query_length(&self) -> usize;
+    /// The name of the target sequence (reference), or `*` if the read is unmapped.
+    fn target_name(&self) -> &str;
+    /// The length of the target sequence (reference).
+    fn target_length(&self) -> usize;
+    /// The 0-based start position of the alignment on the target sequence.
+    fn target_start(&self) -> usize;
+    /// The end position of the alignment on the target sequence, exclusive.
+    fn target_end(&self) -> usize;
+    /// The strand of the alignment, `+`, `-`, or `*` if the read is unmapped.
+    fn strand(&self) -> char;
+    /// The mapping quality of the alignment, or `None` if it is missing.
+    fn mapping_quality(&self) -> Option<u8>;
+    /// The alignment block length, including gaps.
+    fn alignment_block_length(&self) -> usize;
+    /// The gap-compressed identity of the alignment in the range `0.0..=1.0`, or `None` if it can't be calculated.
+    fn identity(&self) -> Option<f64>;
+    /// The typed value of the optional tag with the given two character name, if present.
+    fn tag(&self, key: &str) -> Option<Tag>;
+    /// Whether the read has no alignment, so is neither on nor off target.
+    fn is_unmapped(&self) -> bool;
+}
+
 /// Store a PafRecord for quick unpacking to update the summary
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PafRecord {
     /// The name of the query sequence (read).
     pub query_name: String,
@@ -80,15 +327,16 @@ pub struct PafRecord {
     pub target_start: usize,
     /// The end position of the alignment on the target sequence (reference).
     pub target_end: usize,
-    /// The number of matching bases in the alignment.
+    /// The number of residue matches in the alignment (column 10).
     pub nmatch: usize,
-    /// The total length of the alignment.
+    /// The alignment block length, including gaps (column 11).
     pub aln_len: usize,
-    /// The mapping quality of the alignment.
-    pub mapq: usize,
+    /// The mapping quality of the alignment (column 12). A value of [`MAPQ_MISSING`] (255)
+    /// indicates that the mapping quality is not available.
+    pub mapq: u8,
     // pub cigar: CigarString,
-    // A vector of additional tags associated with the alignment.
-    // pub tags: Vec<String>,
+    /// The optional SAM-like tags associated with the alignment, e.g. `tp:A:P` or `cg:Z:10M`.
+    pub tags: Vec<String>,
     // pub tpos_aln: Vec<u64>,
     // pub qpos_aln: Vec<u64>,
     // pub long_cigar: CigarString,
@@ -96,69 +344,581 @@ pub struct PafRecord {
     // pub order: u64,
     // pub contained: bool,
 }
-/// Errors that can occur while parsing PAF (Pairwise mApping Format) files.
-#[derive(Debug)]
-pub enum Error {
+/// Errors that can occur while reading, parsing or demultiplexing PAF (Pairwise mApping Format) files.
+///
+/// Functions returning a [`DynResult`] box these errors, so the variant can be recovered with `downcast_ref::<PafError>()`.
+#[derive(Debug, thiserror::Error)]
+pub enum PafError {
+    /// The PAF file contains no data.
+    #[error("Error: empty file")]
+    EmptyFile,
+    /// An IO error occurred while opening or reading the PAF file.
+    #[error("Error: could not read PAF file: {0}")]
+    Io(#[from] std::io::Error),
+    /// A line of the PAF file is missing one of the first twelve columns, or one of them cannot be parsed.
+    #[error(
+        "Error: malformed PAF line{}, {reason}: {line}",
+        .line_number.map_or(String::new(), |line_number| format!(" {line_number}"))
+    )]
+    MalformedLine {
+        /// The 1-based number of the line in the PAF file, if known.
+        line_number: Option<usize>,
+        /// Why the line could not be parsed.
+        reason: String,
+        /// The offending line.
+        line: String,
+    },
+    /// A read has no custom `ch` or `ba` tag, and the value could not be found elsewhere.
+    #[error("Error: read {query_name} has no `{tag}` tag in the PAF file, and {reason}")]
+    MissingTag {
+        /// The name of the missing tag.
+        tag: &'static str,
+        /// The name of the read.
+        query_name: String,
+        /// Why the tag was required.
+        reason: &'static str,
+    },
+    /// A read could not be found in the sequencing summary file.
+    #[error("Error: sequencing summary record not found for read {query_name}")]
+    MissingSeqSumRecord {
+        /// The name of the read.
+        query_name: String,
+    },
     /// An error occurred while parsing the CIGAR string in the PAF record.
+    #[error("Error: could not parse CIGAR: {msg}")]
     PafParseCigar {
         /// The error message.
         msg: String,
     },
     /// An error occurred while parsing the CS (Coordinate System) tag in the PAF record.
+    #[error("Error: could not parse cs tag: {msg}")]
     PafParseCS {
         /// The error message.
         msg: String,
     },
     /// An error occurred while parsing an integer value in the PAF record.
+    #[error("Error: could not parse integer: {msg}")]
     ParseIntError {
         /// The error message.
         msg: String,
     },
     /// An error occurred while parsing a column in the PAF record.
+    #[error("Error: could not parse a mandatory column of the PAF record")]
     ParsePafColumn {},
 }
 
-/// A type alias for a Result with the error type specialized to `crate::paf::Error`.
-type PafResult<T> = Result<T, crate::paf::Error>;
+impl PafError {
+    /// Attach the line number of the PAF file to a [`PafError::MalformedLine`]. Other errors are returned unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let err = PafRecord::from_line("read123 200 0 200").unwrap_err().with_line_number(3);
+    /// assert!(err.to_string().starts_with("Error: malformed PAF line 3"));
+    /// ```
+    pub fn with_line_number(self, line_number: usize) -> PafError {
+        match self {
+            PafError::MalformedLine { reason, line, .. } => PafError::MalformedLine {
+                line_number: Some(line_number),
+                reason,
+                line,
+            },
+            err => err,
+        }
+    }
+}
+
+/// A type alias for a Result with the error type specialized to [`PafError`].
+pub type PafResult<T> = Result<T, PafError>;
 
 impl PafRecord {
     /// New paf record
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PafError::MalformedLine`] if there are fewer than 12 columns, or a [`PafError::ParsePafColumn`] if
+    /// any of the numeric columns cannot be parsed.
     pub fn new(t: Vec<&str>) -> PafResult<PafRecord> {
+        if t.len() < 12 {
+            return Err(PafError::MalformedLine {
+                line_number: None,
+                reason: format!("expected at least 12 columns but found {}", t.len()),
+                line: t.join(" "),
+            });
+        }
+        let columns: &[&str; 12] = t[..12].try_into().unwrap();
+        PafRecord::from_columns(columns, t[12..].iter().map(|tag| tag.to_string()).collect())
+    }
+
+    /// Build a [`PafRecord`] from the 12 mandatory PAF columns and the tags after them.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PafError::ParsePafColumn`] if any of the numeric columns cannot be parsed.
+    fn from_columns(t: &[&str; 12], tags: Vec<String>) -> PafResult<PafRecord> {
         // make the record
         let rec = PafRecord {
             query_name: t[0].to_string(),
             query_length: t[1]
                 .parse::<usize>()
-                .map_err(|_| Error::ParsePafColumn {})?,
+                .map_err(|_| PafError::ParsePafColumn {})?,
             query_start: t[2]
                 .parse::<usize>()
-                .map_err(|_| Error::ParsePafColumn {})?,
+                .map_err(|_| PafError::ParsePafColumn {})?,
             query_end: t[3]
                 .parse::<usize>()
-                .map_err(|_| Error::ParsePafColumn {})?,
-            strand: t[4].parse::<char>().map_err(|_| Error::ParsePafColumn {})?,
+                .map_err(|_| PafError::ParsePafColumn {})?,
+            strand: t[4]
+                .parse::<char>()
+                .map_err(|_| PafError::ParsePafColumn {})?,
             target_name: t[5].to_string(),
             target_length: t[6]
                 .parse::<usize>()
-                .map_err(|_| Error::ParsePafColumn {})?,
+                .map_err(|_| PafError::ParsePafColumn {})?,
             target_start: t[7]
                 .parse::<usize>()
-                .map_err(|_| Error::ParsePafColumn {})?,
+                .map_err(|_| PafError::ParsePafColumn {})?,
             target_end: t[8]
                 .parse::<usize>()
-                .map_err(|_| Error::ParsePafColumn {})?,
+                .map_err(|_| PafError::ParsePafColumn {})?,
             nmatch: t[9]
                 .parse::<usize>()
-                .map_err(|_| Error::ParsePafColumn {})?,
+                .map_err(|_| PafError::ParsePafColumn {})?,
             aln_len: t[10]
                 .parse::<usize>()
-                .map_err(|_| Error::ParsePafColumn {})?,
+                .map_err(|_| PafError::ParsePafColumn {})?,
             mapq: t[11]
-                .parse::<usize>()
-                .map_err(|_| Error::ParsePafColumn {})?,
+                .parse::<u8>()
+                .map_err(|_| PafError::ParsePafColumn {})?,
+            tags,
         };
         Ok(rec)
     }
+
+    /// Parse a single line of a PAF file into a [`PafRecord`].
+    ///
+    /// The line is split on ASCII whitespace and the 12 mandatory PAF columns are parsed.
+    /// Any optional SAM-like tags after the twelfth column are kept in [`PafRecord::tags`].
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - A line from a PAF file.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PafError::MalformedLine`] containing the offending line if there are fewer than 12 columns,
+    /// if any of the first 12 columns contains a `:`, which means a column is missing and a tag has taken its place,
+    /// or if any of the numeric columns cannot be parsed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 50 ch=1").unwrap();
+    /// assert_eq!(paf_record.query_length, 200);
+    /// assert_eq!(paf_record.target_name, "contig123");
+    /// assert!(PafRecord::from_line("read123 200 0 200 + contig123").is_err());
+    /// ```
+    pub fn from_line(line: &str) -> PafResult<PafRecord> {
+        let malformed = |reason: String| PafError::MalformedLine {
+            line_number: None,
+            reason,
+            line: line.to_string(),
+        };
+        // The mandatory columns are split into an array, so only the tags after them are collected
+        let mut fields = line.split_ascii_whitespace();
+        let mut t = [""; 12];
+        for (index, column) in t.iter_mut().enumerate() {
+            *column = fields.next().ok_or_else(|| {
+                malformed(format!("expected at least 12 columns but found {}", index))
+            })?;
+        }
+        // Check first 12 columns for missing items, assumes tags will have been brought forwards
+        if t.iter().any(|item| item.contains(':')) {
+            return Err(malformed(
+                "missing one of the first twelve columns, or values contain a :".to_string(),
+            ));
+        }
+        PafRecord::from_columns(&t, fields.map(|tag| tag.to_string()).collect())
+            .map_err(|_| malformed("could not parse the mandatory columns".to_string()))
+    }
+
+    /// Whether the read has no alignment, shown by a target name of `*` (as written by minimap2's
+    /// `--paf-no-hit`) or a zero-length alignment block.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let paf_record = PafRecord::from_line("read123 200 0 0 * * 0 0 0 0 0 0").unwrap();
+    /// assert!(paf_record.is_unmapped());
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 50").unwrap();
+    /// assert!(!paf_record.is_unmapped());
+    /// ```
+    pub fn is_unmapped(&self) -> bool {
+        self.target_name == "*" || self.aln_len == 0
+    }
+
+    /// Get the mapping quality of the alignment, or `None` if it is missing (255).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 255").unwrap();
+    /// assert_eq!(paf_record.mapping_quality(), None);
+    /// ```
+    pub fn mapping_quality(&self) -> Option<u8> {
+        if self.mapq == MAPQ_MISSING {
+            None
+        } else {
+            Some(self.mapq)
+        }
+    }
+
+    /// Get the value of the optional tag with the given two character name, if present. If the tag is repeated, the
+    /// last value is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 50 tp:A:P").unwrap();
+    /// assert_eq!(paf_record.tag_value("tp"), Some("P"));
+    /// assert_eq!(paf_record.tag_value("cg"), None);
+    /// ```
+    pub fn tag_value(&self, tag: &str) -> Option<&str> {
+        self.raw_tag(tag).map(|(_, value)| value)
+    }
+
+    /// Get the type field and value of the optional tag with the given two character name, if present. If the tag is
+    /// repeated, the last one is returned. Tokens that aren't `XX:T:VALUE` tags are skipped.
+    fn raw_tag(&self, tag: &str) -> Option<(&str, &str)> {
+        self.tags.iter().rev().find_map(|token| {
+            PAF_TAG
+                .captures(token)
+                .filter(|caps| &caps[1] == tag)
+                .and_then(|caps| Some((caps.get(2)?.as_str(), caps.get(3)?.as_str())))
+        })
+    }
+
+    /// Get the typed value of the optional tag with the given two character name, if present, parsed from
+    /// [`PafRecord::tags`] with [`Tag::parse`]. If the tag is repeated, the last value is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::{PafRecord, Tag};
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 50 tp:A:P NM:i:3 de:f:0.01").unwrap();
+    /// assert_eq!(paf_record.get_tag("tp"), Some(Tag::Char('P')));
+    /// assert_eq!(paf_record.get_tag("NM"), Some(Tag::Int(3)));
+    /// assert_eq!(paf_record.get_tag("de"), Some(Tag::Float(0.01)));
+    /// assert_eq!(paf_record.get_tag("cg"), None);
+    /// ```
+    pub fn get_tag(&self, key: &str) -> Option<Tag> {
+        self.raw_tag(key)
+            .map(|(tag_type, value)| Tag::parse(tag_type, value))
+    }
+
+    /// Get the value of the optional tag with the given two character name as an integer, such as the edit
+    /// distance `NM:i`. See [`Tag::as_i64`] for how other tag types are coerced. If the tag is repeated, the last
+    /// value is used.
+    ///
+    /// # Returns
+    ///
+    /// The integer value, or `None` if the tag is missing or isn't an integer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 50 NM:i:3 tp:A:P").unwrap();
+    /// assert_eq!(paf_record.tag_i64("NM"), Some(3));
+    /// assert_eq!(paf_record.tag_i64("tp"), None);
+    /// assert_eq!(paf_record.tag_i64("cm"), None);
+    /// ```
+    pub fn tag_i64(&self, key: &str) -> Option<i64> {
+        self.get_tag(key).as_ref().and_then(Tag::as_i64)
+    }
+
+    /// Get the value of the optional tag with the given two character name as a floating point number, such as the
+    /// divergence `de:f`. See [`Tag::as_f64`] for how other tag types are coerced. If the tag is repeated, the last
+    /// value is used.
+    ///
+    /// # Returns
+    ///
+    /// The floating point value, or `None` if the tag is missing or isn't a number.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 50 de:f:0.01 NM:i:3").unwrap();
+    /// assert_eq!(paf_record.tag_f64("de"), Some(0.01));
+    /// assert_eq!(paf_record.tag_f64("NM"), Some(3.0));
+    /// ```
+    pub fn tag_f64(&self, key: &str) -> Option<f64> {
+        self.get_tag(key).as_ref().and_then(Tag::as_f64)
+    }
+
+    /// Get the value of the optional string tag with the given two character name, such as the CIGAR `cg:Z`. See
+    /// [`Tag::as_str`]. If the tag is repeated, the last value is used.
+    ///
+    /// # Returns
+    ///
+    /// The string, or `None` if the tag is missing or isn't a string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 50 cg:Z:200M NM:i:3").unwrap();
+    /// assert_eq!(paf_record.tag_str("cg"), Some("200M"));
+    /// assert_eq!(paf_record.tag_str("NM"), None);
+    /// ```
+    pub fn tag_str(&self, key: &str) -> Option<&str> {
+        self.raw_tag(key)
+            .filter(|&(tag_type, value)| Tag::parse(tag_type, value).as_str().is_some())
+            .map(|(_, value)| value)
+    }
+
+    /// Get the gap-compressed identity of the alignment.
+    ///
+    /// If present, the gap-compressed per-base divergence `de:f:` tag emitted by minimap2 is used,
+    /// and the identity is `1.0 - de`. Otherwise the identity is calculated from the number of residue
+    /// matches divided by the alignment block length (columns 10 and 11).
+    ///
+    /// # Returns
+    ///
+    /// The identity in the range `0.0..=1.0`, or `None` if there is no `de` tag and the alignment
+    /// block length is 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 150 200 50 de:f:0.1").unwrap();
+    /// assert_eq!(paf_record.identity(), Some(0.9));
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 150 200 50").unwrap();
+    /// assert_eq!(paf_record.identity(), Some(0.75));
+    /// ```
+    pub fn identity(&self) -> Option<f64> {
+        if let Some(Tag::Float(divergence)) = self.get_tag("de") {
+            return Some(1.0 - divergence);
+        }
+        if self.aln_len == 0 {
+            None
+        } else {
+            Some(self.nmatch as f64 / self.aln_len as f64)
+        }
+    }
+
+    /// Parse the `cg:Z:` CIGAR tag of the alignment into `(length, operation)` pairs.
+    ///
+    /// The optional tags of the record are scanned for a `cg` tag, which is then parsed
+    /// as a run-length encoded CIGAR string. Valid operations are `MIDNSHP=X`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(ops))` - The parsed CIGAR operations.
+    /// * `Ok(None)` - If the record has no `cg` tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PafError::PafParseCigar`] if the CIGAR string is malformed, such as an unknown
+    /// operation (`10Q`), an operation missing its length, or a trailing length with no operation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let paf_record = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 50 cg:Z:100M2I98M").unwrap();
+    /// assert_eq!(paf_record.cigar_ops().unwrap(), Some(vec![(100, 'M'), (2, 'I'), (98, 'M')]));
+    /// ```
+    pub fn cigar_ops(&self) -> PafResult<Option<Vec<(u32, char)>>> {
+        let cigar = match self.tag_value("cg") {
+            Some(cigar) => cigar,
+            None => return Ok(None),
+        };
+        let mut ops = Vec::new();
+        let mut length = String::new();
+        for c in cigar.chars() {
+            if c.is_ascii_digit() {
+                length.push(c);
+            } else if "MIDNSHP=X".contains(c) {
+                let op_length = length.parse::<u32>().map_err(|_| PafError::PafParseCigar {
+                    msg: format!("Missing length for operation {} in CIGAR {}", c, cigar),
+                })?;
+                ops.push((op_length, c));
+                length.clear();
+            } else {
+                return Err(PafError::PafParseCigar {
+                    msg: format!("Invalid operation {} in CIGAR {}", c, cigar),
+                });
+            }
+        }
+        if !length.is_empty() {
+            return Err(PafError::PafParseCigar {
+                msg: format!("Trailing length with no operation in CIGAR {}", cigar),
+            });
+        }
+        Ok(Some(ops))
+    }
+}
+
+impl FromStr for PafRecord {
+    type Err = PafError;
+
+    /// Parse a line of a PAF file, see [`PafRecord::from_line`].
+    fn from_str(line: &str) -> PafResult<PafRecord> {
+        PafRecord::from_line(line)
+    }
+}
+
+impl AlignmentRecord for PafRecord {
+    fn query_name(&self) -> &str {
+        &self.query_name
+    }
+
+    fn query_length(&self) -> usize {
+        self.query_length
+    }
+
+    fn target_name(&self) -> &str {
+        &self.target_name
+    }
+
+    fn target_length(&self) -> usize {
+        self.target_length
+    }
+
+    fn target_start(&self) -> usize {
+        self.target_start
+    }
+
+    fn target_end(&self) -> usize {
+        self.target_end
+    }
+
+    fn strand(&self) -> char {
+        self.strand
+    }
+
+    fn mapping_quality(&self) -> Option<u8> {
+        PafRecord::mapping_quality(self)
+    }
+
+    fn alignment_block_length(&self) -> usize {
+        self.aln_len
+    }
+
+    fn identity(&self) -> Option<f64> {
+        PafRecord::identity(self)
+    }
+
+    fn tag(&self, key: &str) -> Option<Tag> {
+        self.get_tag(key)
+    }
+
+    fn is_unmapped(&self) -> bool {
+        PafRecord::is_unmapped(self)
+    }
+}
+
+impl fmt::Display for PafRecord {
+    /// Write the record as a tab separated PAF line, the 12 mandatory columns followed by any tags, without a
+    /// trailing newline. Parsing the line again gives back the same record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::PafRecord;
+    /// let line = "read123\t200\t0\t200\t+\tcontig123\t300\t0\t300\t200\t200\t50\ttp:A:P";
+    /// let paf_record: PafRecord = line.parse().unwrap();
+    /// assert_eq!(paf_record.to_string(), line);
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.query_name,
+            self.query_length,
+            self.query_start,
+            self.query_end,
+            self.strand,
+            self.target_name,
+            self.target_length,
+            self.target_start,
+            self.target_end,
+            self.nmatch,
+            self.aln_len,
+            self.mapq
+        )?;
+        for tag in &self.tags {
+            write!(f, "\t{}", tag)?;
+        }
+        Ok(())
+    }
+}
+
+/// Filters that exclude noisy alignments from the [`Summary`] before they are demultiplexed.
+///
+/// Each filter is off by default and they compose, so a record must pass every filter that is set. Records that
+/// fail are counted in [`Summary::filtered_out`]. Unmapped reads have no alignment, so are only filtered on
+/// their read length, and are otherwise counted as unmapped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadFilter {
+    /// The minimum mapping quality (column 12) of an alignment. A missing mapping quality ([`MAPQ_MISSING`]) is
+    /// not compared, and is only filtered by `exclude_missing_mapq`.
+    pub min_mapq: Option<u8>,
+    /// The minimum length of the read (column 2).
+    pub min_read_length: Option<usize>,
+    /// The minimum alignment block length (column 11).
+    pub min_block_len: Option<usize>,
+    /// Whether to exclude alignments with a missing mapping quality ([`MAPQ_MISSING`]). Defaults to `false`, so they
+    /// are kept.
+    pub exclude_missing_mapq: bool,
+}
+
+impl ReadFilter {
+    /// Whether the record passes every filter that is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The alignment to check.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use readfish_tools::paf::{PafRecord, ReadFilter};
+    /// let filter = ReadFilter { min_mapq: Some(20), min_read_length: Some(500), ..Default::default() };
+    /// let paf_record = PafRecord::from_line("read123 1000 0 1000 + contig123 3000 0 1000 900 1000 5").unwrap();
+    /// assert!(!filter.passes(&paf_record));
+    /// let paf_record = PafRecord::from_line("read123 1000 0 1000 + contig123 3000 0 1000 900 1000 255").unwrap();
+    /// assert!(filter.passes(&paf_record));
+    /// let filter = ReadFilter { exclude_missing_mapq: true, ..filter };
+    /// assert!(!filter.passes(&paf_record));
+    /// ```
+    pub fn passes(&self, record: &impl AlignmentRecord) -> bool {
+        if self
+            .min_read_length
+            .is_some_and(|min_read_length| record.query_length() < min_read_length)
+        {
+            return false;
+        }
+        if record.is_unmapped() {
+            return true;
+        }
+        let mapq_fails = match record.mapping_quality() {
+            Some(mapq) => self.min_mapq.is_some_and(|min_mapq| mapq < min_mapq),
+            None => self.exclude_missing_mapq,
+        };
+        let block_len_fails = self
+            .min_block_len
+            .is_some_and(|min_block_len| record.alignment_block_length() < min_block_len);
+        !(mapq_fails || block_len_fails)
+    }
 }
 
 /// A struct representing a PAF record reader and writers for demultiplexing.
@@ -202,12 +962,96 @@ pub struct Paf {
     pub paf_file: PathBuf,
     /// Reader for the Paf file.
     pub reader: Box<dyn BufRead + Send>,
+    /// Whether malformed lines abort demultiplexing, rather than being logged and skipped. Defaults to `false`.
+    pub strict: bool,
+    /// Whether reads missing from the sequencing summary abort demultiplexing, rather than being logged and skipped.
+    /// Defaults to `false`.
+    pub strict_seqsum: bool,
+    /// Whether each read is counted once in the read counts and yields, by its primary alignment, rather than once per
+    /// PAF line. Defaults to `false`.
+    pub deduplicate_reads: bool,
+    /// Whether the summaries of each thread keep the read lengths of their contigs, to calculate the contig N50s, see
+    /// [`Paf::set_keep_read_lengths`]. Defaults to `false`.
+    pub keep_read_lengths: bool,
+    /// Alignments that fail this filter are skipped, and counted in [`Summary::filtered_out`], see
+    /// [`Paf::set_read_filter`]. Defaults to no filtering.
+    pub read_filter: ReadFilter,
+    /// The format read assignments are written in, see [`Paf::set_assignments_format`]. Defaults to
+    /// [`AssignmentsFormat::Tsv`].
+    pub assignments_format: AssignmentsFormat,
     // / Multiple writes, one for each demultiplexed file.
     // pub writers: Vec<Box<dyn Write>>,
 }
 
-impl Paf {
-    /// Create a new `Paf` object with the given PAF file.
+/// Writes the PAF records of each condition to a file of their own, `<condition>.paf` in a directory, see
+/// [`Paf::demultiplex`].
+///
+/// Files are only created once a condition has a record, and stay open until the writers are dropped, so the records
+/// of several PAF files demultiplexed with the same writers are appended to the same file per condition.
+pub struct ConditionPafWriters {
+    /// The directory the PAF files are written to.
+    output_dir: PathBuf,
+    /// The open writer of each condition, keyed on the condition name.
+    writers: HashMap<String, Box<dyn Write>>,
+}
+
+impl ConditionPafWriters {
+    /// Create the writers, creating the output directory if it doesn't exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_dir` - The directory to write the PAF files to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the output directory cannot be created.
+    pub fn new(output_dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        create_dir_all(&output_dir)?;
+        Ok(ConditionPafWriters {
+            output_dir: output_dir.as_ref().to_path_buf(),
+            writers: HashMap::new(),
+        })
+    }
+
+    /// Write a PAF line unchanged to the file of its condition, creating the file if this is the first record of the
+    /// condition. Composite `region/barcode` conditions are written to `region/barcode.paf`.
+    ///
+    /// # Arguments
+    ///
+    /// * `condition_name` - The condition the record was assigned to.
+    /// * `line` - The PAF line of the record, without a trailing newline.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file of the condition cannot be created or written to.
+    pub fn write_line(&mut self, condition_name: &str, line: &str) -> std::io::Result<()> {
+        if !self.writers.contains_key(condition_name) {
+            // Composite region/barcode keys are written into a directory per region
+            let out_path = self.output_dir.join(format!("{}.paf", condition_name));
+            if let Some(parent) = out_path.parent() {
+                create_dir_all(parent)?;
+            }
+            self.writers
+                .insert(condition_name.to_string(), writer(&out_path)?);
+        }
+        writeln!(self.writers.get_mut(condition_name).unwrap(), "{}", line)
+    }
+
+    /// Flush the file of every condition.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be flushed.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        for paf_writer in self.writers.values_mut() {
+            paf_writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Paf {
+    /// Create a new `Paf` object with the given PAF file.
     ///
     /// This function creates a new `Paf` object by parsing the specified PAF file
     /// and initializing the `reader` field with the resulting buffered input reader.
@@ -224,6 +1068,7 @@ impl Paf {
     /// # Panics
     ///
     /// This function will panic if there is an error while parsing the PAF file or creating the buffered input reader.
+    /// Use [`Paf::try_new`] to handle the error instead.
     ///
     /// # Examples
     ///
@@ -237,10 +1082,142 @@ impl Paf {
     /// ```
     ///
     pub fn new(paf_file: impl AsRef<Path>) -> Paf {
-        Paf {
+        Paf::try_new(paf_file).unwrap()
+    }
+
+    /// Create a new `Paf` object with the given PAF file, returning an error rather than panicking
+    /// if the file cannot be opened or is not a valid PAF file.
+    ///
+    /// # Arguments
+    ///
+    /// * `paf_file`: An implementation of the `AsRef<Path>` trait representing the path to the PAF file.
+    ///
+    /// # Errors
+    ///
+    /// The same errors as [`open_paf_for_reading`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// use readfish_tools::paf::{Paf, PafError};
+    ///
+    /// match Paf::try_new("example.paf") {
+    ///     Ok(paf) => println!("Opened {}", paf.paf_file.display()),
+    ///     Err(PafError::EmptyFile) => eprintln!("example.paf is empty"),
+    ///     Err(err) => eprintln!("{err}"),
+    /// }
+    /// ```
+    pub fn try_new(paf_file: impl AsRef<Path>) -> PafResult<Paf> {
+        Ok(Paf {
             paf_file: paf_file.as_ref().to_path_buf(),
-            reader: open_paf_for_reading(paf_file).unwrap(),
+            reader: open_paf_for_reading(paf_file)?,
+            strict: false,
+            strict_seqsum: false,
+            deduplicate_reads: false,
+            keep_read_lengths: false,
+            read_filter: ReadFilter::default(),
+            assignments_format: AssignmentsFormat::default(),
             // writers: vec![],
+        })
+    }
+
+    /// Set whether malformed lines abort demultiplexing.
+    ///
+    /// This defaults to `false`, so malformed lines are logged with their line number and skipped, and counted in
+    /// [`Summary::skipped_lines`]. In strict mode the first malformed line is returned as a [`PafError::MalformedLine`].
+    ///
+    /// # Arguments
+    ///
+    /// * `strict` - `true` to error on malformed lines, `false` to skip them.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Set whether reads missing from the sequencing summary abort demultiplexing.
+    ///
+    /// This defaults to `false`, so reads that aren't in the sequencing summary are logged, skipped, and counted in
+    /// [`Summary::reads_missing_from_seqsum`]. In strict mode the first missing read is returned as a
+    /// [`PafError::MissingSeqSumRecord`], which catches a sequencing summary from a different run to the PAF file.
+    ///
+    /// # Arguments
+    ///
+    /// * `strict_seqsum` - `true` to error on reads missing from the sequencing summary, `false` to skip them.
+    pub fn set_strict_seqsum(&mut self, strict_seqsum: bool) {
+        self.strict_seqsum = strict_seqsum;
+    }
+
+    /// Set whether each read is counted once, rather than once per alignment.
+    ///
+    /// A read can have several PAF lines, a primary alignment and any secondary or supplementary alignments. This
+    /// defaults to `false`, so every line is counted as a read. When set, the consecutive lines of each read id are
+    /// grouped, and only the primary alignment is counted in the read counts and yields of its condition. The
+    /// primary alignment is the longest alignment block tagged `tp:A:P`, or the longest alignment block if the lines
+    /// have no `tp` tag. The other alignments still update the statistics of the contigs they are on, see
+    /// [`Summary::add_secondary_alignment`].
+    ///
+    /// The lines of a read must be consecutive, as minimap2 writes them.
+    ///
+    /// # Arguments
+    ///
+    /// * `deduplicate_reads` - `true` to count each read id once, `false` to count every alignment.
+    pub fn set_deduplicate_reads(&mut self, deduplicate_reads: bool) {
+        self.deduplicate_reads = deduplicate_reads;
+    }
+
+    /// Set whether the summaries built by [`Paf::demultiplex_parallel`] keep the read length of every alignment, to
+    /// calculate the N50 of each contig, see [`Summary::set_keep_read_lengths`]. [`Paf::demultiplex`] updates the
+    /// given summary, so follows its setting instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep_read_lengths` - `true` to keep the read lengths and calculate the N50 of each contig.
+    pub fn set_keep_read_lengths(&mut self, keep_read_lengths: bool) {
+        self.keep_read_lengths = keep_read_lengths;
+    }
+
+    /// Set the filters that alignments must pass to be demultiplexed.
+    ///
+    /// Alignments that fail are skipped before they are looked up in the sequencing summary, so aren't written out
+    /// per condition, and are counted in [`Summary::filtered_out`]. When deduplicating reads, filtered alignments
+    /// can't be chosen as the primary alignment of their read.
+    ///
+    /// # Arguments
+    ///
+    /// * `read_filter` - The filters to apply, see [`ReadFilter`].
+    pub fn set_read_filter(&mut self, read_filter: ReadFilter) {
+        self.read_filter = read_filter;
+    }
+
+    /// Set the format the read assignments passed to [`Paf::demultiplex`] are written in.
+    ///
+    /// # Arguments
+    ///
+    /// * `assignments_format` - The format to write, see [`AssignmentsFormat`].
+    pub fn set_assignments_format(&mut self, assignments_format: AssignmentsFormat) {
+        self.assignments_format = assignments_format;
+    }
+
+    /// Lazily iterate over the records in the PAF file.
+    ///
+    /// Each line is read from the already opened `reader` and parsed with [`PafRecord::from_line`] only
+    /// when the iterator is advanced, so arbitrarily large PAF files can be processed without loading
+    /// them into memory. As the records are consumed from the underlying reader, the records can only be iterated over once.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// use readfish_tools::paf::Paf;
+    ///
+    /// let mut paf = Paf::new("example.paf");
+    /// for record in paf.records() {
+    ///     let record = record?;
+    ///     println!("{} {}", record.query_name, record.target_name);
+    /// }
+    /// ```
+    pub fn records(&mut self) -> PafRecords<'_> {
+        PafRecords {
+            lines: (&mut self.reader).lines(),
+            line_number: 0,
         }
     }
 
@@ -249,8 +1226,8 @@ impl Paf {
     /// This function reads the PAF file line by line, parses each line, and processes the custom tags present in the PAF format.
     /// These custom tags are add by readfish's implementation summarise on the Aligner.
     /// If the `sequencing_summary` argument is provided, it retrieves the sequencing summary record for each line's query name.
-    /// The function processes custom tags in the PAF file and ensures they are present. If `sequencing_summary` is None and custom tags are missing,
-    /// the function will panic.
+    /// If `sequencing_summary` is None, the channel and barcode of each read are taken from the `ch` and `ba` custom tags instead,
+    /// and an error is returned if they are missing.
     ///
     /// If `sequencing_summary` is provided, the function retrieves the sequencing summary record for each query name using the `get_record` function.
     /// If a sequencing summary record is not found in the buffer, the function reads from the sequencing summary file until the record is found.
@@ -261,50 +1238,347 @@ impl Paf {
     ///
     /// - `toml`: A reference to the `Conf` struct, which contains configuration settings.
     /// - `sequencing_summary`: An optional mutable reference to the `SeqSum` struct, representing the sequencing summary file.
+    /// - `summary`: An optional mutable reference to a [`Summary`], which is updated with each PAF record.
+    /// - `output`: Optional [`ConditionPafWriters`] to write the demultiplexed PAF records to. One file is created per
+    ///   condition, named after the condition with a `.paf` suffix, and each PAF line is written unchanged to the file for
+    ///   its condition. Composite `region/barcode` conditions are written to `region/barcode.paf`. The writers can be
+    ///   shared by several PAF files, whose records are appended to the same file per condition.
+    /// - `assignments`: An optional writer to stream the assignment of every read to as it is classified, in the
+    ///   format set by [`Paf::set_assignments_format`]. By default this is a row of its read ID, condition, whether it
+    ///   is on target and the contig it aligned to, see [`ASSIGNMENTS_HEADER`]. A read with several alignments has a
+    ///   row per alignment, and reads that are filtered out or missing from the sequencing summary are left out. The
+    ///   header isn't written, so a writer can be shared by several PAF files.
     ///
     /// # Errors
     ///
     /// This function returns a `DynResult`, which is a specialized `Result` type with an error message.
-    /// An error is returned if there is any issue reading the PAF file or if the sequencing summary file is not found.
+    /// A [`PafError`] is returned if there is any issue reading the PAF file, if a line is malformed in strict mode
+    /// (see [`Paf::set_strict`]), if a read is not found in the sequencing summary, or if no sequencing summary is provided and a read is missing the `ch` tag.
     ///
     /// # Examples
     ///
     /// ```rust,ignore
     /// // Import necessary libraries
     /// use std::error::Error;
-    /// use my_crate::{SeqSum, Conf};
+    /// use my_crate::{SeqSum, Conf, Paf, ConditionPafWriters};
     ///
     /// // Create a new sequencing summary instance
     /// let mut sequencing_summary = SeqSum::from_file("path/to/sequencing_summary.toml")?;
     ///
     /// // Load the TOML configuration
-    /// let toml = Conf::from_file("path/to/config.toml")?;
+    /// let mut toml = Conf::from_file("path/to/config.toml")?;
     ///
     /// // Demultiplex the PAF file using the sequencing summary
-    /// sequencing_summary.demultiplex(&toml, Some(&mut sequencing_summary))?;
+    /// let mut paf = Paf::try_new("path/to/alignments.paf")?;
+    /// let mut output = ConditionPafWriters::new("demultiplexed")?;
+    /// paf.demultiplex(&mut toml, Some(&mut sequencing_summary), None, Some(&mut output), None)?;
     /// ```
     pub fn demultiplex(
         &mut self,
         _toml: &mut Conf,
         sequencing_summary: Option<&mut SeqSum>,
         mut summary: Option<&mut Summary>,
+        mut output: Option<&mut ConditionPafWriters>,
+        mut assignments: Option<&mut dyn Write>,
     ) -> DynResult<()> {
-        let seq_sum = sequencing_summary.unwrap();
+        let mut seq_sum = sequencing_summary;
+        log::debug!("Demultiplexing PAF file {}", self.paf_file.display());
+        // The alignments of the current read, when deduplicating reads
+        let mut read_alignments: Vec<ClassifiedRead> = Vec::new();
 
+        // A single line buffer is reused for every line, rather than allocating a String per line
+        let mut line_buffer = String::new();
+        let mut line_index = 0;
         // Remove multiple mappings from seq_sum dictionary only when the new Read Id is not the same as the old read_id
-        for line in open_paf_for_reading(self.paf_file.clone())?.lines() {
-            let (paf_record, read_on, condition_name) =
-                _parse_paf_line(line?, _toml, None, Some(seq_sum))?;
+        loop {
+            line_buffer.clear();
+            if self
+                .reader
+                .read_line(&mut line_buffer)
+                .map_err(PafError::from)?
+                == 0
+            {
+                break;
+            }
+            line_index += 1;
+            let line = line_buffer
+                .strip_suffix('\n')
+                .map(|line| line.strip_suffix('\r').unwrap_or(line))
+                .unwrap_or(&line_buffer);
+            let paf_record = match PafRecord::from_line(line) {
+                Ok(paf_record) => paf_record,
+                Err(err) => {
+                    skip_malformed_line(self.strict, err, line_index, summary.as_deref_mut())?;
+                    continue;
+                }
+            };
+            if !self.read_filter.passes(&paf_record) {
+                skip_filtered_read(&paf_record, summary.as_deref_mut());
+                continue;
+            }
+            let read = match classify_record(paf_record, _toml, None, seq_sum.as_deref_mut()) {
+                Ok(read) => read,
+                Err(err) => {
+                    skip_missing_read(self.strict_seqsum, err, summary.as_deref_mut())?;
+                    continue;
+                }
+            };
+            if let (Some(summary), Some((seq_sum_channel, tag_channel))) =
+                (summary.as_deref_mut(), read.channel_mismatch)
+            {
+                summary.add_channel_mismatch(&read.record.query_name, seq_sum_channel, tag_channel);
+            }
+            log::trace!(
+                "line {}: read {} is in condition {}, on target: {}",
+                line_index,
+                read.record.query_name,
+                read.condition_name,
+                read.read_on
+            );
+
+            if let Some(output) = output.as_mut() {
+                output.write_line(&read.condition_name, line)?;
+            }
+            if let Some(assignments) = assignments.as_mut() {
+                read.write_assignment(assignments, self.assignments_format)?;
+            }
 
             if let Some(summary) = summary.as_deref_mut() {
-                let condition_summary = summary.conditions(condition_name.as_str());
-                condition_summary.update(paf_record, read_on).unwrap();
+                if !self.deduplicate_reads {
+                    read.add_to(summary)?;
+                    continue;
+                }
+                if read_alignments
+                    .first()
+                    .is_some_and(|previous| previous.record.query_name != read.record.query_name)
+                {
+                    add_read_alignments(summary, &mut read_alignments)?;
+                }
+                read_alignments.push(read);
             }
         }
+        if let Some(output) = output {
+            output.flush()?;
+        }
+        if let Some(assignments) = assignments {
+            assignments.flush()?;
+        }
+        if let Some(summary) = summary {
+            add_read_alignments(summary, &mut read_alignments)?;
+            summary.finalize();
+        }
         Ok(())
     }
 }
 
+#[cfg(feature = "rayon")]
+impl Paf {
+    /// Demultiplexes the PAF file into a [`Summary`], parsing and summarising the records across a rayon thread pool.
+    ///
+    /// The PAF file is read in chunks of [`PARALLEL_CHUNK_SIZE`] lines. The lines of each chunk are parsed in parallel,
+    /// then the channel and barcode of each read are looked up in order, as the sequencing summary is read sequentially.
+    /// The reads are then classified and summarised in parallel, into a partial [`Summary`] per thread, which are
+    /// merged with [`Summary::merge`]. The result is the same as [`Paf::demultiplex`], but the records can't be written
+    /// out per condition. When deduplicating reads, see [`Paf::set_deduplicate_reads`], the primary alignment of each
+    /// read is chosen whilst the records are in order, so a read whose lines span two chunks is still counted once.
+    ///
+    /// # Arguments
+    ///
+    /// - `toml`: A reference to the `Conf` struct, which contains configuration settings.
+    /// - `sequencing_summary`: An optional mutable reference to the `SeqSum` struct, representing the sequencing summary file.
+    /// - `threads`: The number of threads in the pool. 0 uses rayon's default, the number of logical CPUs.
+    ///
+    /// # Returns
+    ///
+    /// The finalized [`Summary`] of every record in the PAF file.
+    ///
+    /// # Errors
+    ///
+    /// The same errors as [`Paf::demultiplex`], or an error if the thread pool cannot be built.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let conf = Conf::from_file("resources/human_barcode.toml");
+    /// let mut seq_sum = SeqSum::from_file("resources/seq_sum_PAK09329.txt")?;
+    /// let mut paf = Paf::try_new("resources/test_paf_barcode05_NA12878.chr.paf")?;
+    /// let summary = paf.demultiplex_parallel(&conf, Some(&mut seq_sum), 4)?;
+    /// ```
+    pub fn demultiplex_parallel(
+        &mut self,
+        _toml: &Conf,
+        sequencing_summary: Option<&mut SeqSum>,
+        threads: usize,
+    ) -> DynResult<Summary> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        let mut seq_sum = sequencing_summary;
+        let keep_read_lengths = self.keep_read_lengths;
+        let new_summary = move || {
+            let mut summary = Summary::new();
+            summary.set_keep_read_lengths(keep_read_lengths);
+            summary
+        };
+        let mut summary = new_summary();
+        let mut lines_read = 0;
+        // The alignments of the current read, when deduplicating reads, carried over between chunks
+        let mut read_alignments: Vec<(PafRecord, ReadLookup)> = Vec::new();
+        log::debug!(
+            "Demultiplexing PAF file {} with {} threads",
+            self.paf_file.display(),
+            pool.current_num_threads()
+        );
+        loop {
+            let chunk = (&mut self.reader)
+                .lines()
+                .take(PARALLEL_CHUNK_SIZE)
+                .collect::<Result<Vec<String>, _>>()
+                .map_err(PafError::from)?;
+            let finished = chunk.is_empty();
+            let first_line_number = lines_read + 1;
+            lines_read += chunk.len();
+            let parsed: Vec<PafResult<PafRecord>> = pool.install(|| {
+                chunk
+                    .par_iter()
+                    .map(|line| PafRecord::from_line(line))
+                    .collect()
+            });
+
+            let mut reads = Vec::with_capacity(parsed.len());
+            for (line_index, paf_record) in parsed.into_iter().enumerate() {
+                let paf_record = match paf_record {
+                    Ok(paf_record) => paf_record,
+                    Err(err) => {
+                        skip_malformed_line(
+                            self.strict,
+                            err,
+                            first_line_number + line_index,
+                            Some(&mut summary),
+                        )?;
+                        continue;
+                    }
+                };
+                if !self.read_filter.passes(&paf_record) {
+                    skip_filtered_read(&paf_record, Some(&mut summary));
+                    continue;
+                }
+                let lookup = match read_channel_and_barcode(
+                    &paf_record,
+                    _toml,
+                    None,
+                    seq_sum.as_deref_mut(),
+                ) {
+                    Ok(lookup) => lookup,
+                    Err(err) => {
+                        skip_missing_read(self.strict_seqsum, err, Some(&mut summary))?;
+                        continue;
+                    }
+                };
+                if let Some((seq_sum_channel, tag_channel)) = lookup.channel_mismatch {
+                    summary.add_channel_mismatch(
+                        &paf_record.query_name,
+                        seq_sum_channel,
+                        tag_channel,
+                    );
+                }
+                let alignment = (paf_record, lookup);
+                if !self.deduplicate_reads {
+                    reads.push((alignment, true));
+                    continue;
+                }
+                if read_alignments
+                    .first()
+                    .is_some_and(|(previous, ..)| previous.query_name != alignment.0.query_name)
+                {
+                    reads.extend(drain_read_alignments(
+                        &mut read_alignments,
+                        |(paf_record, ..)| paf_record,
+                    ));
+                }
+                read_alignments.push(alignment);
+            }
+            if finished {
+                reads.extend(drain_read_alignments(
+                    &mut read_alignments,
+                    |(paf_record, ..)| paf_record,
+                ));
+            }
+
+            let chunk_summary = pool.install(|| {
+                reads
+                    .into_par_iter()
+                    .try_fold(
+                        new_summary,
+                        |mut partial, ((paf_record, lookup), primary)| {
+                            let ReadLookup {
+                                channel,
+                                barcode,
+                                seq_sum_record,
+                                ..
+                            } = lookup;
+                            let (condition_name, read_on, targets) =
+                                classify_read(&paf_record, _toml, channel, barcode.as_deref())?;
+                            if primary {
+                                partial
+                                    .add_record(
+                                        &condition_name,
+                                        channel,
+                                        &paf_record,
+                                        read_on,
+                                        seq_sum_record,
+                                    )
+                                    .map_err(|err| err.to_string())?;
+                            } else {
+                                partial
+                                    .add_secondary_alignment(&condition_name, &paf_record, read_on)
+                                    .map_err(|err| err.to_string())?;
+                            }
+                            partial.add_target_hits(&condition_name, &paf_record, targets);
+                            Ok::<Summary, String>(partial)
+                        },
+                    )
+                    .try_reduce(new_summary, |mut summary, partial| {
+                        summary.merge(partial);
+                        Ok(summary)
+                    })
+            })?;
+            summary.merge(chunk_summary);
+            if finished {
+                break;
+            }
+        }
+        summary.finalize();
+        Ok(summary)
+    }
+}
+
+/// A streaming iterator over the records of a PAF file, created by [`Paf::records`].
+///
+/// Yields a [`PafResult`] holding each parsed [`PafRecord`], or the error encountered
+/// whilst reading or parsing the line. Malformed lines are reported with their line number.
+pub struct PafRecords<'a> {
+    /// The lines of the PAF file being iterated over.
+    lines: Lines<&'a mut Box<dyn BufRead + Send>>,
+    /// The number of lines read so far.
+    line_number: usize,
+}
+
+impl<'a> Iterator for PafRecords<'a> {
+    type Item = PafResult<PafRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.lines.next()?;
+        self.line_number += 1;
+        Some(match line {
+            Ok(line) => {
+                PafRecord::from_line(&line).map_err(|err| err.with_line_number(self.line_number))
+            }
+            Err(err) => Err(err.into()),
+        })
+    }
+}
+
 /// Parses the PAF file and returns a buffered reader for further processing.
 ///
 /// This function takes the `file_name` as an input and returns a `Result` containing
@@ -322,10 +1596,10 @@ impl Paf {
 /// # Errors
 ///
 /// The function returns an `Err` in the following cases:
-/// - If the file is empty (contains no bytes), it returns an error with the message "Error: empty file".
+/// - If the file is empty (contains no bytes), it returns [`PafError::EmptyFile`].
 /// - If the file format is invalid, specifically if any of the first twelve columns contains a ':' character,
-///   it returns an error with the message "Error: invalid format for PAF file. Missing one of first twelve columns, or values contain a :."
-/// - If there are any I/O errors while reading the file, the function returns an error with the specific I/O error message.
+///   it returns [`PafError::MalformedLine`] for line 1.
+/// - If the file does not exist, or there are any I/O errors while reading the file, it returns [`PafError::Io`].
 ///
 /// # Example
 ///
@@ -355,25 +1629,30 @@ impl Paf {
 ///     }
 /// }
 /// ```
-pub fn open_paf_for_reading(file_name: impl AsRef<Path>) -> DynResult<Box<dyn BufRead + Send>> {
-    // create reader to check file first line
-    let mut paf_file = reader(&file_name, None);
+pub fn open_paf_for_reading(file_name: impl AsRef<Path>) -> PafResult<Box<dyn BufRead + Send>> {
+    // A single reader is used, peeking at the buffered start of the file rather than consuming it
+    let mut paf_file = try_reader(&file_name, None)?;
 
     // Check the file isn't empty
-    let mut buffer = [0; 1];
-    let bytes_read = paf_file.read(&mut buffer)?;
-    if bytes_read == 0 {
-        return Err("Error: empty file".into());
+    let buffer = paf_file.fill_buf()?;
+    if buffer.is_empty() {
+        return Err(PafError::EmptyFile);
     }
-    let mut line = String::new();
-    paf_file.read_line(&mut line)?;
+    // Only the buffered portion of the first line is checked, which will always hold the first twelve columns in practice
+    let first_line = buffer
+        .split(|&byte| byte == b'\n')
+        .next()
+        .unwrap_or_default();
+    let line = String::from_utf8_lossy(first_line);
     let t: Vec<&str> = line.split_ascii_whitespace().collect();
     if t.iter().take(12).any(|item| item.contains(':')) {
-        return Err("Error: invalid format for Paf file. Missing one of first twelve columns, or values contain a :.".into());
+        return Err(PafError::MalformedLine {
+            line_number: Some(1),
+            reason: "missing one of the first twelve columns, or values contain a :".to_string(),
+            line: line.to_string(),
+        });
     }
 
-    let paf_file = reader(file_name, None);
-
     Ok(paf_file)
 }
 
@@ -382,7 +1661,8 @@ pub fn open_paf_for_reading(file_name: impl AsRef<Path>) -> DynResult<Box<dyn Bu
 /// This function takes a PAF line (as a reference to a string) and attempts to parse it to extract
 /// relevant information, including creating a [`PafRecord`] and making decisions based on the provided
 /// metadata or sequencing summary. It returns a tuple containing the `PafRecord`, a boolean value
-/// indicating if the read is considered "on-target", and the condition name associated with the read.
+/// indicating if the read is considered "on-target", the condition name associated with the read,
+/// and the matching sequencing summary record, if a sequencing summary was provided.
 ///
 /// # Arguments
 ///
@@ -396,12 +1676,22 @@ pub fn open_paf_for_reading(file_name: impl AsRef<Path>) -> DynResult<Box<dyn Bu
 /// A `DynResult` holding a tuple containing the following elements:
 /// * `PafRecord`: The parsed PAF record representing the alignment information.
 /// * `bool`: A boolean value indicating if the read is considered "on-target".
-/// * `&'a String`: A reference to the condition name associated with the read.
+/// * `String`: The condition key the read is summarised under, see [`Conf::condition_key`]. This is
+///   [`UNCLASSIFIED_CONDITION`] if the read matches no region or barcode.
+/// * `Option<SeqSumRecord>`: The sequencing summary record for the read, `None` if metadata was used instead.
 ///
-/// # Panics
+/// The barcode for the read is taken from the `ba` tag of the PAF record if present, otherwise from the metadata
+/// or the `barcode_arrangement` column of the sequencing summary.
 ///
-/// This function panics if the PAF line contains missing items in the first 12 columns or if both `meta_data`
-/// and `sequencing_summary` are `None`.
+/// # Errors
+///
+/// Returns a [`PafError::MalformedLine`] if the PAF line is missing items in the first 12 columns,
+/// a [`PafError::MissingSeqSumRecord`] if the read is not found in the sequencing summary, or a [`PafError::MissingTag`]
+/// if the TOML has barcodes but the read has no `ba` tag and the sequencing summary has no `barcode_arrangement` column.
+/// Any other error reading the sequencing summary, such as a malformed line, is returned as it is, so it isn't mistaken
+/// for a missing read.
+/// If both `meta_data` and `sequencing_summary` are `None`, the channel and barcode are taken from the `ch` and `ba` tags
+/// of the PAF record, and a [`PafError::MissingTag`] is returned if either is missing.
 ///
 /// # Examples
 ///
@@ -421,7 +1711,7 @@ pub fn open_paf_for_reading(file_name: impl AsRef<Path>) -> DynResult<Box<dyn Bu
 /// let result = _parse_paf_line(paf_line, &_toml, Some(&mut metadata), Some(&mut seq_sum));
 ///
 /// match result {
-///     Ok((paf_record, read_on, condition_name)) => {
+///     Ok((paf_record, read_on, condition_name, seq_sum_record)) => {
 ///         // Do something with the parsed data
 ///     }
 ///     Err(err) => {
@@ -429,88 +1719,447 @@ pub fn open_paf_for_reading(file_name: impl AsRef<Path>) -> DynResult<Box<dyn Bu
 ///     }
 /// }
 /// ```
-pub fn _parse_paf_line<'a>(
+pub fn _parse_paf_line(
     paf_line: impl AsRef<str>,
-    _toml: &'a Conf,
+    _toml: &Conf,
     meta_data: Option<&mut Metadata>,
     sequencing_summary: Option<&mut SeqSum>,
-) -> DynResult<(PafRecord, bool, &'a String)> {
-    let line = paf_line.as_ref();
-    let t: Vec<&str> = line.split_ascii_whitespace().collect();
-    // Todo do without clone
-    let paf_record = PafRecord::new(t.clone()).unwrap();
-    // Check first 12 columns for missing items, assumes tags will have been brought forwards
-    assert!(
-        t.iter().take(12).all(|item| !item.contains(':')),
-        "Missing colon in PAF line: {}",
-        line
+) -> DynResult<(PafRecord, bool, String, Option<SeqSumRecord>)> {
+    let paf_record = PafRecord::from_line(paf_line.as_ref())?;
+    _classify_paf_record(paf_record, _toml, meta_data, sequencing_summary)
+}
+
+/// Assigns a parsed [`PafRecord`] to a condition and decides whether it is on target.
+///
+/// This holds the logic shared by [`_parse_paf_line`] and the BAM reader, which converts each alignment into a
+/// [`PafRecord`] first. The read's channel and barcode are looked up from the metadata or sequencing summary, and
+/// used to find the condition the read belongs to.
+///
+/// # Arguments
+///
+/// * `paf_record`: The parsed alignment.
+/// * `_toml`: A reference to a `Conf` struct, holding configuration information.
+/// * `meta_data`: An optional mutable reference to a `Metadata` struct containing read metadata.
+/// * `sequencing_summary`: An optional mutable reference to a `SeqSum` struct containing sequencing summary data.
+///
+/// # Returns
+///
+/// The same tuple as [`_parse_paf_line`].
+///
+/// # Errors
+///
+/// The same errors as [`_parse_paf_line`].
+pub fn _classify_paf_record(
+    paf_record: PafRecord,
+    _toml: &Conf,
+    meta_data: Option<&mut Metadata>,
+    sequencing_summary: Option<&mut SeqSum>,
+) -> DynResult<(PafRecord, bool, String, Option<SeqSumRecord>)> {
+    let read = classify_record(paf_record, _toml, meta_data, sequencing_summary)?;
+    Ok((
+        read.record,
+        read.read_on,
+        read.condition_name,
+        read.seq_sum_record,
+    ))
+}
+
+/// A read that has been assigned to a condition, ready to be added to a [`Summary`].
+pub(crate) struct ClassifiedRead<R = PafRecord> {
+    /// The condition key the read was assigned to.
+    pub(crate) condition_name: String,
+    /// The channel the read was sequenced on.
+    pub(crate) channel: usize,
+    /// The alignment of the read.
+    pub(crate) record: R,
+    /// Whether the alignment is on target.
+    pub(crate) read_on: bool,
+    /// The indices of the merged targets of the condition that the alignment overlaps, empty if it is off target.
+    pub(crate) targets: Range<usize>,
+    /// The sequencing summary record of the read, if one was found.
+    pub(crate) seq_sum_record: Option<SeqSumRecord>,
+    /// The sequencing summary and PAF `ch` tag channels of the read, if they disagree.
+    pub(crate) channel_mismatch: Option<(usize, usize)>,
+}
+
+impl<R: AlignmentRecord> ClassifiedRead<R> {
+    /// Add the read to the summary, counting it in the targets it overlaps, see [`Summary::add_record`] and
+    /// [`Summary::add_target_hits`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the summary cannot be updated with the alignment.
+    pub(crate) fn add_to(self, summary: &mut Summary) -> DynResult<()> {
+        summary.add_record(
+            &self.condition_name,
+            self.channel,
+            &self.record,
+            self.read_on,
+            self.seq_sum_record,
+        )?;
+        summary.add_target_hits(&self.condition_name, &self.record, self.targets);
+        Ok(())
+    }
+
+    /// Add a secondary or supplementary alignment of a read that has already been added, counting it in the targets
+    /// it overlaps, see [`Summary::add_secondary_alignment`] and [`Summary::add_target_hits`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the summary cannot be updated with the alignment.
+    pub(crate) fn add_secondary_to(self, summary: &mut Summary) -> DynResult<()> {
+        summary.add_secondary_alignment(&self.condition_name, &self.record, self.read_on)?;
+        summary.add_target_hits(&self.condition_name, &self.record, self.targets);
+        Ok(())
+    }
+
+    /// Get the assignment of the read to its condition.
+    pub(crate) fn assignment(&self) -> ReadAssignment<'_> {
+        ReadAssignment {
+            read_id: self.record.query_name(),
+            condition: &self.condition_name,
+            contig: self.record.target_name(),
+            on_target: self.read_on,
+            query_length: self.record.query_length(),
+            identity: self.record.identity(),
+        }
+    }
+
+    /// Write the assignment of the read in the given format, as a tab separated row under [`ASSIGNMENTS_HEADER`] or a
+    /// line of JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the assignment cannot be written.
+    pub(crate) fn write_assignment(
+        &self,
+        w: &mut impl Write,
+        format: AssignmentsFormat,
+    ) -> DynResult<()> {
+        let assignment = self.assignment();
+        match format {
+            AssignmentsFormat::Tsv => writeln!(
+                w,
+                "{}\t{}\t{}\t{}",
+                assignment.read_id, assignment.condition, assignment.on_target, assignment.contig
+            )?,
+            #[cfg(feature = "serde")]
+            AssignmentsFormat::Ndjson => {
+                serde_json::to_writer(&mut *w, &assignment)?;
+                writeln!(w)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Assigns a parsed alignment, such as a [`PafRecord`], to a condition, keeping the channel of the read, see
+/// [`_classify_paf_record`].
+///
+/// # Errors
+///
+/// The same errors as [`_parse_paf_line`].
+pub(crate) fn classify_record<R: AlignmentRecord>(
+    record: R,
+    _toml: &Conf,
+    meta_data: Option<&mut Metadata>,
+    sequencing_summary: Option<&mut SeqSum>,
+) -> DynResult<ClassifiedRead<R>> {
+    let ReadLookup {
+        channel,
+        barcode,
+        seq_sum_record,
+        channel_mismatch,
+    } = read_channel_and_barcode(&record, _toml, meta_data, sequencing_summary)?;
+    let (condition_name, read_on, targets) =
+        classify_read(&record, _toml, channel, barcode.as_deref())?;
+    Ok(ClassifiedRead {
+        condition_name,
+        channel,
+        record,
+        read_on,
+        targets,
+        seq_sum_record,
+        channel_mismatch,
+    })
+}
+
+/// Drain the alignments of a single read, pairing each with whether it is the primary alignment of the read.
+///
+/// The primary alignment is the longest alignment block tagged `tp:A:P`, or the longest alignment block if none of
+/// the alignments are tagged. On a tie, the first of the alignments is the primary.
+///
+/// # Arguments
+///
+/// * `alignments` - The alignments of the read, which are removed.
+/// * `paf_record` - Gets the [`PafRecord`] of an alignment.
+fn drain_read_alignments<T>(
+    alignments: &mut Vec<T>,
+    paf_record: impl Fn(&T) -> &PafRecord,
+) -> impl Iterator<Item = (T, bool)> + '_ {
+    let primary = alignments
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, alignment)| {
+            let paf_record = paf_record(alignment);
+            (
+                paf_record.get_tag("tp") == Some(Tag::Char('P')),
+                paf_record.aln_len,
+            )
+        })
+        .map_or(0, |(index, _)| index);
+    alignments
+        .drain(..)
+        .enumerate()
+        .map(move |(index, alignment)| (alignment, index == primary))
+}
+
+/// Add the alignments of a single read to the summary, counting the read once by its primary alignment, and
+/// updating the contigs of the others, see [`Paf::set_deduplicate_reads`].
+///
+/// # Errors
+///
+/// Returns an error if the summary cannot be updated with the alignments of the read.
+fn add_read_alignments(
+    summary: &mut Summary,
+    alignments: &mut Vec<ClassifiedRead>,
+) -> DynResult<()> {
+    for (read, primary) in drain_read_alignments(alignments, |read| &read.record) {
+        if primary {
+            read.add_to(summary)?;
+        } else {
+            read.add_secondary_to(summary)?;
+        }
+    }
+    Ok(())
+}
+
+/// Skip a malformed PAF line, logging it and counting it on the summary, or return the error if `strict`.
+///
+/// # Errors
+///
+/// Returns the [`PafError`] with its line number if `strict` is set.
+fn skip_malformed_line(
+    strict: bool,
+    err: PafError,
+    line_number: usize,
+    summary: Option<&mut Summary>,
+) -> PafResult<()> {
+    let err = err.with_line_number(line_number);
+    if strict {
+        return Err(err);
+    }
+    log::warn!("Skipping {}", err);
+    if let Some(summary) = summary {
+        summary.skipped_lines += 1;
+    }
+    Ok(())
+}
+
+/// Skip an alignment that failed the [`ReadFilter`], counting it on the summary.
+pub(crate) fn skip_filtered_read(record: &impl AlignmentRecord, summary: Option<&mut Summary>) {
+    log::trace!(
+        "Filtering out alignment of read {} to {}",
+        record.query_name(),
+        record.target_name()
     );
+    if let Some(summary) = summary {
+        summary.filtered_out += 1;
+    }
+}
+
+/// Skip a read that is missing from the sequencing summary, logging it and counting it on the summary, or return the
+/// error if `strict_seqsum`. Any other error is returned.
+///
+/// # Errors
+///
+/// Returns `err` if `strict_seqsum` is set, or if it isn't a [`PafError::MissingSeqSumRecord`].
+pub(crate) fn skip_missing_read(
+    strict_seqsum: bool,
+    err: Box<dyn std::error::Error>,
+    summary: Option<&mut Summary>,
+) -> DynResult<()> {
+    if strict_seqsum
+        || !matches!(
+            err.downcast_ref(),
+            Some(PafError::MissingSeqSumRecord { .. })
+        )
+    {
+        return Err(err);
+    }
+    log::warn!("Skipping read: {}", err);
+    if let Some(summary) = summary {
+        summary.reads_missing_from_seqsum += 1;
+    }
+    Ok(())
+}
+
+/// The channel and barcode of a read, found by [`read_channel_and_barcode`].
+struct ReadLookup {
+    /// The channel the read was sequenced on.
+    channel: usize,
+    /// The barcode of the read, if there is one.
+    barcode: Option<String>,
+    /// The sequencing summary record of the read, if a sequencing summary was provided.
+    seq_sum_record: Option<SeqSumRecord>,
+    /// The sequencing summary and PAF `ch` tag channels of the read, if they disagree.
+    channel_mismatch: Option<(usize, usize)>,
+}
+
+/// Look up the channel and barcode of a read, from the sequencing summary, the metadata, or the custom PAF tags.
+///
+/// The sequencing summary is read in order, so this must be called on the records in the order they appear in the PAF file.
+/// If the read is in the sequencing summary and also has a `ch` tag, the two channels are compared, and the
+/// channel of the [`SeqSum::channel_source`] is used.
+///
+/// # Returns
+///
+/// The channel, the barcode if there is one, the sequencing summary record if a sequencing summary was provided,
+/// and the two channels if they disagree.
+///
+/// # Errors
+///
+/// The same errors as [`_parse_paf_line`].
+fn read_channel_and_barcode(
+    alignment: &impl AlignmentRecord,
+    _toml: &Conf,
+    meta_data: Option<&mut Metadata>,
+    sequencing_summary: Option<&mut SeqSum>,
+) -> DynResult<ReadLookup> {
     // check if we have custom tags from readfish aligner analyse
     let channel: usize;
     let barcode: Option<String>;
-    // for token in t.iter().skip(12) {
-    //     debug_assert!(PAF_TAG.is_match(token));
-    //     let caps = PAF_TAG.captures(token).unwrap();
-    //     let tag = &caps[1];
-    //     // let value = &caps[3];
-    //     if (tag == "ch") | (tag == "ba") {
-    //         has_tags = true;
-    //     }
-    // }
-    // Break the Paf line into its components
-    let query_name = t[0];
-    // let query_length: usize = t[1].parse()?;
-    let strand = t[4];
-    let contig = t[5];
-    // let contig_length: usize = t[6].parse()?;
-    let mapping_start: usize = t[7].parse()?;
-    let read_on: bool;
-    if meta_data.is_none() & sequencing_summary.is_none() {
-        panic!("Cannot parse paf line without provided metdata or sequencing summary_file");
-    }
+    let mut matched_record: Option<SeqSumRecord> = None;
+    let mut channel_mismatch = None;
+    let query_name = alignment.query_name();
     // If sequencing summary is provided, get the sequencing summary record for the query name
     // Use it for things like barcodes and channels
     if let Some(seq_sum_struct) = sequencing_summary {
-        let seq_sum_record = seq_sum_struct.get_record(query_name, None);
-        if let Ok(record) = seq_sum_record {
-            read_on = _toml.make_decision(
-                record.1.get_channel().unwrap(),
-                record.2.get_barcode().map(|x| x.as_str()),
-                contig,
-                strand,
-                mapping_start,
-            );
-            channel = record.1.get_channel().unwrap();
-            barcode = Some(record.2.get_barcode().unwrap_or(&"".to_string()).clone());
-        } else {
-            return Err("Error: sequencing summary record not found".into());
-        }
+        // Only a read that isn't in the sequencing summary can be skipped, any other error is returned
+        let record = match seq_sum_struct.get_record(query_name, None) {
+            Ok(record) => record,
+            Err(err) if matches!(err.downcast_ref(), Some(SeqSumError::ReadNotFound { .. })) => {
+                return Err(PafError::MissingSeqSumRecord {
+                    query_name: query_name.to_string(),
+                }
+                .into());
+            }
+            Err(err) => return Err(err),
+        };
+        let tag_channel = tag_channel(alignment);
+        channel_mismatch = tag_channel
+            .filter(|tag_channel| *tag_channel != record.channel)
+            .map(|tag_channel| (record.channel, tag_channel));
+        channel = match (seq_sum_struct.channel_source, tag_channel) {
+            (ChannelSource::PafTag, Some(tag_channel)) => tag_channel,
+            _ => record.channel,
+        };
+        barcode = match (alignment.tag("ba"), seq_sum_struct.has_barcode) {
+            (Some(tag_barcode), _) => Some(tag_barcode.to_string()),
+            (None, true) => record.barcode.clone(),
+            (None, false) if !_toml.barcodes().is_empty() => {
+                return Err(PafError::MissingTag {
+                    tag: "ba",
+                    query_name: query_name.to_string(),
+                    reason: "the TOML configuration contains barcodes but the sequencing summary file has no \
+                             `barcode_arrangement` or `barcode` column",
+                }
+                .into());
+            }
+            (None, false) => None,
+        };
+        log::trace!("seq_sum_record: {:?}", record);
+        matched_record = Some(record);
         seq_sum_struct.previous_read_id = query_name.to_string();
-    // We must have metatdata
-    } else {
-        let metadata = meta_data.unwrap();
-        // println!("{contig}, {strand}, {mapping_start}");
-        read_on = _toml.make_decision(
-            metadata.channel(),
-            metadata.barcode().map(|x| x.as_str()),
-            contig,
-            strand,
-            mapping_start,
-        );
+    } else if let Some(metadata) = meta_data {
         channel = metadata.channel();
-        barcode = Some(metadata.barcode().unwrap_or(&"".to_string()).clone());
+        barcode = Some(
+            alignment
+                .tag("ba")
+                .map(|ba| ba.to_string())
+                .or(metadata.barcode().cloned())
+                .unwrap_or_default(),
+        );
+    // Otherwise we need the custom tags added by readfish aligner analyse
+    } else {
+        channel = tag_channel(alignment).ok_or_else(|| PafError::MissingTag {
+            tag: "ch",
+            query_name: query_name.to_string(),
+            reason: "no sequencing summary was provided",
+        })?;
+        barcode = alignment.tag("ba").map(|ba| ba.to_string());
+        if barcode.is_none() && !_toml.barcodes().is_empty() {
+            return Err(PafError::MissingTag {
+                tag: "ba",
+                query_name: query_name.to_string(),
+                reason: "the TOML configuration contains barcodes but no sequencing summary was provided",
+            }
+            .into());
+        }
     }
-    // get the condition so we can access name etc.
-    let (_control, condition) = _toml.get_conditions(channel, barcode)?;
-    let condition = condition.get_condition();
-    let condition_name = &condition.name;
+    Ok(ReadLookup {
+        channel,
+        barcode,
+        seq_sum_record: matched_record,
+        channel_mismatch,
+    })
+}
+
+/// The channel of a read from the custom `ch` tag, which is usually an integer tag but may be written as a string.
+fn tag_channel(record: &impl AlignmentRecord) -> Option<usize> {
+    record
+        .tag("ch")?
+        .as_i64()
+        .and_then(|channel| usize::try_from(channel).ok())
+}
 
-    Ok((paf_record, read_on, condition_name))
+/// Find the condition key a read is summarised under, whether its alignment is on target, and the targets it overlaps.
+///
+/// This only needs the configuration, so can be called on records in any order, or in parallel.
+///
+/// # Returns
+///
+/// The condition key, see [`Conf::condition_key`], whether the read is on target, and the indices of the merged
+/// targets of the condition that an on-target alignment overlaps, see [`Conf::overlapping_targets`].
+///
+/// # Errors
+///
+/// Returns an error if the condition key cannot be found for a read that matches a region or barcode.
+fn classify_read(
+    record: &impl AlignmentRecord,
+    _toml: &Conf,
+    channel: usize,
+    barcode: Option<&str>,
+) -> Result<(String, bool, Range<usize>), String> {
+    // Summarise under region/barcode, but take the targets from the condition that applies to the read.
+    // Reads that match no region or barcode are bucketed as unclassified, and can never be on target.
+    match _toml.get_conditions(channel, barcode) {
+        Ok((_control, condition)) => {
+            let region = &condition.get_condition().name;
+            // On target if the alignment overlaps any of the condition's merged target intervals
+            let read_on = _toml.is_on_target(region, record);
+            // Stranded targets are pooled when counting the reads of each target
+            let targets = if read_on {
+                _toml.overlapping_targets(
+                    region,
+                    record.target_name(),
+                    record.target_start(),
+                    record.target_end(),
+                )
+            } else {
+                0..0
+            };
+            Ok((_toml.condition_key(channel, barcode)?, read_on, targets))
+        }
+        Err(_) => Ok((UNCLASSIFIED_CONDITION.to_string(), false, 0..0)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{readfish_io::reader, TargetSummary};
 
     fn get_resource_dir() -> PathBuf {
         let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -588,6 +2237,143 @@ mod tests {
         assert_eq!(metadata.barcode(), None);
     }
 
+    #[test]
+    fn test_paf_record_from_line() {
+        let paf_record = PafRecord::from_line(
+            "read123\t200\t0\t200\t+\tcontig123\t300\t0\t300\t200\t200\t50\tch=1",
+        )
+        .unwrap();
+        assert_eq!(paf_record.query_name, "read123");
+        assert_eq!(paf_record.query_length, 200);
+        assert_eq!(paf_record.target_name, "contig123");
+        assert_eq!(paf_record.target_length, 300);
+        assert_eq!(paf_record.strand, '+');
+        assert_eq!(paf_record.nmatch, 200);
+        assert_eq!(paf_record.aln_len, 200);
+        assert_eq!(paf_record.mapq, 50);
+        assert_eq!(paf_record.mapping_quality(), Some(50));
+    }
+
+    #[test]
+    fn test_paf_record_display_round_trip() {
+        let line =
+            "read123\t200\t0\t200\t-\tcontig123\t300\t10\t210\t190\t200\t60\ttp:A:P\tcg:Z:200M";
+        let paf_record: PafRecord = line.parse().unwrap();
+        assert_eq!(paf_record.to_string(), line);
+        let reparsed: PafRecord = paf_record.to_string().parse().unwrap();
+        assert_eq!(reparsed, paf_record);
+        assert!("read123\t200".parse::<PafRecord>().is_err());
+    }
+
+    #[test]
+    fn test_paf_record_typed_tags() {
+        let paf_record = PafRecord::from_line(
+            "read123\t200\t0\t200\t+\tcontig123\t300\t0\t300\t200\t200\t50\ttp:A:P\tcm:i:66\ts1:i:452\tdv:f:0.0219\tch=1\tNM:i:x\ttp:A:S",
+        )
+        .unwrap();
+        // The last of the repeated tp tags is kept
+        assert_eq!(paf_record.get_tag("tp"), Some(Tag::Char('S')));
+        assert_eq!(paf_record.tag_value("tp"), Some("S"));
+        assert_eq!(paf_record.get_tag("cm"), Some(Tag::Int(66)));
+        assert_eq!(paf_record.get_tag("dv"), Some(Tag::Float(0.0219)));
+        assert_eq!(paf_record.get_tag("NM"), Some(Tag::Str("x".to_string())));
+        assert_eq!(paf_record.get_tag("ch"), None);
+        // The tags are parsed from the raw tags, which are written back out unchanged
+        assert!(paf_record.to_string().ends_with("\tch=1\tNM:i:x\ttp:A:S"));
+
+        let paf_record =
+            PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 50").unwrap();
+        assert_eq!(paf_record.get_tag("tp"), None);
+    }
+
+    #[test]
+    fn test_paf_record_typed_tag_accessors() {
+        let paf_record = PafRecord::from_line(
+            "read123 200 0 200 + contig123 300 0 300 200 200 50 tp:A:P NM:i:3 de:f:0.0219 cg:Z:200M ch:Z:12 NM:i:4",
+        )
+        .unwrap();
+        // Integer tags, with the last of a repeated tag winning
+        assert_eq!(paf_record.tag_i64("NM"), Some(4));
+        assert_eq!(paf_record.tag_f64("NM"), Some(4.0));
+        assert_eq!(paf_record.tag_str("NM"), None);
+        // Floating point tags
+        assert_eq!(paf_record.tag_f64("de"), Some(0.0219));
+        assert_eq!(paf_record.tag_i64("de"), None);
+        assert_eq!(paf_record.tag_str("de"), None);
+        // Character tags aren't coerced
+        assert_eq!(paf_record.tag_i64("tp"), None);
+        assert_eq!(paf_record.tag_f64("tp"), None);
+        assert_eq!(paf_record.tag_str("tp"), None);
+        // String tags, which are parsed as numbers if they can be
+        assert_eq!(paf_record.tag_str("cg"), Some("200M"));
+        assert_eq!(paf_record.tag_i64("cg"), None);
+        assert_eq!(paf_record.tag_str("ch"), Some("12"));
+        assert_eq!(paf_record.tag_i64("ch"), Some(12));
+        assert_eq!(paf_record.tag_f64("ch"), Some(12.0));
+        // Missing tags
+        assert_eq!(paf_record.tag_i64("cm"), None);
+        assert_eq!(paf_record.tag_f64("cm"), None);
+        assert_eq!(paf_record.tag_str("cm"), None);
+    }
+
+    #[test]
+    fn test_paf_record_from_line_missing_columns() {
+        let result = PafRecord::from_line("read123 200 0 200 + contig123 300");
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("read123 200 0 200 + contig123 300"));
+    }
+
+    #[test]
+    fn test_paf_record_from_line_non_numeric() {
+        let result = PafRecord::from_line("read123 2O0 0 200 + contig123 300 0 300 200 200 50");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cigar_ops() {
+        let paf_record = PafRecord::from_line(
+            "read123 200 0 200 + contig123 300 0 300 200 200 50 tp:A:P cg:Z:10M1D5I3=2X",
+        )
+        .unwrap();
+        assert_eq!(
+            paf_record.cigar_ops().unwrap(),
+            Some(vec![(10, 'M'), (1, 'D'), (5, 'I'), (3, '='), (2, 'X')])
+        );
+        let paf_record =
+            PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 200 50 tp:A:P")
+                .unwrap();
+        assert_eq!(paf_record.cigar_ops().unwrap(), None);
+    }
+
+    #[test]
+    fn test_identity() {
+        let paf_record =
+            PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 150 200 50 de:f:0.0219")
+                .unwrap();
+        assert!((paf_record.identity().unwrap() - 0.9781).abs() < f64::EPSILON);
+        let paf_record =
+            PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 150 200 50").unwrap();
+        assert_eq!(paf_record.identity(), Some(0.75));
+        let paf_record =
+            PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 0 0 50").unwrap();
+        assert_eq!(paf_record.identity(), None);
+    }
+
+    #[test]
+    fn test_cigar_ops_malformed() {
+        for cigar in ["cg:Z:10Q", "cg:Z:M10", "cg:Z:10M5"] {
+            let paf_record = PafRecord::from_line(&format!(
+                "read123 200 0 200 + contig123 300 0 300 200 200 50 {}",
+                cigar
+            ))
+            .unwrap();
+            assert!(matches!(
+                paf_record.cigar_ops(),
+                Err(PafError::PafParseCigar { .. })
+            ));
+        }
+    }
+
     #[test]
     fn test_from_file_valid_paf() {
         let file_name = get_test_file("test_hum_4000.paf");
@@ -599,6 +2385,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_from_file_returns_unconsumed_reader() {
+        let file_name = get_test_file("test_hum_4000.paf");
+        let mut paf_file = open_paf_for_reading(file_name).unwrap();
+        let mut line = String::new();
+        paf_file.read_line(&mut line).unwrap();
+        assert!(line.starts_with("047030bf-7feb-4b3d-abed-89b60adc3cd4\t635"));
+        assert_eq!(paf_file.lines().count(), 4147);
+    }
+
     #[test]
     fn test_from_file_invalid_paf() {
         let file_name = get_test_file("invalid_file.paf");
@@ -614,11 +2410,714 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
     fn test_from_file_nonexistent_file() {
         let file_name = get_test_file("no_existo.paf");
         let result = open_paf_for_reading(file_name);
-        assert!(result.is_err(), "Expected Err, but got Ok");
+        assert!(matches!(result, Err(PafError::Io(_))));
+    }
+
+    #[test]
+    fn test_paf_try_new() {
+        assert!(Paf::try_new(get_test_file("test_hum_4000.paf")).is_ok());
+        assert!(matches!(
+            Paf::try_new(get_test_file("empty.paf")),
+            Err(PafError::EmptyFile)
+        ));
+        assert!(matches!(
+            Paf::try_new(get_test_file("no_existo.paf")),
+            Err(PafError::Io(_))
+        ));
+        assert!(matches!(
+            Paf::try_new(get_test_file("invalid_file.paf")),
+            Err(PafError::MalformedLine {
+                line_number: Some(1),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_paf_error_line_number() {
+        let err = PafRecord::from_line("read123 200 0 200 + contig123 300 0 300 200 tp:A:P 50")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            PafError::MalformedLine {
+                line_number: None,
+                ..
+            }
+        ));
+        let err = err.with_line_number(42);
+        assert!(matches!(
+            err,
+            PafError::MalformedLine {
+                line_number: Some(42),
+                ..
+            }
+        ));
+        assert!(err.to_string().starts_with("Error: malformed PAF line 42"));
+    }
+
+    #[test]
+    fn test_paf_record_new_too_few_columns() {
+        let err = PafRecord::new("read123 200 0 200".split(' ').collect()).unwrap_err();
+        assert!(matches!(
+            err,
+            PafError::MalformedLine {
+                line_number: None,
+                ..
+            }
+        ));
+        assert!(err
+            .to_string()
+            .contains("expected at least 12 columns but found 4"));
+        assert!(PafRecord::new(
+            "read123 200 0 200 + contig123 300 0 300 200 200 50"
+                .split(' ')
+                .collect()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_parse_paf_line_missing_seq_sum_record() {
+        let conf = Conf::from_file(get_test_file("clockface.toml")).unwrap();
+        let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+        let line = "not_a_read 1000 0 1000 + NC_002516.2 6264404 0 1000 900 1000 60";
+        let err = _parse_paf_line(line, &conf, None, Some(&mut seq_sum)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PafError>(),
+            Some(PafError::MissingSeqSumRecord { query_name }) if query_name == "not_a_read"
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_reads_missing_from_seqsum() {
+        let paf_path = std::env::temp_dir().join("readfish_tools_test_missing_reads.paf");
+        std::fs::write(
+            &paf_path,
+            "not_a_read 1000 0 1000 + chr2 100000 3900 4900 900 1000 60\n\
+             ec44322e-01f0-486e-9708-75619d26daaf 1000 0 1000 + chr2 100000 3900 4900 900 1000 60\n",
+        )
+        .unwrap();
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+
+        // Lenient by default, skipping and counting the missing read
+        let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+        let mut summary = Summary::new();
+        Paf::new(&paf_path)
+            .demultiplex(
+                &mut conf,
+                Some(&mut seq_sum),
+                Some(&mut summary),
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(summary.reads_missing_from_seqsum, 1);
+        let total_reads: usize = summary
+            .conditions
+            .values()
+            .map(|condition| condition.total_reads)
+            .sum();
+        assert_eq!(total_reads, 1);
+
+        let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+        let mut paf = Paf::new(&paf_path);
+        paf.set_strict_seqsum(true);
+        let err = paf
+            .demultiplex(&mut conf, Some(&mut seq_sum), None, None, None)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PafError>(),
+            Some(PafError::MissingSeqSumRecord { query_name }) if query_name == "not_a_read"
+        ));
+        std::fs::remove_file(&paf_path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_corrupt_seqsum() {
+        // A malformed sequencing summary line is an error even when missing reads are skipped, rather than the read
+        // being counted as missing from the sequencing summary
+        let read_id = "ec44322e-01f0-486e-9708-75619d26daaf";
+        let paf_path = std::env::temp_dir().join("readfish_tools_test_corrupt_seqsum.paf");
+        std::fs::write(
+            &paf_path,
+            format!("{read_id} 1000 0 1000 + chr2 100000 3900 4900 900 1000 60\n"),
+        )
+        .unwrap();
+        let seq_sum_path = std::env::temp_dir().join("readfish_tools_test_corrupt_seqsum.txt");
+        std::fs::write(
+            &seq_sum_path,
+            format!("read_id\tchannel\n{read_id}\t514\nread1\t1\n"),
+        )
+        .unwrap();
+        let mut seq_sum = SeqSum::from_file(&seq_sum_path).unwrap();
+        // Evict the read from the record buffer, so it is read from its indexed offset
+        seq_sum.set_buffer_capacity(1);
+        assert!(seq_sum.build_index().unwrap());
+        std::fs::write(
+            &seq_sum_path,
+            format!("read_id\tchannel\n{read_id}\tnot_a_channel\nread1\t1\n"),
+        )
+        .unwrap();
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let mut summary = Summary::new();
+        let err = Paf::new(&paf_path)
+            .demultiplex(
+                &mut conf,
+                Some(&mut seq_sum),
+                Some(&mut summary),
+                None,
+                None,
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Malformed sequencing summary line"));
+        assert_eq!(summary.reads_missing_from_seqsum, 0);
+        std::fs::remove_file(paf_path).unwrap();
+        std::fs::remove_file(seq_sum_path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_channel_mismatches() {
+        // Both reads are in the sequencing summary, on channels 514 and 84, but the second has the wrong ch tag
+        let paf_path = std::env::temp_dir().join("readfish_tools_test_channel_mismatch.paf");
+        std::fs::write(
+            &paf_path,
+            "ec44322e-01f0-486e-9708-75619d26daaf 1000 0 1000 + chr2 100000 3900 4900 900 1000 60 ch:i:514\n\
+             62846467-559d-4c31-9e5a-70edf2c0cb0b 1000 0 1000 + chr2 100000 3900 4900 900 1000 60 ch:i:85\n",
+        )
+        .unwrap();
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+
+        // The sequencing summary channel is used by default
+        let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+        let mut summary = Summary::new();
+        Paf::new(&paf_path)
+            .demultiplex(
+                &mut conf,
+                Some(&mut seq_sum),
+                Some(&mut summary),
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(summary.channel_mismatches, 1);
+        assert!(summary.channels.contains_key(&84));
+        assert!(!summary.channels.contains_key(&85));
+
+        let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+        seq_sum.set_channel_source(ChannelSource::PafTag);
+        let mut summary = Summary::new();
+        Paf::new(&paf_path)
+            .demultiplex(
+                &mut conf,
+                Some(&mut seq_sum),
+                Some(&mut summary),
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(summary.channel_mismatches, 1);
+        assert!(summary.channels.contains_key(&85));
+        assert!(!summary.channels.contains_key(&84));
+        std::fs::remove_file(&paf_path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_output_dir() {
+        let output_dir = std::env::temp_dir().join("readfish_tools_test_demultiplex");
+        let _ = std::fs::remove_dir_all(&output_dir);
+        let mut conf = Conf::from_file(get_test_file("human_barcode.toml")).unwrap();
+        let mut seq_sum = SeqSum::from_file(get_test_file("seq_sum_PAK09329.txt")).unwrap();
+        let mut output = ConditionPafWriters::new(&output_dir).unwrap();
+        let mut paf = Paf::new(get_test_file("test_paf_barcode05_NA12878.chr.paf"));
+        paf.demultiplex(&mut conf, Some(&mut seq_sum), None, Some(&mut output), None)
+            .unwrap();
+        let n_lines = reader(output_dir.join("barcode05_NA12878_tst-170.paf"), None)
+            .lines()
+            .count();
+        assert_eq!(n_lines, 4236);
+        // The records of a second PAF file are appended to the same files
+        let mut seq_sum = SeqSum::from_file(get_test_file("seq_sum_PAK09329.txt")).unwrap();
+        let mut paf = Paf::new(get_test_file("test_paf_barcode05_NA12878.chr.paf"));
+        paf.demultiplex(&mut conf, Some(&mut seq_sum), None, Some(&mut output), None)
+            .unwrap();
+        drop(output);
+        let n_lines = reader(output_dir.join("barcode05_NA12878_tst-170.paf"), None)
+            .lines()
+            .count();
+        assert_eq!(n_lines, 2 * 4236);
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_assignments() {
+        let paf_path = std::env::temp_dir().join("readfish_tools_test_assignments.paf");
+        std::fs::write(
+            &paf_path,
+            "read1 1000 0 1000 + chr2 100000 3900 4900 900 1000 60 ch:i:1\n\
+             read2 1000 0 1000 + chr2 100000 90000 91000 900 1000 60 ch:i:2\n\
+             read3 1000 0 0 * * 0 0 0 0 0 0 ch:i:3\n",
+        )
+        .unwrap();
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let mut assignments = Vec::new();
+        Paf::new(&paf_path)
+            .demultiplex(&mut conf, None, None, None, Some(&mut assignments))
+            .unwrap();
+        let assignments = String::from_utf8(assignments).unwrap();
+        let rows: Vec<Vec<&str>> = assignments
+            .lines()
+            .map(|row| row.split('\t').collect())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        for (row, (read_id, contig)) in
+            rows.iter()
+                .zip([("read1", "chr2"), ("read2", "chr2"), ("read3", "*")])
+        {
+            assert_eq!(row.len(), 4);
+            assert_eq!(row[0], read_id);
+            assert_eq!(row[3], contig);
+        }
+        assert_eq!(rows[2][2], "false");
+        std::fs::remove_file(&paf_path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_assignments_ndjson() {
+        assert_eq!(
+            AssignmentsFormat::from_path("reads.ndjson.gz").unwrap(),
+            AssignmentsFormat::Ndjson
+        );
+        assert_eq!(
+            AssignmentsFormat::from_path("reads.jsonl").unwrap(),
+            AssignmentsFormat::Ndjson
+        );
+        assert_eq!(
+            AssignmentsFormat::from_path("reads.tsv.gz").unwrap(),
+            AssignmentsFormat::Tsv
+        );
+        let paf_path = std::env::temp_dir().join("readfish_tools_test_assignments_ndjson.paf");
+        std::fs::write(
+            &paf_path,
+            "read1 1000 0 1000 + chr2 100000 3900 4900 900 1000 60 ch:i:1\n\
+             read2 1200 0 0 * * 0 0 0 0 0 0 ch:i:3\n",
+        )
+        .unwrap();
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let mut assignments = Vec::new();
+        let mut paf = Paf::new(&paf_path);
+        paf.set_assignments_format(AssignmentsFormat::Ndjson);
+        paf.demultiplex(&mut conf, None, None, None, Some(&mut assignments))
+            .unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(assignments)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        let fields: Vec<&str> = lines[0]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "condition",
+                "contig",
+                "identity",
+                "on_target",
+                "query_length",
+                "read_id"
+            ]
+        );
+        assert_eq!(lines[0]["read_id"], "read1");
+        assert_eq!(lines[0]["contig"], "chr2");
+        assert_eq!(lines[0]["query_length"], 1000);
+        assert_eq!(lines[0]["identity"], 0.9);
+        assert_eq!(lines[1]["contig"], "*");
+        assert_eq!(lines[1]["on_target"], false);
+        assert!(lines[1]["identity"].is_null());
+        std::fs::remove_file(&paf_path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_malformed_lines() {
+        let paf_path = std::env::temp_dir().join("readfish_tools_test_malformed.paf");
+        std::fs::write(
+            &paf_path,
+            "read1 1000 0 1000 - chr2 100000 3900 4900 900 1000 60 ch:i:1\n\
+             read2 1000 0 1000 - chr2 100000 3900 ch:i:1\n\
+             read3 1000 0 1000 - chr2 100000 3900 4900 900 1000 60 ch:i:1\n",
+        )
+        .unwrap();
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+
+        // Lenient by default, skipping and counting the malformed line
+        let mut summary = Summary::new();
+        let mut paf = Paf::new(&paf_path);
+        paf.demultiplex(&mut conf, None, Some(&mut summary), None, None)
+            .unwrap();
+        assert_eq!(summary.skipped_lines, 1);
+        assert_eq!(summary.conditions["Direct_CNS"].total_reads, 2);
+
+        let mut paf = Paf::new(&paf_path);
+        paf.set_strict(true);
+        let err = paf
+            .demultiplex(&mut conf, None, None, None, None)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PafError>(),
+            Some(PafError::MalformedLine {
+                line_number: Some(2),
+                ..
+            })
+        ));
+        std::fs::remove_file(&paf_path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_deduplicate_reads() {
+        // read1 maps three times, a longer secondary alignment, the primary, and a supplementary alignment
+        let paf_path = std::env::temp_dir().join("readfish_tools_test_deduplicate.paf");
+        std::fs::write(
+            &paf_path,
+            "read1 1000 0 1000 + chr2 100000 3900 4900 900 1000 0 ch:i:1 tp:A:S\n\
+             read1 1000 0 800 + chr2 100000 10000 10800 700 800 60 ch:i:1 tp:A:P\n\
+             read1 1000 800 1000 + chr3 100000 500 700 200 200 60 ch:i:1 tp:A:P\n\
+             read2 500 0 500 + chr2 100000 20000 20500 500 500 60 ch:i:1 tp:A:P\n",
+        )
+        .unwrap();
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+
+        // Every alignment is counted as a read by default
+        let mut summary = Summary::new();
+        Paf::new(&paf_path)
+            .demultiplex(&mut conf, None, Some(&mut summary), None, None)
+            .unwrap();
+        let condition = &summary.conditions["Direct_CNS"];
+        assert_eq!(condition.total_reads, 4);
+        assert_eq!(condition.total_yield(), 3500);
+
+        let mut summary = Summary::new();
+        let mut paf = Paf::new(&paf_path);
+        paf.set_deduplicate_reads(true);
+        paf.demultiplex(&mut conf, None, Some(&mut summary), None, None)
+            .unwrap();
+        let condition = &summary.conditions["Direct_CNS"];
+        assert_eq!(condition.total_reads, 2);
+        assert_eq!(condition.total_yield(), 1500);
+        // The secondary and supplementary alignments still count towards their contigs
+        assert_eq!(condition.contigs["chr2"].total_reads(), 3);
+        assert_eq!(condition.contigs["chr3"].total_reads(), 1);
+
+        #[cfg(feature = "rayon")]
+        {
+            let mut paf = Paf::new(&paf_path);
+            paf.set_deduplicate_reads(true);
+            let parallel = paf.demultiplex_parallel(&conf, None, 2).unwrap();
+            let parallel_condition = &parallel.conditions["Direct_CNS"];
+            assert_eq!(parallel_condition.total_reads, 2);
+            assert_eq!(parallel_condition.total_yield(), 1500);
+            assert_eq!(parallel_condition.contigs["chr2"].total_reads(), 3);
+        }
+        std::fs::remove_file(&paf_path).unwrap();
+    }
+
+    #[test]
+    fn test_demultiplex_read_filter() {
+        let paf_path = std::env::temp_dir().join("readfish_tools_test_read_filter.paf");
+        std::fs::write(
+            &paf_path,
+            "read1 1000 0 1000 + chr2 100000 3900 4900 900 1000 5 ch:i:1
+             read2 1000 0 1000 + chr2 100000 3900 4900 900 1000 255 ch:i:1
+             read3 300 0 300 + chr2 100000 3900 4200 300 300 60 ch:i:1
+             read4 1000 0 100 + chr2 100000 3900 4000 100 100 60 ch:i:1
+             read5 1000 0 0 * * 0 0 0 0 0 0 ch:i:1
+             read6 1000 0 1000 + chr2 100000 3900 4900 900 1000 60 ch:i:1
+",
+        )
+        .unwrap();
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let read_filter = ReadFilter {
+            min_mapq: Some(20),
+            min_read_length: Some(500),
+            min_block_len: Some(500),
+            exclude_missing_mapq: false,
+        };
+
+        // read1 fails the mapq, read3 the read length and read4 the block length, the unmapped read5 is kept
+        let mut summary = Summary::new();
+        let mut paf = Paf::new(&paf_path);
+        paf.set_read_filter(read_filter);
+        paf.demultiplex(&mut conf, None, Some(&mut summary), None, None)
+            .unwrap();
+        assert_eq!(summary.filtered_out, 3);
+        let condition = &summary.conditions["Direct_CNS"];
+        assert_eq!(condition.total_reads, 3);
+        assert_eq!(condition.unmapped_read_count, 1);
+
+        let mut summary = Summary::new();
+        let mut paf = Paf::new(&paf_path);
+        paf.set_read_filter(ReadFilter {
+            exclude_missing_mapq: true,
+            ..read_filter
+        });
+        paf.demultiplex(&mut conf, None, Some(&mut summary), None, None)
+            .unwrap();
+        assert_eq!(summary.filtered_out, 4);
+        assert_eq!(summary.conditions["Direct_CNS"].total_reads, 2);
+
+        #[cfg(feature = "rayon")]
+        {
+            let mut paf = Paf::new(&paf_path);
+            paf.set_read_filter(read_filter);
+            let parallel = paf.demultiplex_parallel(&conf, None, 2).unwrap();
+            assert_eq!(parallel.filtered_out, 3);
+            assert_eq!(parallel.conditions["Direct_CNS"].total_reads, 3);
+        }
+        std::fs::remove_file(&paf_path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_target_summaries() {
+        // Direct_CNS targets chr2:3000-4000 and chr20:3000-4000 on the reverse strand
+        let paf_path = std::env::temp_dir().join("readfish_tools_test_target_summaries.paf");
+        std::fs::write(
+            &paf_path,
+            "read1 1000 0 1000 - chr2 100000 3900 4900 900 1000 60 ch:i:1\n\
+             read2 500 0 500 - chr2 100000 3500 4000 500 500 60 ch:i:1\n\
+             read3 500 0 500 + chr2 100000 3500 4000 500 500 60 ch:i:1\n\
+             read4 500 0 500 - chr2 100000 5000 5500 500 500 60 ch:i:1\n",
+        )
+        .unwrap();
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let expected = vec![
+            TargetSummary {
+                contig: "chr2".to_string(),
+                start: 3000,
+                stop: 4000,
+                read_count: 2,
+                total_bases: 1500,
+            },
+            TargetSummary {
+                contig: "chr20".to_string(),
+                start: 3000,
+                stop: 4000,
+                read_count: 0,
+                total_bases: 0,
+            },
+        ];
+
+        // The reads of each target are counted without keeping the read lengths
+        let mut summary = Summary::new();
+        Paf::new(&paf_path)
+            .demultiplex(&mut conf, None, Some(&mut summary), None, None)
+            .unwrap();
+        assert!(!summary.keep_read_lengths);
+        assert_eq!(summary.target_summaries(&conf)["Direct_CNS"], expected);
+
+        #[cfg(feature = "rayon")]
+        {
+            let parallel = Paf::new(&paf_path)
+                .demultiplex_parallel(&conf, None, 2)
+                .unwrap();
+            assert_eq!(parallel.target_summaries(&conf)["Direct_CNS"], expected);
+        }
+        std::fs::remove_file(&paf_path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_parallel_matches_sequential() {
+        let mut conf = Conf::from_file(get_test_file("human_barcode.toml")).unwrap();
+        let paf_path = get_test_file("test_paf_barcode05_NA12878.chr.paf");
+        let seq_sum_path = get_test_file("seq_sum_PAK09329.txt");
+
+        let mut seq_sum = SeqSum::from_file(&seq_sum_path).unwrap();
+        let mut sequential = Summary::new();
+        Paf::new(&paf_path)
+            .demultiplex(
+                &mut conf,
+                Some(&mut seq_sum),
+                Some(&mut sequential),
+                None,
+                None,
+            )
+            .unwrap();
+
+        let mut seq_sum = SeqSum::from_file(&seq_sum_path).unwrap();
+        let parallel = Paf::new(&paf_path)
+            .demultiplex_parallel(&conf, Some(&mut seq_sum), 4)
+            .unwrap();
+
+        assert_eq!(parallel.conditions.len(), sequential.conditions.len());
+        for (name, expected) in &sequential.conditions {
+            let condition = &parallel.conditions[name];
+            assert_eq!(condition.total_reads, expected.total_reads);
+            assert_eq!(
+                condition.on_target_read_count,
+                expected.on_target_read_count
+            );
+            assert_eq!(condition.on_target_yield, expected.on_target_yield);
+            assert_eq!(condition.off_target_yield, expected.off_target_yield);
+            assert_eq!(condition.n50, expected.n50);
+            assert_eq!(condition.on_target_n50, expected.on_target_n50);
+            assert_eq!(condition.off_target_n50, expected.off_target_n50);
+            assert_eq!(
+                condition.on_target_mean_read_length(),
+                expected.on_target_mean_read_length()
+            );
+            assert!(
+                (condition.on_target_mean_identity - expected.on_target_mean_identity).abs() < 1e-9
+            );
+            assert_eq!(condition.contigs.len(), expected.contigs.len());
+            for (contig_name, contig) in &expected.contigs {
+                assert_eq!(
+                    condition.contigs[contig_name].on_target_read_count,
+                    contig.on_target_read_count
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_paf_records() {
+        let mut paf = Paf::new(get_test_file("test_hum_4000.paf"));
+        let records: Vec<PafRecord> = paf.records().map(|record| record.unwrap()).collect();
+        assert_eq!(records.len(), 4148);
+        assert_eq!(records[0].query_length, 635);
+        // The records have been consumed
+        assert_eq!(paf.records().count(), 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_parse_paf_line_target_overlap() {
+        // Channel 1 is in the Direct_CNS region, targeting chr2:3000-4000 on the reverse strand
+        let mut conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let lines = [
+            // Straddles the end of the target
+            (
+                "read1 1000 0 1000 - chr2 100000 3900 4900 900 1000 60",
+                true,
+            ),
+            // Straddles the start of the target
+            (
+                "read2 1000 0 1000 - chr2 100000 2001 3001 900 1000 60",
+                true,
+            ),
+            // Ends exactly where the target starts
+            (
+                "read3 1000 0 1000 - chr2 100000 2000 3000 900 1000 60",
+                false,
+            ),
+            // Starts exactly where the target ends
+            (
+                "read4 1000 0 1000 - chr2 100000 4000 5000 900 1000 60",
+                false,
+            ),
+            // Inside the target, but on the wrong strand
+            ("read5 50 0 50 + chr2 100000 3500 3550 50 50 60", false),
+            // Untargeted contig
+            (
+                "read6 1000 0 1000 - chr1 100000 3500 4500 900 1000 60",
+                false,
+            ),
+        ];
+        for (line, expected) in lines {
+            let mut metadata = Metadata::from(("read".to_string(), 1, None));
+            let (_, read_on, condition_name, _) =
+                _parse_paf_line(line, &conf, Some(&mut metadata), None).unwrap();
+            assert_eq!(condition_name, "Direct_CNS");
+            assert_eq!(read_on, expected, "{line}");
+        }
+        // Without strand awareness, the reverse strand target also counts for forward alignments
+        conf.set_strand_aware(false);
+        let mut metadata = Metadata::from(("read5".to_string(), 1, None));
+        let (_, read_on, _, _) =
+            _parse_paf_line(lines[4].0, &conf, Some(&mut metadata), None).unwrap();
+        assert!(read_on);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_demultiplex_barcodes_without_barcode_column() {
+        let mut conf = Conf::from_file(get_test_file("clockface.toml")).unwrap();
+        let mut seq_sum = SeqSum::from_file(get_test_file("sequencing_summary_0.txt")).unwrap();
+        assert!(!seq_sum.has_barcode);
+        let mut paf = Paf::new(get_test_file("test_paf_with_seq_sum.paf"));
+        let err = paf
+            .demultiplex(&mut conf, Some(&mut seq_sum), None, None, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("barcode_arrangement"));
+    }
+
+    #[test]
+    fn test_parse_paf_line_barcode_tag() {
+        let conf = Conf::from_file(get_test_file("clockface.toml")).unwrap();
+        let mut metadata = Metadata::from(("read1".to_string(), 1, None));
+        let line = "read1 1000 0 1000 + NC_002516.2 6264404 0 1000 900 1000 60 ba:Z:barcode02";
+        let (_, _, condition_name, _) =
+            _parse_paf_line(line, &conf, Some(&mut metadata), None).unwrap();
+        assert_eq!(condition_name, "barcode02");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_parse_paf_line_unclassified() {
+        // Channel 600 is not on a MinION flowcell, so matches no region
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let mut metadata = Metadata::from(("read1".to_string(), 600, None));
+        let line = "read1 1000 0 1000 - chr2 100000 3900 4900 900 1000 60";
+        let (_, read_on, condition_name, _) =
+            _parse_paf_line(line, &conf, Some(&mut metadata), None).unwrap();
+        assert_eq!(condition_name, UNCLASSIFIED_CONDITION);
+        assert!(!read_on);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_parse_paf_line_channel_tag() {
+        // Without a sequencing summary or metadata, the channel and barcode come from the custom tags
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let line = "read1 1000 0 1000 - chr2 100000 3900 4900 900 1000 60 ch:i:1";
+        let (_, read_on, condition_name, seq_sum_record) =
+            _parse_paf_line(line, &conf, None, None).unwrap();
+        assert_eq!(condition_name, "Direct_CNS");
+        assert!(read_on);
+        assert!(seq_sum_record.is_none());
+
+        let line = "read1 1000 0 1000 - chr2 100000 3900 4900 900 1000 60";
+        let err = _parse_paf_line(line, &conf, None, None).unwrap_err();
+        assert!(err.to_string().contains("`ch` tag"));
+
+        let conf = Conf::from_file(get_test_file("clockface.toml")).unwrap();
+        let line =
+            "read1 1000 0 1000 + NC_002516.2 6264404 0 1000 900 1000 60 ch:i:1 ba:Z:barcode02";
+        let (_, _, condition_name, _) = _parse_paf_line(line, &conf, None, None).unwrap();
+        assert_eq!(condition_name, "barcode02");
+
+        let line = "read1 1000 0 1000 + NC_002516.2 6264404 0 1000 900 1000 60 ch:i:1";
+        let err = _parse_paf_line(line, &conf, None, None).unwrap_err();
+        assert!(err.to_string().contains("`ba` tag"));
     }
 
     #[test]
@@ -3,14 +3,20 @@
 //! This allows parsing the TOML Into the correct regions and barcodes, and then using the same methods to get the same results as readfish.
 //!
 
-use crate::nanopore;
+use crate::{
+    nanopore,
+    paf::AlignmentRecord,
+    readfish_io::{try_reader, DynResult},
+};
 use csv::ReaderBuilder;
+use itertools::Itertools;
 use serde::Deserialize;
 use std::{
     any::Any,
     collections::HashMap,
     hash::{Hash, Hasher},
-    io::Cursor,
+    io::{BufRead, Cursor},
+    ops::Range,
     path::{Path, PathBuf},
 };
 use toml::{map::Map, Table, Value};
@@ -28,16 +34,147 @@ pub enum Action {
 }
 /// Type for the Contig -> coordinates hashmap.
 type HashedTargets = HashMap<String, Vec<(usize, usize)>>;
+/// Type for the Strand -> Contig -> coordinates hashmap of a condition's targets.
+type StrandedTargets = HashMap<StrandWrapper, HashedTargets>;
+
+/// Errors that can occur while reading or parsing a readfish TOML configuration, see [`Conf::from_file`].
+///
+/// Functions returning a [`DynResult`] box these errors, so the variant can be recovered with `downcast_ref::<ConfError>()`.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfError {
+    /// The TOML file could not be read.
+    #[error("Error: could not read TOML file {}: {source}", .path.display())]
+    Io {
+        /// The path of the TOML file.
+        path: PathBuf,
+        /// The underlying IO error.
+        source: std::io::Error,
+    },
+    /// The file is not valid TOML.
+    #[error("Error: could not parse TOML: {0}")]
+    Parse(#[from] toml::de::Error),
+    /// A key of the TOML is missing, or its value has the wrong type or can't be used.
+    #[error("Error: invalid `{key}` in {section}: {reason}")]
+    InvalidKey {
+        /// Where the key is, such as the region or barcode it belongs to.
+        section: String,
+        /// The name of the key.
+        key: String,
+        /// Why the value is invalid.
+        reason: String,
+    },
+    /// The TOML is valid, but can't be used by readfish, such as a configuration without any regions or barcodes.
+    #[error("Error: {0}")]
+    Invalid(String),
+    /// The layout of the TOML isn't one of the [`ConfSchema`] layouts that can be parsed.
+    #[error("Error: unsupported readfish TOML schema version, detected {0}")]
+    UnsupportedSchema(String),
+}
+
+/// A type alias for a Result with the error type specialized to [`ConfError`].
+pub type ConfResult<T> = Result<T, ConfError>;
+
+/// The layouts of readfish TOML files, which changed between readfish versions, see [`Conf::schema`].
+///
+/// Both layouts are parsed into the same regions and barcodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfSchema {
+    /// The layout of readfish 2023.1 onwards, with a `[[regions]]` array of tables and/or `[barcodes.<name>]`
+    /// tables, alongside `[caller_settings.<caller>]` and `[mapper_settings.<mapper>]` blocks.
+    Regions,
+    /// The layout of earlier readfish versions, with a numbered `[conditions.<N>]` table for each region of the
+    /// flowcell, in order, a `reference` in the `[conditions]` table and a flat `[caller_settings]` block. A
+    /// `max_chunks` of `inf` is read as the largest number of chunks, 255.
+    Conditions,
+}
+
+impl ConfSchema {
+    /// Detects the layout of a parsed readfish TOML from its top level tables.
+    ///
+    /// The old layout is detected by condition tables inside `[conditions]`, as barcoded TOMLs kept a `[conditions]`
+    /// table with only the `reference` alongside their `[barcodes]`. A TOML with none of these tables is detected as
+    /// [`ConfSchema::Regions`], and then rejected as having no regions or barcodes.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfError::UnsupportedSchema`] if the TOML mixes the `[conditions.<N>]` tables of the old layout
+    /// with the `[[regions]]` or `[barcodes]` of the new one, as it's unclear which readfish would have used.
+    fn detect(toml: &Table) -> ConfResult<ConfSchema> {
+        let has_regions = toml.contains_key("regions") || toml.contains_key("barcodes");
+        let has_conditions = toml
+            .get("conditions")
+            .and_then(Value::as_table)
+            .is_some_and(|conditions| conditions.values().any(Value::is_table));
+        match (has_regions, has_conditions) {
+            (true, true) => Err(ConfError::UnsupportedSchema(
+                "a mix of the `[conditions.N]` layout of readfish before 2023.1 and the `[[regions]]` layout of \
+                 readfish 2023.1 onwards"
+                    .to_string(),
+            )),
+            (false, true) => Ok(ConfSchema::Conditions),
+            _ => Ok(ConfSchema::Regions),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfSchema::Regions => write!(f, "readfish >= 2023.1 ([[regions]] and [barcodes])"),
+            ConfSchema::Conditions => write!(f, "readfish < 2023.1 ([conditions.N])"),
+        }
+    }
+}
+
+/// The number of channels on the MinION flowcell the regions of a [`Conf`] are split across.
+const FLOWCELL_SIZE: usize = 512;
+
+/// The problems found in a parsed [`Conf`] by [`Conf::validate`].
+///
+/// Errors are mistakes that make the configuration unusable or ambiguous, such as two conditions with the same name.
+/// Warnings are settings that are probably mistakes, but can be intended, such as a condition without targets.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConfValidation {
+    /// Problems that are likely to be intentional, and don't stop the configuration being used.
+    pub warnings: Vec<String>,
+    /// Problems that must be fixed before the configuration is used.
+    pub errors: Vec<String>,
+}
+
+impl ConfValidation {
+    /// Whether the configuration has no errors. It may still have warnings.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl std::fmt::Display for ConfValidation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.errors.is_empty() && self.warnings.is_empty() {
+            return writeln!(f, "The configuration is valid");
+        }
+        for error in &self.errors {
+            writeln!(f, "error: {}", error)?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&str> for Action {
+    type Error = String;
 
-impl From<&str> for Action {
-    fn from(source: &str) -> Action {
+    fn try_from(source: &str) -> Result<Action, String> {
         match source {
-            "unblock" => Action::Unblock,
-            "stop_receiving" => Action::StopReceiving,
-            "proceed" => Action::Proceed,
-            _ => {
-                panic!("Unknown Action given")
-            }
+            "unblock" => Ok(Action::Unblock),
+            "stop_receiving" => Ok(Action::StopReceiving),
+            "proceed" => Ok(Action::Proceed),
+            _ => Err(format!(
+                "unknown action \"{}\", expected one of \"unblock\", \"stop_receiving\" or \"proceed\"",
+                source
+            )),
         }
     }
 }
@@ -197,34 +334,40 @@ impl _Condition {
 ///    no_map = "proceed"
 /// ```
 /// All the parsed fields are stored with a _Condition struct, as they could also be from a barcodes table.
+///
+/// A region may list its `channels`, as written by `readfish-tools flowcell --format toml`. Otherwise the flowcell is
+/// split evenly between the regions, in order.
 pub struct Region {
     /// The parsed region settings.
     pub condition: _Condition,
+    /// The channels of the flowcell in this region, sorted. Either the `channels` listed in the TOML, or the section
+    /// of the flowcell split between the regions, see [`Conf::region_for_channel`].
+    pub channels: Vec<usize>,
 }
 
 /// Represents a barcode on the sequencing library. This supercedes any regions.
 ///
 /// ```toml
 ///
-//[barcodes.barcode02]
-//name = "barcode02"
-//control = false
-//min_chunks = 0
-//max_chunks = 4
-//targets = []
-//single_on = "unblock"
-//multi_on = "unblock"
-//single_off = "unblock"
-//multi_off = "unblock"
-//no_seq = "proceed"
-///no_map = "unblock"
+/// [barcodes.barcode02]
+/// name = "barcode02"
+/// control = false
+/// min_chunks = 0
+/// max_chunks = 4
+/// targets = []
+/// single_on = "unblock"
+/// multi_on = "unblock"
+/// single_off = "unblock"
+/// multi_off = "unblock"
+/// no_seq = "proceed"
+/// no_map = "unblock"
 /// ```
 ///
 /// All the parsed fields are stored with a _Condition struct, as they could also be from a regions table.
 #[derive(Debug, PartialEq)]
-struct Barcode {
+pub struct Barcode {
     /// The parsed barcode settings.
-    condition: _Condition,
+    pub condition: _Condition,
 }
 
 // Define a trait to represent the common behaviour of Region and Barcode
@@ -274,41 +417,236 @@ impl Condition for Barcode {
     }
 }
 
-impl From<&Map<String, Value>> for _Condition {
-    fn from(source: &Map<String, Value>) -> Self {
-        let targets: TargetType = source.get("targets").unwrap().into();
-        let target: Targets = Targets::new(targets);
-        _Condition {
-            name: source.get("name").unwrap().as_str().unwrap().to_string(),
+/// Merges overlapping or adjacent intervals in place, leaving them sorted by start coordinate.
+///
+/// Intervals are sorted, then any interval whose start is at or before the end of the previous
+/// interval is coalesced into it, in the same way readfish collapses its target ranges. The merged
+/// intervals are written over the start of the vector, which is then truncated, so nothing is allocated.
+///
+/// # Arguments
+///
+/// * `intervals` - A mutable reference to a vector of `(start, stop)` intervals to be merged.
+///
+/// # Examples
+///
+/// ```rust
+/// use readfish_tools::readfish::merge_intervals;
+///
+/// let mut intervals = vec![(10, 15), (1, 5), (4, 9), (15, 18)];
+/// merge_intervals(&mut intervals);
+/// assert_eq!(intervals, vec![(1, 9), (10, 18)]);
+/// ```
+pub fn merge_intervals(intervals: &mut Vec<(usize, usize)>) {
+    // ToDo consider explicitly forbidding start > end or end < start
+    if intervals.len() < 2 {
+        return;
+    }
+    intervals.sort_unstable();
+    let mut merged = 0;
+    for index in 1..intervals.len() {
+        let (start, end) = intervals[index];
+        let current_end = &mut intervals[merged].1;
+        if start <= *current_end {
+            // Overlapping or adjacent range, extend the current range's end index
+            *current_end = (*current_end).max(end);
+        } else {
+            merged += 1;
+            intervals[merged] = (start, end);
+        }
+    }
+    intervals.truncate(merged + 1);
+}
+
+/// Checks whether the half-open range `start..end` overlaps any of a set of sorted, merged intervals.
+///
+/// The intervals are expected to have been passed through [`merge_intervals`], so a binary search
+/// can be used to find the first interval that ends after `start`.
+///
+/// # Arguments
+///
+/// * `intervals` - Sorted, non-overlapping `(start, stop)` intervals.
+/// * `start` - The start of the range to check.
+/// * `end` - The end of the range to check, exclusive.
+///
+/// # Returns
+///
+/// `true` if the range overlaps at least one interval.
+///
+/// # Examples
+///
+/// ```rust
+/// use readfish_tools::readfish::overlaps_intervals;
+///
+/// let intervals = vec![(100, 200), (300, 400)];
+/// assert!(overlaps_intervals(&intervals, 50, 150));
+/// assert!(overlaps_intervals(&intervals, 390, 500));
+/// assert!(!overlaps_intervals(&intervals, 200, 300));
+/// ```
+pub fn overlaps_intervals(intervals: &[(usize, usize)], start: usize, end: usize) -> bool {
+    let index = intervals.partition_point(|&(_, stop)| stop <= start);
+    intervals
+        .get(index)
+        .is_some_and(|&(interval_start, _)| interval_start < end)
+}
+
+/// Finds the sorted, merged intervals that the half-open range `start..end` overlaps.
+///
+/// As in [`overlaps_intervals`], a binary search finds the first interval that ends after `start`, and a second
+/// finds the first interval that starts at or after `end`.
+///
+/// # Arguments
+///
+/// * `intervals` - Sorted, non-overlapping `(start, stop)` intervals.
+/// * `start` - The start of the range to check.
+/// * `end` - The end of the range to check, exclusive.
+///
+/// # Returns
+///
+/// The indices of the overlapped intervals, empty if the range overlaps none of them.
+///
+/// # Examples
+///
+/// ```rust
+/// use readfish_tools::readfish::overlapping_intervals;
+///
+/// let intervals = vec![(100, 200), (300, 400), (500, 600)];
+/// assert_eq!(overlapping_intervals(&intervals, 150, 350), 0..2);
+/// assert_eq!(overlapping_intervals(&intervals, 390, 700), 1..3);
+/// assert!(overlapping_intervals(&intervals, 200, 300).is_empty());
+/// ```
+pub fn overlapping_intervals(
+    intervals: &[(usize, usize)],
+    start: usize,
+    end: usize,
+) -> Range<usize> {
+    let first = intervals.partition_point(|&(_, stop)| stop <= start);
+    let last =
+        first + intervals[first..].partition_point(|&(interval_start, _)| interval_start < end);
+    first..last
+}
+
+impl _Condition {
+    /// Parses a condition from the table of a region or barcode in the TOML.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The table of the region or barcode.
+    /// * `section` - A description of where the table is, such as `region "Rapid_CNS"`, for error messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfError::InvalidKey`] naming the first key that is missing or has an invalid value, including
+    /// targets that can't be read or parsed.
+    fn from_table(source: &Map<String, Value>, section: &str) -> ConfResult<_Condition> {
+        let invalid = |key: &str, reason: String| ConfError::InvalidKey {
+            section: section.to_string(),
+            key: key.to_string(),
+            reason,
+        };
+        // Targets may be left out of the TOML, if they are loaded from a BED file with `Conf::load_bed_targets`
+        let targets = source
+            .get("targets")
+            .map_or(Ok(TargetType::Direct(Vec::new())), TargetType::try_from)
+            .and_then(Targets::new)
+            .map_err(|reason| invalid("targets", reason))?;
+        let action = |key: &str| {
+            required_str(source, key)
+                .and_then(Action::try_from)
+                .map_err(|reason| invalid(key, reason))
+        };
+        let chunks = |key: &str, default: i64| {
+            let value = source.get(key).map_or(Ok(default), |value| {
+                value
+                    .as_integer()
+                    .ok_or_else(|| format!("expected an integer, found a {}", value.type_str()))
+            });
+            value
+                .and_then(|value| {
+                    u8::try_from(value)
+                        .map_err(|_| format!("{} is not between 0 and {}", value, u8::MAX))
+                })
+                .map_err(|reason| invalid(key, reason))
+        };
+        Ok(_Condition {
+            name: required_str(source, "name")
+                .map_err(|reason| invalid("name", reason))?
+                .to_string(),
             control: source
                 .get("control")
-                .unwrap_or(&toml::Value::Boolean(false))
-                .as_bool()
-                .unwrap(),
-            min_chunks: source
-                .get("min_chunks")
-                .unwrap_or(&toml::Value::Integer(0))
-                .as_integer()
-                .unwrap()
-                .try_into()
-                .unwrap(),
+                .map_or(Ok(false), |value| {
+                    value
+                        .as_bool()
+                        .ok_or_else(|| format!("expected a boolean, found a {}", value.type_str()))
+                })
+                .map_err(|reason| invalid("control", reason))?,
+            min_chunks: chunks("min_chunks", 0)?,
+            max_chunks: chunks("max_chunks", 4)?,
+            targets,
+            single_off: action("single_off")?,
+            single_on: action("single_on")?,
+            multi_on: action("multi_on")?,
+            multi_off: action("multi_off")?,
+            no_map: action("no_map")?,
+            no_seq: action("no_seq")?,
+        })
+    }
+}
 
-            max_chunks: source
-                .get("max_chunks")
-                .unwrap_or(&toml::Value::Integer(4))
+/// Gets the channels listed in the `channels` array of a region table, sorted. Empty if the region has no `channels`,
+/// so it is assigned a section of the flowcell split instead.
+///
+/// # Arguments
+///
+/// * `source` - The table of the region.
+/// * `section` - A description of where the table is, such as `region "Rapid_CNS"`, for error messages.
+///
+/// # Errors
+///
+/// Returns a [`ConfError::InvalidKey`] if `channels` isn't an array of channel numbers above 0, or is empty.
+fn region_channels(source: &Map<String, Value>, section: &str) -> ConfResult<Vec<usize>> {
+    let invalid = |reason: String| ConfError::InvalidKey {
+        section: section.to_string(),
+        key: "channels".to_string(),
+        reason,
+    };
+    let Some(value) = source.get("channels") else {
+        return Ok(Vec::new());
+    };
+    let channels = value.as_array().ok_or_else(|| {
+        invalid(format!(
+            "expected an array of channels, found a {}",
+            value.type_str()
+        ))
+    })?;
+    if channels.is_empty() {
+        return Err(invalid("expected at least one channel".to_string()));
+    }
+    channels
+        .iter()
+        .map(|channel| {
+            channel
                 .as_integer()
-                .unwrap()
-                .try_into()
-                .unwrap(),
-            targets: target,
-            single_off: source.get("single_off").unwrap().as_str().unwrap().into(),
-            single_on: source.get("single_on").unwrap().as_str().unwrap().into(),
-            multi_on: source.get("multi_on").unwrap().as_str().unwrap().into(),
-            multi_off: source.get("multi_off").unwrap().as_str().unwrap().into(),
-            no_map: source.get("no_map").unwrap().as_str().unwrap().into(),
-            no_seq: source.get("no_seq").unwrap().as_str().unwrap().into(),
-        }
-    }
+                .and_then(|channel| usize::try_from(channel).ok())
+                .filter(|&channel| channel > 0)
+                .ok_or_else(|| invalid(format!("{} is not a channel number", channel)))
+        })
+        .collect::<ConfResult<Vec<usize>>>()
+        .map(|mut channels| {
+            channels.sort_unstable();
+            channels
+        })
+}
+
+/// Gets a required string value from a TOML table.
+///
+/// # Errors
+///
+/// Returns why the value can't be used if the key is missing, or its value isn't a string.
+fn required_str<'a>(source: &'a Map<String, Value>, key: &str) -> Result<&'a str, String> {
+    let value = source.get(key).ok_or("missing required key")?;
+    value
+        .as_str()
+        .ok_or_else(|| format!("expected a string, found a {}", value.type_str()))
 }
 
 /// Strand that the target is on.
@@ -374,22 +712,116 @@ enum TargetType {
     /// Variant representing targets that were given as a path to a file that contains targets.
     ViaFile(PathBuf),
 }
-/// Represents a BED record, which is read from a BedFILE. All six columns are expected, however we do not use _name or _score.
-#[derive(Debug, Deserialize)]
-#[serde(deny_unknown_fields)]
+/// Represents a BED record, which is read from a BED file. Only the first three columns are required,
+/// the name (4th) and strand (6th) columns are kept if present, and any other columns are ignored.
+#[derive(Clone, Debug, PartialEq)]
 struct BedRecord {
     /// The contig or chromosome name associated with the record.
     contig: String,
-    /// The starting position of the record.
+    /// The 0-based starting position of the record.
     start: usize,
-    /// The stopping position of the record.
+    /// The stopping position of the record, exclusive.
     stop: usize,
-    /// The name associated with the record (optional).
-    _name: String,
-    /// The score associated with the record (optional).
-    _score: String,
-    /// The strand information of the record.
-    strand: String,
+    /// The name associated with the record, used by [`Conf::load_bed_targets`] to key the record to a condition.
+    name: Option<String>,
+    /// The strand of the record, if it is `+` or `-`. Records without a strand are targets on both strands.
+    strand: Option<String>,
+}
+
+impl BedRecord {
+    /// Parses a single line of a BED file.
+    ///
+    /// Blank lines, comments starting with `#`, and `track` or `browser` lines are skipped. The coordinates are
+    /// 0-based and half-open, which is how target intervals are stored, so they are kept as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The tab separated line to parse.
+    ///
+    /// # Returns
+    ///
+    /// The parsed [`BedRecord`], or [`None`] if the line is not a record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the line has fewer than three columns, or if the start or stop is not an integer.
+    fn from_line(line: &str) -> Result<Option<BedRecord>, String> {
+        let line = line.trim_end();
+        if line.trim().is_empty()
+            || line.starts_with('#')
+            || line.starts_with("track")
+            || line.starts_with("browser")
+        {
+            return Ok(None);
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 3 {
+            return Err(format!(
+                "expected at least 3 columns, found {}",
+                fields.len()
+            ));
+        }
+        let parse_coord = |column: &str, value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("{column} `{value}` is not an integer"))
+        };
+        Ok(Some(BedRecord {
+            contig: fields[0].to_string(),
+            start: parse_coord("start", fields[1])?,
+            stop: parse_coord("stop", fields[2])?,
+            name: fields
+                .get(3)
+                .filter(|name| !name.is_empty() && **name != ".")
+                .map(|name| name.to_string()),
+            strand: fields
+                .get(5)
+                .filter(|strand| matches!(**strand, "+" | "-"))
+                .map(|strand| strand.to_string()),
+        }))
+    }
+}
+
+/// Reads every record from a BED file, see [`BedRecord::from_line`] for the lines that are skipped.
+///
+/// # Arguments
+///
+/// * `bed_path` - The path to the BED file.
+///
+/// # Returns
+///
+/// The records of the BED file, in file order.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, or if a line is malformed, naming the file and line number.
+fn read_bed_records(bed_path: &Path) -> DynResult<Vec<BedRecord>> {
+    let file = try_reader(bed_path, None)
+        .map_err(|err| format!("Could not open BED file {}: {err}", bed_path.display()))?;
+    let mut records = Vec::new();
+    for (index, line) in file.lines().enumerate() {
+        let record = BedRecord::from_line(&line?).map_err(|err| {
+            format!(
+                "Malformed BED line {} of {}: {err}",
+                index + 1,
+                bed_path.display()
+            )
+        })?;
+        records.extend(record);
+    }
+    Ok(records)
+}
+
+/// Whether a targets file is a BED file, from a `.bed` extension, before any `.gz` or `.bz2` extension of a
+/// compressed file.
+fn is_bed_path(path: &Path) -> bool {
+    let path = match path.extension() {
+        Some(extension) if extension == "gz" || extension == "bz2" => {
+            Path::new(path.file_stem().unwrap_or_default())
+        }
+        _ => path,
+    };
+    path.extension().is_some_and(|extension| extension == "bed")
 }
 
 /// CSV record parsed from targets specified in TOML file,
@@ -417,7 +849,7 @@ impl From<BedRecord> for CsvRecord {
             contig: source.contig,
             start: Some(source.start),
             stop: Some(source.stop),
-            strand: Some(source.strand),
+            strand: source.strand,
         }
     }
 }
@@ -524,18 +956,28 @@ impl CsvRecord {
     }
 }
 
-impl From<&Value> for TargetType {
-    fn from(source: &Value) -> TargetType {
-        match source.is_array() {
-            true => TargetType::Direct(
-                source
-                    .as_array()
-                    .unwrap()
-                    .iter()
-                    .map(|x| x.as_str().unwrap().to_string())
-                    .collect(),
-            ),
-            false => TargetType::ViaFile(PathBuf::from(source.as_str().unwrap())),
+impl TryFrom<&Value> for TargetType {
+    type Error = String;
+
+    fn try_from(source: &Value) -> Result<TargetType, String> {
+        match source {
+            Value::Array(targets) => targets
+                .iter()
+                .map(|target| {
+                    target.as_str().map(str::to_string).ok_or_else(|| {
+                        format!(
+                            "expected an array of strings, found a {}",
+                            target.type_str()
+                        )
+                    })
+                })
+                .collect::<Result<_, _>>()
+                .map(TargetType::Direct),
+            Value::String(path) => Ok(TargetType::ViaFile(PathBuf::from(path))),
+            _ => Err(format!(
+                "expected an array of strings or a file path, found a {}",
+                source.type_str()
+            )),
         }
     }
 }
@@ -551,6 +993,12 @@ pub struct Conf {
     barcodes: HashMap<String, Barcode>,
     /// The mapping of channel number to the index of the region that channel belongs to.
     _channel_map: HashMap<usize, usize>,
+    /// Whether on-target classification only considers targets on the same strand as the alignment.
+    strand_aware: bool,
+    /// The layout of the TOML the configuration was parsed from.
+    schema: ConfSchema,
+    /// The keys of `barcodes`, in the order they were declared in the TOML.
+    barcode_order: Vec<String>,
 }
 #[derive(Debug, PartialEq)]
 /// Holds the targets for a given region or barcode.
@@ -560,7 +1008,10 @@ pub struct Targets {
     value: TargetType,
     /// A hashmap containg the targets themselves, in the form of
     /// Strand => Contig => Start and stop target coordinates.
-    _targets: HashMap<StrandWrapper, HashedTargets>,
+    _targets: StrandedTargets,
+    /// The targets of both strands, merged per contig. Built once, so that overlap queries that ignore the strand
+    /// can binary search the sorted intervals of a contig, rather than pooling and merging the strands for every read.
+    _pooled_targets: HashedTargets,
 }
 
 impl Targets {
@@ -586,6 +1037,11 @@ impl Targets {
     ///
     /// * `targets` - The target data in the form of [`TargetType`].
     ///
+    /// # Errors
+    ///
+    /// Returns why the targets can't be used if the targets file can't be read, or a target can't be parsed, see
+    /// [`Targets::from_parsed_toml`].
+    ///
     /// # Examples
     ///
     /// ```rust, ignore
@@ -605,11 +1061,31 @@ impl Targets {
     /// // NOTE the single contig target chr1 is on both strands in its entirety.
     ///
     /// ```
-    fn new(targets: TargetType) -> Targets {
+    fn new(targets: TargetType) -> Result<Targets, String> {
         let t = targets.clone();
+        Ok(Targets::from_hashed(targets, Targets::from_parsed_toml(t)?))
+    }
+
+    /// Creates [`Targets`] from targets that have already been grouped by strand and contig, and merged, pooling
+    /// the strands of each contig for overlap queries that ignore the strand.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The target data the targets were parsed from.
+    /// * `targets` - The merged targets, grouped by strand and contig.
+    fn from_hashed(value: TargetType, targets: StrandedTargets) -> Targets {
+        let mut pooled_targets: HashedTargets = HashMap::new();
+        for (contig, intervals) in targets.values().flatten() {
+            pooled_targets
+                .entry(contig.clone())
+                .or_default()
+                .extend_from_slice(intervals);
+        }
+        pooled_targets.values_mut().for_each(merge_intervals);
         Targets {
-            value: targets,
-            _targets: Targets::from_parsed_toml(t),
+            value,
+            _targets: targets,
+            _pooled_targets: pooled_targets,
         }
     }
 
@@ -627,17 +1103,17 @@ impl Targets {
     ///
     /// # Arguments
     ///
-    /// * `targets` - A mutable reference to the `HashMap<StrandWrapper, HashedTargets>]` where the record will be inserted.
+    /// * `targets` - A mutable reference to the `StrandedTargets` where the record will be inserted.
     /// * `record` - A reference to the `CsvRecord` containing the record information.
     /// * `strand` - The strand information associated with the record.
     ///
     /// # Examples
     ///
     /// ```rust,ignore
-    /// use readfish_tools::{insert_into_targets, CsvRecord, Strand, StrandWrapper, HashedTargets};
+    /// use readfish_tools::{insert_into_targets, CsvRecord, Strand, StrandWrapper, StrandedTargets};
     /// use std::collections::HashMap;
     ///
-    /// let mut targets: HashMap<StrandWrapper, HashedTargets> = HashMap::new();
+    /// let mut targets: StrandedTargets = HashMap::new();
     ///
     /// let record = CsvRecord {
     ///     contig: "chr1".to_string(),
@@ -653,11 +1129,7 @@ impl Targets {
     /// assert_eq!(targets.get(&StrandWrapper(Strand::Forward)).unwrap().get("chr1").unwrap().len(), 1);
     /// assert_eq!(targets.get(&StrandWrapper(Strand::Forward)).unwrap().get("chr1").unwrap()[0], (100, 200));
     /// ```
-    fn insert_into_targets(
-        targets: &mut HashMap<StrandWrapper, HashedTargets>,
-        record: &CsvRecord,
-        strand: Strand,
-    ) {
+    fn insert_into_targets(targets: &mut StrandedTargets, record: &CsvRecord, strand: Strand) {
         let coords = targets
             .entry(StrandWrapper(strand))
             .or_insert(HashMap::new())
@@ -681,7 +1153,7 @@ impl Targets {
     /// for deserialization of BED records. The appropriate deserialization is performed based on the file type.
     ///
     /// After populating the hashmap, the function merges overlapping intervals within each contig
-    /// using the [`Self::_merge_intervals()`] helper function.
+    /// using the [`merge_intervals()`] helper function.
     ///
     /// # Arguments
     ///
@@ -691,6 +1163,11 @@ impl Targets {
     ///
     /// A hashmap of targets grouped by strand and contig, with start and stop coordinates as values.
     ///
+    /// # Errors
+    ///
+    /// Returns why the targets can't be used if the targets file can't be opened or read, or a target isn't a
+    /// `contig[,start,stop,strand]` CSV record.
+    ///
     /// # Examples
     ///
     /// ```rust,ignore
@@ -703,155 +1180,223 @@ impl Targets {
     ///     "chr1,10,20,+".to_string(),
     /// ]);
     ///
-    /// let targets = from_parsed_toml(target_data);
+    /// let targets = from_parsed_toml(target_data)?;
     ///
     /// assert_eq!(targets.len(), 2);
     /// assert_eq!(targets.get(&StrandWrapper(Strand::Forward)).unwrap().get("chr2").unwrap()[0], (0_usize, usize::MAX));
     /// assert_eq!(targets.get(&StrandWrapper(Strand::Forward)).unwrap().get("chr1").unwrap()[0], (10_usize,20_usize));
     /// ```
-    fn from_parsed_toml(
-        targets: TargetType,
-    ) -> HashMap<StrandWrapper, HashMap<String, Vec<(usize, usize)>>> {
+    fn from_parsed_toml(targets: TargetType) -> Result<StrandedTargets, String> {
         let mut results = HashMap::new();
-        let mut bed_file = false;
-        let mut delim = b',';
         match targets {
             TargetType::Direct(target_vec) => {
                 if target_vec.is_empty() {
-                    return results;
+                    return Ok(results);
                 }
                 let csv_data = target_vec.join("\n");
                 let file = Cursor::new(csv_data);
-                let mut reader = ReaderBuilder::new()
+                let reader = ReaderBuilder::new()
                     .flexible(true)
                     .has_headers(false)
-                    .delimiter(delim)
                     .from_reader(file);
-                for record in reader.records() {
-                    let record = record.unwrap();
-                    let record: CsvRecord = record.deserialize(None).unwrap();
-                    if record.has_coords() {
-                        Targets::insert_into_targets(
-                            &mut results,
-                            &record,
-                            record.get_strand().unwrap(),
-                        );
-                    } else {
-                        Targets::insert_into_targets(&mut results, &record, Strand::Forward);
-                        Targets::insert_into_targets(&mut results, &record, Strand::Reverse);
-                    }
-                }
+                Targets::insert_csv_records(&mut results, reader)?;
+            }
+            TargetType::ViaFile(file_path) if is_bed_path(&file_path) => {
+                let records = read_bed_records(&file_path).map_err(|err| {
+                    format!(
+                        "could not read targets BED file {}: {}",
+                        file_path.display(),
+                        err
+                    )
+                })?;
+                Targets::insert_bed_records(&mut results, records);
             }
             TargetType::ViaFile(file_path) => {
-                // TODO won't handle gzipped bed files
-                if file_path.extension().unwrap() == "bed" {
-                    bed_file = true;
-                    delim = b'\t';
-                }
-                let mut rdr = ReaderBuilder::new()
-                    .delimiter(delim)
+                let file = try_reader(&file_path, None).map_err(|err| {
+                    format!(
+                        "could not open targets file {}: {}",
+                        file_path.display(),
+                        err
+                    )
+                })?;
+                let reader = ReaderBuilder::new()
                     .flexible(true)
                     .has_headers(false)
-                    .from_path(file_path)
-                    .expect("Could not open targets file!");
-                for record in rdr.records() {
-                    let record = record.unwrap();
-                    let record: CsvRecord = match bed_file {
-                        true => {
-                            let x: BedRecord = record.deserialize(None).unwrap();
-                            x.into()
-                        }
-                        false => {
-                            let x: CsvRecord = record.deserialize(None).unwrap();
-                            x
-                        }
-                    };
-                    // Has coordinates and strand provided
-                    if record.has_coords() {
-                        Targets::insert_into_targets(
-                            &mut results,
-                            &record,
-                            record.get_strand().unwrap(),
-                        );
-                    } else {
-                        Targets::insert_into_targets(&mut results, &record, Strand::Forward);
-                        Targets::insert_into_targets(&mut results, &record, Strand::Reverse);
-                    }
+                    .from_reader(file);
+                Targets::insert_csv_records(&mut results, reader)
+                    .map_err(|err| format!("{} in targets file {}", err, file_path.display()))?;
+            }
+        }
+        Targets::merge_all(&mut results);
+        Ok(results)
+    }
+
+    /// Inserts the `contig[,start,stop,strand]` records of CSV targets into the `targets` hashmap.
+    ///
+    /// Records with coordinates are targets on their strand, and records with only a contig are targets of the whole
+    /// contig on both strands. The intervals are not merged, see [`Targets::merge_all`].
+    ///
+    /// # Arguments
+    ///
+    /// * `targets` - The hashmap of targets, grouped by strand and contig, to insert into.
+    /// * `reader` - The CSV reader of the targets, without a header.
+    ///
+    /// # Errors
+    ///
+    /// Returns why a record can't be used if it can't be read, or its coordinates aren't integers.
+    fn insert_csv_records<R: std::io::Read>(
+        targets: &mut StrandedTargets,
+        mut reader: csv::Reader<R>,
+    ) -> Result<(), String> {
+        for record in reader.records() {
+            let record = record.map_err(|err| format!("could not read target: {}", err))?;
+            let record: CsvRecord = record.deserialize(None).map_err(|err| {
+                format!(
+                    "could not parse target \"{}\": {}",
+                    record.iter().join(","),
+                    err
+                )
+            })?;
+            // Has coordinates and strand provided
+            match record.get_strand().filter(|_| record.has_coords()) {
+                Some(strand) => Targets::insert_into_targets(targets, &record, strand),
+                None => {
+                    Targets::insert_into_targets(targets, &record, Strand::Forward);
+                    Targets::insert_into_targets(targets, &record, Strand::Reverse);
                 }
             }
         }
-        results.iter_mut().for_each(|(_strand, contig_hashmap)| {
+        Ok(())
+    }
+
+    /// Merges the overlapping intervals of every contig on every strand, using [`merge_intervals`].
+    ///
+    /// # Arguments
+    ///
+    /// * `targets` - The hashmap of targets, grouped by strand and contig, to merge in place.
+    fn merge_all(targets: &mut StrandedTargets) {
+        targets.iter_mut().for_each(|(_strand, contig_hashmap)| {
             contig_hashmap
                 .iter_mut()
-                .for_each(|(_, v)| *v = Targets::_merge_intervals(v))
+                .for_each(|(_, v)| merge_intervals(v))
         });
-        results
     }
 
-    /// Merges overlapping intervals within a vector of intervals.
+    /// Inserts the intervals of BED records into the `targets` hashmap.
+    ///
+    /// Records with a `+` or `-` strand are only targets on that strand, and records without a strand are targets on both strands.
+    /// The intervals are not merged, see [`Targets::merge_all`].
+    ///
+    /// # Arguments
+    ///
+    /// * `targets` - The hashmap of targets, grouped by strand and contig, to insert into.
+    /// * `records` - The BED records to insert.
+    fn insert_bed_records(
+        targets: &mut StrandedTargets,
+        records: impl IntoIterator<Item = BedRecord>,
+    ) {
+        for record in records {
+            let record: CsvRecord = record.into();
+            match record.get_strand() {
+                Some(strand) => Targets::insert_into_targets(targets, &record, strand),
+                None => {
+                    Targets::insert_into_targets(targets, &record, Strand::Forward);
+                    Targets::insert_into_targets(targets, &record, Strand::Reverse);
+                }
+            }
+        }
+    }
+
+    /// Creates [`Targets`] from the records of a BED file.
+    ///
+    /// # Arguments
+    ///
+    /// * `bed_path` - The path of the BED file the records were read from, kept as the [`TargetType::ViaFile`] value.
+    /// * `records` - The BED records to use as targets.
+    ///
+    /// # Returns
+    ///
+    /// The [`Targets`], with the intervals of each contig merged.
+    fn from_bed_records(bed_path: &Path, records: impl IntoIterator<Item = BedRecord>) -> Targets {
+        let mut targets = HashMap::new();
+        Targets::insert_bed_records(&mut targets, records);
+        Targets::merge_all(&mut targets);
+        Targets::from_hashed(TargetType::ViaFile(bed_path.to_path_buf()), targets)
+    }
+
+    /// Returns the names of the contigs with targets on either strand, sorted and without duplicates.
+    fn contigs(&self) -> Vec<&str> {
+        self._targets
+            .values()
+            .flat_map(|contig_targets| contig_targets.keys().map(String::as_str))
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    /// Returns the merged target intervals for a given contig, optionally restricted to one strand.
+    ///
+    /// If no `strand` is given, intervals from the forward and reverse strands are pooled. The intervals have been
+    /// passed through [`merge_intervals`], so the result is sorted and free of overlaps.
+    /// Whole-contig targets are returned as `(0, usize::MAX)`.
     ///
-    /// This function takes a mutable reference to a vector of intervals represented as tuples `(usize, usize)`
-    /// and merges any overlapping intervals into collapsed ranges. The intervals are expected to be sorted
-    /// based on the starting index before calling this function.
+    /// # Arguments
+    ///
+    /// * `contig` - The contig to return the intervals for.
+    /// * `strand` - The strand to return the intervals for, or `None` for both strands.
     ///
-    /// If the number of intervals is less than 2, the function returns a clone of the input vector as there
-    /// are no overlapping intervals to merge.
+    /// # Returns
     ///
-    /// The function iterates over the sorted intervals and maintains a current range. For each interval,
-    /// if it overlaps with the current range, the end index of the current range is updated to the maximum
-    /// of the current end index and the interval's end index. If the interval is non-overlapping, the
-    /// current range is added to the collapsed ranges and updated to the new interval. If it's the first
-    /// range encountered, the current range is initialized. Finally, the last current range (if any) is added
-    /// to the collapsed ranges.
+    /// A slice of merged `(start, stop)` intervals, empty if the contig is not targeted.
+    fn contig_intervals(&self, contig: &str, strand: Option<Strand>) -> &[(usize, usize)] {
+        let contig_map = match strand {
+            Some(strand) => self._targets.get(&StrandWrapper(strand)),
+            None => Some(&self._pooled_targets),
+        };
+        contig_map
+            .and_then(|contig_map| contig_map.get(contig))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Checks whether the half-open range `start..end` of a contig overlaps any target, on either strand.
     ///
-    /// The resulting collapsed ranges are returned as a new vector.
+    /// The merged intervals of each contig are built once, so each query is a hash lookup and a binary search,
+    /// see [`overlaps_intervals`].
     ///
     /// # Arguments
     ///
-    /// * `intervals` - A mutable reference to a vector of intervals to be merged.
+    /// * `contig` - The contig of the range.
+    /// * `start` - The start of the range.
+    /// * `end` - The end of the range, exclusive.
     ///
     /// # Returns
     ///
-    /// A vector of collapsed ranges after merging overlapping intervals.
+    /// `true` if the range overlaps at least one target.
     ///
     /// # Examples
     ///
     /// ```rust,ignore
+    /// let targets = Targets::new(TargetType::Direct(vec!["chr1,100,200,+".to_string()]));
+    /// assert!(targets.overlaps("chr1", 150, 250));
+    /// assert!(!targets.overlaps("chr1", 200, 250));
+    /// ```
+    pub fn overlaps(&self, contig: &str, start: usize, end: usize) -> bool {
+        overlaps_intervals(self.contig_intervals(contig, None), start, end)
+    }
+
+    /// Finds the targets of a contig that the half-open range `start..end` overlaps, pooled across strands.
     ///
-    /// let mut intervals = vec![(1, 5), (4, 9), (10, 15), (13, 18)];
-    /// let collapsed_ranges = Targets::_merge_intervals(&mut intervals);
+    /// # Arguments
     ///
-    /// assert_eq!(collapsed_ranges, vec![(1, 9), (10, 18)]);
-    /// ```
-    fn _merge_intervals(intervals: &mut Vec<(usize, usize)>) -> Vec<(usize, usize)> {
-        // ToDo consider explicitly forbidding start > end or end < start
-        let n_args = intervals.len();
-        if n_args < 2 {
-            return intervals.clone();
-        }
-        intervals.sort(); // Sort the ranges based on the starting index
-        let mut collapsed_ranges: Vec<(usize, usize)> = Vec::new();
-        let mut current_range: Option<(usize, usize)> = None;
-        for &(start, end) in intervals.iter() {
-            if let Some((current_start, current_end)) = current_range {
-                if start <= current_end {
-                    // Overlapping range, update the current range's end index
-                    current_range = Some((current_start, current_end.max(end)));
-                } else {
-                    // Non-overlapping range, add the current range and update the current range
-                    collapsed_ranges.push((current_start, current_end));
-                    current_range = Some((start, end));
-                }
-            } else {
-                // First range encountered, initialize the current range
-                current_range = Some((start, end));
-            }
-        }
-        // Add the last current range (if any)
-        if let Some((current_start, current_end)) = current_range {
-            collapsed_ranges.push((current_start, current_end));
-        }
-        collapsed_ranges
+    /// * `contig` - The contig of the range.
+    /// * `start` - The start of the range.
+    /// * `end` - The end of the range, exclusive.
+    ///
+    /// # Returns
+    ///
+    /// The indices of the overlapped targets in the merged targets of the contig, see [`overlapping_intervals`].
+    pub fn overlapping(&self, contig: &str, start: usize, end: usize) -> Range<usize> {
+        overlapping_intervals(self.contig_intervals(contig, None), start, end)
     }
 
     /// Checks if the given coordinate falls within any of the target intervals for the specified contig and strand.
@@ -947,19 +1492,19 @@ impl Conf {
     ///     # ...
     /// "#;
     ///
-    /// let conf = Conf::from_string(toml_str);
+    /// let conf = Conf::from_string(toml_str)?;
     /// ```
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// This function panics if the TOML string fails to parse or if there
-    /// are any invalid values in the TOML data.
+    /// Returns a [`ConfError`] if the TOML string fails to parse or if there
+    /// are any invalid values in the TOML data, see [`Conf::new`].
     ///
     /// # Returns
     ///
     /// The constructed `Conf` instance.
     ///
-    fn from_string(toml_string: &str) -> Conf {
+    fn from_string(toml_string: &str) -> ConfResult<Conf> {
         Conf::new(toml_string)
     }
 
@@ -973,82 +1518,227 @@ impl Conf {
     ///
     /// * `toml_path` - The path to the TOML file to be parsed.
     ///
-    /// # Panics
+    /// Both the `[[regions]]` layout of current readfish versions and the `[conditions.<N>]` layout of older ones
+    /// are read, see [`ConfSchema`].
+    ///
+    /// # Errors
     ///
-    /// This function panics if the TOML file cannot be read or if parsing the TOML
-    /// content into a `Conf` instance fails.
+    /// Returns a [`ConfError::UnsupportedSchema`] if the TOML mixes both layouts, a [`ConfError::Io`] if the TOML file cannot be read, or another [`ConfError`] naming the key
+    /// that failed if parsing the TOML content into a `Conf` instance fails, see [`Conf::new`].
     ///
     /// # Examples
     ///
-    /// ```rust,ignore
-    /// use std::path::Path;
+    /// ```
+    /// use readfish_tools::readfish::Conf;
     ///
-    /// let toml_path = Path::new("config.toml");
-    /// let conf = Conf::from_file(toml_path);
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml")?;
+    /// assert_eq!(conf.regions()[0].condition.name, "Rapid_CNS");
+    /// assert!(Conf::from_file("resources/missing.toml").is_err());
+    /// # Ok::<(), readfish_tools::readfish::ConfError>(())
     /// ```
-    pub fn from_file(toml_path: impl AsRef<Path>) -> Conf {
-        let toml_content = std::fs::read_to_string(toml_path).unwrap();
+    pub fn from_file(toml_path: impl AsRef<Path>) -> ConfResult<Conf> {
+        let toml_path = toml_path.as_ref();
+        let toml_content = std::fs::read_to_string(toml_path).map_err(|source| ConfError::Io {
+            path: toml_path.to_path_buf(),
+            source,
+        })?;
         Conf::new(&toml_content)
     }
 
-    /// Constructs a new [`Conf`] instance by parsing a String representation of TOML file.
-    ///
-    /// This function takes a String representation of a toml file (`toml_content`).
-    /// The TOML content is then parsed into a `Table` using the `parse::<Table>` method. The [`Table`] represents
-    /// the parsed TOML structure.
-    ///
-    /// The function initializes empty vectors `regions` and `barcodes` to hold the parsed regions and barcodes,
-    /// respectively. It then checks if the parsed TOML structure contains the "regions" and "barcodes" sections.
-    /// If the sections are present, the function iterates over the corresponding values and converts them into
-    /// [`Region`] and [`Barcode`] structs, which are added to the `regions` and `barcodes` vectors, respectively.
-    ///
-    /// Finally, the function constructs and returns a new [`Conf`] instance with the populated `regions` and `barcodes`
-    /// vectors. The `channels` field is set to 0, and the `_channel_map` field is initialized as an empty [`HashMap].
-    ///
-    /// # Arguments
-    ///
-    /// * `toml_path` - The path to the TOML file.
+    /// Returns the layout of the TOML the configuration was parsed from, see [`ConfSchema`].
     ///
-    /// # Returns
+    /// # Examples
     ///
-    /// A new [`Conf`] instance with the parsed regions and barcodes.
+    /// ```
+    /// use readfish_tools::readfish::{Conf, ConfSchema};
     ///
-    /// # Panics
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml")?;
+    /// assert_eq!(conf.schema(), ConfSchema::Regions);
+    /// # Ok::<(), readfish_tools::readfish::ConfError>(())
+    /// ```
+    pub fn schema(&self) -> ConfSchema {
+        self.schema
+    }
+
+    /// Returns the regions parsed from the `[[regions]]` array of the TOML, in the order they were declared.
     ///
-    /// This function panics if there is an error reading the TOML file or parsing its contents.
+    /// The position of each [`Region`] in the returned slice is the index used in the channel map,
+    /// so the first region is assigned the first section of the flowcell.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml");
+    /// assert_eq!(conf.regions()[0].condition.name, "Rapid_CNS");
+    /// ```
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// Returns the barcodes parsed from the `[barcodes]` table of the TOML, keyed on the barcode name.
+    ///
+    /// The keys are the names the basecaller gives to the barcodes, for example `barcode01`, as found in the
+    /// `ba` tag of a PAF record or the `barcode_arrangement` column of a sequencing summary file.
+    /// The `classified` and `unclassified` keys hold the conditions for barcodes that are not listed explicitly.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let conf = Conf::from_file("resources/clockface.toml");
+    /// assert_eq!(conf.barcodes()["barcode01"].condition.name, "barcode01");
+    /// ```
+    pub fn barcodes(&self) -> &HashMap<String, Barcode> {
+        &self.barcodes
+    }
+
+    /// Returns the names of the region conditions, in the order they were declared in the TOML.
+    ///
+    /// These are the condition names reads from each region are summarised under, so outputs can be prepared before
+    /// any reads are processed. Reads matching both a region and a barcode are summarised under
+    /// `"region_name/barcode_name"`, see [`Conf::condition_key`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use readfish_tools::readfish::Conf;
+    ///
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml")?;
+    /// assert_eq!(conf.region_names(), ["Rapid_CNS", "Direct_CNS"]);
+    /// # Ok::<(), readfish_tools::readfish::ConfError>(())
+    /// ```
+    pub fn region_names(&self) -> Vec<&str> {
+        self.regions
+            .iter()
+            .map(|region| region.condition.name.as_str())
+            .collect()
+    }
+
+    /// Returns the names of the barcode conditions, in the order they were declared in the TOML, rather than the
+    /// order of the [`Conf::barcodes`] hashmap.
+    ///
+    /// These are the `name` of each barcode table, which reads are summarised under, and may differ from the barcode
+    /// keys, such as `unclassified_reads` for the `unclassified` barcode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use readfish_tools::readfish::Conf;
+    ///
+    /// let conf = Conf::from_file("resources/human_barcode.toml")?;
+    /// assert_eq!(conf.barcode_names()[..2], ["unclassified_reads", "classified_reads"]);
+    /// # Ok::<(), readfish_tools::readfish::ConfError>(())
+    /// ```
+    pub fn barcode_names(&self) -> Vec<&str> {
+        self.barcode_order
+            .iter()
+            .map(|key| self.barcodes[key].condition.name.as_str())
+            .collect()
+    }
+
+    /// Constructs a new [`Conf`] instance by parsing a String representation of TOML file.
+    ///
+    /// This function takes a String representation of a toml file (`toml_content`).
+    /// The TOML content is then parsed into a `Table` using the `parse::<Table>` method. The [`Table`] represents
+    /// the parsed TOML structure.
+    ///
+    /// The function initializes empty vectors `regions` and `barcodes` to hold the parsed regions and barcodes,
+    /// respectively. It then checks if the parsed TOML structure contains the "regions" and "barcodes" sections.
+    /// If the sections are present, the function iterates over the corresponding values and converts them into
+    /// [`Region`] and [`Barcode`] structs, which are added to the `regions` and `barcodes` vectors, respectively.
+    ///
+    /// Finally, the function constructs and returns a new [`Conf`] instance with the populated `regions` and `barcodes`
+    /// vectors. The `channels` field is set to 0, and the `_channel_map` field is initialized as an empty [`HashMap].
+    ///
+    /// # Arguments
+    ///
+    /// * `toml_path` - The path to the TOML file.
+    ///
+    /// # Returns
+    ///
+    /// A new [`Conf`] instance with the parsed regions and barcodes.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfError::Parse`] if the content isn't valid TOML, a [`ConfError::InvalidKey`] naming the section
+    /// and key if a region or barcode is missing a key or has an invalid value, or a [`ConfError::Invalid`] if there
+    /// are no regions or barcodes readfish can use.
     ///
     /// # Examples
     ///
     /// ```rust,ignore
     /// use my_module::Conf;
     ///
-    /// let conf = Conf::new("config.toml");
+    /// let conf = Conf::new("config.toml")?;
     ///
     /// // Perform operations on the `conf` instance
     /// ```
-    fn new(toml_content: &str) -> Conf {
-        let value = toml_content.parse::<Table>().unwrap();
-        let mut regions = Vec::new();
+    fn new(toml_content: &str) -> ConfResult<Conf> {
+        let value = toml_content.parse::<Table>()?;
+        let schema = ConfSchema::detect(&value)?;
+        let mut regions = match schema {
+            ConfSchema::Conditions => Conf::legacy_regions(&value)?,
+            ConfSchema::Regions => Vec::new(),
+        };
         if let Some(parsed_regions) = value.get("regions") {
-            let parsed_regions = parsed_regions.as_array().unwrap();
-            for region in parsed_regions {
-                let x = region.as_table().unwrap();
+            let parsed_regions =
+                parsed_regions
+                    .as_array()
+                    .ok_or_else(|| ConfError::InvalidKey {
+                        section: "the TOML".to_string(),
+                        key: "regions".to_string(),
+                        reason: format!(
+                            "expected an array of tables, found a {}",
+                            parsed_regions.type_str()
+                        ),
+                    })?;
+            for (index, region) in parsed_regions.iter().enumerate() {
+                // Name the region in errors if it has a name, otherwise its position
+                let section = match region.get("name").and_then(Value::as_str) {
+                    Some(name) => format!("region \"{}\"", name),
+                    None => format!("[[regions]] entry {}", index + 1),
+                };
+                let x = region.as_table().ok_or_else(|| ConfError::InvalidKey {
+                    section: section.clone(),
+                    key: "regions".to_string(),
+                    reason: format!("expected a table, found a {}", region.type_str()),
+                })?;
                 let z: Region = Region {
-                    condition: x.try_into().unwrap(),
+                    condition: _Condition::from_table(x, &section)?,
+                    channels: region_channels(x, &section)?,
                 };
                 regions.push(z);
             }
         }
 
         let mut barcodes = HashMap::new();
+        let mut barcode_order = Vec::new();
         if let Some(parsed_barcodes) = value.get("barcodes") {
-            let parsed_barcodes = parsed_barcodes.as_table().unwrap().iter();
+            let parsed_barcodes = parsed_barcodes
+                .as_table()
+                .ok_or_else(|| ConfError::InvalidKey {
+                    section: "the TOML".to_string(),
+                    key: "barcodes".to_string(),
+                    reason: format!("expected a table, found a {}", parsed_barcodes.type_str()),
+                })?
+                .iter();
             for (barcode_name, barcode_value) in parsed_barcodes {
-                let barcode_table = barcode_value.as_table().unwrap();
+                let section = format!("barcode \"{}\"", barcode_name);
+                let barcode_table =
+                    barcode_value
+                        .as_table()
+                        .ok_or_else(|| ConfError::InvalidKey {
+                            section: section.clone(),
+                            key: barcode_name.clone(),
+                            reason: format!(
+                                "expected a table, found a {}",
+                                barcode_value.type_str()
+                            ),
+                        })?;
                 let barcode_struct: Barcode = Barcode {
-                    condition: barcode_table.try_into().unwrap(),
+                    condition: _Condition::from_table(barcode_table, &section)?,
                 };
                 barcodes.insert(barcode_name.clone(), barcode_struct);
+                barcode_order.push(barcode_name.clone());
             }
         }
         let mut conf = Conf {
@@ -1056,10 +1746,74 @@ impl Conf {
             regions,
             barcodes,
             _channel_map: HashMap::new(),
+            strand_aware: true,
+            schema,
+            barcode_order,
         };
-        conf.validate_post_init().unwrap();
-        conf.generate_channel_map(512).unwrap();
-        conf
+        conf.validate_post_init().map_err(ConfError::Invalid)?;
+        conf.generate_channel_map(FLOWCELL_SIZE)
+            .map_err(ConfError::Invalid)?;
+        Ok(conf)
+    }
+
+    /// Parses the numbered `[conditions.<N>]` tables of a TOML in the [`ConfSchema::Conditions`] layout into regions,
+    /// in the order of their numbers, so the flowcell is split between them as readfish did.
+    ///
+    /// Other keys of the `[conditions]` table, such as the `reference`, are ignored. A `min_chunks` or `max_chunks`
+    /// of `inf` is read as 255.
+    ///
+    /// # Arguments
+    ///
+    /// * `toml` - The parsed TOML.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfError::InvalidKey`] if `conditions` isn't a table, a condition table isn't numbered, or a
+    /// condition can't be parsed, see [`_Condition::from_table`].
+    fn legacy_regions(toml: &Table) -> ConfResult<Vec<Region>> {
+        let Some(conditions) = toml.get("conditions") else {
+            return Ok(Vec::new());
+        };
+        let conditions = conditions.as_table().ok_or_else(|| ConfError::InvalidKey {
+            section: "the TOML".to_string(),
+            key: "conditions".to_string(),
+            reason: format!("expected a table, found a {}", conditions.type_str()),
+        })?;
+        let mut numbered = Vec::new();
+        for (key, condition) in conditions {
+            let Some(condition) = condition.as_table() else {
+                continue;
+            };
+            let number: usize = key.parse().map_err(|_| ConfError::InvalidKey {
+                section: "[conditions]".to_string(),
+                key: key.clone(),
+                reason: "expected numbered condition tables, such as [conditions.0]".to_string(),
+            })?;
+            numbered.push((number, key, condition));
+        }
+        numbered.sort_by_key(|(number, _, _)| *number);
+        numbered
+            .into_iter()
+            .map(|(_, key, condition)| {
+                let mut condition = condition.clone();
+                for chunks_key in ["min_chunks", "max_chunks"] {
+                    if let Some(Value::Float(chunks)) = condition.get(chunks_key) {
+                        if chunks.is_infinite() && chunks.is_sign_positive() {
+                            condition
+                                .insert(chunks_key.to_string(), Value::Integer(u8::MAX.into()));
+                        }
+                    }
+                }
+                let section = match condition.get("name").and_then(Value::as_str) {
+                    Some(name) => format!("condition \"{}\"", name),
+                    None => format!("[conditions.{}]", key),
+                };
+                Ok(Region {
+                    condition: _Condition::from_table(&condition, &section)?,
+                    channels: Vec::new(),
+                })
+            })
+            .collect()
     }
 
     /// Validates the state of the [`Conf`] struct after initialization.
@@ -1097,8 +1851,10 @@ impl Conf {
 
     /// Generates a channel map based on the given number of channels and regions.
     ///
-    /// This method splits the channels evenly among the regions and assigns each channel
-    /// a corresponding region index, linking to the position of the region in `Conf.regions`.
+    /// If the regions list their `channels`, each channel is assigned to the region listing it. Otherwise, this
+    /// method splits the channels evenly among the regions with [`nanopore::channel_section_map`], and sets the
+    /// `channels` of each region to its section. Each channel is assigned a corresponding region index, linking to
+    /// the position of the region in `Conf.regions`.
     ///
     /// # Arguments
     ///
@@ -1147,24 +1903,79 @@ impl Conf {
     /// assert_eq!(channel_map.get(&7), None);
     /// ```
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// This method will panic if the `channels` parameter is zero.
+    /// This method returns an error if the `channels` parameter is not a recognised flowcell size,
+    /// or the flowcell cannot be split evenly between the regions, if only some of the regions list their `channels`,
+    /// or if a channel is listed by more than one region.
     fn generate_channel_map(&mut self, channels: usize) -> Result<(), String> {
-        let split_channels =
-            nanopore::generate_flowcell(channels, self.regions.len().max(1), 1, false);
-        let mut channel_map = HashMap::new();
+        let listed = self
+            .regions
+            .iter()
+            .filter(|region| !region.channels.is_empty())
+            .count();
+        if listed == 0 {
+            let sections =
+                nanopore::channel_section_map(channels, self.regions.len().max(1), 1, false)?;
+            for (channel, section) in sections {
+                if let Some(region) = self.regions.get_mut(section) {
+                    region.channels.push(channel);
+                }
+            }
+            self.regions
+                .iter_mut()
+                .for_each(|region| region.channels.sort_unstable());
+        } else if listed < self.regions.len() {
+            return Err(format!(
+                "{} of the {} regions list their `channels`, but either every region or none of them must",
+                listed,
+                self.regions.len()
+            ));
+        }
 
-        for (pos, (channels, _region)) in split_channels.iter().zip(self.regions.iter()).enumerate()
-        {
-            for &channel in channels.iter() {
-                channel_map.insert(channel, pos);
+        let mut channel_map = HashMap::new();
+        for (pos, region) in self.regions.iter().enumerate() {
+            for &channel in &region.channels {
+                if let Some(other) = channel_map.insert(channel, pos) {
+                    return Err(format!(
+                        "channel {} is in both region \"{}\" and region \"{}\"",
+                        channel, self.regions[other].condition.name, region.condition.name
+                    ));
+                }
             }
         }
         self._channel_map = channel_map;
         Ok(())
     }
 
+    /// Get the name of the region a channel is in, using the channels of each region.
+    ///
+    /// This is how reads are assigned to a region when they have a channel, from a `ch` tag or the sequencing summary,
+    /// but no barcode.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel number the read was sequenced on.
+    ///
+    /// # Returns
+    ///
+    /// The name of the region, or `None` if the channel isn't in any region.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use readfish_tools::readfish::Conf;
+    ///
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml")?;
+    /// assert_eq!(conf.region_for_channel(1), Some("Direct_CNS"));
+    /// assert_eq!(conf.region_for_channel(513), None);
+    /// # Ok::<(), readfish_tools::readfish::ConfError>(())
+    /// ```
+    pub fn region_for_channel(&self, channel: usize) -> Option<&str> {
+        self.get_region(channel)
+            .map(|region| region.condition.name.as_str())
+    }
+
     /// Get the condition for a given channel or barcode from the Conf TOML
     ///
     /// The barcode should be passed as an optional `&str` parameter. If barcoding
@@ -1209,6 +2020,54 @@ impl Conf {
         }
     }
 
+    /// Get the key that reads from a given channel and barcode are summarised under.
+    ///
+    /// When the read matches both a region and a barcode, the key is the composite `"region_name/barcode_name"`,
+    /// so that each barcode is broken down by the region of the flowcell it was sequenced on. When only a region
+    /// or only a barcode matches, the key is just the name of that region or barcode.
+    ///
+    /// A read that the basecaller could not barcode in a barcoded run has a `barcode_arrangement` of `unclassified`,
+    /// so it is keyed on the `unclassified` barcode condition, e.g. `"region_name/unclassified_reads"`. A barcode that is
+    /// not listed in the TOML falls back to the `classified` barcode condition. A read with no barcode information at all
+    /// is keyed on its region alone.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel number the read was sequenced on.
+    /// * `barcode` - Optional barcode classification from basecalling.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok` - The condition key for the read.
+    /// * `Err` - A `String` containing an error message if neither a region nor a barcode match.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml");
+    /// assert_eq!(conf.condition_key(1, None::<&str>).unwrap(), "Direct_CNS");
+    /// ```
+    pub fn condition_key<T: AsRef<str> + std::fmt::Debug>(
+        &self,
+        channel: usize,
+        barcode: Option<T>,
+    ) -> Result<String, String> {
+        let region_ = self.get_region(channel);
+        let barcode_ = self.get_barcode(barcode.as_ref());
+        match (region_, barcode_) {
+            (Some(region), Some(barcode)) => Ok(format!(
+                "{}/{}",
+                region.condition.name, barcode.condition.name
+            )),
+            (Some(region), None) => Ok(region.condition.name.clone()),
+            (None, Some(barcode)) => Ok(barcode.condition.name.clone()),
+            (None, None) => Err(format!(
+                "Both region (channel={}) and barcode ({:?}) were not found. This config is invalid!",
+                channel, barcode
+            )),
+        }
+    }
+
     /// Get the region for a given channel.
     ///
     /// Parameters:
@@ -1248,9 +2107,8 @@ impl Conf {
     }
     /// Get the targets associated with a specific channel and barcode (if provided) from the configuration.
     ///
-    /// This function looks up the given `channel` and `barcode` (optional) in the configuration and returns the corresponding targets.
-    /// If the combination of `channel` and `barcode` is not found in the configuration, or if the condition associated with the
-    /// combination does not have targets, this function will return a reference to the default targets.
+    /// This function looks up the given `channel` and `barcode` (optional) in the configuration and returns the
+    /// targets of the matching condition, see [`Conf::get_conditions`].
     ///
     /// # Arguments
     ///
@@ -1260,10 +2118,469 @@ impl Conf {
     /// # Returns
     ///
     /// A reference to the `Targets` associated with the given `channel` and `barcode` combination.
-    /// If the combination is not found, the function returns a reference to the default targets.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither a region nor a barcode match the `channel` and `barcode`.
+    pub fn get_condition_targets(
+        &self,
+        channel: usize,
+        barcode: Option<&str>,
+    ) -> Result<&Targets, String> {
+        let (_control, condition) = self.get_conditions(channel, barcode)?;
+        Ok(condition.get_targets())
+    }
+
+    /// Get the targets associated with a specific channel and barcode (if provided) from the configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if neither a region nor a barcode match the `channel` and `barcode`, see
+    /// [`Conf::get_condition_targets`], which returns an error instead.
+    #[deprecated(
+        note = "use `Conf::get_condition_targets`, which returns an error rather than panicking, or \
+                `Conf::get_contig_targets` for the merged target intervals on a contig"
+    )]
     pub fn get_targets(&self, channel: usize, barcode: Option<&str>) -> &Targets {
-        let (_control, condition) = self.get_conditions(channel, barcode).unwrap();
-        condition.get_targets()
+        self.get_condition_targets(channel, barcode)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Get the merged, sorted target intervals for a contig in a named region or barcode.
+    ///
+    /// The `region` is matched against the names of the `[[regions]]` first, and then against the keys
+    /// of the `[barcodes]` table. Intervals on both strands are pooled and merged with [`merge_intervals`].
+    /// Targets given as just a contig name, with no coordinates, span the whole contig, so they are
+    /// returned as a single `(0, contig_length)` interval. All other intervals are clamped to `contig_length`.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The name of the region, or the barcode, to fetch the targets for.
+    /// * `contig` - The contig to fetch the targets for.
+    /// * `contig_length` - The length of the contig, as reported in the PAF file.
+    ///
+    /// # Returns
+    ///
+    /// A vector of merged `(start, stop)` intervals. Empty if the region is unknown or the contig is not targeted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml");
+    /// assert_eq!(conf.get_contig_targets("Direct_CNS", "chr2", 10_000), vec![(3000, 4000)]);
+    /// ```
+    pub fn get_contig_targets(
+        &self,
+        region: &str,
+        contig: &str,
+        contig_length: usize,
+    ) -> Vec<(usize, usize)> {
+        self.condition_intervals(region, contig, contig_length, None)
+    }
+
+    /// Get the contigs that a named condition has targets on, on either strand.
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - The name of the region, or the barcode, to get the target contigs of.
+    ///
+    /// # Returns
+    ///
+    /// The sorted contig names, empty if the condition is unknown or has no targets.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml");
+    /// assert_eq!(conf.target_contigs("Direct_CNS"), vec!["chr2", "chr20"]);
+    /// ```
+    pub fn target_contigs(&self, condition: &str) -> Vec<&str> {
+        self.find_condition(condition)
+            .map_or_else(Vec::new, |condition| condition.targets.contigs())
+    }
+
+    /// Calculate the fraction of a reference genome that is targeted by a named condition, used to calculate the enrichment.
+    ///
+    /// Targets are pooled across strands, and whole-contig targets are clamped to the contig length, as in [`Conf::get_contig_targets`].
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - The name of the region, or the barcode, to calculate the target fraction of.
+    /// * `reference_lengths` - The length of every contig in the reference, keyed on the contig name.
+    ///
+    /// # Returns
+    ///
+    /// The targeted fraction (0.0 - 1.0) of the reference, or [`None`] if the condition is unknown or has no targets
+    /// on the reference.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml");
+    /// let reference_lengths = HashMap::from([("chr2".to_string(), 10_000)]);
+    /// assert_eq!(conf.target_fraction("Direct_CNS", &reference_lengths), Some(0.1));
+    /// ```
+    pub fn target_fraction(
+        &self,
+        condition: &str,
+        reference_lengths: &HashMap<String, usize>,
+    ) -> Option<f64> {
+        self.find_condition(condition)?;
+        let genome_length: usize = reference_lengths.values().sum();
+        let targeted_length: usize = reference_lengths
+            .iter()
+            .flat_map(|(contig, &length)| self.get_contig_targets(condition, contig, length))
+            .map(|(start, stop)| stop.saturating_sub(start))
+            .sum();
+        (targeted_length > 0).then(|| targeted_length as f64 / genome_length as f64)
+    }
+
+    /// Find a condition by name, see [`Conf::find_named_condition`].
+    ///
+    /// A composite `"region_name/barcode_name"` key, see [`Conf::condition_key`], finds the barcode, as reads that match
+    /// both a region and a barcode are classified by the targets of the barcode.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The region name, barcode key, barcode name or composite condition key to look for.
+    ///
+    /// # Returns
+    ///
+    /// The matching [`_Condition`], or [`None`] if no region or barcode has that name.
+    fn find_condition(&self, name: &str) -> Option<&_Condition> {
+        self.find_named_condition(name).or_else(|| {
+            name.split_once('/')
+                .and_then(|(_region, barcode)| self.find_named_condition(barcode))
+        })
+    }
+
+    /// Find a condition by name, looking through the regions, then the barcodes by key and by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The region name, barcode key or barcode name to look for.
+    ///
+    /// # Returns
+    ///
+    /// The matching [`_Condition`], or [`None`] if no region or barcode has that name.
+    fn find_named_condition(&self, name: &str) -> Option<&_Condition> {
+        self.regions
+            .iter()
+            .map(|region| region.get_condition())
+            .find(|condition| condition.name == name)
+            .or_else(|| {
+                self.barcodes
+                    .get(name)
+                    .map(|barcode| barcode.get_condition())
+            })
+            .or_else(|| {
+                self.barcodes
+                    .values()
+                    .map(|barcode| barcode.get_condition())
+                    .find(|condition| condition.name == name)
+            })
+    }
+
+    /// Replaces the targets of every region and barcode with the intervals of a BED file, so targets can be kept
+    /// in a BED file, with a TOML that only splits the flowcell into regions.
+    ///
+    /// A record whose name (4th) column is the name of a region, or the key or name of a barcode, is a target of that
+    /// condition only. Every other record, including records without a name, is a target of every condition.
+    /// Conditions left without any records have no targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `bed_path` - The path to the BED file, see [`BedRecord::from_line`] for how each line is parsed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the BED file cannot be read, or if a line is malformed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let mut conf = Conf::from_file("regions_only.toml");
+    /// conf.load_bed_targets("targets.bed")?;
+    /// let targets = conf.get_contig_targets("analysis", "chr1", 248_956_422);
+    /// ```
+    pub fn load_bed_targets(&mut self, bed_path: impl AsRef<Path>) -> DynResult<()> {
+        let bed_path = bed_path.as_ref();
+        let records = read_bed_records(bed_path)?;
+        let keyed_to_condition = |name: &str| self.find_named_condition(name).is_some();
+        let (keyed, shared): (Vec<BedRecord>, Vec<BedRecord>) = records
+            .into_iter()
+            .partition(|record| record.name.as_deref().is_some_and(keyed_to_condition));
+        let conditions = self
+            .regions
+            .iter_mut()
+            .map(|region| (None, &mut region.condition))
+            .chain(
+                self.barcodes
+                    .iter_mut()
+                    .map(|(key, barcode)| (Some(key.as_str()), &mut barcode.condition)),
+            );
+        for (key, condition) in conditions {
+            let own_records = keyed.iter().filter(|record| {
+                record
+                    .name
+                    .as_deref()
+                    .is_some_and(|name| name == condition.name || Some(name) == key)
+            });
+            let targets = Targets::from_bed_records(bed_path, own_records.chain(&shared).cloned());
+            condition.set_targets(targets);
+        }
+        Ok(())
+    }
+
+    /// Checks the parsed configuration for mistakes that parsing alone doesn't catch.
+    ///
+    /// The checks are:
+    ///
+    /// * Every channel of the flowcell is in a region, if there are regions. A channel in more than one region is
+    ///   already an error when the configuration is parsed.
+    /// * Every region and barcode has a unique name, as the summary is keyed on the names.
+    /// * Targets files given as a path still exist.
+    /// * `min_chunks` is not above `max_chunks`.
+    /// * Conditions without targets are marked as a `control`, as they can't enrich for anything. A warning.
+    /// * At least one region or barcode is a `control`, to compare the others against. A warning.
+    ///
+    /// # Returns
+    ///
+    /// The [`ConfValidation`], with a message for each problem found. It has no errors if the configuration is valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use readfish_tools::readfish::Conf;
+    ///
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml")?;
+    /// let validation = conf.validate();
+    /// assert!(validation.is_valid());
+    /// # Ok::<(), readfish_tools::readfish::ConfError>(())
+    /// ```
+    pub fn validate(&self) -> ConfValidation {
+        let mut validation = ConfValidation::default();
+        if !self.regions.is_empty() {
+            for &channel in self._channel_map.keys().sorted() {
+                if !(1..=FLOWCELL_SIZE).contains(&channel) {
+                    validation.errors.push(format!(
+                        "channel {} is outside the {} channel flowcell",
+                        channel, FLOWCELL_SIZE
+                    ));
+                }
+            }
+            for channel in 1..=FLOWCELL_SIZE {
+                if !self._channel_map.contains_key(&channel) {
+                    validation
+                        .errors
+                        .push(format!("channel {} is not in any region", channel));
+                }
+            }
+        }
+
+        let conditions: Vec<(String, &_Condition)> = self
+            .regions
+            .iter()
+            .map(|region| {
+                (
+                    format!("region \"{}\"", region.condition.name),
+                    &region.condition,
+                )
+            })
+            .chain(
+                self.barcodes
+                    .iter()
+                    .sorted_by_key(|(key, _)| key.as_str())
+                    .map(|(key, barcode)| (format!("barcode \"{}\"", key), &barcode.condition)),
+            )
+            .collect();
+        let name_counts = conditions
+            .iter()
+            .counts_by(|(_, condition)| &condition.name);
+        for (name, count) in name_counts.into_iter().sorted() {
+            if count > 1 {
+                validation.errors.push(format!(
+                    "{} regions and barcodes are named \"{}\", but condition names must be unique",
+                    count, name
+                ));
+            }
+        }
+        for (section, condition) in &conditions {
+            if let TargetType::ViaFile(path) = &condition.targets.value {
+                if !path.exists() {
+                    validation.errors.push(format!(
+                        "the targets file {} of {} does not exist",
+                        path.display(),
+                        section
+                    ));
+                }
+            }
+            if condition.min_chunks > condition.max_chunks {
+                validation.errors.push(format!(
+                    "{} has a min_chunks of {}, above its max_chunks of {}",
+                    section, condition.min_chunks, condition.max_chunks
+                ));
+            }
+            if condition.targets._targets.values().all(HashMap::is_empty) && !condition.control {
+                validation.warnings.push(format!(
+                    "{} has no targets, but isn't marked as a control",
+                    section
+                ));
+            }
+        }
+        if !conditions.iter().any(|(_, condition)| condition.control) {
+            validation
+                .warnings
+                .push("no region or barcode is marked as a control".to_string());
+        }
+        validation
+    }
+
+    /// Get the merged target intervals for a contig in a named condition, optionally restricted to one strand.
+    ///
+    /// See [`Conf::get_contig_targets`] for how whole-contig targets and `contig_length` are handled.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The name of the region, or the barcode, to fetch the targets for.
+    /// * `contig` - The contig to fetch the targets for.
+    /// * `contig_length` - The length of the contig.
+    /// * `strand` - The strand to fetch the targets for, or `None` for both strands.
+    ///
+    /// # Returns
+    ///
+    /// A vector of merged `(start, stop)` intervals. Empty if the region is unknown or the contig is not targeted.
+    fn condition_intervals(
+        &self,
+        region: &str,
+        contig: &str,
+        contig_length: usize,
+        strand: Option<Strand>,
+    ) -> Vec<(usize, usize)> {
+        self.find_condition(region)
+            .map_or_else(Vec::new, |condition| {
+                condition
+                    .targets
+                    .contig_intervals(contig, strand)
+                    .iter()
+                    .filter(|&&(start, _)| start < contig_length)
+                    .map(|&(start, stop)| (start, stop.min(contig_length)))
+                    .collect()
+            })
+    }
+
+    /// Set whether on-target classification should only consider targets on the same strand as the alignment.
+    ///
+    /// This defaults to `true`, matching readfish, which only counts targets on the strand the read mapped to.
+    ///
+    /// # Arguments
+    ///
+    /// * `strand_aware` - `true` to only consider targets on the alignment strand, `false` to consider both strands.
+    pub fn set_strand_aware(&mut self, strand_aware: bool) {
+        self.strand_aware = strand_aware;
+    }
+
+    /// Whether on-target classification only considers targets on the same strand as the alignment, see
+    /// [`Conf::set_strand_aware`].
+    pub fn is_strand_aware(&self) -> bool {
+        self.strand_aware
+    }
+
+    /// Checks whether the half-open range `start..end` of a contig overlaps any target of a named region or barcode,
+    /// on either strand. See [`Targets::overlaps`].
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - The name of the region, or the barcode.
+    /// * `contig` - The contig of the range.
+    /// * `start` - The start of the range.
+    /// * `end` - The end of the range, exclusive.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the range overlaps at least one target, `false` if it doesn't or the condition is unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml");
+    /// assert!(conf.overlaps("Direct_CNS", "chr2", 3900, 4900));
+    /// ```
+    pub fn overlaps(&self, condition: &str, contig: &str, start: usize, end: usize) -> bool {
+        self.find_condition(condition)
+            .is_some_and(|condition| condition.targets.overlaps(contig, start, end))
+    }
+
+    /// Finds the targets of a named region or barcode on a contig that the half-open range `start..end` overlaps,
+    /// pooled across strands. See [`Targets::overlapping`].
+    ///
+    /// The indices are into the merged targets of the contig, in the same order as [`Conf::get_contig_targets`].
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - The name of the region, or the barcode.
+    /// * `contig` - The contig of the range.
+    /// * `start` - The start of the range.
+    /// * `end` - The end of the range, exclusive.
+    ///
+    /// # Returns
+    ///
+    /// The indices of the overlapped targets, empty if there are none or the condition is unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml");
+    /// assert_eq!(conf.overlapping_targets("Direct_CNS", "chr2", 3900, 4900), 0..1);
+    /// ```
+    pub fn overlapping_targets(
+        &self,
+        condition: &str,
+        contig: &str,
+        start: usize,
+        end: usize,
+    ) -> Range<usize> {
+        self.find_condition(condition).map_or(0..0, |condition| {
+            condition.targets.overlapping(contig, start, end)
+        })
+    }
+
+    /// Decide whether an alignment is on target for the named region or barcode.
+    ///
+    /// The merged target intervals for the alignment's contig are built once, when the targets are parsed, and the
+    /// alignment is on target if `target_start..target_end` overlaps any of them, found by binary search. If the
+    /// [`Conf`] is strand aware (the default, see [`Conf::set_strand_aware`]), only targets on the alignment's strand
+    /// are considered.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The name of the region, or the barcode, the read was assigned to.
+    /// * `record` - The alignment to classify.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the alignment overlaps a target, `false` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let conf = Conf::from_file("resources/RAPID_CNS2.toml");
+    /// let record = PafRecord::from_line("read1 1000 0 1000 - chr2 10000 3900 4900 900 1000 60")?;
+    /// assert!(conf.is_on_target("Direct_CNS", &record));
+    /// ```
+    pub fn is_on_target(&self, region: &str, record: &impl AlignmentRecord) -> bool {
+        let strand = self
+            .strand_aware
+            .then(|| Strand::from(record.strand().to_string().as_str()));
+        self.find_condition(region).is_some_and(|condition| {
+            overlaps_intervals(
+                condition
+                    .targets
+                    .contig_intervals(record.target_name(), strand),
+                record.target_start(),
+                record.target_end(),
+            )
+        })
     }
 
     /// Make a decision based on the provided inputs for the specified channel and barcode (if provided).
@@ -1282,7 +2599,7 @@ impl Conf {
     /// # Returns
     ///
     /// A boolean value indicating whether the given `contig`, `strand`, and `coord` are considered "on target" or not based on the configuration.
-    /// If the combination of `channel` and `barcode` is not found in the configuration, the function will use the default targets.
+    /// If the combination of `channel` and `barcode` is not found in the configuration, it is not on target.
     ///
     /// # Example
     ///
@@ -1306,8 +2623,8 @@ impl Conf {
         strand: T,
         coord: usize,
     ) -> bool {
-        let targets = self.get_targets(channel, barcode);
-        targets.check_coords(contig, strand, coord)
+        self.get_condition_targets(channel, barcode)
+            .is_ok_and(|targets| targets.check_coords(contig, strand, coord))
     }
 }
 
@@ -1431,6 +2748,173 @@ mod tests {
         no_map = "proceed""#
     }
 
+    #[test]
+    fn test_conf_errors() {
+        let err = Conf::from_file(get_test_file("not_a_config.toml")).unwrap_err();
+        assert!(matches!(err, ConfError::Io { .. }));
+        assert!(err.to_string().contains("not_a_config.toml"));
+
+        let err = Conf::from_string("[[regions]\nname = ").unwrap_err();
+        assert!(matches!(err, ConfError::Parse(_)));
+
+        let toml = test_toml_string().replacen(
+            r#"single_on = "stop_receiving""#,
+            r#"single_on = "stop""#,
+            1,
+        );
+        let err = Conf::from_string(&toml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: invalid `single_on` in region \"Rapid_CNS\": unknown action \"stop\", expected one of \
+             \"unblock\", \"stop_receiving\" or \"proceed\""
+        );
+
+        let toml = test_toml_string().replacen(r#"name = "Direct_CNS""#, "", 1);
+        let err = Conf::from_string(&toml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: invalid `name` in [[regions]] entry 2: missing required key"
+        );
+
+        let toml = test_toml_string().replacen("max_chunks = 4", "max_chunks = 400", 1);
+        let err = Conf::from_string(&toml).unwrap_err();
+        assert!(err.to_string().contains("`max_chunks`"));
+        assert!(err.to_string().contains("400 is not between 0 and 255"));
+
+        let toml = test_toml_string().replacen("min_chunks = 1", r#"min_chunks = "1""#, 1);
+        let err = Conf::from_string(&toml).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("expected an integer, found a string"));
+
+        let toml = test_toml_string().replacen(
+            "resources/panel_adaptive_nogenenames_20122021_hg38.bed",
+            "resources/missing_targets.bed",
+            1,
+        );
+        let err = Conf::from_string(&toml).unwrap_err();
+        assert!(matches!(&err, ConfError::InvalidKey { key, .. } if key == "targets"));
+        assert!(err.to_string().contains("missing_targets.bed"));
+
+        let toml =
+            test_toml_string().replacen(r#""chr2,3001,4000,-""#, r#""chr2,start,4000,-""#, 1);
+        let err = Conf::from_string(&toml).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("could not parse target \"chr2,start,4000,-\""));
+
+        let err = Conf::from_string("channels = 512").unwrap_err();
+        assert!(matches!(err, ConfError::Invalid(_)));
+    }
+
+    #[test]
+    fn test_conf_validate() {
+        let validation = Conf::from_string(test_toml_string()).unwrap().validate();
+        assert!(validation.is_valid());
+        assert_eq!(
+            validation.warnings,
+            ["no region or barcode is marked as a control"]
+        );
+
+        let toml = test_toml_string()
+            .replacen(r#"name = "Direct_CNS""#, r#"name = "Rapid_CNS""#, 1)
+            .replacen("min_chunks = 1", "min_chunks = 5", 1)
+            .replacen(
+                r#"targets = ["chr2,3001,4000,-", "chr2,3000,4000,-", "chr20,3000,4000,-"]"#,
+                "control = false",
+                1,
+            );
+        let validation = Conf::from_string(&toml).unwrap().validate();
+        assert!(!validation.is_valid());
+        assert_eq!(
+            validation.errors,
+            [
+                "2 regions and barcodes are named \"Rapid_CNS\", but condition names must be unique",
+                "region \"Rapid_CNS\" has a min_chunks of 5, above its max_chunks of 4",
+            ]
+        );
+        assert_eq!(
+            validation.warnings,
+            [
+                "region \"Rapid_CNS\" has no targets, but isn't marked as a control",
+                "no region or barcode is marked as a control",
+            ]
+        );
+        assert!(validation.to_string().starts_with("error: 2 regions"));
+
+        let toml =
+            test_toml_string().replacen("max_chunks = 4", "max_chunks = 4\ncontrol = true", 1);
+        let validation = Conf::from_string(&toml).unwrap().validate();
+        assert_eq!(validation, ConfValidation::default());
+
+        // A channel in more than one region is already an error when parsing, so only gaps are left to find
+        let mut conf = Conf::from_string(&toml).unwrap();
+        let region = conf._channel_map.remove(&1).unwrap();
+        conf._channel_map.insert(FLOWCELL_SIZE + 1, region);
+        assert_eq!(
+            conf.validate().errors,
+            [
+                format!(
+                    "channel {} is outside the {} channel flowcell",
+                    FLOWCELL_SIZE + 1,
+                    FLOWCELL_SIZE
+                ),
+                "channel 1 is not in any region".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conf_legacy_schema() {
+        let conf = Conf::from_file(get_test_file("RAPID_CNS2.toml")).unwrap();
+        let legacy = Conf::from_file(get_test_file("RAPID_CNS2_legacy.toml")).unwrap();
+        assert_eq!(conf.schema(), ConfSchema::Regions);
+        assert_eq!(legacy.schema(), ConfSchema::Conditions);
+        // The conditions are in the order of their numbers, not the order of the tables
+        assert_eq!(legacy.regions()[0], conf.regions()[0]);
+        assert_eq!(legacy.regions()[1].condition.name, "Direct_CNS");
+        assert_eq!(legacy.regions()[1].condition.max_chunks, u8::MAX);
+        assert_eq!(
+            legacy.regions()[1].condition.targets,
+            conf.regions()[1].condition.targets
+        );
+        assert_eq!(legacy._channel_map, conf._channel_map);
+        // Barcoded TOMLs kept a `[conditions]` table with only the reference
+        let barcoded = Conf::from_file(get_test_file("human_barcode.toml")).unwrap();
+        assert_eq!(barcoded.schema(), ConfSchema::Regions);
+
+        let toml = format!("{}\n[conditions.0]\nname = \"old\"", test_toml_string());
+        let err = Conf::from_string(&toml).unwrap_err();
+        assert!(matches!(err, ConfError::UnsupportedSchema(_)));
+        assert!(err
+            .to_string()
+            .starts_with("Error: unsupported readfish TOML schema version, detected a mix"));
+
+        let err = Conf::from_string("[conditions.first]\nname = \"old\"").unwrap_err();
+        assert!(matches!(&err, ConfError::InvalidKey { key, .. } if key == "first"));
+    }
+
+    #[test]
+    fn test_conf_condition_names() {
+        let conf = Conf::from_string(test_toml_string()).unwrap();
+        assert_eq!(conf.region_names(), ["Rapid_CNS", "Direct_CNS"]);
+        assert!(conf.barcode_names().is_empty());
+
+        // The barcodes are in the order of the TOML, which isn't sorted
+        let barcoded = Conf::from_file(get_test_file("human_barcode.toml")).unwrap();
+        assert!(barcoded.region_names().is_empty());
+        assert_eq!(
+            barcoded.barcode_names(),
+            [
+                "unclassified_reads",
+                "classified_reads",
+                "barcode05_NA12878_tst-170",
+                "barcode06_NB4_tst-fusion",
+                "barcode07_22RV1_COSMIC",
+            ]
+        );
+    }
+
     #[test]
     fn test_from_string() {
         let toml_str = r#"
@@ -1447,7 +2931,7 @@ mod tests {
             no_map = "proceed"
         "#;
 
-        let conf = Conf::from_string(toml_str);
+        let conf = Conf::from_string(toml_str).unwrap();
 
         // Assert that the Conf instance is constructed correctly
         assert_eq!(conf.regions.len(), 1);
@@ -1460,19 +2944,53 @@ mod tests {
             region.condition.targets.value,
             TargetType::ViaFile("resources/panel_adaptive_nogenenames_20122021_hg38.bed".into())
         );
-        assert_eq!(region.condition.single_off, "unblock".into());
-        assert_eq!(region.condition.multi_off, "unblock".into());
-        assert_eq!(region.condition.single_on, "stop_receiving".into());
-        assert_eq!(region.condition.multi_on, "stop_receiving".into());
-        assert_eq!(region.condition.no_seq, "proceed".into());
-        assert_eq!(region.condition.no_map, "proceed".into());
+        assert_eq!(region.condition.single_off, Action::Unblock);
+        assert_eq!(region.condition.multi_off, Action::Unblock);
+        assert_eq!(region.condition.single_on, Action::StopReceiving);
+        assert_eq!(region.condition.multi_on, Action::StopReceiving);
+        assert_eq!(region.condition.no_seq, Action::Proceed);
+        assert_eq!(region.condition.no_map, Action::Proceed);
+    }
+
+    #[test]
+    fn test_regions() {
+        let conf = Conf::from_string(test_toml_string()).unwrap();
+        let regions = conf.regions();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].condition.name, "Rapid_CNS");
+        assert_eq!(regions[1].condition.name, "Direct_CNS");
+        assert!(!regions[1].condition.control);
+        assert_eq!(regions[1].condition.min_chunks, 1);
+        assert_eq!(regions[1].condition.max_chunks, 4);
+        assert_eq!(
+            regions[1].condition.targets.value,
+            TargetType::Direct(vec![
+                "chr2,3001,4000,-".to_string(),
+                "chr2,3000,4000,-".to_string(),
+                "chr20,3000,4000,-".to_string()
+            ])
+        );
+        let conf = Conf::from_string(test_barcoded_toml_string()).unwrap();
+        assert!(conf.regions().is_empty());
+    }
+
+    #[test]
+    fn test_barcodes() {
+        let conf = Conf::from_string(test_barcoded_toml_string()).unwrap();
+        let barcodes = conf.barcodes();
+        assert!(barcodes.contains_key("classified"));
+        assert!(barcodes.contains_key("unclassified"));
+        assert_eq!(barcodes["barcode02"].condition.name, "barcode02");
+        assert_eq!(barcodes["barcode02"].condition.no_map, Action::Unblock);
+        let conf = Conf::from_string(test_toml_string()).unwrap();
+        assert!(conf.barcodes().is_empty());
     }
 
     // todo need a barcode and region containing toml
     #[test]
     fn test_get_conditions() {
         let test_toml = test_toml_string();
-        let conf = Conf::from_string(test_toml);
+        let conf = Conf::from_string(test_toml).unwrap();
         let (_control, x) = conf.get_conditions::<String>(10, None).unwrap();
         // Convert the `Box<dyn Condition>` back into a `Region` if it is one
         if let Some(region) = x.any().downcast_ref::<Region>() {
@@ -1487,10 +3005,52 @@ mod tests {
         }
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn test_get_condition_targets() {
+        let conf = Conf::from_string(test_toml_string()).unwrap();
+        let targets = conf.get_condition_targets(1, None).unwrap();
+        assert!(std::ptr::eq(targets, conf.get_targets(1, None)));
+        // A channel outside every region is an error, rather than a panic
+        assert!(conf.get_condition_targets(100_000, None).is_err());
+        assert!(!conf.make_decision(100_000, None, "chr2", "+", 3500));
+    }
+
+    #[test]
+    fn test_condition_key() {
+        let mut conf = Conf::from_string(test_toml_string()).unwrap();
+        assert_eq!(conf.condition_key(1, None::<&str>).unwrap(), "Direct_CNS");
+        assert_eq!(
+            conf.condition_key(128, Some("barcode01")).unwrap(),
+            "Rapid_CNS"
+        );
+        let barcoded = Conf::from_string(test_barcoded_toml_string()).unwrap();
+        assert_eq!(
+            barcoded.condition_key(1, Some("barcode01")).unwrap(),
+            "barcode01"
+        );
+        assert!(barcoded.condition_key(1, None::<&str>).is_err());
+        // Both regions and barcodes give a composite key
+        conf.barcodes = barcoded.barcodes;
+        assert_eq!(
+            conf.condition_key(1, Some("barcode01")).unwrap(),
+            "Direct_CNS/barcode01"
+        );
+        assert_eq!(
+            conf.condition_key(128, Some("unclassified")).unwrap(),
+            "Rapid_CNS/unclassified_reads"
+        );
+        assert_eq!(
+            conf.condition_key(128, Some("barcode99")).unwrap(),
+            "Rapid_CNS/classified_reads"
+        );
+        assert_eq!(conf.condition_key(1, None::<&str>).unwrap(), "Direct_CNS");
+    }
+
     #[test]
     fn test_get_region() {
         let test_toml = test_toml_string();
-        let conf = Conf::from_string(test_toml);
+        let conf = Conf::from_string(test_toml).unwrap();
         let region = conf.get_region(1).unwrap();
         assert_eq!(region.condition.name, "Direct_CNS");
         let region = conf.get_region(128).unwrap();
@@ -1500,7 +3060,7 @@ mod tests {
     #[test]
     fn test_get_regions_no_regions() {
         let test_toml = test_barcoded_toml_string();
-        let conf = Conf::from_string(test_toml);
+        let conf = Conf::from_string(test_toml).unwrap();
         let region = conf.get_region(1);
         assert_eq!(region, None);
         let region = conf.get_region(128);
@@ -1510,16 +3070,68 @@ mod tests {
     #[test]
     fn test_generate_channel_map() {
         let test_toml = test_toml_string();
-        let mut conf = Conf::from_string(test_toml);
+        let mut conf = Conf::from_string(test_toml).unwrap();
         conf.generate_channel_map(512).unwrap();
         assert_eq!(conf._channel_map.get(&121).unwrap(), &0_usize);
         assert_eq!(conf._channel_map.get(&12).unwrap(), &1_usize);
     }
 
+    #[test]
+    fn test_region_for_channel() {
+        // Without `channels`, the flowcell is split in half by columns, so both sections have boundary channels
+        let conf = Conf::from_string(test_toml_string()).unwrap();
+        let sections = nanopore::generate_flowcell(512, 2, 1, false).unwrap();
+        for (section, name) in sections.iter().zip(["Rapid_CNS", "Direct_CNS"]) {
+            assert!(section
+                .iter()
+                .all(|&channel| conf.region_for_channel(channel) == Some(name)));
+        }
+        assert_eq!(conf.regions()[0].channels.len(), 256);
+        assert_eq!(conf.region_for_channel(0), None);
+        assert_eq!(conf.region_for_channel(513), None);
+
+        // Listed channels are used as given, with the boundary between 256 and 257
+        let listed = test_toml_string()
+            .replacen(
+                "max_chunks = 4",
+                "max_chunks = 4\nchannels = [1, 256, 3, 2]",
+                1,
+            )
+            .replacen(
+                "max_chunks = 4\n        targets = [",
+                "max_chunks = 4\nchannels = [257, 512]\ntargets = [",
+                1,
+            );
+        let conf = Conf::from_string(&listed).unwrap();
+        assert_eq!(conf.regions()[0].channels, [1, 2, 3, 256]);
+        assert_eq!(conf.region_for_channel(256), Some("Rapid_CNS"));
+        assert_eq!(conf.region_for_channel(257), Some("Direct_CNS"));
+        assert_eq!(conf.region_for_channel(512), Some("Direct_CNS"));
+        assert_eq!(conf.region_for_channel(4), None);
+        assert_eq!(conf.condition_key(257, None::<&str>).unwrap(), "Direct_CNS");
+
+        let overlapping = listed.replacen("channels = [257, 512]", "channels = [256, 512]", 1);
+        let err = Conf::from_string(&overlapping).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: channel 256 is in both region \"Rapid_CNS\" and region \"Direct_CNS\""
+        );
+
+        let partial = listed.replacen("channels = [257, 512]", "", 1);
+        let err = Conf::from_string(&partial).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("1 of the 2 regions list their `channels`"));
+
+        let invalid = listed.replacen("channels = [257, 512]", "channels = [0]", 1);
+        let err = Conf::from_string(&invalid).unwrap_err();
+        assert!(matches!(&err, ConfError::InvalidKey { key, .. } if key == "channels"));
+    }
+
     #[test]
     fn test_generate_channel_map_barcode() {
         let test_toml = test_barcoded_toml_string();
-        let mut conf = Conf::from_string(test_toml);
+        let mut conf = Conf::from_string(test_toml).unwrap();
         conf.generate_channel_map(512).unwrap();
         assert_eq!(conf._channel_map.get(&121), None);
         assert_eq!(conf._channel_map.get(&12), None);
@@ -1528,7 +3140,7 @@ mod tests {
     #[test]
     fn test_conf_validate_post_init() {
         let test_toml = test_barcoded_toml_string();
-        let conf = Conf::from_string(test_toml);
+        let conf = Conf::from_string(test_toml).unwrap();
         conf.validate_post_init().unwrap();
     }
 
@@ -1537,11 +3149,39 @@ mod tests {
     #[should_panic]
     fn test_conf_validate_post_init_panic() {
         let test_toml = test_barcoded_toml_string();
-        let mut conf = Conf::from_string(test_toml);
+        let mut conf = Conf::from_string(test_toml).unwrap();
         conf.barcodes.remove("unclassified");
         conf.validate_post_init().unwrap();
     }
 
+    #[test]
+    fn test_is_on_target_stranded_targets() {
+        let toml = test_toml_string().replacen(
+            r#"targets = ["chr2,3001,4000,-", "chr2,3000,4000,-", "chr20,3000,4000,-"]"#,
+            r#"targets = ["chr2,3000,4000,+", "chr20,3000,4000"]"#,
+            1,
+        );
+        let mut conf = Conf::from_string(&toml).unwrap();
+        assert!(conf.is_strand_aware());
+        for (line, expected) in [
+            // A stranded target only matches reads on its strand
+            ("read1 500 0 500 + chr2 10000 3500 4000 500 500 60", true),
+            ("read2 500 0 500 - chr2 10000 3500 4000 500 500 60", false),
+            // A target with no strand matches both
+            ("read3 500 0 500 + chr20 10000 3500 4000 500 500 60", true),
+            ("read4 500 0 500 - chr20 10000 3500 4000 500 500 60", true),
+        ] {
+            let record = crate::paf::PafRecord::from_line(line).unwrap();
+            assert_eq!(conf.is_on_target("Direct_CNS", &record), expected, "{line}");
+        }
+        conf.set_strand_aware(false);
+        assert!(!conf.is_strand_aware());
+        let record =
+            crate::paf::PafRecord::from_line("read2 500 0 500 - chr2 10000 3500 4000 500 500 60")
+                .unwrap();
+        assert!(conf.is_on_target("Direct_CNS", &record));
+    }
+
     #[test]
     fn test_get_csv_record_strand() {
         let record = CsvRecord {
@@ -1565,7 +3205,7 @@ mod tests {
     #[test]
     fn test_insert_into_targets() {
         use std::collections::HashMap;
-        let mut targets: HashMap<StrandWrapper, HashedTargets> = HashMap::new();
+        let mut targets: StrandedTargets = HashMap::new();
         let record = CsvRecord {
             contig: "chr1".to_string(),
             start: Some(100),
@@ -1619,7 +3259,7 @@ mod tests {
     #[cfg_attr(miri, ignore)]
     fn test_load_conf() {
         let test_toml = get_test_file("RAPID_CNS2.toml");
-        let conf = Conf::from_file(test_toml);
+        let conf = Conf::from_file(test_toml).unwrap();
         assert!(conf
             .regions
             .get(0)
@@ -1644,17 +3284,53 @@ mod tests {
     #[test]
     fn test_merge_intervals() {
         assert_eq!(
-            Targets::_merge_intervals(&mut vec![
-                (11, 15),
-                (1, 3),
-                (14, 17),
-                (2, 4),
-                (15, 100),
-                (169, 173),
-                (10, 29)
-            ]),
+            {
+                let mut intervals = vec![
+                    (11, 15),
+                    (1, 3),
+                    (14, 17),
+                    (2, 4),
+                    (15, 100),
+                    (169, 173),
+                    (10, 29),
+                ];
+                merge_intervals(&mut intervals);
+                intervals
+            },
             vec![(1, 4), (10, 100), (169, 173)]
-        )
+        );
+        // Adjacent intervals are coalesced, single intervals are left untouched
+        let mut intervals = vec![(20, 30), (10, 20)];
+        merge_intervals(&mut intervals);
+        assert_eq!(intervals, vec![(10, 30)]);
+        let mut intervals = vec![(5, 6)];
+        merge_intervals(&mut intervals);
+        assert_eq!(intervals, vec![(5, 6)]);
+    }
+
+    #[test]
+    fn test_conf_get_contig_targets() {
+        let conf = Conf::from_string(test_toml_string()).unwrap();
+        assert_eq!(
+            conf.get_contig_targets("Direct_CNS", "chr2", 10_000),
+            vec![(3000, 4000)]
+        );
+        // Clamped to the contig length
+        assert_eq!(
+            conf.get_contig_targets("Direct_CNS", "chr20", 3500),
+            vec![(3000, 3500)]
+        );
+        assert!(conf
+            .get_contig_targets("Direct_CNS", "chr1", 10_000)
+            .is_empty());
+        assert!(conf
+            .get_contig_targets("Not_A_Region", "chr2", 10_000)
+            .is_empty());
+        let conf = Conf::from_file(get_test_file("clockface.toml")).unwrap();
+        assert_eq!(
+            conf.get_contig_targets("barcode03", "NC_002516.2", 6_264_404),
+            vec![(0, 6_264_404)]
+        );
     }
 
     #[test]
@@ -1662,7 +3338,8 @@ mod tests {
         let targets: Targets = Targets::new(TargetType::Direct(vec![
             "chr1,10,20,+".to_string(),
             "chr1,15,30,+".to_string(),
-        ]));
+        ]))
+        .unwrap();
         assert_eq!(
             targets
                 ._targets
@@ -1679,7 +3356,8 @@ mod tests {
         let targets: Targets = Targets::new(TargetType::Direct(vec![
             "chr1,10,20,+".to_string(),
             "chr1,15,30,+".to_string(),
-        ]));
+        ]))
+        .unwrap();
         assert_eq!(
             targets
                 ._targets
@@ -1698,9 +3376,199 @@ mod tests {
         assert!(!targets.check_coords("chr1", -1, 15));
     }
 
+    #[test]
+    fn test_bed_record_from_line() {
+        assert_eq!(BedRecord::from_line("track name=targets").unwrap(), None);
+        assert_eq!(BedRecord::from_line("browser position chr1").unwrap(), None);
+        assert_eq!(BedRecord::from_line("# comment").unwrap(), None);
+        assert_eq!(BedRecord::from_line("").unwrap(), None);
+        assert_eq!(
+            BedRecord::from_line("chr1\t100\t200").unwrap(),
+            Some(BedRecord {
+                contig: "chr1".to_string(),
+                start: 100,
+                stop: 200,
+                name: None,
+                strand: None,
+            })
+        );
+        assert_eq!(
+            BedRecord::from_line("chr1\t100\t200\t.\t0\t.").unwrap(),
+            Some(BedRecord {
+                contig: "chr1".to_string(),
+                start: 100,
+                stop: 200,
+                name: None,
+                strand: None,
+            })
+        );
+        assert!(BedRecord::from_line("chr1\t100").is_err());
+        assert!(BedRecord::from_line("chr1\tstart\t200").is_err());
+    }
+
+    #[test]
+    fn test_load_bed_targets() {
+        let toml = test_toml_string()
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("targets"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut conf = Conf::from_string(&toml).unwrap();
+        assert!(conf
+            .get_contig_targets("Rapid_CNS", "chr1", 10_000)
+            .is_empty());
+        let bed_path = std::env::temp_dir().join("readfish_tools_test_load_bed_targets.bed");
+        std::fs::write(
+            &bed_path,
+            "track name=targets\n\
+             # Shared by both regions\n\
+             \n\
+             chr1\t100\t200\n\
+             chr3\t10\t20\tgeneX\t0\t+\n\
+             chr2\t3000\t4000\tDirect_CNS\t0\t-\n",
+        )
+        .unwrap();
+        conf.load_bed_targets(&bed_path).unwrap();
+        assert_eq!(
+            conf.get_contig_targets("Rapid_CNS", "chr1", 10_000),
+            vec![(100, 200)]
+        );
+        assert_eq!(
+            conf.get_contig_targets("Direct_CNS", "chr1", 10_000),
+            vec![(100, 200)]
+        );
+        assert_eq!(
+            conf.get_contig_targets("Rapid_CNS", "chr3", 10_000),
+            vec![(10, 20)]
+        );
+        assert!(conf
+            .get_contig_targets("Rapid_CNS", "chr2", 10_000)
+            .is_empty());
+        assert_eq!(
+            conf.get_contig_targets("Direct_CNS", "chr2", 10_000),
+            vec![(3000, 4000)]
+        );
+        // 0-based half-open, so the stop coordinate is not a target
+        let targets = conf.get_contig_targets("Rapid_CNS", "chr1", 10_000);
+        assert!(overlaps_intervals(&targets, 199, 300));
+        assert!(!overlaps_intervals(&targets, 200, 300));
+        assert!(!overlaps_intervals(&targets, 0, 100));
+        // The strand of a record is kept
+        let rapid_cns = conf.find_condition("Rapid_CNS").unwrap();
+        assert!(rapid_cns.targets.check_coords("chr3", "+", 15));
+        assert!(!rapid_cns.targets.check_coords("chr3", "-", 15));
+        assert!(rapid_cns.targets.check_coords("chr1", "-", 150));
+
+        std::fs::write(&bed_path, "chr1\t100\n").unwrap();
+        let err = conf.load_bed_targets(&bed_path).unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+        std::fs::remove_file(bed_path).unwrap();
+
+        // Compressed BED files are decompressed, whatever their extension
+        let bed_path = std::env::temp_dir().join("readfish_tools_test_load_bed_targets.bed.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, b"chr4\t500\t600\n").unwrap();
+        std::fs::write(&bed_path, encoder.finish().unwrap()).unwrap();
+        conf.load_bed_targets(&bed_path).unwrap();
+        assert_eq!(
+            conf.get_contig_targets("Rapid_CNS", "chr4", 10_000),
+            vec![(500, 600)]
+        );
+        std::fs::remove_file(bed_path).unwrap();
+    }
+
+    #[test]
+    fn test_is_bed_path() {
+        assert!(is_bed_path(Path::new("targets.bed")));
+        assert!(is_bed_path(Path::new("panel/targets.bed.gz")));
+        assert!(is_bed_path(Path::new("targets.bed.bz2")));
+        assert!(!is_bed_path(Path::new("targets.csv")));
+        assert!(!is_bed_path(Path::new("targets.csv.gz")));
+        assert!(!is_bed_path(Path::new("bed.gz")));
+    }
+
+    #[test]
+    fn test_targets_overlaps() {
+        let targets = Targets::new(TargetType::Direct(vec![
+            "chr1,100,200,+".to_string(),
+            "chr1,150,300,-".to_string(),
+            "chr1,500,600,-".to_string(),
+            "chr2".to_string(),
+        ]))
+        .unwrap();
+        // Both strands are pooled and merged once
+        assert_eq!(
+            targets.contig_intervals("chr1", None),
+            &[(100, 300), (500, 600)]
+        );
+        assert_eq!(
+            targets.contig_intervals("chr1", Some(Strand::Forward)),
+            &[(100, 200)]
+        );
+        assert!(targets.overlaps("chr1", 250, 260));
+        assert!(targets.overlaps("chr1", 0, 101));
+        assert!(!targets.overlaps("chr1", 300, 500));
+        assert!(targets.overlaps("chr2", 1_000_000, 1_000_100));
+        assert!(!targets.overlaps("chr3", 0, 100));
+        assert_eq!(targets.overlapping("chr1", 250, 550), 0..2);
+        assert_eq!(targets.overlapping("chr1", 550, 700), 1..2);
+        assert!(targets.overlapping("chr1", 300, 500).is_empty());
+        assert!(targets.overlapping("chr3", 0, 100).is_empty());
+
+        let conf = Conf::from_string(test_toml_string()).unwrap();
+        assert!(conf.overlaps("Direct_CNS", "chr2", 3900, 4900));
+        assert!(!conf.overlaps("Direct_CNS", "chr2", 4000, 4900));
+        assert!(!conf.overlaps("Not_A_Region", "chr2", 3900, 4900));
+        assert_eq!(
+            conf.overlapping_targets("Direct_CNS", "chr2", 3900, 4900),
+            0..1
+        );
+        assert!(conf
+            .overlapping_targets("Not_A_Region", "chr2", 3900, 4900)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_conf_target_contigs() {
+        let conf = Conf::from_string(test_toml_string()).unwrap();
+        assert_eq!(conf.target_contigs("Direct_CNS"), vec!["chr2", "chr20"]);
+        assert!(conf.target_contigs("Not_A_Region").is_empty());
+        // Composite region/barcode keys find the targets of the barcode
+        let conf = Conf::from_file(get_test_file("clockface.toml")).unwrap();
+        assert_eq!(
+            conf.target_contigs("Not_A_Region/barcode03"),
+            conf.target_contigs("barcode03")
+        );
+        assert_eq!(
+            conf.get_contig_targets("Not_A_Region/barcode03", "NC_002516.2", 6_264_404),
+            vec![(0, 6_264_404)]
+        );
+    }
+
+    #[test]
+    fn test_conf_target_fraction() {
+        let conf = Conf::from_string(test_toml_string()).unwrap();
+        let reference_lengths = HashMap::from([
+            ("chr2".to_string(), 10_000),
+            ("chr20".to_string(), 3500),
+            ("chr21".to_string(), 6500),
+        ]);
+        // chr2 3000-4000 and chr20 3000-3500, clamped to the contig length
+        assert_eq!(
+            conf.target_fraction("Direct_CNS", &reference_lengths),
+            Some(0.075)
+        );
+        assert_eq!(conf.target_fraction("Rapid_CNS", &reference_lengths), None);
+        assert_eq!(
+            conf.target_fraction("Not_A_Region", &reference_lengths),
+            None
+        );
+        assert_eq!(conf.target_fraction("Direct_CNS", &HashMap::new()), None);
+    }
+
     #[test]
     fn test_get_coord_contig() {
-        let targets: Targets = Targets::new(TargetType::Direct(vec!["chr1".to_string()]));
+        let targets: Targets = Targets::new(TargetType::Direct(vec!["chr1".to_string()])).unwrap();
         assert_eq!(
             targets
                 ._targets
@@ -1723,7 +3591,7 @@ mod tests {
     #[cfg_attr(miri, ignore)]
     fn test_load_barcoded_conf() {
         let test_toml = get_test_file("clockface.toml");
-        let conf = Conf::from_file(test_toml);
+        let conf = Conf::from_file(test_toml).unwrap();
         assert!(conf.regions.is_empty());
         assert_eq!(
             conf.barcodes.get("barcode01").unwrap().condition.name,
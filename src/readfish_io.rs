@@ -1,19 +1,87 @@
 //! Io functions for reading/writing gzipped or uncompressed files.
 
-use flate2::{read::GzDecoder, Compression};
+use bzip2::read::MultiBzDecoder;
+use flate2::{read::MultiGzDecoder, Compression};
 use gzp::{deflate::Bgzf, ZBuilder};
 use std::{
+    collections::HashMap,
     error::Error,
     ffi::OsStr,
     fs::File,
     io::{
-        stdin, stdout, BufRead, BufReader, BufWriter, Read, Result as ioResult, Seek, SeekFrom,
-        Write,
+        self, stdin, stdout, BufRead, BufReader, BufWriter, Read, Result as ioResult, Seek,
+        SeekFrom, Write,
     },
     path::{Path, PathBuf},
 };
 /// Small default BUFFER_SIZE for buffered readers
 const BUFFER_SIZE: usize = 32 * 1024;
+/// The magic bytes found at the start of every gzip (and bgzip) member.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// The magic bytes found at the start of every bzip2 stream, `BZh`.
+const BZIP2_MAGIC: [u8; 3] = [0x42, 0x5a, 0x68];
+
+/// The compression of an input file or stream, detected from the magic bytes at its start rather than its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionFormat {
+    /// Gzip or bgzip compressed, starting with `1f 8b`.
+    Gzip,
+    /// Bzip2 compressed, starting with `BZh` (`42 5a 68`).
+    Bzip2,
+    /// Anything else, read as plain text.
+    Uncompressed,
+}
+
+impl CompressionFormat {
+    /// The longest magic bytes that are checked, so the number of bytes needed to detect any format.
+    pub const MAGIC_LEN: usize = BZIP2_MAGIC.len();
+
+    /// Detect the compression from the first bytes of a file or stream. Fewer than [`Self::MAGIC_LEN`] bytes, such as
+    /// an empty file, are uncompressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `start`: The first bytes of the file, which may be longer than the magic bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// assert_eq!(CompressionFormat::detect(&[0x1f, 0x8b, 0x08]), CompressionFormat::Gzip);
+    /// assert_eq!(CompressionFormat::detect(b"BZh91AY"), CompressionFormat::Bzip2);
+    /// assert_eq!(CompressionFormat::detect(b"read1\t200"), CompressionFormat::Uncompressed);
+    /// ```
+    pub fn detect(start: &[u8]) -> CompressionFormat {
+        if start.starts_with(&GZIP_MAGIC) {
+            CompressionFormat::Gzip
+        } else if start.starts_with(&BZIP2_MAGIC) {
+            CompressionFormat::Bzip2
+        } else {
+            CompressionFormat::Uncompressed
+        }
+    }
+
+    /// Detect the compression of a file from its first bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file to check.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or read.
+    pub fn of_file(path: impl AsRef<Path>) -> ioResult<CompressionFormat> {
+        let mut magic = Vec::with_capacity(Self::MAGIC_LEN);
+        File::open(path)?
+            .take(Self::MAGIC_LEN as u64)
+            .read_to_end(&mut magic)?;
+        Ok(CompressionFormat::detect(&magic))
+    }
+
+    /// Whether the format is compressed, so decompressed offsets can't be seeked to directly.
+    pub fn is_compressed(&self) -> bool {
+        *self != CompressionFormat::Uncompressed
+    }
+}
 
 /// Dynamic result type for holding either a generic value or an error
 pub type DynResult<T> = Result<T, Box<dyn Error + 'static>>;
@@ -135,9 +203,11 @@ impl<R: BufRead> BufRead for ByteCounter<R> {
 /// A wrapper struct for a Gzip-compressed reader that also supports seeking.
 /// It allows for consuming exact bytes to efficiently skip to a specific point in the reader.
 /// I couldn't find the functionality I needed in the flate2 crate, so I had to implement it myself.
+///
+/// A `MultiGzDecoder` is used, so that bgzipped files (which are made of many gzip members) are read in their entirety.
 struct GzSeekable<R: Read + BufRead + Seek> {
-    /// The inner reader, wrapping the GzDecoder
-    inner: BufReader<GzDecoder<R>>,
+    /// The inner reader, wrapping the MultiGzDecoder
+    inner: BufReader<MultiGzDecoder<R>>,
 }
 impl<R: Read + BufRead + Seek> GzSeekable<R> {
     /// Create a new `GzSeekable` instance wrapping the provided reader `inner`.
@@ -151,7 +221,7 @@ impl<R: Read + BufRead + Seek> GzSeekable<R> {
     /// A new `GzSeekable` instance.
     fn new(inner: R) -> Self {
         GzSeekable {
-            inner: BufReader::new(GzDecoder::new(inner)),
+            inner: BufReader::new(MultiGzDecoder::new(inner)),
         }
     }
 }
@@ -167,10 +237,11 @@ impl<R: Read + BufRead + Seek> GzSeekable<R> {
     ///
     /// * `amt`: The number of bytes to consume from the reader.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// This function will panic if any I/O error occurs while reading from the underlying reader,
-    /// which can happen if the input data is truncated or corrupted.
+    /// Returns an error if any I/O error occurs while reading from the underlying reader, which can happen if the
+    /// input data is truncated or corrupted, or an [`io::ErrorKind::UnexpectedEof`] error if the decompressed data is
+    /// shorter than `amt`.
     ///
     /// # Example
     ///
@@ -184,21 +255,20 @@ impl<R: Read + BufRead + Seek> GzSeekable<R> {
     /// let mut reader = GzSeekable::new(&data[..]);
     ///
     /// // Consume the first 10 bytes without decoding the remaining data.
-    /// reader.consume_exact(10);
+    /// reader.consume_exact(10)?;
     /// ```
     ///
     /// In this example, the `consume_exact` method is used to skip the first 10 bytes of the compressed data
     /// without decoding the rest of the data, which can be useful for seeking to a specific position efficiently.
-
-    fn consume_exact(&mut self, amt: usize) {
-        let q = amt.checked_div_euclid(10000).unwrap();
-        let r = amt.checked_rem_euclid(10000).unwrap();
+    fn consume_exact(&mut self, amt: usize) -> ioResult<()> {
+        let q = amt / 10000;
+        let r = amt % 10000;
         let mut buf = [0; 10000];
         for _i in 0..q {
-            self.inner.read_exact(&mut buf).unwrap();
+            self.inner.read_exact(&mut buf)?;
         }
         let mut buf = vec![0; r];
-        self.inner.read_exact(&mut buf).unwrap();
+        self.inner.read_exact(&mut buf)
     }
 }
 
@@ -217,48 +287,82 @@ impl<R: Read + BufRead + Seek> Read for GzSeekable<R> {
     }
 }
 
-/// Get a buffered input reader from stdin or a file
+/// Check whether a file is gzip (or bgzip) compressed, from the magic bytes at its start. See
+/// [`CompressionFormat::of_file`] to check for any compression.
 ///
 /// # Arguments
 ///
-/// * `path`: Optional path to a file. If provided, the function will attempt to open the file and create a buffered reader from it. If set to `None`, the function will create a buffered reader from stdin.
-/// * `seek_bytes`: Optional number of bytes to seek into the file before reading. If `Some`, the function will seek to the specified number of bytes before reading the file. If `None`, the function will read the file from the beginning.
+/// * `path`: The path of the file to check.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or read.
+pub fn is_gzipped(path: impl AsRef<Path>) -> ioResult<bool> {
+    Ok(CompressionFormat::of_file(path)? == CompressionFormat::Gzip)
+}
+
+/// Peek at the start of a buffered reader, without consuming anything, to detect its compression.
+///
+/// # Errors
+///
+/// Returns an error if the start of the input cannot be read.
+fn peek_compression(buf_reader: &mut impl BufRead) -> ioResult<CompressionFormat> {
+    Ok(CompressionFormat::detect(buf_reader.fill_buf()?))
+}
+
+/// Wrap a buffered reader, whose compression has already been peeked, in the decoder for that compression.
+///
+/// # Arguments
+///
+/// * `format`: The compression of the input, see [`peek_compression`].
+/// * `buf_reader`: The reader, positioned at the start of the input, so the magic bytes are passed to the decoder.
+fn decoder<R: BufRead + Send + 'static>(
+    format: CompressionFormat,
+    buf_reader: R,
+) -> Box<dyn BufRead + Send + 'static> {
+    match format {
+        CompressionFormat::Gzip => Box::new(BufReader::with_capacity(
+            BUFFER_SIZE,
+            MultiGzDecoder::new(buf_reader),
+        )),
+        CompressionFormat::Bzip2 => Box::new(BufReader::with_capacity(
+            BUFFER_SIZE,
+            MultiBzDecoder::new(buf_reader),
+        )),
+        CompressionFormat::Uncompressed => Box::new(buf_reader),
+    }
+}
+
+/// Get a buffered reader over a stream that can't seek, such as stdin, decompressing it if it is gzip or bzip2
+/// compressed.
+///
+/// The start of the stream is peeked, without consuming it, to check for the magic bytes, as with [`reader`].
+///
+/// # Arguments
+///
+/// * `inner`: The stream to read from.
+///
 /// # Returns
 ///
-/// A dynamic result that represents either a buffered reader on success or an error wrapped in a `Box<dyn Error + 'static>` on failure.
+/// A boxed trait object implementing `BufRead` over the (decompressed) contents of the stream.
 ///
-/// # Examples
+/// # Errors
 ///
-/// ```rust,ignore
-/// let reader = _get_reader_from_path(Some(PathBuf::from("path/to/file")))?;
-/// ```
-fn _get_reader_from_path(
-    path: Option<PathBuf>,
-    seek_bytes: Option<usize>,
-) -> DynResult<Box<dyn BufRead + Send + 'static>> {
-    let reader: Box<dyn BufRead + Send + 'static> = match path {
-        Some(path) => {
-            // stdin
-            if path.as_os_str() == "-" {
-                Box::new(BufReader::with_capacity(BUFFER_SIZE, stdin()))
-            } else {
-                // open file
-                let mut buf = BufReader::with_capacity(BUFFER_SIZE, File::open(path)?);
-                buf.seek(SeekFrom::Start(seek_bytes.unwrap_or(0) as u64))?;
-                Box::new(buf)
-            }
-        }
-        // nothing passed as input, read from stdin
-        None => Box::new(BufReader::with_capacity(BUFFER_SIZE, stdin())),
-    };
-    Ok(reader)
+/// Returns an error if the start of the stream cannot be read.
+pub fn stream_reader<R: Read + Send + 'static>(
+    inner: R,
+) -> ioResult<Box<dyn BufRead + Send + 'static>> {
+    let mut buf_reader = BufReader::with_capacity(BUFFER_SIZE, inner);
+    let format = peek_compression(&mut buf_reader)?;
+    Ok(decoder(format, buf_reader))
 }
 
 /// Read normal or compressed files seamlessly
 ///
 /// This function provides a convenient way to read both normal and compressed files.
-///  It automatically detects whether the file is compressed based on the presence of a
-/// `.gz` or `.bgz` extension in the filename.
+/// It automatically detects whether the file is gzip (or bgzip) or bzip2 compressed by checking
+/// for the gzip (`1f 8b`) or bzip2 (`42 5a 68`) magic bytes at the start of the file, so the file extension does not
+/// matter. See [`CompressionFormat`].
 ///
 /// # Examples
 ///
@@ -285,42 +389,86 @@ fn _get_reader_from_path(
 ///
 /// # Arguments
 ///
-/// * `filename`: The path or filename of the file to read. If "-" is provided, the function will read from stdin.
+/// * `filename`: The path or filename of the file to read. If "-" is provided, the function will read from stdin,
+///   which is also decompressed if gzipped, see [`stream_reader`].
 /// * `seek_bytes`: Optional number of bytes to seek into the file before reading. If `Some`, the function will seek to the specified number of bytes before reading the file. If `None`, the function will read the file from the beginning.
+///   For compressed files, this is the number of decompressed bytes to skip. Stdin can't be rewound, so can only be read from the start.
 ///
 /// # Returns
 ///
 /// A boxed trait object implementing `BufRead`, which can be used to read the contents of the file.
-/// Uses the presence of a `.gz` or `.bgz` extension to decide
+/// Uses the magic bytes at the start of the file to decide how to decompress it.
+///
+/// # Panics
+///
+/// Panics if the file cannot be opened or read, or if a seek into stdin is requested. See [`try_reader`] to handle
+/// these errors instead.
 pub fn reader(
     filename: impl AsRef<Path>,
     seek_bytes: Option<usize>,
 ) -> Box<dyn BufRead + Send + 'static> {
-    let ext = filename.as_ref().extension();
-    let path: PathBuf = filename.as_ref().to_path_buf();
-    // Handle Gzipped files first, since need to use flate2::read::GzDecoder
-
-    if ext == Some(OsStr::new("gz")) {
-        let file = match File::open(&path) {
-            Err(why) => panic!("couldn't open {}: {}", path.display(), why),
-            Ok(file) => file,
-        };
-        // Wrap the file in a `BufReader`
-        let buf_reader = BufReader::new(file);
-
-        // Wrap the `BufReader` in a `GzSeekable`, which are implementations that hopefully let us seek a certain amount into the file
-        let mut wrapper = GzSeekable::new(buf_reader);
-        // Read bytes indiscriminately, should be faster than iterating over lines?
-        wrapper.consume_exact(seek_bytes.unwrap_or(0));
-        let x = BufReader::new(wrapper);
-        Box::new(x)
-
-    // } else if ext == Some(OsStr::new("bgz")) {
-    //     Box::new(BufReader::new(BgzfSyncReader::new(
-    //         get_input(Some(path)).expect("Error: cannot read input file."),
-    //     )))
-    } else {
-        _get_reader_from_path(Some(path), seek_bytes).expect("Error: cannot read input file")
+    let path = filename.as_ref();
+    try_reader(path, seek_bytes)
+        .unwrap_or_else(|why| panic!("couldn't open {}: {}", path.display(), why))
+}
+
+/// Read normal or compressed files seamlessly, returning an error rather than panicking as [`reader`] does.
+///
+/// The file is only opened once. Its start is peeked into the read buffer, without consuming it, to detect the
+/// compression, and the same buffered reader is then handed to the decoder, magic bytes and all.
+///
+/// # Arguments
+///
+/// * `filename`: The path of the file to read, or "-" for stdin.
+/// * `seek_bytes`: Optional number of bytes to skip before reading, of the decompressed contents for a compressed
+///   file. Stdin can only be read from the start.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened, read or seeked, or if a seek into stdin is requested.
+pub fn try_reader(
+    filename: impl AsRef<Path>,
+    seek_bytes: Option<usize>,
+) -> ioResult<Box<dyn BufRead + Send + 'static>> {
+    let path = filename.as_ref();
+    let seek_bytes = seek_bytes.unwrap_or(0);
+    if path.as_os_str() == "-" {
+        if seek_bytes != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "cannot seek in stdin, it can only be read once from the start",
+            ));
+        }
+        return stream_reader(stdin());
+    }
+    let mut buf_reader = BufReader::with_capacity(BUFFER_SIZE, File::open(path)?);
+    match peek_compression(&mut buf_reader)? {
+        CompressionFormat::Gzip => {
+            // Wrap the `BufReader` in a `GzSeekable`, which are implementations that hopefully let us seek a certain amount into the file
+            let mut wrapper = GzSeekable::new(buf_reader);
+            // Read bytes indiscriminately, should be faster than iterating over lines?
+            wrapper.consume_exact(seek_bytes)?;
+            Ok(Box::new(BufReader::new(wrapper)))
+        }
+        CompressionFormat::Bzip2 => {
+            let mut decoded = decoder(CompressionFormat::Bzip2, buf_reader);
+            // Bzip2 blocks can't be seeked into, so the decompressed bytes before the offset are read and dropped
+            let skipped = io::copy(
+                &mut decoded.by_ref().take(seek_bytes as u64),
+                &mut io::sink(),
+            )?;
+            if skipped < seek_bytes as u64 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("cannot skip {seek_bytes} bytes of a file of {skipped} bytes"),
+                ));
+            }
+            Ok(decoded)
+        }
+        CompressionFormat::Uncompressed => {
+            buf_reader.seek(SeekFrom::Start(seek_bytes as u64))?;
+            Ok(Box::new(buf_reader))
+        }
     }
 }
 
@@ -360,7 +508,7 @@ pub fn reader(
 ///     }
 /// }
 /// ```
-fn _get_writer_from_path(path: Option<PathBuf>) -> DynResult<Box<dyn Write + Send + 'static>> {
+fn _get_writer_from_path(path: Option<PathBuf>) -> ioResult<Box<dyn Write + Send + 'static>> {
     let writer: Box<dyn Write + Send + 'static> = match path {
         Some(path) => {
             if path.as_os_str() == "-" {
@@ -379,35 +527,88 @@ fn _get_writer_from_path(path: Option<PathBuf>) -> DynResult<Box<dyn Write + Sen
 ///
 /// # Arguments
 ///
-/// * `filename` - The name of the file to write to, including the extension.
+/// * `filename` - The path of the file to write to, including the extension, or `-` for stdout.
 ///
 /// # Returns
 ///
 /// A boxed trait object (`Box<dyn Write>`) representing the writer for the specified file.
 ///
+/// # Errors
+///
+/// Returns an error if the file cannot be created, for example if its directory doesn't exist.
+///
 /// # Examples
 ///
 /// ```rust,ignore
 /// use std::io::Write;
-/// let mut writer = writer("output.txt");
-/// writer.write_all(b"Hello, world!").expect("Failed to write data");
+/// let mut writer = writer("output.txt")?;
+/// writer.write_all(b"Hello, world!")?;
 /// ```
-pub fn writer(filename: &str) -> Box<dyn Write> {
-    let ext = Path::new(filename).extension();
-    let path = PathBuf::from(filename);
-    let buffer = _get_writer_from_path(Some(path)).expect("Error: cannot create output file");
+pub fn writer(filename: impl AsRef<Path>) -> ioResult<Box<dyn Write>> {
+    let path = filename.as_ref();
+    let buffer = _get_writer_from_path(Some(path.to_path_buf()))?;
 
-    if ext == Some(OsStr::new("gz")) {
+    if path.extension() == Some(OsStr::new("gz")) {
         let writer = ZBuilder::<Bgzf, _>::new()
             .num_threads(8)
             .compression_level(Compression::new(6))
             .from_writer(buffer);
-        Box::new(writer)
+        Ok(Box::new(writer))
     } else {
-        buffer
+        Ok(buffer)
     }
 }
 
+/// Reads the contig lengths from a samtools faidx `.fai` index of a reference.
+///
+/// Only the first two columns, the contig name and its length, are used. Blank lines are skipped.
+///
+/// # Arguments
+///
+/// * `fai_path` - The path to the `.fai` file.
+///
+/// # Returns
+///
+/// A hashmap of contig name to contig length.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, or if a line has no length or the length is not an integer,
+/// naming the file and line number.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// let lengths = read_fai("hg38.fa.fai")?;
+/// assert_eq!(lengths["chr1"], 248_956_422);
+/// ```
+pub fn read_fai(fai_path: impl AsRef<Path>) -> DynResult<HashMap<String, usize>> {
+    let fai_path = fai_path.as_ref();
+    let file = File::open(fai_path)
+        .map_err(|err| format!("Could not open fai file {}: {err}", fai_path.display()))?;
+    let mut contig_lengths = HashMap::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let contig = fields.next().unwrap_or_default();
+        let length = fields
+            .next()
+            .and_then(|length| length.trim().parse::<usize>().ok())
+            .ok_or_else(|| {
+                format!(
+                    "Malformed fai line {} of {}: expected a contig name and an integer length",
+                    index + 1,
+                    fai_path.display()
+                )
+            })?;
+        contig_lengths.insert(contig.to_string(), length);
+    }
+    Ok(contig_lengths)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -438,7 +639,7 @@ mod tests {
         let mut reader = GzSeekable::new(buf_reader);
 
         // Consume 20 bytes.
-        reader.consume_exact(20);
+        reader.consume_exact(20).unwrap();
         let mut bc = ByteCounter::new(reader);
         bc.read_to_end(&mut Vec::new()).unwrap();
         // Verify that the reader position has been updated correctly.
@@ -456,13 +657,131 @@ mod tests {
         let buf_reader = BufReader::new(Cursor::new(compressed_data));
         let mut reader = GzSeekable::new(buf_reader);
         // Consume 20 bytes.
-        reader.consume_exact(43219);
+        reader.consume_exact(43219).unwrap();
         let mut bc = ByteCounter::new(reader);
         bc.read_to_end(&mut Vec::new()).unwrap();
         // Verify that the reader position has been updated correctly.
         assert_eq!(bc.bytes_read(), 56781);
     }
 
+    #[test]
+    fn test_consume_exact_past_end() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[0; 100]).unwrap();
+        let compressed_data = encoder.finish().unwrap();
+        let mut reader = GzSeekable::new(BufReader::new(Cursor::new(compressed_data)));
+        let err = reader.consume_exact(10_050).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        // Seeking past the end of a gzipped file is an error, rather than a panic
+        let path = get_test_file("test_hum_4000.paf.gz");
+        let err = try_reader(path, Some(usize::MAX / 2)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_is_gzipped() {
+        assert!(is_gzipped(get_test_file("test_hum_4000.paf.gz")).unwrap());
+        assert!(!is_gzipped(get_test_file("test_hum_4000.paf")).unwrap());
+        assert!(!is_gzipped(get_test_file("empty.paf")).unwrap());
+    }
+
+    #[test]
+    fn test_compression_format() {
+        assert_eq!(
+            CompressionFormat::detect(&[0x1f, 0x8b, 0x08]),
+            CompressionFormat::Gzip
+        );
+        assert_eq!(
+            CompressionFormat::detect(b"BZh91AY&SY"),
+            CompressionFormat::Bzip2
+        );
+        assert_eq!(
+            CompressionFormat::detect(b"read1\t200"),
+            CompressionFormat::Uncompressed
+        );
+        // Too short to hold any magic bytes
+        assert_eq!(
+            CompressionFormat::detect(b"BZ"),
+            CompressionFormat::Uncompressed
+        );
+        assert_eq!(
+            CompressionFormat::of_file(get_test_file("test_hum_4000.paf.gz")).unwrap(),
+            CompressionFormat::Gzip
+        );
+        assert_eq!(
+            CompressionFormat::of_file(get_test_file("empty.paf")).unwrap(),
+            CompressionFormat::Uncompressed
+        );
+        assert!(CompressionFormat::Bzip2.is_compressed());
+        assert!(!CompressionFormat::Uncompressed.is_compressed());
+    }
+
+    #[test]
+    fn test_reader_bzip2() {
+        // Bzip2 data with no .bz2 extension, written as two streams like a concatenated bzip2 file
+        let path = std::env::temp_dir().join("readfish_tools_test_bzip2_magic.paf");
+        let mut file = File::create(&path).unwrap();
+        for data in [b"Line 1\nLine 2\n", b"Line 3\nLine 4\n"] {
+            let mut encoder =
+                bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            encoder.write_all(data).unwrap();
+            file.write_all(&encoder.finish().unwrap()).unwrap();
+        }
+        drop(file);
+        assert_eq!(
+            CompressionFormat::of_file(&path).unwrap(),
+            CompressionFormat::Bzip2
+        );
+        let lines: Vec<String> = reader(&path, None).lines().map(|l| l.unwrap()).collect();
+        assert_eq!(lines, vec!["Line 1", "Line 2", "Line 3", "Line 4"]);
+        let mut line = String::new();
+        reader(&path, Some(14)).read_line(&mut line).unwrap();
+        assert_eq!(line, "Line 3\n");
+        assert!(try_reader(&path, Some(100)).is_err());
+        let stream = File::open(&path).unwrap();
+        assert_eq!(stream_reader(stream).unwrap().lines().count(), 4);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_try_reader_errors() {
+        assert!(try_reader(get_resource_dir().join("not_a_file.paf"), None).is_err());
+        assert!(try_reader("-", Some(10)).is_err());
+    }
+
+    #[test]
+    fn test_writer() {
+        // A file that can't be created is an error, rather than a panic
+        assert!(writer(std::env::temp_dir().join("not_a_dir").join("out.paf")).is_err());
+
+        let path = std::env::temp_dir().join("readfish_tools_test_writer.txt.gz");
+        {
+            let mut w = writer(&path).unwrap();
+            w.write_all(b"line1\n").unwrap();
+        }
+        let lines: Vec<String> = reader(path.to_str().unwrap(), None)
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+        assert_eq!(lines, ["line1"]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_stream_reader() {
+        // Streams can't seek, so the gzip magic bytes must be peeked rather than read
+        let gzipped = std::fs::File::open(get_test_file("test_hum_4000.paf.gz")).unwrap();
+        let n_lines = stream_reader(gzipped).unwrap().lines().count();
+        assert_eq!(n_lines, 4148);
+        let plain = Cursor::new(b"line1\nline2\n".to_vec());
+        let lines: Vec<String> = stream_reader(plain)
+            .unwrap()
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+        assert_eq!(lines, ["line1", "line2"]);
+    }
+
     #[test]
     fn test_byte_counter_new() {
         let data = b"Hello, world!";
@@ -524,6 +843,25 @@ mod tests {
             .count();
         assert_eq!(n_lines_gz, 4148usize);
     }
+    #[test]
+    fn test_reader_gzip_magic_bytes() {
+        // Gzipped data with no .gz extension, written as two gzip members like a bgzipped file
+        let path = std::env::temp_dir().join("readfish_tools_test_gzip_magic.paf");
+        let mut file = File::create(&path).unwrap();
+        for data in [b"Line 1\nLine 2\n", b"Line 3\nLine 4\n"] {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).unwrap();
+            file.write_all(&encoder.finish().unwrap()).unwrap();
+        }
+        drop(file);
+        let lines: Vec<String> = reader(&path, None).lines().map(|l| l.unwrap()).collect();
+        assert_eq!(lines, vec!["Line 1", "Line 2", "Line 3", "Line 4"]);
+        let mut line = String::new();
+        reader(&path, Some(14)).read_line(&mut line).unwrap();
+        assert_eq!(line, "Line 3\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_reader_seek() {
         let mut reader = reader(get_test_file("test_hum_4000.paf"), Some(36));
@@ -545,4 +883,23 @@ mod tests {
         assert_eq!(line.len(), 119);
         assert_eq!(line, "635\t36\t632\t+\tNC_000007.14\t159345973\t115197340\t115197933\t453\t597\t60\ttp:A:P\tcm:i:66\ts1:i:452\ts2:i:63\tdv:f:0.0219\trl:i:138");
     }
+
+    #[test]
+    fn test_read_fai() {
+        let fai_path = std::env::temp_dir().join("readfish_tools_test_read_fai.fa.fai");
+        std::fs::write(
+            &fai_path,
+            "chr1\t248956422\t112\t70\t71\n\nchrM\t16569\t253105752\t70\t71\n",
+        )
+        .unwrap();
+        let contig_lengths = read_fai(&fai_path).unwrap();
+        assert_eq!(contig_lengths.len(), 2);
+        assert_eq!(contig_lengths["chr1"], 248_956_422);
+        assert_eq!(contig_lengths["chrM"], 16569);
+        std::fs::write(&fai_path, "chr1\tlong\n").unwrap();
+        let err = read_fai(&fai_path).unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+        assert!(read_fai(get_resource_dir().join("not_a_file.fai")).is_err());
+        std::fs::remove_file(fai_path).unwrap();
+    }
 }
@@ -3,15 +3,30 @@
 //! to match the demultiplexed PAF file on Read ID. This is the purpose of the `get_record` function.
 //!
 //! A buffer is used to store the sequencing summary records. The buffer is a linked hash map,
-//!  with read ID as the key and tuples containing `SeqSumInfo` variants as the values.
+//!  with read ID as the key and [`SeqSumRecord`]s as the values.
 //! Currently 100,000 records are stored in the buffer, with the oldest record being removed when a new record is added.
 //! If a PAF record is not found in the buffer, the file is rolled along until the record is found.
-use crate::readfish_io::{reader, ByteCounter, DynResult};
+//!
+//! Alternatively, [`SeqSum::build_index`] scans the file once for the byte offset of every record, so records can be
+//! looked up in any order by seeking straight to them. This is unavailable for compressed files, which fall back to the buffer.
+//!
+//! The columns are found from the header line with a [`SeqSumSchema`], which knows the names MinKNOW has used for each
+//! field across versions, such as `barcode_arrangement` and `barcode`, and can be overridden for unusual summaries.
+//!
+//! The sequencing summary can be read from stdin by passing a path of `-`, in which case it is read as a single
+//! forward stream. Random access into the sequencing summary, such as reopening it at an offset, is unavailable on stdin.
+//!
+//! Multi-flowcell or resumed runs write several sequencing summaries, which [`SeqSum::from_files`] reads as one, rolling
+//! along each file in turn, so they don't have to be concatenated first.
+use crate::readfish_io::{try_reader, ByteCounter, CompressionFormat, DynResult};
 use linked_hash_map::LinkedHashMap;
 // use rayon::prelude::*;
 use std::io::Lines;
 use std::{
-    io::{BufRead, Read},
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
 };
 /// Data structure representing sequencing summary information.
@@ -19,14 +34,15 @@ use std::{
 /// The `SeqSum` struct stores various sequencing summary related fields:
 /// - `sequencing_summary_path`: Path to the sequencing summary file.
 /// - `writers`: A vector of multiple writers, one for each demultiplexed file.
-/// - `record_buffer`: A linked hash map storing the sequencing summary records, with read ID as the key and tuples containing `SeqSumInfo` variants as the values.
+/// - `record_buffer`: A linked hash map storing the sequencing summary records, with read ID as the key and [`SeqSumRecord`]s as the values.
 /// - `has_barcode`: A boolean indicating whether barcode arrangement is present in the sequencing summary file.
 /// - `current_position`: The current position in the file read by the `BufReader`.
-/// - `column_indices`: A tuple representing the column indices of `read_id`, `channel`, and `barcode_arrangement` in the sequencing summary file.
+/// - `has_qscore`: A boolean indicating whether mean_qscore_template is present in the sequencing summary file.
+/// - `column_indices`: The [`SeqSumColumns`] holding the index of each field in the sequencing summary file.
 ///
 /// # Examples
 /// ```rust,ignore
-/// use crate::sequencing_summary::{SeqSum, SeqSumInfo};
+/// use crate::sequencing_summary::{SeqSum, SeqSumColumns};
 /// use std::path::PathBuf;
 /// use std::io::Write;
 /// use linked_hash_map::LinkedHashMap;
@@ -34,88 +50,450 @@ use std::{
 /// // Create a new `SeqSum` instance
 /// let sequencing_summary_path = PathBuf::from("sequencing_summary.txt");
 /// let writers: Vec<Box<dyn Write>> = Vec::new();
-/// let record_buffer: LinkedHashMap<String, (SeqSumInfo, SeqSumInfo, SeqSumInfo)> = LinkedHashMap::new();
+/// let record_buffer: LinkedHashMap<String, SeqSumRecord> = LinkedHashMap::new();
 /// let has_barcode = false;
+/// let has_qscore = false;
 /// let current_position = 0;
-/// let column_indices = (0, 1, 2);
+/// let column_indices = SeqSumColumns { read_id: 0, channel: 1, barcode: None, length: None, mean_qscore: None, start_time: None, end_reason: None };
 /// let seq_sum = SeqSum {
 ///     sequencing_summary_path,
 ///     writers,
 ///     record_buffer,
 ///     has_barcode,
+///     has_qscore,
 ///     current_position,
 ///     column_indices,
 /// };
 /// ```
 pub struct SeqSum {
-    /// Path to the sequencing summary file.
+    /// Path to the sequencing summary file, or the file currently being read if there are several, see
+    /// [`SeqSum::from_files`].
     pub sequencing_summary_path: PathBuf,
     /// Multiple writes, one for each demultiplexed file.
     // pub writers: Vec<Box<dyn Write>>,
-    /// Record buffer for the sequencing summary
-    pub record_buffer: LinkedHashMap<String, (SeqSumInfo, SeqSumInfo, SeqSumInfo)>,
-    /// Is barcode_arrangement in this sequencing summary file?
+    /// Record buffer for the sequencing summary, holding at most [`SeqSum::buffer_capacity`] records, with the least
+    /// recently used first.
+    pub record_buffer: LinkedHashMap<String, SeqSumRecord>,
+    /// Is there a barcode column in this sequencing summary file?
     pub has_barcode: bool,
+    /// Is there a mean qscore column in this sequencing summary file? If not, every record has a `mean_qscore` of `None`.
+    pub has_qscore: bool,
     /// Current position in file from BufReader
     pub current_position: usize,
-    /// Column_indices: (read_id, channel, barcode_arrangement)
-    pub column_indices: (usize, usize, usize),
+    /// The index of the column holding each field of a record.
+    pub column_indices: SeqSumColumns,
     /// Previous read id. Used to check that we have consumed all of a multiple mapping.
     pub previous_read_id: String,
+    /// Which channel is used when the sequencing summary and the PAF `ch` tag disagree, see [`SeqSum::set_channel_source`].
+    pub channel_source: ChannelSource,
+    /// The open sequencing summary, kept when reading from a stream such as stdin, which can't be reopened to roll along.
+    stream: Option<ByteCounter<Box<dyn BufRead + Send>>>,
+    /// The index of every record, built by [`SeqSum::build_index`].
+    index: Option<SeqSumIndex>,
+    /// The maximum number of records held in the record buffer, see [`SeqSum::set_buffer_capacity`].
+    buffer_capacity: usize,
+    /// The path of every sequencing summary file, in order, with the byte offset of its first record, after the header
+    /// line, to start reading it from.
+    files: Vec<(PathBuf, usize)>,
+    /// The index in `files` of the file currently being read.
+    file_index: usize,
+    /// Whether to rescan the file from the first record when a read is not in the rest of the file, see
+    /// [`SeqSum::set_rescan_on_miss`].
+    rescan_on_miss: bool,
 }
 
-/// Enumeration representing sequenced summary information.
+/// The index in [`SeqSum::files`] and byte offset of every record, keyed by read ID, and the open files to seek in.
+type SeqSumIndex = (HashMap<String, (usize, u64)>, Vec<BufReader<File>>);
+
+/// The default number of sequencing summary records held in the record buffer, see [`SeqSum::set_buffer_capacity`].
+pub const DEFAULT_BUFFER_CAPACITY: usize = 100_000;
+
+/// Errors that can occur while looking up a read in a sequencing summary, see [`SeqSum::get_record`].
 ///
-/// The `SeqSumInfo` enum holds three possible variants, each representing a different filled:
-/// 1. `Channel(usize)`: Stores the channel number of the sequence.
-/// 2. `Barcode(String)`: Stores the barcode associated with the sequence.
-/// 3. `ReadId(String)`: Stores the unique identifier of the sequence (read ID).
+/// Functions returning a [`DynResult`] box these errors, so a read that simply isn't in the sequencing summary can be
+/// told apart from an IO error or a malformed line with `downcast_ref::<SeqSumError>()`.
+#[derive(Debug, thiserror::Error)]
+pub enum SeqSumError {
+    /// The read is not in the sequencing summary, or has already been evicted from the record buffer.
+    #[error("Error: read {query_name} not found in the sequencing summary file")]
+    ReadNotFound {
+        /// The name of the read.
+        query_name: String,
+    },
+}
+
+/// The source trusted for the channel of a read, when both the sequencing summary and the PAF `ch` tag report one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChannelSource {
+    /// The channel column of the sequencing summary.
+    #[default]
+    SequencingSummary,
+    /// The `ch:i:` tag added to the PAF record by readfish.
+    PafTag,
+}
+
+/// A record from the sequencing summary file, with each field named for what it holds rather than its column header.
 ///
 /// # Examples
 /// ```rust,ignore
-/// use crate::SeqSumInfo;
-///
-/// let channel_info = SeqSumInfo::Channel(3);
-/// let barcode_info = SeqSumInfo::Barcode("barcode01".to_string());
-/// let read_id_info = SeqSumInfo::ReadId("read12345".to_string());
+/// let record = seq_sum.get_record("read123", None)?;
+/// println!("{} was sequenced on channel {}", record.read_id, record.channel);
 /// ```
-#[derive(Debug, Clone)]
-pub enum SeqSumInfo {
-    /// Represents a channel with the given usize value.
-    Channel(usize),
-    /// Represents a barcode with the given String value.
-    Barcode(String),
-    /// Represents a read ID with the given String value.
-    ReadId(String),
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeqSumRecord {
+    /// The unique identifier of the read.
+    pub read_id: String,
+    /// The channel the read was sequenced on.
+    pub channel: usize,
+    /// The barcode the read was classified as, if there is a barcode column.
+    pub barcode: Option<String>,
+    /// The length of the read in bases, if present.
+    pub length: Option<usize>,
+    /// The mean template qscore of the read, `None` if the column is absent or unparseable.
+    pub mean_qscore: Option<f64>,
+    /// The start time of the read in seconds since the run started, `None` if the column is absent or unparseable.
+    pub start_time: Option<f64>,
+    /// The reason the read ended, such as an unblock, if present.
+    pub end_reason: Option<EndReason>,
 }
 
-impl SeqSumInfo {
-    /// Get the channel value if the enum variant is Channel, otherwise return None.
-    pub fn get_channel(&self) -> Option<usize> {
-        if let SeqSumInfo::Channel(channel) = self {
-            Some(*channel)
-        } else {
-            None
+/// The reason a read ended, from the `end_reason` column of the sequencing summary, which records the decisions
+/// readfish made.
+///
+/// End reasons not known to this version, such as those added in a later MinKNOW, are kept as [`EndReason::Other`],
+/// so [`EndReason::as_str`] always gives back the original column value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EndReason {
+    /// The read was sequenced to completion and the strand left the pore.
+    SignalPositive,
+    /// The signal was lost, such as when the pore became blocked.
+    SignalNegative,
+    /// The read was unblocked (rejected) by readfish, through the MinKNOW API.
+    DataServiceUnblockMuxChange,
+    /// The read was unblocked by MinKNOW itself.
+    UnblockMuxChange,
+    /// The read was ended by a mux change, when MinKNOW switched the well the channel reads from.
+    MuxChange,
+    /// Any other end reason, as written in the sequencing summary.
+    Other(String),
+}
+
+impl EndReason {
+    /// Get the end reason as written in the `end_reason` column of the sequencing summary.
+    pub fn as_str(&self) -> &str {
+        match self {
+            EndReason::SignalPositive => "signal_positive",
+            EndReason::SignalNegative => "signal_negative",
+            EndReason::DataServiceUnblockMuxChange => "data_service_unblock_mux_change",
+            EndReason::UnblockMuxChange => "unblock_mux_change",
+            EndReason::MuxChange => "mux_change",
+            EndReason::Other(end_reason) => end_reason,
         }
     }
+}
 
-    /// Get the barcode value if the enum variant is Barcode, otherwise return None.
-    pub fn get_barcode(&self) -> Option<&String> {
-        if let SeqSumInfo::Barcode(barcode) = self {
-            Some(barcode)
-        } else {
-            None
+impl From<&str> for EndReason {
+    /// Parse an end reason from the sequencing summary. Unknown end reasons are kept as [`EndReason::Other`].
+    fn from(end_reason: &str) -> Self {
+        match end_reason {
+            "signal_positive" => EndReason::SignalPositive,
+            "signal_negative" => EndReason::SignalNegative,
+            "data_service_unblock_mux_change" => EndReason::DataServiceUnblockMuxChange,
+            "unblock_mux_change" => EndReason::UnblockMuxChange,
+            "mux_change" => EndReason::MuxChange,
+            other => EndReason::Other(other.to_string()),
         }
     }
+}
 
-    /// Get the read ID value if the enum variant is ReadId, otherwise return None.
-    pub fn get_read_id(&self) -> Option<&String> {
-        if let SeqSumInfo::ReadId(read_id) = self {
-            Some(read_id)
-        } else {
-            None
+impl fmt::Display for EndReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The logical fields of a sequencing summary record, which are found in differently named columns across MinKNOW versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SeqSumField {
+    /// The read ID. Required.
+    ReadId,
+    /// The channel. Required.
+    Channel,
+    /// The barcode.
+    Barcode,
+    /// The read length.
+    Length,
+    /// The mean qscore.
+    MeanQScore,
+    /// The read start time.
+    StartTime,
+    /// The end reason.
+    EndReason,
+}
+
+impl SeqSumField {
+    /// Every field, in the order they are resolved.
+    pub const ALL: [SeqSumField; 7] = [
+        SeqSumField::ReadId,
+        SeqSumField::Channel,
+        SeqSumField::Barcode,
+        SeqSumField::Length,
+        SeqSumField::MeanQScore,
+        SeqSumField::StartTime,
+        SeqSumField::EndReason,
+    ];
+
+    /// The column headers this field has been found under, in order of preference.
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            SeqSumField::ReadId => &["read_id"],
+            SeqSumField::Channel => &["channel"],
+            SeqSumField::Barcode => &["barcode_arrangement", "barcode"],
+            SeqSumField::Length => &["sequence_length_template", "sequence_length"],
+            SeqSumField::MeanQScore => &["mean_qscore_template", "mean_qscore"],
+            SeqSumField::StartTime => &["start_time"],
+            SeqSumField::EndReason => &["end_reason"],
         }
     }
+
+    /// Whether the field must be present in every sequencing summary.
+    pub fn is_required(&self) -> bool {
+        matches!(self, SeqSumField::ReadId | SeqSumField::Channel)
+    }
+}
+
+/// The index of the column holding each field of a sequencing summary record, resolved by a [`SeqSumSchema`].
+/// Optional fields that are not in the sequencing summary are `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeqSumColumns {
+    /// The index of the read ID column.
+    pub read_id: usize,
+    /// The index of the channel column.
+    pub channel: usize,
+    /// The index of the barcode column.
+    pub barcode: Option<usize>,
+    /// The index of the read length column.
+    pub length: Option<usize>,
+    /// The index of the mean qscore column.
+    pub mean_qscore: Option<usize>,
+    /// The index of the start time column.
+    pub start_time: Option<usize>,
+    /// The index of the end reason column.
+    pub end_reason: Option<usize>,
+}
+
+/// Maps the logical fields of a sequencing summary record to the column headers that hold them.
+///
+/// By default each field is auto-detected from the header line, taking the first of its [`SeqSumField::aliases`]
+/// that is present. A column can be given explicitly with [`SeqSumSchema::with_column`] for summaries that use
+/// other names.
+///
+/// # Examples
+/// ```rust,ignore
+/// let schema = SeqSumSchema::default().with_column(SeqSumField::Barcode, "alias");
+/// let seq_sum = SeqSum::from_file_with_schema("sequencing_summary.txt", &schema)?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SeqSumSchema {
+    /// Column headers given explicitly, overriding the auto-detected columns.
+    overrides: HashMap<SeqSumField, String>,
+}
+
+impl SeqSumSchema {
+    /// Override the column header that holds a field.
+    ///
+    /// # Arguments
+    ///
+    /// * `field`: The logical field.
+    /// * `column`: The header of the column that holds the field.
+    pub fn with_column(mut self, field: SeqSumField, column: impl Into<String>) -> Self {
+        self.overrides.insert(field, column.into());
+        self
+    }
+
+    /// Find the index of the column for each field in a tab separated header line.
+    ///
+    /// # Arguments
+    ///
+    /// * `header`: The header line of the sequencing summary file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a required field, or a field with an overridden column, is not found in the header.
+    pub fn resolve(&self, header: &str) -> DynResult<SeqSumColumns> {
+        let headers: Vec<&str> = header.trim_end().split('\t').collect();
+        let find = |field: SeqSumField| -> DynResult<Option<usize>> {
+            if let Some(column) = self.overrides.get(&field) {
+                return match headers.iter().position(|header| header == column) {
+                    Some(index) => Ok(Some(index)),
+                    None => Err(format!(
+                        "{column} column for {field:?} not found in sequencing summary header"
+                    )
+                    .into()),
+                };
+            }
+            let index = field
+                .aliases()
+                .iter()
+                .find_map(|alias| headers.iter().position(|header| header == alias));
+            if index.is_none() && field.is_required() {
+                return Err(format!(
+                    "{} column header not found in sequencing summary. Header row is likely missing from sequencing summary file.",
+                    field.aliases().join(" or ")
+                )
+                .into());
+            }
+            Ok(index)
+        };
+        Ok(SeqSumColumns {
+            read_id: find(SeqSumField::ReadId)?.unwrap_or_default(),
+            channel: find(SeqSumField::Channel)?.unwrap_or_default(),
+            barcode: find(SeqSumField::Barcode)?,
+            length: find(SeqSumField::Length)?,
+            mean_qscore: find(SeqSumField::MeanQScore)?,
+            start_time: find(SeqSumField::StartTime)?,
+            end_reason: find(SeqSumField::EndReason)?,
+        })
+    }
+}
+
+impl SeqSumRecord {
+    /// Parse a tab separated line of the sequencing summary file into a record.
+    ///
+    /// Optional fields whose column is missing from the line, or whose value can't be parsed, are `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `line`: A line from the sequencing summary file, after the header.
+    /// * `column_indices`: The index of the column holding each field, see [`SeqSumSchema::resolve`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the line has no read ID or channel column, or if the channel isn't an integer.
+    pub fn from_line(line: &str, column_indices: SeqSumColumns) -> DynResult<SeqSumRecord> {
+        let fields: Vec<&str> = line.trim_end().split('\t').collect();
+        let field = |index: Option<usize>| index.and_then(|index| fields.get(index).copied());
+        let read_id = field(Some(column_indices.read_id)).ok_or("missing the read_id column")?;
+        let channel = field(Some(column_indices.channel)).ok_or("missing the channel column")?;
+        Ok(SeqSumRecord {
+            read_id: read_id.to_string(),
+            channel: channel
+                .parse()
+                .map_err(|err| format!("channel {channel:?} is not an integer: {err}"))?,
+            barcode: field(column_indices.barcode).map(|barcode| barcode.to_string()),
+            length: field(column_indices.length).and_then(|length| length.parse().ok()),
+            mean_qscore: field(column_indices.mean_qscore).and_then(|qscore| qscore.parse().ok()),
+            start_time: field(column_indices.start_time)
+                .and_then(|start_time| start_time.parse().ok()),
+            end_reason: field(column_indices.end_reason).map(EndReason::from),
+        })
+    }
+}
+
+/// Parse a line of the sequencing summary file into its read ID and record.
+///
+/// # Arguments
+///
+/// * `line`: A line from the sequencing summary file.
+/// * `column_indices`: The index of the column holding each field.
+///
+/// # Returns
+///
+/// A tuple of the read ID and its [`SeqSumRecord`].
+///
+/// # Errors
+///
+/// Returns an error quoting the line if it is malformed, see [`SeqSumRecord::from_line`].
+fn parse_seq_sum_line(
+    line: &str,
+    column_indices: SeqSumColumns,
+) -> DynResult<(String, SeqSumRecord)> {
+    let record = SeqSumRecord::from_line(line, column_indices)
+        .map_err(|err| format!("Malformed sequencing summary line {line:?}: {err}"))?;
+    Ok((record.read_id.clone(), record))
+}
+
+/// Open a sequencing summary file, starting `seek_bytes` into it, see [`try_reader`].
+///
+/// # Errors
+///
+/// Returns an error naming the file if it cannot be opened.
+fn open_seq_sum(
+    sequencing_summary_path: &Path,
+    seek_bytes: Option<usize>,
+) -> DynResult<Box<dyn BufRead + Send>> {
+    try_reader(sequencing_summary_path, seek_bytes).map_err(|err| {
+        format!(
+            "Could not read sequencing summary {}: {err}",
+            sequencing_summary_path.display()
+        )
+        .into()
+    })
+}
+
+/// Read the header line of a sequencing summary file.
+///
+/// # Returns
+///
+/// The header line, without the line ending, and the number of bytes it took up, which is the offset of the first
+/// record.
+///
+/// # Errors
+///
+/// Returns an error if the file is empty, or cannot be read.
+fn read_header(sequencing_summary_path: &Path) -> DynResult<(String, usize)> {
+    let mut header = String::new();
+    let header_length = open_seq_sum(sequencing_summary_path, None)?.read_line(&mut header)?;
+    if header_length == 0 {
+        return Err(format!(
+            "The sequencing summary file {} is empty",
+            sequencing_summary_path.display()
+        )
+        .into());
+    }
+    Ok((
+        header.trim_end_matches(['\n', '\r']).to_string(),
+        header_length,
+    ))
+}
+
+/// Read records from `reader` into the record buffer until the record with the given Read ID is found, see
+/// [`SeqSum::roll_along_file`]. Once the buffer holds `buffer_capacity` records, the least recently used record is
+/// removed for each record read.
+///
+/// # Returns
+///
+/// The number of bytes read.
+///
+/// # Errors
+///
+/// Returns an error if a line cannot be read, or is malformed.
+fn roll_along_reader(
+    reader: &mut impl BufRead,
+    record_buffer: &mut LinkedHashMap<String, SeqSumRecord>,
+    buffer_capacity: usize,
+    column_indices: SeqSumColumns,
+    query_record_read_id: &str,
+) -> DynResult<usize> {
+    let mut bytes_read = 0;
+    let mut line = String::new();
+    loop {
+        let line_length = reader.read_line(&mut line)?;
+        if line_length == 0 {
+            break;
+        }
+        bytes_read += line_length;
+        let (key, record) = parse_seq_sum_line(&line, column_indices)?;
+        while record_buffer.len() >= buffer_capacity {
+            record_buffer.pop_front();
+        }
+        record_buffer.insert(key.clone(), record);
+        if key == query_record_read_id {
+            break;
+        }
+        line.clear();
+    }
+    Ok(bytes_read)
 }
 
 impl SeqSum {
@@ -129,7 +507,7 @@ impl SeqSum {
     /// # Arguments
     ///
     /// * `sequencing_summary_path`: An implementation of the `AsRef<Path>` trait that represents the path to the sequencing summary file.
-    ///  It can be either a string or a `PathBuf`.
+    ///   It can be either a string or a `PathBuf`. A path of `-` reads the sequencing summary from stdin, see [`SeqSum::from_reader`].
     ///
     /// # Errors
     ///
@@ -138,7 +516,7 @@ impl SeqSum {
     ///
     /// # Examples
     /// ```rust,ignore
-    /// # use my_crate::{SeqSum, SeqSumInfo};
+    /// # use my_crate::SeqSum;
     /// # use my_crate::DynResult;
     /// #
     /// # fn main() -> DynResult<()> {
@@ -151,92 +529,422 @@ impl SeqSum {
     /// # }
     /// ```
     pub fn from_file(sequencing_summary_path: impl AsRef<Path>) -> DynResult<SeqSum> {
-        let sequencing_summary_path = sequencing_summary_path.as_ref().to_path_buf();
-        // let writers = vec![];
+        SeqSum::from_file_with_schema(sequencing_summary_path, &SeqSumSchema::default())
+    }
 
-        let reader = reader(&sequencing_summary_path, None);
-        let mut reader = ByteCounter::new(reader);
-        let mut lines: Lines<&mut ByteCounter<Box<dyn BufRead + Send>>> = reader.by_ref().lines();
-        let headers = lines.next();
-        // TODO rewrite function to get the index of a column header
-        let read_id_index = headers
-            .as_ref()
-            .unwrap()
-            .as_ref()
-            .unwrap()
-            .split('\t')
-            .position(|column_header| column_header == "read_id");
-        let barcode_index = headers
-            .as_ref()
-            .unwrap()
-            .as_ref()
-            .unwrap()
-            .split('\t')
-            .position(|column_header| column_header == "barcode_arrangement");
-        let channel_index = headers
-            .as_ref()
-            .unwrap()
-            .as_ref()
-            .unwrap()
-            .split('\t')
-            .position(|column_header| column_header == "channel");
-        assert!(
-            read_id_index.is_some() && channel_index.is_some(),
-            "read_id column header not found in sequencing summary. Header row is likely missing from sequencing summary file."
-        );
-        let lines_iter = lines.take(100000);
-        let processed_lines = LinkedHashMap::from_iter(lines_iter.map(|line| {
-            if let Ok(line_content) = line {
-                // Process the line content here
-                let key = line_content
-                    .split('\t')
-                    .nth(read_id_index.unwrap())
-                    .unwrap()
-                    .to_string();
-                let selected_elements: Vec<_> = line_content
-                    .split('\t')
-                    .enumerate()
-                    .filter(|(index, _)| {
-                        [
-                            read_id_index.unwrap_or(usize::MAX),
-                            channel_index.unwrap_or(usize::MAX),
-                            barcode_index.unwrap_or(usize::MAX),
-                        ]
-                        .contains(index)
-                    })
-                    .map(|(_, value)| value)
-                    .collect();
-                (
-                    key,
-                    (
-                        SeqSumInfo::ReadId(selected_elements[0].to_string()),
-                        SeqSumInfo::Channel(selected_elements[1].parse().unwrap()),
-                        SeqSumInfo::Barcode(
-                            selected_elements
-                                .get(2)
-                                .unwrap_or(&"no_barcode")
-                                .to_string(),
-                        ),
-                    ),
+    /// Create a `SeqSum` instance from a sequencing summary file, finding the columns with the given [`SeqSumSchema`].
+    ///
+    /// # Arguments
+    ///
+    /// * `sequencing_summary_path`: The path to the sequencing summary file, or `-` for stdin.
+    /// * `schema`: The schema mapping each field to its column header.
+    ///
+    /// # Errors
+    ///
+    /// The same errors as [`SeqSum::from_file`], or if the schema cannot be resolved against the header line.
+    ///
+    /// # Examples
+    /// ```rust,ignore
+    /// let schema = SeqSumSchema::default().with_column(SeqSumField::Barcode, "alias");
+    /// let seq_sum = SeqSum::from_file_with_schema("sequencing_summary.txt", &schema)?;
+    /// ```
+    pub fn from_file_with_schema(
+        sequencing_summary_path: impl AsRef<Path>,
+        schema: &SeqSumSchema,
+    ) -> DynResult<SeqSum> {
+        Ok(SeqSum::open_with_header(sequencing_summary_path.as_ref(), schema)?.0)
+    }
+
+    /// Open the sequencing summary at `sequencing_summary_path`, see [`SeqSum::from_file_with_schema`].
+    ///
+    /// # Returns
+    ///
+    /// The `SeqSum`, and its header line without the line ending, so that the header doesn't have to be read again,
+    /// which would be impossible for stdin.
+    ///
+    /// # Errors
+    ///
+    /// The same errors as [`SeqSum::from_file_with_schema`].
+    fn open_with_header(
+        sequencing_summary_path: &Path,
+        schema: &SeqSumSchema,
+    ) -> DynResult<(SeqSum, String)> {
+        let keep_stream = sequencing_summary_path.as_os_str() == "-";
+        let reader = open_seq_sum(sequencing_summary_path, None)?;
+        SeqSum::from_buf_read(
+            sequencing_summary_path.to_path_buf(),
+            reader,
+            schema,
+            keep_stream,
+        )
+    }
+
+    /// Create a `SeqSum` instance from several sequencing summary files, such as from a multi-flowcell or resumed run,
+    /// which are read as a single sequencing summary.
+    ///
+    /// The files are read in the order given, so should be in the order the reads were base-called. When a read isn't
+    /// in the rest of one file, the next file is rolled along, and [`SeqSum::build_index`] indexes every file.
+    ///
+    /// # Arguments
+    ///
+    /// * `sequencing_summary_paths`: The paths to the sequencing summary files, in order.
+    ///
+    /// # Errors
+    ///
+    /// The same errors as [`SeqSum::from_file`], or if no paths are given, if any path is `-`, as stdin can only be a
+    /// single sequencing summary, or if the files don't all have the same columns.
+    ///
+    /// # Examples
+    /// ```rust,ignore
+    /// let seq_sum = SeqSum::from_files(&["sequencing_summary_FAQ1.txt", "sequencing_summary_FAQ2.txt"])?;
+    /// ```
+    pub fn from_files(sequencing_summary_paths: &[impl AsRef<Path>]) -> DynResult<SeqSum> {
+        SeqSum::from_files_with_schema(sequencing_summary_paths, &SeqSumSchema::default())
+    }
+
+    /// Create a `SeqSum` instance from several sequencing summary files, finding the columns with the given
+    /// [`SeqSumSchema`], see [`SeqSum::from_files`].
+    ///
+    /// # Arguments
+    ///
+    /// * `sequencing_summary_paths`: The paths to the sequencing summary files, in order.
+    /// * `schema`: The schema mapping each field to its column header.
+    ///
+    /// # Errors
+    ///
+    /// The same errors as [`SeqSum::from_files`], or if the schema cannot be resolved against the header line.
+    pub fn from_files_with_schema(
+        sequencing_summary_paths: &[impl AsRef<Path>],
+        schema: &SeqSumSchema,
+    ) -> DynResult<SeqSum> {
+        let Some((first_path, other_paths)) = sequencing_summary_paths.split_first() else {
+            return Err("No sequencing summary files were given".into());
+        };
+        let first_path = first_path.as_ref();
+        if sequencing_summary_paths.len() > 1
+            && sequencing_summary_paths
+                .iter()
+                .any(|path| path.as_ref() == Path::new("-"))
+        {
+            return Err("Only a single sequencing summary can be read from stdin".into());
+        }
+        let (mut seq_sum, first_header) = SeqSum::open_with_header(first_path, schema)?;
+        for path in other_paths {
+            let path = path.as_ref();
+            let (header, records_start) = read_header(path)?;
+            if header != first_header {
+                return Err(format!(
+                    "Sequencing summary {} has different columns to {}",
+                    path.display(),
+                    first_path.display()
                 )
-            } else {
-                // Handle any errors that occurred while reading the line
-                panic!("failed to read sequencing summary line");
+                .into());
             }
-        }));
+            seq_sum.files.push((path.to_path_buf(), records_start));
+        }
+        Ok(seq_sum)
+    }
+
+    /// Create a `SeqSum` instance from a sequencing summary that can only be read once, such as stdin.
+    ///
+    /// The reader is kept open, and read forwards as records are looked up with [`SeqSum::get_record`], rather than
+    /// reopening the file at an offset. The reader should already be decompressed, see [`crate::readfish_io::stream_reader`].
+    ///
+    /// # Arguments
+    ///
+    /// * `reader`: The buffered reader over the sequencing summary, starting at the header line.
+    ///
+    /// # Errors
+    ///
+    /// The same errors as [`SeqSum::from_file`].
+    ///
+    /// # Examples
+    /// ```rust,ignore
+    /// let seq_sum = SeqSum::from_reader(stream_reader(std::io::stdin())?)?;
+    /// ```
+    pub fn from_reader(reader: Box<dyn BufRead + Send>) -> DynResult<SeqSum> {
+        Ok(SeqSum::from_buf_read(PathBuf::from("-"), reader, &SeqSumSchema::default(), true)?.0)
+    }
+
+    /// Create a `SeqSum` by reading the header and the first [`DEFAULT_BUFFER_CAPACITY`] records from `reader`.
+    ///
+    /// If `keep_stream` is set, the reader is kept to roll along the sequencing summary, otherwise the
+    /// file at `sequencing_summary_path` is reopened.
+    ///
+    /// # Returns
+    ///
+    /// The `SeqSum`, and its header line without the line ending.
+    ///
+    /// # Errors
+    ///
+    /// The same errors as [`SeqSum::from_file`].
+    fn from_buf_read(
+        sequencing_summary_path: PathBuf,
+        reader: Box<dyn BufRead + Send>,
+        schema: &SeqSumSchema,
+        keep_stream: bool,
+    ) -> DynResult<(SeqSum, String)> {
+        // let writers = vec![];
+
+        let mut reader = ByteCounter::new(reader);
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Err("The sequencing summary file is empty".into());
+        }
+        let records_start = reader.bytes_read();
+        let header = header.trim_end_matches(['\n', '\r']).to_string();
+        let column_indices = schema.resolve(&header)?;
+        let lines: Lines<&mut ByteCounter<Box<dyn BufRead + Send>>> = reader.by_ref().lines();
+        let processed_lines = lines
+            .take(DEFAULT_BUFFER_CAPACITY)
+            .map(|line| parse_seq_sum_line(&line?, column_indices))
+            .collect::<DynResult<LinkedHashMap<String, SeqSumRecord>>>()?;
 
-        Ok(SeqSum {
-            sequencing_summary_path,
+        let seq_sum = SeqSum {
+            sequencing_summary_path: sequencing_summary_path.clone(),
             // writers,
             record_buffer: processed_lines,
-            has_barcode: barcode_index.is_some(),
+            has_barcode: column_indices.barcode.is_some(),
+            has_qscore: column_indices.mean_qscore.is_some(),
             current_position: reader.bytes_read(),
-            column_indices: (
-                read_id_index.unwrap(),
-                channel_index.unwrap(),
-                barcode_index.unwrap_or(usize::MAX),
-            ),
+            column_indices,
             previous_read_id: String::new(),
+            channel_source: ChannelSource::default(),
+            stream: keep_stream.then_some(reader),
+            index: None,
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            files: vec![(sequencing_summary_path, records_start)],
+            file_index: 0,
+            rescan_on_miss: false,
+        };
+        Ok((seq_sum, header))
+    }
+
+    /// Set the maximum number of records held in the record buffer, which defaults to [`DEFAULT_BUFFER_CAPACITY`].
+    ///
+    /// Records are read into the buffer as the sequencing summary is rolled along to find a read, and the least
+    /// recently used record is removed once the buffer is full, so memory use is bounded by the capacity rather than
+    /// by the size of the sequencing summary. A read that has already been removed from the buffer is not found,
+    /// unless the file is rescanned, see [`SeqSum::set_rescan_on_miss`]. A PAF file far out of order with the
+    /// sequencing summary needs either a larger capacity, trading memory for time, or an index, see
+    /// [`SeqSum::build_index`].
+    ///
+    /// If the buffer already holds more records than the capacity, the least recently used are removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer_capacity` - The maximum number of records to buffer. A capacity of 0 is clamped to 1.
+    pub fn set_buffer_capacity(&mut self, buffer_capacity: usize) {
+        self.buffer_capacity = buffer_capacity.max(1);
+        while self.record_buffer.len() > self.buffer_capacity {
+            self.record_buffer.pop_front();
+        }
+    }
+
+    /// Set whether a read that is not in the rest of the sequencing summary is searched for again from the first
+    /// record, to find reads that were removed from a full record buffer, see [`SeqSum::set_buffer_capacity`].
+    ///
+    /// Each rescan reads the whole file again, so this is only worthwhile when few reads are out of order. A read that
+    /// is missing from the sequencing summary entirely is always rescanned for, so runs with many missing reads should
+    /// leave this off, or build an index with [`SeqSum::build_index`]. A sequencing summary read from stdin can't be
+    /// rescanned. Defaults to `false`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rescan_on_miss` - Whether to rescan the file for reads that are not in the rest of it.
+    pub fn set_rescan_on_miss(&mut self, rescan_on_miss: bool) {
+        self.rescan_on_miss = rescan_on_miss;
+    }
+
+    /// Get the maximum number of records held in the record buffer, see [`SeqSum::set_buffer_capacity`].
+    pub fn buffer_capacity(&self) -> usize {
+        self.buffer_capacity
+    }
+
+    /// Set which channel is used when a PAF record has a `ch` tag that disagrees with the sequencing summary.
+    ///
+    /// Conditions are assigned by channel, so a disagreement signals that the PAF and sequencing summary are from
+    /// different runs. Either way the disagreement is counted in [`crate::Summary::channel_mismatches`]. This defaults
+    /// to [`ChannelSource::SequencingSummary`].
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_source` - The source of the channel to trust.
+    pub fn set_channel_source(&mut self, channel_source: ChannelSource) {
+        self.channel_source = channel_source;
+    }
+
+    /// Iterate over every record of the sequencing summary, in file order, independently of looking up reads with
+    /// [`SeqSum::get_record`], such as to calculate the stats of a run before it is aligned.
+    ///
+    /// Each file is reopened from its first record, so the record buffer and index are untouched, and compressed
+    /// files are decompressed as they are read. Every file of a sequencing summary split across several files is
+    /// read in turn, see [`SeqSum::from_files`]. Blank lines are skipped.
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the records, with an error for each line that can't be read or parsed, naming the file and
+    /// line number. A sequencing summary read from stdin can't be reopened, so gives a single error.
+    ///
+    /// # Examples
+    /// ```rust,ignore
+    /// let seq_sum = SeqSum::from_file("sequencing_summary.txt.gz")?;
+    /// let mut total_bases = 0;
+    /// for record in seq_sum.records() {
+    ///     total_bases += record?.length.unwrap_or_default();
+    /// }
+    /// ```
+    pub fn records(&self) -> impl Iterator<Item = DynResult<SeqSumRecord>> + '_ {
+        let column_indices = self.column_indices;
+        let (stdin_error, files) = if self.stream.is_some() {
+            (
+                Some(Err(
+                    "Cannot iterate over a sequencing summary read from stdin".into(),
+                )),
+                &self.files[..0],
+            )
+        } else {
+            (None, &self.files[..])
+        };
+        stdin_error.into_iter().chain(files.iter().flat_map(
+            move |(path, records_start)| -> Box<dyn Iterator<Item = DynResult<SeqSumRecord>>> {
+                let lines = match try_reader(path, Some(*records_start)) {
+                    Ok(reader) => reader.lines(),
+                    Err(err) => {
+                        return Box::new(std::iter::once(Err(format!(
+                            "Could not read sequencing summary {}: {}",
+                            path.display(),
+                            err
+                        )
+                        .into())))
+                    }
+                };
+                // The header is the first line
+                Box::new(
+                    lines
+                        .enumerate()
+                        .map(move |(index, line)| {
+                            line.map_err(|err| err.to_string())
+                                .and_then(|line| {
+                                    if line.trim().is_empty() {
+                                        return Ok(None);
+                                    }
+                                    SeqSumRecord::from_line(&line, column_indices)
+                                        .map(Some)
+                                        .map_err(|err| err.to_string())
+                                })
+                                .map_err(|err| {
+                                    format!(
+                                        "Malformed sequencing summary line {} of {}: {}",
+                                        index + 2,
+                                        path.display(),
+                                        err
+                                    )
+                                    .into()
+                                })
+                        })
+                        .filter_map(Result::transpose),
+                )
+            },
+        ))
+    }
+
+    /// Build an index of the byte offset of every record in the sequencing summary, keyed by read ID.
+    ///
+    /// The file is scanned once, after which [`SeqSum::get_record`] seeks directly to each record, rather than rolling
+    /// along the file. This means records can be looked up in any order, such as for a PAF file that is not in the
+    /// order the reads were base-called. Compressed files can't be seeked in, so keep the streaming behaviour. Every file
+    /// of a sequencing summary split across several files is indexed, see [`SeqSum::from_files`].
+    ///
+    /// # Returns
+    ///
+    /// Whether the index was built, `false` if any sequencing summary file is compressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sequencing summary is read from stdin, which can't be seeked in, or if the file cannot be read.
+    ///
+    /// # Examples
+    /// ```rust,ignore
+    /// let mut seq_sum = SeqSum::from_file("sequencing_summary.txt")?;
+    /// seq_sum.build_index()?;
+    /// let record = seq_sum.get_record("read123", None)?;
+    /// ```
+    pub fn build_index(&mut self) -> DynResult<bool> {
+        if self.stream.is_some() {
+            return Err("Cannot index a sequencing summary read from stdin".into());
+        }
+        for (path, _) in &self.files {
+            if CompressionFormat::of_file(path)?.is_compressed() {
+                log::debug!(
+                    "Sequencing summary {} is compressed, so will be read in order",
+                    path.display()
+                );
+                return Ok(false);
+            }
+        }
+        let mut offsets = HashMap::new();
+        let mut files = Vec::with_capacity(self.files.len());
+        let mut line = String::new();
+        for (file_index, (path, _)) in self.files.iter().enumerate() {
+            let mut file = BufReader::new(File::open(path)?);
+            // Skip the header
+            let mut offset = file.read_line(&mut line)? as u64;
+            line.clear();
+            loop {
+                let line_length = file.read_line(&mut line)?;
+                if line_length == 0 {
+                    break;
+                }
+                if let Some(read_id) = line.trim_end().split('\t').nth(self.column_indices.read_id)
+                {
+                    offsets.insert(read_id.to_string(), (file_index, offset));
+                }
+                offset += line_length as u64;
+                line.clear();
+            }
+            files.push(file);
+        }
+        log::debug!(
+            "Indexed {} records in sequencing summary {}",
+            offsets.len(),
+            self.files
+                .iter()
+                .map(|(path, _)| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        self.index = Some((offsets, files));
+        Ok(true)
+    }
+
+    /// Read the record for `query_name` by seeking to its offset in the index built by [`SeqSum::build_index`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SeqSumError::ReadNotFound`] if the Read ID is not in the index, or an error if the record cannot be
+    /// read or is malformed.
+    fn get_indexed_record(&mut self, query_name: &str) -> DynResult<SeqSumRecord> {
+        let (offsets, files) = self
+            .index
+            .as_mut()
+            .ok_or("The sequencing summary has not been indexed")?;
+        let (file_index, offset) =
+            offsets
+                .get(query_name)
+                .ok_or_else(|| SeqSumError::ReadNotFound {
+                    query_name: query_name.to_string(),
+                })?;
+        let file = &mut files[*file_index];
+        file.seek(SeekFrom::Start(*offset))?;
+        let mut line = String::new();
+        file.read_line(&mut line)?;
+        SeqSumRecord::from_line(&line, self.column_indices).map_err(|err| {
+            format!(
+                "Malformed sequencing summary line {line:?} at offset {offset} of {}: {err}",
+                self.files[*file_index].0.display()
+            )
+            .into()
         })
     }
     /// Roll along the sequencing summary file until a specific record with the given Read ID is found.
@@ -246,8 +954,13 @@ impl SeqSum {
     /// the function stops rolling and the current position in the file is updated.
     ///
     /// The `record_buffer` of the `SeqSum` struct is used to store the sequencing summary records as a linked hash map,
-    /// with the Read ID as the key and tuples containing `SeqSumInfo` variants as the values.
-    /// The buffer holds a maximum of 100,000 records, and the oldest record is removed when a new record is added.
+    /// with the Read ID as the key and [`SeqSumRecord`]s as the values.
+    /// The buffer holds at most [`SeqSum::buffer_capacity`] records, and the least recently used record is removed when
+    /// a new record is added to a full buffer.
+    ///
+    /// If the record is not in the rest of the file, the next file is rolled along, if there are several, see
+    /// [`SeqSum::from_files`]. If it is in none of them, it may have been read and removed from the buffer already, so
+    /// the files are rescanned from the first record if [`SeqSum::set_rescan_on_miss`] is set, unless it is a stream.
     ///
     /// # Arguments
     ///
@@ -270,57 +983,64 @@ impl SeqSum {
     /// # }
     /// ```
     fn roll_along_file(&mut self, query_record_read_id: String) -> DynResult<()> {
-        let mut reader = ByteCounter::new(reader(
-            &self.sequencing_summary_path,
-            Some(self.current_position),
-        ));
-        let mut line = String::new();
-        while reader.read_line(&mut line)? != 0 {
-            // do something with line
-            let selected_elements: Vec<_> = line
-                .split('\t')
-                .enumerate()
-                .filter(|(index, _)| {
-                    [
-                        self.column_indices.0,
-                        self.column_indices.1,
-                        self.column_indices.2,
-                    ]
-                    .contains(index)
-                })
-                .map(|(_, value)| value)
-                .collect();
-
-            let key = line
-                .split('\t')
-                .nth(self.column_indices.0)
-                .unwrap()
-                .to_string();
-            self.record_buffer.pop_front().unwrap();
-            self.record_buffer.insert(
-                key,
-                (
-                    SeqSumInfo::ReadId(selected_elements[0].to_string()),
-                    SeqSumInfo::Channel(selected_elements[1].parse().unwrap()),
-                    SeqSumInfo::Barcode(
-                        selected_elements
-                            .get(2)
-                            .unwrap_or(&"no_barcode")
-                            .to_string(),
-                    ),
-                ),
+        if let Some(stream) = self.stream.as_mut() {
+            // A stream can't be reopened, so carry on from where the last roll stopped
+            self.current_position += roll_along_reader(
+                stream,
+                &mut self.record_buffer,
+                self.buffer_capacity,
+                self.column_indices,
+                &query_record_read_id,
+            )?;
+            return Ok(());
+        }
+        self.roll_along_files(&query_record_read_id)?;
+        if self.rescan_on_miss && !self.record_buffer.contains_key(&query_record_read_id) {
+            // The record may have been removed from the buffer already, so rescan from the first record
+            log::debug!(
+                "Read {} is not in the rest of the sequencing summary, rescanning from the start",
+                query_record_read_id
             );
-            if *selected_elements[0] == query_record_read_id {
-                break;
-            }
-            line.clear();
+            self.move_to_file(0);
+            self.roll_along_files(&query_record_read_id)?;
         }
-        self.current_position += reader.bytes_read();
         Ok(())
     }
 
+    /// Roll along the current sequencing summary file from the current position, and then each file after it, until
+    /// the record with the given Read ID is found, or the last file has been read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be opened, or a line cannot be read or is malformed.
+    fn roll_along_files(&mut self, query_record_read_id: &str) -> DynResult<()> {
+        loop {
+            self.current_position += roll_along_reader(
+                &mut open_seq_sum(&self.sequencing_summary_path, Some(self.current_position))?,
+                &mut self.record_buffer,
+                self.buffer_capacity,
+                self.column_indices,
+                query_record_read_id,
+            )?;
+            if self.record_buffer.contains_key(query_record_read_id)
+                || self.file_index + 1 >= self.files.len()
+            {
+                return Ok(());
+            }
+            self.move_to_file(self.file_index + 1);
+        }
+    }
+
+    /// Move to the first record of the sequencing summary file at `file_index` in `files`.
+    fn move_to_file(&mut self, file_index: usize) {
+        let (path, records_start) = &self.files[file_index];
+        self.file_index = file_index;
+        self.sequencing_summary_path = path.clone();
+        self.current_position = *records_start;
+    }
+
     /// Get the sequencing summary record associated with the given `query_name`.
-    /// The record is returned as a tuple containing three `SeqSumInfo` variants.
+    /// The record is returned as a [`SeqSumRecord`].
     ///
     /// This function searches for the sequencing summary record corresponding to the provided `query_name` in the record buffer of the `SeqSum` struct.
     ///  If the record is found in the buffer, it is returned.
@@ -334,14 +1054,15 @@ impl SeqSum {
     ///
     /// # Errors
     ///
-    /// This function returns a `DynResult<(SeqSumInfo, SeqSumInfo, SeqSumInfo)>`,
-    /// which is a type alias for `Result<(SeqSumInfo, SeqSumInfo, SeqSumInfo), Box<dyn Error + 'static>>`.
+    /// This function returns a `DynResult<SeqSumRecord>`,
+    /// which is a type alias for `Result<SeqSumRecord, Box<dyn Error + 'static>>`.
     /// It can return an error if there is an issue reading the sequencing summary file while rolling along
-    /// to find the record with the matching Read ID.
+    /// to find the record with the matching Read ID, or a [`SeqSumError::ReadNotFound`] if the Read ID is not in the rest
+    /// of the file.
     ///
     /// # Examples
     /// ```rust,ignore
-    /// # use my_crate::{SeqSum, SeqSumInfo};
+    /// # use my_crate::SeqSum;
     /// # use my_crate::DynResult;
     /// #
     /// # fn main() -> DynResult<()> {
@@ -351,14 +1072,9 @@ impl SeqSum {
     /// let previous_read_id = Some(&mut String::new());
     ///
     /// let record = seq_sum.get_record(query_name, previous_read_id)?;
-    /// match record {
-    ///     (SeqSumInfo::ReadId(read_id), SeqSumInfo::Channel(channel), SeqSumInfo::Barcode(barcode)) => {
-    ///         println!("Read ID: {}", read_id);
-    ///         println!("Channel: {}", channel);
-    ///         println!("Barcode: {}", barcode);
-    ///     },
-    ///     _ => println!("Record not found."),
-    /// }
+    /// println!("Read ID: {}", record.read_id);
+    /// println!("Channel: {}", record.channel);
+    /// println!("Barcode: {:?}", record.barcode);
     /// # Ok(())
     /// # }
     /// ```
@@ -366,20 +1082,25 @@ impl SeqSum {
         &mut self,
         query_name: &str,
         previous_query_name: Option<&str>,
-    ) -> DynResult<(SeqSumInfo, SeqSumInfo, SeqSumInfo)> {
+    ) -> DynResult<SeqSumRecord> {
         if (query_name != previous_query_name.unwrap_or(&self.previous_read_id))
             & (!previous_query_name.unwrap_or("").is_empty())
         {
-            self.record_buffer
-                .remove(previous_query_name.unwrap())
-                .unwrap();
+            self.record_buffer.remove(previous_query_name.unwrap());
         }
-        match self.record_buffer.get(query_name) {
+        // Move the record to the back of the buffer, as the most recently used
+        match self.record_buffer.get_refresh(query_name) {
             Some(record) => Ok(record.clone()),
+            None if self.index.is_some() => self.get_indexed_record(query_name),
             None => {
                 // Assuming multiple mappings are in a block in a PAF file
                 self.roll_along_file(query_name.to_string())?;
-                Ok(self.record_buffer.get(query_name).unwrap().clone())
+                self.record_buffer.get(query_name).cloned().ok_or_else(|| {
+                    SeqSumError::ReadNotFound {
+                        query_name: query_name.to_string(),
+                    }
+                    .into()
+                })
             }
         }
     }
@@ -411,5 +1132,344 @@ mod tests {
         );
         assert_eq!(seq_sum.record_buffer.len(), 100000);
         assert!(seq_sum.has_barcode);
+        assert!(seq_sum.has_qscore);
+    }
+
+    #[test]
+    fn test_seq_sum_buffer_capacity() {
+        let seq_sum_path = std::env::temp_dir().join("readfish_tools_test_buffer_capacity.txt");
+        let mut contents = "read_id\tchannel\n".to_string();
+        for read in 0..50 {
+            contents.push_str(&format!("read{}\t{}\n", read, read + 1));
+        }
+        std::fs::write(&seq_sum_path, contents).unwrap();
+        let mut seq_sum = SeqSum::from_file(&seq_sum_path).unwrap();
+        assert_eq!(seq_sum.buffer_capacity(), DEFAULT_BUFFER_CAPACITY);
+        assert_eq!(seq_sum.record_buffer.len(), 50);
+        seq_sum.set_buffer_capacity(10);
+        assert_eq!(seq_sum.record_buffer.len(), 10);
+        // Without rescanning, an evicted read is not found
+        assert!(seq_sum.get_record("read0", None).is_err());
+        seq_sum.set_rescan_on_miss(true);
+        // More distinct reads than the buffer holds, out of order, so evicted reads are found by rescanning
+        let order: Vec<usize> = (0..50)
+            .map(|read| (read * 17) % 50)
+            .chain((0..50).rev())
+            .collect();
+        for read in order {
+            let record = seq_sum.get_record(&format!("read{}", read), None).unwrap();
+            assert_eq!(record.channel, read + 1);
+            assert!(seq_sum.record_buffer.len() <= 10);
+        }
+        assert!(seq_sum.get_record("read50", None).is_err());
+        assert!(seq_sum.record_buffer.len() <= 10);
+        std::fs::remove_file(seq_sum_path).unwrap();
+    }
+
+    #[test]
+    fn test_seq_sum_from_files() {
+        let header = "read_id\tchannel\tbarcode_arrangement\n";
+        let paths: Vec<PathBuf> = (0..3)
+            .map(|file| {
+                let path = std::env::temp_dir()
+                    .join(format!("readfish_tools_test_from_files_{}.txt", file));
+                let mut contents = header.to_string();
+                for read in file * 10..(file + 1) * 10 {
+                    contents.push_str(&format!("read{}\t{}\tbarcode0{}\n", read, read + 1, file));
+                }
+                std::fs::write(&path, contents).unwrap();
+                path
+            })
+            .collect();
+        // Rolling along from one file into the next
+        let mut seq_sum = SeqSum::from_files(&paths).unwrap();
+        assert_eq!(seq_sum.record_buffer.len(), 10);
+        // Shrinking the buffer evicts read0 to read4
+        seq_sum.set_buffer_capacity(5);
+        for read in 5..30 {
+            let record = seq_sum.get_record(&format!("read{}", read), None).unwrap();
+            assert_eq!(record.channel, read + 1);
+            assert_eq!(record.barcode, Some(format!("barcode0{}", read / 10)));
+        }
+        assert_eq!(seq_sum.sequencing_summary_path, paths[2]);
+        assert!(seq_sum.get_record("read30", None).is_err());
+        // Indexing every file
+        let mut seq_sum = SeqSum::from_files(&paths).unwrap();
+        assert!(seq_sum.build_index().unwrap());
+        for read in (0..30).rev() {
+            let record = seq_sum.get_record(&format!("read{}", read), None).unwrap();
+            assert_eq!(record.channel, read + 1);
+        }
+        // The files must have the same columns
+        let mismatched_path =
+            std::env::temp_dir().join("readfish_tools_test_from_files_mismatched.txt");
+        std::fs::write(&mismatched_path, "read_id\tchannel\nread30\t31\n").unwrap();
+        let err = SeqSum::from_files(&[&paths[0], &mismatched_path])
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("different columns"));
+        assert!(SeqSum::from_files(&[&paths[0], Path::new("-")]).is_err());
+        assert!(SeqSum::from_files(&[] as &[PathBuf]).is_err());
+        for path in paths.iter().chain([&mismatched_path]) {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_seq_sum_errors() {
+        // A missing file is an error naming it, rather than a panic
+        let missing_path = get_test_file("not_a_seq_sum.txt");
+        let err = SeqSum::from_file(&missing_path).err().unwrap().to_string();
+        assert!(err.contains("not_a_seq_sum.txt"));
+        assert!(SeqSum::from_files(&[&missing_path]).is_err());
+
+        // A malformed line in the initial record buffer
+        let seq_sum_path = std::env::temp_dir().join("readfish_tools_test_seq_sum_errors.txt");
+        std::fs::write(
+            &seq_sum_path,
+            "read_id\tchannel\nread0\t1\nread1\tnot_a_channel\n",
+        )
+        .unwrap();
+        let err = SeqSum::from_file(&seq_sum_path).err().unwrap().to_string();
+        assert!(err.contains("Malformed sequencing summary line"));
+
+        // A malformed line found while rolling along the file
+        std::fs::write(&seq_sum_path, "read_id\tchannel\nread0\t1\n").unwrap();
+        let mut seq_sum = SeqSum::from_file(&seq_sum_path).unwrap();
+        std::fs::write(&seq_sum_path, "read_id\tchannel\nread0\t1\nread1\n").unwrap();
+        let err = seq_sum.get_record("read1", None).unwrap_err().to_string();
+        assert!(err.contains("Malformed sequencing summary line"));
+
+        // The file is removed before rolling along it
+        std::fs::remove_file(&seq_sum_path).unwrap();
+        let err = seq_sum.get_record("read2", None).unwrap_err().to_string();
+        assert!(err.contains("Could not read sequencing summary"));
+    }
+
+    #[test]
+    fn test_seq_sum_from_files_stdin() {
+        // Rerun this test in a child process with the sequencing summary piped into its stdin
+        if std::env::var_os("READFISH_TOOLS_TEST_SEQ_SUM_STDIN").is_some() {
+            let mut seq_sum = SeqSum::from_files(&["-"]).unwrap();
+            // The header is only read once, so the first record isn't lost
+            assert_eq!(seq_sum.get_record("read0", None).unwrap().channel, 1);
+            assert_eq!(seq_sum.get_record("read1", None).unwrap().channel, 2);
+            return;
+        }
+        let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "sequencing_summary::tests::test_seq_sum_from_files_stdin",
+            ])
+            .env("READFISH_TOOLS_TEST_SEQ_SUM_STDIN", "1")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        std::io::Write::write_all(
+            &mut child.stdin.take().unwrap(),
+            b"read_id\tchannel\nread0\t1\nread1\t2\n",
+        )
+        .unwrap();
+        assert!(child.wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_end_reason() {
+        for end_reason in [
+            EndReason::SignalPositive,
+            EndReason::SignalNegative,
+            EndReason::DataServiceUnblockMuxChange,
+            EndReason::UnblockMuxChange,
+            EndReason::MuxChange,
+        ] {
+            assert_eq!(EndReason::from(end_reason.as_str()), end_reason);
+        }
+        let other = EndReason::from("analysis_config_change");
+        assert_eq!(
+            other,
+            EndReason::Other("analysis_config_change".to_string())
+        );
+        assert_eq!(other.to_string(), "analysis_config_change");
+    }
+
+    #[test]
+    fn test_seq_sum_mean_qscore() {
+        let seq_sum_file_path = get_test_file("seq_sum_PAK09329.txt");
+        let mut seq_sum = SeqSum::from_file(seq_sum_file_path).unwrap();
+        let record = seq_sum
+            .get_record("f8c27003-5d3d-4f66-871a-1d5f4bded1de", None)
+            .unwrap();
+        assert_eq!(record.channel, 2418);
+        assert_eq!(record.barcode.as_deref(), Some("unclassified"));
+        assert_eq!(record.mean_qscore, Some(5.78822));
+        assert_eq!(record.start_time, Some(3833.4985));
+        assert_eq!(
+            record.end_reason.unwrap(),
+            EndReason::DataServiceUnblockMuxChange
+        );
+    }
+
+    #[test]
+    fn test_seq_sum_from_reader() {
+        // The stream is kept open, and read forwards past the initial buffer
+        let file = std::fs::File::open(get_test_file("seq_sum_PAK09329.txt")).unwrap();
+        let mut seq_sum = SeqSum::from_reader(Box::new(std::io::BufReader::new(file))).unwrap();
+        assert_eq!(seq_sum.sequencing_summary_path, PathBuf::from("-"));
+        assert_eq!(seq_sum.record_buffer.len(), 100000);
+        let last_line = std::fs::read_to_string(get_test_file("seq_sum_PAK09329.txt"))
+            .unwrap()
+            .lines()
+            .last()
+            .unwrap()
+            .to_string();
+        let (read_id, expected) = parse_seq_sum_line(&last_line, seq_sum.column_indices).unwrap();
+        let record = seq_sum.get_record(&read_id, None).unwrap();
+        assert_eq!(record, expected);
+        assert!(seq_sum.get_record("not_a_read", None).is_err());
+    }
+
+    #[test]
+    fn test_seq_sum_records() {
+        let contents = std::fs::read_to_string(get_test_file("seq_sum_PAK09329.txt")).unwrap();
+        let last_line = contents.lines().last().unwrap();
+        // Gzipped sequencing summaries are decompressed
+        for file in ["seq_sum_PAK09329.txt", "seq_sum_PAk09329_comp.txt.gz"] {
+            let seq_sum = SeqSum::from_file(get_test_file(file)).unwrap();
+            let records: Vec<SeqSumRecord> =
+                seq_sum.records().map(|record| record.unwrap()).collect();
+            assert_eq!(records.len(), 150_000);
+            assert_eq!(
+                records.last().unwrap(),
+                &SeqSumRecord::from_line(last_line, seq_sum.column_indices).unwrap()
+            );
+            // The record buffer is untouched
+            assert_eq!(seq_sum.record_buffer.len(), DEFAULT_BUFFER_CAPACITY);
+        }
+
+        // Every file of a split sequencing summary is read
+        let (header, rest) = contents.split_once('\n').unwrap();
+        let first_path = std::env::temp_dir().join("readfish_tools_test_seq_sum_records_1.txt");
+        let second_path = std::env::temp_dir().join("readfish_tools_test_seq_sum_records_2.txt");
+        let records: Vec<&str> = rest.lines().take(3).collect();
+        std::fs::write(
+            &first_path,
+            format!("{header}\n{}\n", records[..2].join("\n")),
+        )
+        .unwrap();
+        std::fs::write(&second_path, format!("{header}\n{}\n", records[2])).unwrap();
+        let seq_sum = SeqSum::from_files(&[&first_path, &second_path]).unwrap();
+        assert_eq!(seq_sum.records().count(), 3);
+
+        // A malformed line is an error naming the line, and the records after it are still read, skipping blank
+        // lines. The file is rewritten after opening, as the initial record buffer would error on the malformed line
+        let seq_sum = SeqSum::from_file(&first_path).unwrap();
+        std::fs::write(&first_path, format!("{header}\nread1\n\n{}\n", records[0])).unwrap();
+        let results: Vec<DynResult<SeqSumRecord>> = seq_sum.records().collect();
+        assert_eq!(results.len(), 2);
+        let err = results[0].as_ref().unwrap_err().to_string();
+        assert!(err.contains("line 2 of"));
+        assert!(results[1].is_ok());
+        std::fs::remove_file(first_path).unwrap();
+        std::fs::remove_file(second_path).unwrap();
+
+        let file = std::fs::File::open(get_test_file("seq_sum_PAK09329.txt")).unwrap();
+        let seq_sum = SeqSum::from_reader(Box::new(std::io::BufReader::new(file))).unwrap();
+        assert!(seq_sum.records().next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_seq_sum_index_shuffled() {
+        // Look up reads in the reverse of the order they were base-called, which rolling along the file can't do
+        let seq_sum_file_path = get_test_file("seq_sum_PAK09329.txt");
+        let contents = std::fs::read_to_string(&seq_sum_file_path).unwrap();
+        let mut seq_sum = SeqSum::from_file(&seq_sum_file_path).unwrap();
+        assert!(seq_sum.build_index().unwrap());
+        let lines: Vec<&str> = contents.lines().skip(1).step_by(1000).collect();
+        for line in lines.into_iter().rev() {
+            let (read_id, expected) = parse_seq_sum_line(line, seq_sum.column_indices).unwrap();
+            let record = seq_sum.get_record(&read_id, None).unwrap();
+            assert_eq!(record.read_id, read_id);
+            assert_eq!(record, expected);
+        }
+        assert!(seq_sum.get_record("not_a_read", None).is_err());
+    }
+
+    #[test]
+    fn test_seq_sum_index_truncated() {
+        // A truncated line at an indexed offset is an error, rather than a panic
+        let seq_sum_path = std::env::temp_dir().join("readfish_tools_test_index_truncated.txt");
+        std::fs::write(&seq_sum_path, "read_id\tchannel\nread0\t1\nread1\t2\n").unwrap();
+        let mut seq_sum = SeqSum::from_file(&seq_sum_path).unwrap();
+        assert!(seq_sum.build_index().unwrap());
+        assert_eq!(seq_sum.get_indexed_record("read1").unwrap().channel, 2);
+        std::fs::write(&seq_sum_path, "read_id\tchannel\nread0\t1\nrea").unwrap();
+        let err = seq_sum.get_indexed_record("read1").unwrap_err().to_string();
+        assert!(err.contains("Malformed sequencing summary line"));
+        std::fs::remove_file(seq_sum_path).unwrap();
+    }
+
+    #[test]
+    fn test_seq_sum_index_gzipped() {
+        let mut seq_sum = SeqSum::from_file(get_test_file("seq_sum_PAk09329_comp.txt.gz")).unwrap();
+        assert!(!seq_sum.build_index().unwrap());
+    }
+
+    #[test]
+    fn test_parse_seq_sum_line_without_qscore() {
+        let column_indices = SeqSumSchema::default()
+            .resolve("read_id\tchannel\n")
+            .unwrap();
+        let (read_id, record) = parse_seq_sum_line("read1\t12\n", column_indices).unwrap();
+        assert_eq!(read_id, "read1");
+        assert_eq!(record.channel, 12);
+        assert_eq!(record.barcode, None);
+        assert_eq!(record.length, None);
+        assert_eq!(record.mean_qscore, None);
+        assert_eq!(record.start_time, None);
+        assert_eq!(record.end_reason, None);
+    }
+
+    #[test]
+    fn test_seq_sum_schema_aliases() {
+        // Older summaries
+        let columns = SeqSumSchema::default()
+            .resolve("filename\tread_id\tchannel\tsequence_length_template\tmean_qscore_template\tbarcode_arrangement")
+            .unwrap();
+        assert_eq!(columns.read_id, 1);
+        assert_eq!(columns.channel, 2);
+        assert_eq!(columns.length, Some(3));
+        assert_eq!(columns.mean_qscore, Some(4));
+        assert_eq!(columns.barcode, Some(5));
+        assert_eq!(columns.start_time, None);
+        // Newer summaries
+        let columns = SeqSumSchema::default()
+            .resolve("read_id\tbarcode\tchannel\tsequence_length\tmean_qscore\tend_reason")
+            .unwrap();
+        assert_eq!(columns.barcode, Some(1));
+        assert_eq!(columns.length, Some(3));
+        assert_eq!(columns.mean_qscore, Some(4));
+        assert_eq!(columns.end_reason, Some(5));
+        let (_, record) =
+            parse_seq_sum_line("read1\tbarcode01\t7\t1500\t12.5\tsignal_positive", columns)
+                .unwrap();
+        assert_eq!(record.barcode.as_deref(), Some("barcode01"));
+        assert_eq!(record.length, Some(1500));
+    }
+
+    #[test]
+    fn test_seq_sum_schema_overrides() {
+        let schema = SeqSumSchema::default()
+            .with_column(SeqSumField::Barcode, "alias")
+            .with_column(SeqSumField::ReadId, "parent_read_id");
+        let columns = schema
+            .resolve("read_id\tparent_read_id\tchannel\tbarcode_arrangement\talias")
+            .unwrap();
+        assert_eq!(columns.read_id, 1);
+        assert_eq!(columns.barcode, Some(4));
+        // An overridden column must be present, even for an optional field
+        assert!(schema.resolve("parent_read_id\tchannel").is_err());
+        // Required fields must be present
+        assert!(SeqSumSchema::default().resolve("read_id\tbarcode").is_err());
     }
 }
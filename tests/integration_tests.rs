@@ -1,4 +1,4 @@
-use readfish_tools::_demultiplex_paf;
+use readfish_tools::{_demultiplex_paf, DemultiplexOptions};
 
 // importing the common code for tests.
 mod common;
@@ -13,5 +13,509 @@ fn test_region_based_paf_demultiplex() {
         .unwrap()
         .to_string();
     let toml_path = common::get_test_file("human_barcode.toml");
-    _demultiplex_paf(toml_path, paf, Some(seq_sum), true, None::<String>)
+    let summary = _demultiplex_paf(
+        toml_path,
+        &[paf],
+        Some(seq_sum),
+        &DemultiplexOptions {
+            print_summary: true,
+            strict: true,
+            strict_seqsum: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(!summary.conditions.is_empty());
+}
+
+#[test]
+fn test_multiple_paf_demultiplex() {
+    // A run split across two PAF files summarises the same as the whole file
+    let paf = common::get_test_file("test_paf_barcode05_NA12878.chr.paf");
+    let seq_sum = common::get_test_file("seq_sum_PAK09329.txt");
+    let toml_path = common::get_test_file("human_barcode.toml");
+    let lines: Vec<String> = std::fs::read_to_string(&paf)
+        .unwrap()
+        .lines()
+        .map(|line| format!("{line}\n"))
+        .collect();
+    let (first, second) = lines.split_at(lines.len() / 2);
+    let first_path = std::env::temp_dir().join("readfish_tools_test_batch_0.paf");
+    let second_path = std::env::temp_dir().join("readfish_tools_test_batch_1.paf");
+    std::fs::write(&first_path, first.concat()).unwrap();
+    std::fs::write(&second_path, second.concat()).unwrap();
+    let assignments_path = std::env::temp_dir().join("readfish_tools_test_assignments.tsv");
+    let out_dir = std::env::temp_dir().join("readfish_tools_test_batches");
+
+    let expected = _demultiplex_paf(
+        &toml_path,
+        &[paf],
+        Some(&seq_sum),
+        &DemultiplexOptions {
+            strict: true,
+            strict_seqsum: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let summary = _demultiplex_paf(
+        &toml_path,
+        &[&first_path, &second_path],
+        Some(&seq_sum),
+        &DemultiplexOptions {
+            strict: true,
+            strict_seqsum: true,
+            output_dir: Some(out_dir.clone()),
+            assignments_out: Some(assignments_path.clone()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(summary.conditions.len(), expected.conditions.len());
+    for (condition_name, expected_condition) in &expected.conditions {
+        let condition = &summary.conditions[condition_name];
+        assert_eq!(condition.total_reads, expected_condition.total_reads);
+        assert_eq!(
+            condition.on_target_yield,
+            expected_condition.on_target_yield
+        );
+        assert_eq!(condition.n50, expected_condition.n50);
+    }
+    // The assignments of both files are written to one file, under a single header
+    let assignments = std::fs::read_to_string(&assignments_path).unwrap();
+    let mut rows = assignments.lines();
+    assert_eq!(rows.next(), Some("read_id\tcondition\ton_target\tcontig"));
+    let rows: Vec<Vec<&str>> = rows.map(|row| row.split('\t').collect()).collect();
+    assert_eq!(rows.len(), lines.len());
+    let on_target_rows = rows.iter().filter(|row| row[2] == "true").count();
+    let expected_on_target: usize = expected
+        .conditions
+        .values()
+        .map(|condition| condition.on_target_read_count)
+        .sum();
+    assert_eq!(on_target_rows, expected_on_target);
+    std::fs::remove_file(assignments_path).unwrap();
+    // The records of both files are written to the same file per condition
+    let demultiplexed =
+        std::fs::read_to_string(out_dir.join("barcode05_NA12878_tst-170.paf")).unwrap();
+    assert_eq!(demultiplexed, lines.concat());
+    std::fs::remove_dir_all(out_dir).unwrap();
+
+    // Errors name the file that failed
+    let missing_path = std::env::temp_dir().join("readfish_tools_test_missing.paf");
+    let err = _demultiplex_paf(
+        &toml_path,
+        &[&first_path, &missing_path],
+        Some(&seq_sum),
+        &DemultiplexOptions {
+            strict: true,
+            strict_seqsum: true,
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains(missing_path.to_str().unwrap()));
+    std::fs::remove_file(first_path).unwrap();
+    std::fs::remove_file(second_path).unwrap();
+}
+
+#[test]
+fn test_strict_contig_lengths_paf_demultiplex() {
+    let seq_sum = common::get_test_file("seq_sum_PAK09329.txt");
+    let toml_path = common::get_test_file("human_barcode.toml");
+    let paf = std::fs::read_to_string(common::get_test_file("test_paf_barcode05_NA12878.chr.paf"))
+        .unwrap();
+    // chr2 of an older reference version, in the second alignment to it
+    let mut lines = paf.lines().take(3).map(str::to_string).collect::<Vec<_>>();
+    lines[2] = lines[2].replace("242193529", "243199373");
+    let paf_path = std::env::temp_dir().join("readfish_tools_test_contig_lengths.paf");
+    std::fs::write(&paf_path, lines.join("\n") + "\n").unwrap();
+    // Malformed lines being an error doesn't make contig length mismatches one
+    let summary = _demultiplex_paf(
+        &toml_path,
+        &[&paf_path],
+        Some(&seq_sum),
+        &DemultiplexOptions {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(summary.contig_length_mismatches, 1);
+    let err = _demultiplex_paf(
+        &toml_path,
+        &[&paf_path],
+        Some(&seq_sum),
+        &DemultiplexOptions {
+            strict_contig_lengths: true,
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Contig chr2 has a length of 243199373"));
+    std::fs::remove_file(paf_path).unwrap();
+}
+
+#[test]
+fn test_paf_and_seq_sum_both_from_stdin() {
+    let toml_path = common::get_test_file("human_barcode.toml");
+    let err = _demultiplex_paf(
+        toml_path,
+        &["-"],
+        Some("-"),
+        &DemultiplexOptions {
+            strict: true,
+            strict_seqsum: true,
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("stdin"));
+}
+
+#[test]
+fn test_shuffled_paf_demultiplex() {
+    // Reads far apart in the sequencing summary, out of order, which can't all be held in the rolling buffer
+    let seq_sum = common::get_test_file("seq_sum_PAK09329.txt");
+    let toml_path = common::get_test_file("human_barcode.toml");
+    let contents = std::fs::read_to_string(&seq_sum).unwrap();
+    let mut lines = contents.lines();
+    let read_id_index = lines
+        .next()
+        .unwrap()
+        .split('\t')
+        .position(|column| column == "read_id")
+        .unwrap();
+    let read_ids: Vec<&str> = lines
+        .map(|line| line.split('\t').nth(read_id_index).unwrap())
+        .collect();
+    let shuffled_path = std::env::temp_dir().join("readfish_tools_test_shuffled.paf");
+    let paf: String = [149_000, 10, 120_000, 5]
+        .iter()
+        .map(|&index| {
+            format!(
+                "{}\t1000\t0\t1000\t+\tchr1\t248956422\t0\t1000\t900\t1000\t60\n",
+                read_ids[index]
+            )
+        })
+        .collect();
+    std::fs::write(&shuffled_path, paf).unwrap();
+
+    let summary = _demultiplex_paf(
+        &toml_path,
+        &[&shuffled_path],
+        Some(&seq_sum),
+        &DemultiplexOptions {
+            strict: true,
+            strict_seqsum: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let total_reads: usize = summary
+        .conditions
+        .values()
+        .map(|condition| condition.total_reads)
+        .sum();
+    assert_eq!(total_reads, 4);
+    std::fs::remove_file(shuffled_path).unwrap();
+}
+
+#[test]
+fn test_bed_targets_paf_demultiplex() {
+    // The same targets as human_barcode.toml, moved out of the TOML into a BED file keyed by barcode
+    let paf = common::get_test_file("test_paf_barcode05_NA12878.chr.paf");
+    let seq_sum = common::get_test_file("seq_sum_PAK09329.txt");
+    let toml_path = common::get_test_file("human_barcode.toml");
+    let toml = std::fs::read_to_string(&toml_path).unwrap();
+    let regions_only: String = toml
+        .lines()
+        .filter(|line| !line.contains("_coords_extended.csv"))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    let mut bed =
+        String::from("track name=\"tst panels\"\n# contig\tstart\tstop\tname\tscore\tstrand\n");
+    for (barcode, csv) in [
+        ("barcode05", "tst_170_coords_extended.csv"),
+        ("barcode06", "tst_fusion_coords_extended.csv"),
+    ] {
+        let targets = std::fs::read_to_string(common::get_test_file(csv)).unwrap();
+        for target in targets.lines() {
+            let columns: Vec<&str> = target.split(',').collect();
+            bed.push_str(&format!(
+                "{}\t{}\t{}\t{barcode}\t0\t{}\n",
+                columns[0], columns[1], columns[2], columns[3]
+            ));
+        }
+    }
+    let regions_only_path = std::env::temp_dir().join("readfish_tools_test_no_targets.toml");
+    let bed_path = std::env::temp_dir().join("readfish_tools_test_targets.bed");
+    std::fs::write(&regions_only_path, regions_only).unwrap();
+    std::fs::write(&bed_path, bed).unwrap();
+
+    let expected = _demultiplex_paf(
+        &toml_path,
+        &[&paf],
+        Some(&seq_sum),
+        &DemultiplexOptions {
+            strict: true,
+            strict_seqsum: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let summary = _demultiplex_paf(
+        &regions_only_path,
+        &[&paf],
+        Some(&seq_sum),
+        &DemultiplexOptions {
+            strict: true,
+            strict_seqsum: true,
+            targets_bed: Some(bed_path.clone()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for (condition_name, expected_condition) in &expected.conditions {
+        let condition = &summary.conditions[condition_name];
+        assert_eq!(
+            condition.on_target_read_count,
+            expected_condition.on_target_read_count
+        );
+        assert_eq!(
+            condition.on_target_yield,
+            expected_condition.on_target_yield
+        );
+    }
+    assert!(expected
+        .conditions
+        .values()
+        .any(|condition| condition.on_target_read_count > 0));
+    std::fs::remove_file(regions_only_path).unwrap();
+    std::fs::remove_file(bed_path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn test_cli_demultiplex() {
+    let paf = common::get_test_file("test_paf_barcode05_NA12878.chr.paf");
+    let seq_sum = common::get_test_file("seq_sum_PAK09329.txt");
+    let toml_path = common::get_test_file("human_barcode.toml");
+    let out_dir = std::env::temp_dir().join("readfish_tools_test_cli");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_readfish-tools"))
+        .arg("demultiplex")
+        .arg("--toml")
+        .arg(&toml_path)
+        .arg("--paf")
+        .arg(&paf)
+        .arg("--seq-sum")
+        .arg(&seq_sum)
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .args(["--format", "csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let csv = String::from_utf8(output.stdout).unwrap();
+    let mut lines = csv.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("\"condition\",\"total_reads\""));
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("\"barcode05_NA12878_tst-170\",4236,"));
+    let demultiplexed =
+        std::fs::read_to_string(out_dir.join("barcode05_NA12878_tst-170.paf")).unwrap();
+    assert_eq!(demultiplexed.lines().count(), 4236);
+    std::fs::remove_dir_all(out_dir).unwrap();
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn test_cli_sql() {
+    let sql_path = std::env::temp_dir().join("readfish_tools_test_cli.sql");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_readfish-tools"))
+        .arg("demultiplex")
+        .arg("--toml")
+        .arg(common::get_test_file("human_barcode.toml"))
+        .arg("--paf")
+        .arg(common::get_test_file("test_paf_barcode05_NA12878.chr.paf"))
+        .arg("--seq-sum")
+        .arg(common::get_test_file("seq_sum_PAK09329.txt"))
+        .arg("--sql")
+        .arg(&sql_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let sql = std::fs::read_to_string(&sql_path).unwrap();
+    assert!(sql.starts_with("BEGIN TRANSACTION;\n"));
+    // The run is named after the PAF file
+    assert!(sql.contains(
+        "INSERT OR REPLACE INTO runs (run) VALUES ('test_paf_barcode05_NA12878.chr');\n"
+    ));
+    assert!(sql
+        .contains("VALUES ('test_paf_barcode05_NA12878.chr', 'barcode05_NA12878_tst-170', 4236,"));
+    std::fs::remove_file(sql_path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn test_cli_sqlite() {
+    let db_path = std::env::temp_dir().join("readfish_tools_test_cli.db");
+    let assignments_path = std::env::temp_dir().join("readfish_tools_test_cli_assignments.tsv");
+    let _ = std::fs::remove_file(&db_path);
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_readfish-tools"))
+        .arg("demultiplex")
+        .arg("--toml")
+        .arg(common::get_test_file("human_barcode.toml"))
+        .arg("--paf")
+        .arg(common::get_test_file("test_paf_barcode05_NA12878.chr.paf"))
+        .arg("--seq-sum")
+        .arg(common::get_test_file("seq_sum_PAK09329.txt"))
+        .arg("--assignments")
+        .arg(&assignments_path)
+        .arg("--sqlite")
+        .arg(&db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let connection = rusqlite::Connection::open(&db_path).unwrap();
+    // The run is named after the PAF file
+    let total_reads: i64 = connection
+        .query_row(
+            "SELECT total_reads FROM conditions WHERE run = 'test_paf_barcode05_NA12878.chr' \
+             AND condition = 'barcode05_NA12878_tst-170'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(total_reads, 4236);
+    let assignments: i64 = connection
+        .query_row("SELECT COUNT(*) FROM assignments", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(assignments, 4236);
+    drop(connection);
+    std::fs::remove_file(db_path).unwrap();
+    std::fs::remove_file(assignments_path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn test_cli_seqsum_only() {
+    // Without a PAF file, the run is summarised from the sequencing summary alone
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_readfish-tools"))
+        .arg("demultiplex")
+        .arg("--toml")
+        .arg(common::get_test_file("human_barcode.toml"))
+        .arg("--seq-sum")
+        .arg(common::get_test_file("seq_sum_PAK09329.txt"))
+        .arg("--format")
+        .arg("json")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let conditions = summary["conditions"].as_object().unwrap();
+    let total_reads: u64 = conditions
+        .values()
+        .map(|condition| condition["total_reads"].as_u64().unwrap())
+        .sum();
+    let unmapped_reads: u64 = conditions
+        .values()
+        .map(|condition| condition["unmapped_read_count"].as_u64().unwrap())
+        .sum();
+    assert_eq!(total_reads, 150_000);
+    assert_eq!(unmapped_reads, 150_000);
+
+    // A PAF file or sequencing summary is still required
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_readfish-tools"))
+        .arg("demultiplex")
+        .arg("--toml")
+        .arg(common::get_test_file("human_barcode.toml"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn test_cli_validate() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_readfish-tools"))
+        .arg("demultiplex")
+        .arg("--toml")
+        .arg(common::get_test_file("RAPID_CNS2.toml"))
+        .arg("--validate")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "warning: no region or barcode is marked as a control\n"
+    );
+
+    // Both regions have the same name
+    let toml = std::fs::read_to_string(common::get_test_file("RAPID_CNS2.toml"))
+        .unwrap()
+        .replace("Direct_CNS", "Rapid_CNS");
+    let toml_path = std::env::temp_dir().join("readfish_tools_test_cli_validate.toml");
+    std::fs::write(&toml_path, toml).unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_readfish-tools"))
+        .arg("demultiplex")
+        .arg("--toml")
+        .arg(&toml_path)
+        .arg("--validate")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("error: 2 regions and barcodes are named \"Rapid_CNS\""));
+    std::fs::remove_file(toml_path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn test_cli_flowcell() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_readfish-tools"))
+        .args([
+            "flowcell",
+            "--size",
+            "126",
+            "--odd-even",
+            "--format",
+            "toml",
+            "--names",
+            "control,analysis",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let toml = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = toml.lines().collect();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[0], "[[regions]]");
+    assert_eq!(lines[1], "name = \"control\"");
+    assert!(lines[2].starts_with("channels = [1, 3, 5,"));
+    assert_eq!(lines[5], "name = \"analysis\"");
+    assert!(lines[6].ends_with("124, 126]"));
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_readfish-tools"))
+        .args(["flowcell", "--split", "4"])
+        .output()
+        .unwrap();
+    let grid = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        grid.lines().next(),
+        Some("########........++++++++oooooooo")
+    );
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_readfish-tools"))
+        .args(["flowcell", "--split", "3"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
 }
@@ -8,7 +8,7 @@ from typing import Optional
 import pytest
 from mappy import fastx_read
 import mappy_rs
-from readfish_tools import summarise_paf, ReadfishSummary
+from readfish_tools import demultiplex_paf, summarise_paf, ReadfishSummary
 
 
 RESOURCES = Path(__file__).parent.resolve().parent.resolve() / "resources/"
@@ -245,3 +245,15 @@ def test_demultiplex_pathlib(toml_file_path, paf_file_path, seq_sum_file_path):
         paf_file_path,
         seq_sum_file_path,
     )
+
+
+def test_demultiplex_without_seq_sum_missing_tags(toml_file_path, paf_file_path):
+    # The test PAF has no custom ch tags, so a sequencing summary is required
+    with pytest.raises(RuntimeError, match="`ch` tag"):
+        demultiplex_paf(toml_file_path, paf_file_path)
+
+
+def test_demultiplex_paf_list(toml_file_path, paf_file_path, seq_sum_file_path):
+    # A list of PAF files is summarised into one Summary
+    summary = demultiplex_paf(toml_file_path, [paf_file_path], seq_sum_file_path)
+    assert summary.conditions